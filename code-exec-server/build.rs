@@ -0,0 +1,6 @@
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // Generates the gRPC service and messages, which needs protoc on the PATH
+    #[cfg(feature = "grpc")]
+    tonic_build::compile_protos("proto/code_exec.proto")?;
+    Ok(())
+}
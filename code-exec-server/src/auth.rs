@@ -0,0 +1,82 @@
+//! API-key authentication of execution requests
+//!
+//! Clients send one of the configured keys as `Authorization: Bearer <key>`. A server
+//! without keys accepts every request, which is only meant for local development.
+
+use axum::{
+    extract::{Request, State},
+    http::header::AUTHORIZATION,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::{collections::HashSet, io, path::Path};
+
+use crate::{AppState, ServerError};
+
+/// Keys accepted by the server
+#[derive(Debug, Default)]
+pub struct ApiKeys {
+    keys: HashSet<String>,
+}
+
+impl ApiKeys {
+    /// Accept the given keys
+    pub fn new<I: IntoIterator<Item = String>>(keys: I) -> Self {
+        Self {
+            keys: keys.into_iter().collect(),
+        }
+    }
+
+    /// Read keys from a file with one key per line, skipping blank lines and `#` comments
+    pub fn from_file(path: &Path) -> io::Result<Self> {
+        let contents = std::fs::read_to_string(path)?;
+        Ok(Self::new(
+            contents
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .map(str::to_string),
+        ))
+    }
+
+    /// Whether authentication is disabled
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Whether `key` is one of the accepted keys
+    pub fn contains(&self, key: &str) -> bool {
+        self.keys.contains(key)
+    }
+}
+
+/// Key a request was authenticated with, added to the request's extensions
+#[derive(Debug, Clone)]
+pub struct ApiKey(pub String);
+
+/// Reject requests without a valid bearer key, unless no keys are configured
+pub async fn require_api_key(
+    State(state): State<AppState>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    if state.api_keys.is_empty() {
+        return next.run(request).await;
+    }
+
+    let key = request
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::trim)
+        .filter(|key| state.api_keys.contains(key))
+        .map(str::to_string);
+    match key {
+        Some(key) => {
+            request.extensions_mut().insert(ApiKey(key));
+            next.run(request).await
+        }
+        None => ServerError::Unauthorized.into_response(),
+    }
+}
@@ -0,0 +1,245 @@
+//! One-shot code execution from the command line
+
+use clap::ValueEnum;
+use code_exec::{
+    CodeExecutionService, ExecutionRequest, ExecutionResult, ExecutionStatus, Language,
+    ProcessStats,
+};
+use serde::{Deserialize, Serialize};
+use std::{io::Write, path::PathBuf, time::Duration};
+use tokio::io::AsyncReadExt;
+
+use crate::ServerError;
+
+/// Output format for the `exec` subcommand
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Human-readable program output
+    Text,
+    /// A single `ExecutionResult` object
+    Json,
+    /// One `ExecutionEvent` per line
+    Ndjson,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct ExecArgs {
+    /// Language of the source file
+    #[arg(short, long)]
+    pub language: String,
+
+    /// Source file to execute (`-` reads from stdin)
+    pub file: PathBuf,
+
+    /// Input passed to the program's stdin
+    #[arg(short, long)]
+    pub input: Option<String>,
+
+    /// Execution timeout in seconds
+    #[arg(short, long, default_value = "30")]
+    pub timeout: u64,
+
+    /// Output format
+    #[arg(short, long, value_enum, default_value_t = OutputFormat::Text)]
+    pub format: OutputFormat,
+
+    /// Fix the timezone and hash and random seeds so repeated runs match
+    #[arg(long)]
+    pub deterministic: bool,
+
+    /// Command-line arguments passed to the program, after `--`
+    #[arg(last = true)]
+    pub args: Vec<String>,
+}
+
+/// Event emitted by the `exec` subcommand in `ndjson` format
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum ExecutionEvent {
+    Started {
+        language: Language,
+    },
+    Stdout {
+        data: String,
+    },
+    Stderr {
+        data: String,
+    },
+    Finished {
+        status: ExecutionStatus,
+        process_stats: ProcessStats,
+    },
+    Failed {
+        error: String,
+    },
+}
+
+/// Execute a source file once and write the outcome to `out`.
+///
+/// Returns `false` if the execution failed.
+pub async fn run_exec(
+    service: &CodeExecutionService,
+    args: ExecArgs,
+    out: &mut impl Write,
+) -> Result<bool, ServerError> {
+    let language: Language = args
+        .language
+        .parse()
+        .map_err(|_| ServerError::InvalidLanguage(args.language.clone()))?;
+
+    let code = if args.file.as_os_str() == "-" {
+        let mut code = String::new();
+        tokio::io::stdin()
+            .read_to_string(&mut code)
+            .await
+            .map_err(|e| ServerError::ServerError(format!("Failed to read stdin: {}", e)))?;
+        code
+    } else {
+        tokio::fs::read_to_string(&args.file).await.map_err(|e| {
+            ServerError::ServerError(format!("Failed to read {}: {}", args.file.display(), e))
+        })?
+    };
+
+    let request = ExecutionRequest {
+        language,
+        code,
+        input: args.input,
+        args: args.args,
+        timeout: Duration::from_secs(args.timeout),
+        deterministic: args.deterministic,
+        ..Default::default()
+    };
+
+    if args.format == OutputFormat::Ndjson {
+        write_event(out, &ExecutionEvent::Started { language })?;
+    }
+
+    let result = service.execute(request).await;
+    let success = matches!(&result, Ok(result) if result.status == ExecutionStatus::Success);
+
+    match (args.format, result) {
+        (OutputFormat::Text, Ok(result)) => {
+            write_output(out, result.stdout.as_bytes())?;
+            if !result.stderr.is_empty() {
+                eprint!("{}", result.stderr);
+            }
+            if result.status != ExecutionStatus::Success {
+                eprintln!("Execution ended with status: {}", result.status);
+            }
+        }
+        (OutputFormat::Text, Err(e)) => eprintln!("{}", e),
+        (OutputFormat::Json, Ok(result)) => write_json(out, &result)?,
+        (OutputFormat::Json, Err(e)) => {
+            write_json(out, &serde_json::json!({ "error": e.to_string() }))?
+        }
+        (OutputFormat::Ndjson, Ok(result)) => write_events(out, result)?,
+        (OutputFormat::Ndjson, Err(e)) => write_event(
+            out,
+            &ExecutionEvent::Failed {
+                error: e.to_string(),
+            },
+        )?,
+    }
+
+    Ok(success)
+}
+
+/// Run [`CodeExecutionService::self_test`] and write one line per language to `out`.
+///
+/// Returns `false` if any language failed.
+pub async fn run_self_test(
+    service: &CodeExecutionService,
+    timeout: Duration,
+    out: &mut impl Write,
+) -> Result<bool, ServerError> {
+    let results = service.self_test(timeout).await;
+    let mut success = true;
+    for (language, result) in results {
+        let line = match result {
+            Ok(()) => format!("{}: ok\n", language),
+            Err(e) => {
+                success = false;
+                format!("{}: failed: {}\n", language, e)
+            }
+        };
+        write_output(out, line.as_bytes())?;
+    }
+    Ok(success)
+}
+
+fn write_events(out: &mut impl Write, result: ExecutionResult) -> Result<(), ServerError> {
+    if !result.stdout.is_empty() {
+        write_event(
+            out,
+            &ExecutionEvent::Stdout {
+                data: result.stdout,
+            },
+        )?;
+    }
+    if !result.stderr.is_empty() {
+        write_event(
+            out,
+            &ExecutionEvent::Stderr {
+                data: result.stderr,
+            },
+        )?;
+    }
+    write_event(
+        out,
+        &ExecutionEvent::Finished {
+            status: result.status,
+            process_stats: result.process_stats,
+        },
+    )
+}
+
+fn write_event(out: &mut impl Write, event: &ExecutionEvent) -> Result<(), ServerError> {
+    write_json(out, event)
+}
+
+fn write_json(out: &mut impl Write, value: &impl Serialize) -> Result<(), ServerError> {
+    let line = serde_json::to_string(value)
+        .map_err(|e| ServerError::ServerError(format!("Failed to serialize output: {}", e)))?;
+    write_output(out, format!("{}\n", line).as_bytes())
+}
+
+fn write_output(out: &mut impl Write, bytes: &[u8]) -> Result<(), ServerError> {
+    out.write_all(bytes)
+        .and_then(|_| out.flush())
+        .map_err(|e| ServerError::ServerError(format!("Failed to write output: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use code_exec::ResourceLimits;
+
+    #[tokio::test]
+    async fn test_exec_json_format() {
+        let service = CodeExecutionService::new(1, ResourceLimits::default())
+            .await
+            .expect("Failed to create service");
+
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("hello.py");
+        std::fs::write(&file, r#"print("Hello, World!")"#).unwrap();
+
+        let args = ExecArgs {
+            language: "python".to_string(),
+            file,
+            input: None,
+            timeout: 5,
+            format: OutputFormat::Json,
+            deterministic: false,
+            args: vec![],
+        };
+
+        let mut out = Vec::new();
+        let success = run_exec(&service, args, &mut out).await.unwrap();
+        assert!(success);
+
+        let result: ExecutionResult = serde_json::from_slice(&out).unwrap();
+        assert_eq!(result.stdout.trim(), "Hello, World!");
+        assert_eq!(result.status, ExecutionStatus::Success);
+    }
+}
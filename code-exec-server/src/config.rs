@@ -0,0 +1,617 @@
+//! Server configuration file
+//!
+//! `--config` reads the server's settings from TOML, or from JSON if the file name ends
+//! in `.json`. Every key is optional and defaults to the matching command line flag's
+//! default, and flags given on the command line override the file. Unknown keys are
+//! rejected so a misspelt limit is not silently ignored.
+
+use code_exec::{
+    EnvPolicy, Language, PolicyRule, RegexPolicy, ResourceLimits, SandboxBackend,
+    DEFAULT_COMPILE_TIMEOUT, DEFAULT_INSTALL_TIMEOUT, DEFAULT_MAX_ENV_BYTES, DEFAULT_MAX_ENV_VARS,
+    DEFAULT_MAX_SOURCE_BYTES, IMPORT_CHECKED_LANGUAGES, OSV_API_URL,
+};
+use serde::Deserialize;
+use std::{collections::HashMap, io, net::SocketAddr, path::Path, path::PathBuf};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("Failed to read config file: {0}")]
+    Read(#[from] io::Error),
+    #[error("Failed to parse config file: {0}")]
+    Parse(String),
+    #[error("Invalid config: {0}")]
+    Invalid(String),
+}
+
+/// Contents of a configuration file
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConfigFile {
+    pub server: ServerSection,
+    pub limits: LimitsSection,
+    /// Dependency cache, disabled if the section is missing
+    pub dependency_cache: Option<DependencyCacheSection>,
+    /// Vulnerability scan of requested dependencies, disabled if the section is missing
+    pub dependency_scan: Option<DependencyScanSection>,
+    pub auth: AuthSection,
+    pub rate_limit: RateLimitSection,
+    pub policy: PolicySection,
+    /// Settings per language, keyed by language name
+    pub languages: HashMap<Language, LanguageSection>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerSection {
+    pub addr: SocketAddr,
+    pub max_concurrent: usize,
+    /// Seconds a response is replayed for retries with the same Idempotency-Key
+    pub idempotency_ttl: u64,
+    /// Languages whose toolchains must be installed for `/ready` to succeed
+    pub required_languages: Vec<Language>,
+    pub allow_shell: bool,
+    /// Programs the commands of custom language requests may run
+    pub custom_programs: Vec<String>,
+    /// Largest `code` and `input` accepted, in bytes
+    pub max_source_bytes: usize,
+    /// Take dependencies from the dependency cache only
+    pub offline: bool,
+    /// Seconds the sandbox of a failed execution is kept for inspection, removed right
+    /// away if unset
+    pub keep_failed_sandboxes: Option<u64>,
+    /// Directory, relative to the sandbox, request fixtures are written to
+    pub fixtures_dir: Option<PathBuf>,
+    /// Longest `timeout` a request may ask for in seconds, unlimited if unset
+    pub max_timeout: Option<u64>,
+    /// Refuse requests over `max_timeout` with 400 instead of lowering their timeout
+    pub reject_over_max_timeout: bool,
+    /// Origins browsers may call the API from, `"*"` allowing any
+    pub allowed_origins: Vec<String>,
+}
+
+impl Default for ServerSection {
+    fn default() -> Self {
+        Self {
+            addr: SocketAddr::from(([0, 0, 0, 0], 3000)),
+            max_concurrent: 10,
+            idempotency_ttl: 600,
+            required_languages: vec![
+                Language::Python,
+                Language::JavaScript,
+                Language::TypeScript,
+                Language::Rust,
+                Language::Go,
+            ],
+            allow_shell: false,
+            custom_programs: Vec::new(),
+            max_source_bytes: DEFAULT_MAX_SOURCE_BYTES,
+            offline: false,
+            keep_failed_sandboxes: None,
+            fixtures_dir: None,
+            max_timeout: None,
+            reject_over_max_timeout: false,
+            allowed_origins: Vec::new(),
+        }
+    }
+}
+
+/// Resource limits applied to every execution, see [`ResourceLimits`]
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LimitsSection {
+    /// Memory limit in bytes
+    pub memory: u64,
+    /// CPU time limit in seconds
+    pub cpu_time: u32,
+    /// Wall-clock time limit in seconds
+    pub wall_time: u64,
+    /// Time limit for installing dependencies in seconds
+    pub install_timeout: u64,
+    /// Time limit for compiling in seconds
+    pub compile_timeout: u64,
+    pub processes: u32,
+    /// File size limit in bytes
+    pub file_size: u64,
+    pub max_files: u64,
+    /// Disk space limit in bytes
+    pub disk_space: u64,
+    pub strict_env: bool,
+    /// Most environment variables a request may set
+    pub max_env_vars: usize,
+    /// Most bytes a request's environment variable names and values may add up to
+    pub max_env_bytes: usize,
+    pub seccomp: bool,
+    pub seccomp_profile: Option<PathBuf>,
+    /// Run programs in gVisor containers instead of as native processes
+    pub gvisor: bool,
+    pub readonly_mounts: Vec<PathBuf>,
+    pub run_as_uid: Option<u32>,
+    pub run_as_gid: Option<u32>,
+}
+
+impl Default for LimitsSection {
+    fn default() -> Self {
+        Self {
+            memory: 100 * 1024 * 1024, // 100MB
+            cpu_time: 5,
+            wall_time: 60,
+            install_timeout: DEFAULT_INSTALL_TIMEOUT,
+            compile_timeout: DEFAULT_COMPILE_TIMEOUT,
+            processes: 10,
+            file_size: 10 * 1024 * 1024, // 10MB
+            max_files: 10_000,
+            disk_space: 100 * 1024 * 1024, // 100MB
+            strict_env: false,
+            max_env_vars: DEFAULT_MAX_ENV_VARS,
+            max_env_bytes: DEFAULT_MAX_ENV_BYTES,
+            seccomp: false,
+            seccomp_profile: None,
+            gvisor: false,
+            readonly_mounts: Vec::new(),
+            run_as_uid: None,
+            run_as_gid: None,
+        }
+    }
+}
+
+impl LimitsSection {
+    pub fn resource_limits(&self) -> ResourceLimits {
+        ResourceLimits {
+            memory: self.memory,
+            cpu_time: self.cpu_time,
+            wall_time: self.wall_time,
+            install_timeout: self.install_timeout,
+            compile_timeout: self.compile_timeout,
+            processes: self.processes,
+            file_size: self.file_size,
+            max_files: self.max_files,
+            disk_space: self.disk_space,
+            env_policy: EnvPolicy {
+                strict: self.strict_env,
+                max_vars: self.max_env_vars,
+                max_bytes: self.max_env_bytes,
+                ..EnvPolicy::default()
+            },
+            backend: if self.gvisor {
+                SandboxBackend::Gvisor
+            } else {
+                SandboxBackend::default()
+            },
+            seccomp: self.seccomp || self.seccomp_profile.is_some(),
+            seccomp_profile: self.seccomp_profile.clone(),
+            readonly_mounts: self.readonly_mounts.clone(),
+            run_as_uid: self.run_as_uid,
+            run_as_gid: self.run_as_gid,
+            toolchain_paths: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DependencyCacheSection {
+    pub dir: PathBuf,
+    /// Maximum cache size in bytes
+    #[serde(default = "default_cache_max_size")]
+    pub max_size: u64,
+}
+
+fn default_cache_max_size() -> u64 {
+    2 * 1024 * 1024 * 1024 // 2GB
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DependencyScanSection {
+    /// OSV compatible advisory API
+    pub advisory_url: String,
+    /// Refuse dependencies that cannot be checked: ranges, unversioned ones and git
+    /// sources
+    pub require_exact_versions: bool,
+}
+
+impl Default for DependencyScanSection {
+    fn default() -> Self {
+        Self {
+            advisory_url: OSV_API_URL.to_string(),
+            require_exact_versions: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuthSection {
+    /// File with one API key per line, the execution endpoints are open if unset
+    pub api_keys_file: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RateLimitSection {
+    /// Executions each client may start per minute, unlimited if unset
+    pub requests_per_minute: Option<u32>,
+}
+
+/// Patterns that get submissions refused before they run
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PolicySection {
+    /// Keep the built-in fork bomb, `rm -rf /` and crypto miner rules
+    pub builtin_rules: bool,
+    /// Rules checked in addition to the built-in ones
+    pub rules: Vec<PolicyRule>,
+}
+
+impl Default for PolicySection {
+    fn default() -> Self {
+        Self {
+            builtin_rules: true,
+            rules: Vec::new(),
+        }
+    }
+}
+
+impl PolicySection {
+    pub fn rules(&self) -> Vec<PolicyRule> {
+        let mut rules = if self.builtin_rules {
+            PolicyRule::builtin()
+        } else {
+            Vec::new()
+        };
+        rules.extend(self.rules.iter().cloned());
+        rules
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LanguageSection {
+    /// Toolchain version the language's executor is created with
+    pub version: Option<String>,
+    /// Cargo target directory shared by all builds, Rust only
+    pub target_dir: Option<PathBuf>,
+    /// Executions of the language that may run at once, within `server.max_concurrent`
+    pub max_concurrent: Option<usize>,
+    /// Modules programs in the language must not import
+    pub banned_imports: Vec<String>,
+    /// Directories searched for the language's commands before the system's
+    pub toolchain_paths: Vec<PathBuf>,
+}
+
+impl ConfigFile {
+    /// Read and validate a config file, parsed as JSON if it ends in `.json` and as TOML
+    /// otherwise
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        let contents = std::fs::read_to_string(path)?;
+        let config = if path.extension().is_some_and(|ext| ext == "json") {
+            Self::from_json(&contents)?
+        } else {
+            Self::from_toml(&contents)?
+        };
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn from_toml(contents: &str) -> Result<Self, ConfigError> {
+        toml::from_str(contents).map_err(|e| ConfigError::Parse(e.to_string()))
+    }
+
+    pub fn from_json(contents: &str) -> Result<Self, ConfigError> {
+        serde_json::from_str(contents).map_err(|e| ConfigError::Parse(e.to_string()))
+    }
+
+    /// Reject values the server cannot run with
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.server.max_concurrent == 0 {
+            return Err(ConfigError::Invalid(
+                "server.max_concurrent must be at least 1".to_string(),
+            ));
+        }
+        if self.server.max_source_bytes == 0 {
+            return Err(ConfigError::Invalid(
+                "server.max_source_bytes must be at least 1".to_string(),
+            ));
+        }
+        if self.server.max_timeout == Some(0) {
+            return Err(ConfigError::Invalid(
+                "server.max_timeout must be at least 1 second".to_string(),
+            ));
+        }
+        if self.rate_limit.requests_per_minute == Some(0) {
+            return Err(ConfigError::Invalid(
+                "rate_limit.requests_per_minute must be at least 1".to_string(),
+            ));
+        }
+        if self.limits.wall_time == 0 || self.limits.cpu_time == 0 {
+            return Err(ConfigError::Invalid(
+                "limits.wall_time and limits.cpu_time must be at least 1 second".to_string(),
+            ));
+        }
+        if self.limits.install_timeout == 0 || self.limits.compile_timeout == 0 {
+            return Err(ConfigError::Invalid(
+                "limits.install_timeout and limits.compile_timeout must be at least 1 second"
+                    .to_string(),
+            ));
+        }
+        if let Some((language, _)) = self.languages.iter().find(|(language, section)| {
+            **language != Language::Rust && section.target_dir.is_some()
+        }) {
+            return Err(ConfigError::Invalid(format!(
+                "languages.{}.target_dir is only supported for Rust",
+                language
+            )));
+        }
+        if self.rust_target_dir().is_some() && self.limits.run_as_uid.is_none() {
+            return Err(ConfigError::Invalid(
+                "languages.rust.target_dir requires limits.run_as_uid, so programs cannot \
+                 write to the shared builds"
+                    .to_string(),
+            ));
+        }
+        if let Some((language, _)) = self
+            .languages
+            .iter()
+            .find(|(_, section)| section.max_concurrent == Some(0))
+        {
+            return Err(ConfigError::Invalid(format!(
+                "languages.{}.max_concurrent must be at least 1",
+                language
+            )));
+        }
+        if let Some((language, _)) = self.languages.iter().find(|(language, section)| {
+            !section.banned_imports.is_empty() && !IMPORT_CHECKED_LANGUAGES.contains(language)
+        }) {
+            return Err(ConfigError::Invalid(format!(
+                "languages.{}.banned_imports is not supported, imports are only checked for {}",
+                language,
+                IMPORT_CHECKED_LANGUAGES
+                    .map(|language| language.as_str())
+                    .join(", ")
+            )));
+        }
+        RegexPolicy::new(self.policy.rules()).map_err(|e| ConfigError::Invalid(e.to_string()))?;
+        Ok(())
+    }
+
+    /// Cargo target directory shared by Rust builds, if configured
+    pub fn rust_target_dir(&self) -> Option<PathBuf> {
+        self.languages.get(&Language::Rust)?.target_dir.clone()
+    }
+
+    /// Pinned toolchain versions by language
+    pub fn language_versions(&self) -> HashMap<Language, String> {
+        self.languages
+            .iter()
+            .filter_map(|(language, section)| Some((*language, section.version.clone()?)))
+            .collect()
+    }
+
+    /// Banned imports by language, for languages that ban any
+    pub fn banned_imports(&self) -> HashMap<Language, Vec<String>> {
+        self.languages
+            .iter()
+            .filter(|(_, section)| !section.banned_imports.is_empty())
+            .map(|(language, section)| (*language, section.banned_imports.clone()))
+            .collect()
+    }
+
+    /// Toolchain directories by language, for languages that have any
+    pub fn toolchain_paths(&self) -> HashMap<Language, Vec<PathBuf>> {
+        self.languages
+            .iter()
+            .filter(|(_, section)| !section.toolchain_paths.is_empty())
+            .map(|(language, section)| (*language, section.toolchain_paths.clone()))
+            .collect()
+    }
+
+    /// Concurrency caps by language
+    pub fn language_concurrency(&self) -> HashMap<Language, usize> {
+        self.languages
+            .iter()
+            .filter_map(|(language, section)| Some((*language, section.max_concurrent?)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = r#"
+        [server]
+        addr = "127.0.0.1:8080"
+        max_concurrent = 4
+        required_languages = ["python", "go"]
+        allowed_origins = ["https://app.example.com"]
+
+        [limits]
+        memory = 268435456
+        cpu_time = 10
+        wall_time = 30
+        processes = 20
+        max_env_vars = 32
+        seccomp_profile = "/etc/code-exec/seccomp.json"
+        readonly_mounts = ["/opt/toolchains"]
+        run_as_uid = 65534
+
+        [dependency_cache]
+        dir = "/var/cache/code-exec"
+
+        [rate_limit]
+        requests_per_minute = 120
+
+        [languages.python]
+        version = "3.11"
+        banned_imports = ["subprocess", "socket"]
+
+        [languages.rust]
+        target_dir = "/var/cache/code-exec/cargo-target"
+        max_concurrent = 1
+
+        [languages.go]
+        toolchain_paths = ["/opt/go/bin"]
+    "#;
+
+    #[test]
+    fn test_load_toml_config() {
+        let config = ConfigFile::from_toml(SAMPLE).unwrap();
+        config.validate().unwrap();
+
+        let limits = config.limits.resource_limits();
+        assert_eq!(limits.memory, 256 * 1024 * 1024);
+        assert_eq!(limits.cpu_time, 10);
+        assert_eq!(limits.wall_time, 30);
+        assert_eq!(limits.processes, 20);
+        // Keys left out keep their defaults
+        assert_eq!(limits.file_size, 10 * 1024 * 1024);
+        assert_eq!(limits.max_files, 10_000);
+        assert_eq!(limits.env_policy.max_vars, 32);
+        assert_eq!(limits.env_policy.max_bytes, DEFAULT_MAX_ENV_BYTES);
+        assert!(limits.seccomp);
+        assert_eq!(
+            limits.seccomp_profile,
+            Some(PathBuf::from("/etc/code-exec/seccomp.json"))
+        );
+        assert_eq!(
+            limits.readonly_mounts,
+            vec![PathBuf::from("/opt/toolchains")]
+        );
+        assert_eq!(limits.run_as_uid, Some(65534));
+        assert_eq!(limits.run_as_gid, None);
+
+        assert_eq!(config.server.addr, "127.0.0.1:8080".parse().unwrap());
+        assert_eq!(config.server.max_concurrent, 4);
+        assert_eq!(
+            config.server.required_languages,
+            vec![Language::Python, Language::Go]
+        );
+        assert_eq!(
+            config.server.allowed_origins,
+            vec!["https://app.example.com".to_string()]
+        );
+        assert_eq!(
+            config.dependency_cache.as_ref().unwrap().max_size,
+            default_cache_max_size()
+        );
+        assert_eq!(config.rate_limit.requests_per_minute, Some(120));
+        assert_eq!(
+            config.language_versions().get(&Language::Python),
+            Some(&"3.11".to_string())
+        );
+        assert_eq!(
+            config.rust_target_dir(),
+            Some(PathBuf::from("/var/cache/code-exec/cargo-target"))
+        );
+        assert_eq!(
+            config.language_concurrency(),
+            HashMap::from([(Language::Rust, 1)])
+        );
+        assert_eq!(
+            config.banned_imports(),
+            HashMap::from([(
+                Language::Python,
+                vec!["subprocess".to_string(), "socket".to_string()]
+            )])
+        );
+        assert_eq!(
+            config.toolchain_paths(),
+            HashMap::from([(Language::Go, vec![PathBuf::from("/opt/go/bin")])])
+        );
+    }
+
+    #[test]
+    fn test_json_matches_toml() {
+        let json = r#"{
+            "server": {
+                "addr": "127.0.0.1:8080", "max_concurrent": 4, "required_languages": ["python", "go"],
+                "allowed_origins": ["https://app.example.com"]
+            },
+            "limits": {
+                "memory": 268435456, "cpu_time": 10, "wall_time": 30, "processes": 20,
+                "max_env_vars": 32,
+                "seccomp_profile": "/etc/code-exec/seccomp.json",
+                "readonly_mounts": ["/opt/toolchains"], "run_as_uid": 65534
+            },
+            "dependency_cache": { "dir": "/var/cache/code-exec" },
+            "rate_limit": { "requests_per_minute": 120 },
+            "languages": {
+                "python": { "version": "3.11", "banned_imports": ["subprocess", "socket"] },
+                "rust": { "target_dir": "/var/cache/code-exec/cargo-target", "max_concurrent": 1 },
+                "go": { "toolchain_paths": ["/opt/go/bin"] }
+            }
+        }"#;
+        assert_eq!(
+            ConfigFile::from_json(json).unwrap(),
+            ConfigFile::from_toml(SAMPLE).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_rejects_unknown_keys() {
+        let err = ConfigFile::from_toml("[limits]\nmemroy = 1024\n").unwrap_err();
+        assert!(err.to_string().contains("memroy"), "{}", err);
+
+        assert!(ConfigFile::from_toml("[limit]\nmemory = 1024\n").is_err());
+    }
+
+    #[test]
+    fn test_policy_rules() {
+        let config = ConfigFile::from_toml(
+            r#"
+            [policy]
+            builtin_rules = false
+
+            [[policy.rules]]
+            name = "no_network"
+            pattern = "import (socket|urllib)"
+            languages = ["python"]
+            "#,
+        )
+        .unwrap();
+        config.validate().unwrap();
+        assert_eq!(
+            config.policy.rules(),
+            vec![PolicyRule {
+                name: "no_network".to_string(),
+                pattern: "import (socket|urllib)".to_string(),
+                languages: vec![Language::Python],
+            }]
+        );
+
+        // Built-in rules are kept unless turned off
+        let config = ConfigFile::default();
+        assert_eq!(config.policy.rules(), PolicyRule::builtin());
+
+        let config = ConfigFile::from_toml(
+            "[[policy.rules]]
+name = \"broken\"
+pattern = \"(\"
+",
+        )
+        .unwrap();
+        assert!(matches!(config.validate(), Err(ConfigError::Invalid(_))));
+    }
+
+    #[test]
+    fn test_rejects_invalid_values() {
+        let config = ConfigFile::from_toml("[rate_limit]\nrequests_per_minute = 0\n").unwrap();
+        assert!(matches!(config.validate(), Err(ConfigError::Invalid(_))));
+
+        let config =
+            ConfigFile::from_toml("[languages.go]\ntarget_dir = \"/tmp/target\"\n").unwrap();
+        assert!(matches!(config.validate(), Err(ConfigError::Invalid(_))));
+
+        let config = ConfigFile::from_toml("[languages.rust]\nmax_concurrent = 0\n").unwrap();
+        assert!(matches!(config.validate(), Err(ConfigError::Invalid(_))));
+
+        let config =
+            ConfigFile::from_toml("[languages.rust]\nbanned_imports = [\"std::process\"]\n")
+                .unwrap();
+        assert!(matches!(config.validate(), Err(ConfigError::Invalid(_))));
+
+        let config = ConfigFile::from_toml("[limits]\ninstall_timeout = 0\n").unwrap();
+        assert!(matches!(config.validate(), Err(ConfigError::Invalid(_))));
+    }
+}
@@ -0,0 +1,130 @@
+//! Executions submitted in the background, which can be polled and cancelled by id
+
+use code_exec::CancellationToken;
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+use uuid::Uuid;
+
+use crate::{ExecuteResponse, ServerError};
+
+/// How long a finished result waits to be collected before it is dropped
+const RESULT_TTL: Duration = Duration::from_secs(600);
+
+/// Executions tracked at once by default, running or waiting to be collected
+pub const DEFAULT_MAX_TRACKED: usize = 1000;
+
+enum Tracked {
+    Running(CancellationToken),
+    Finished {
+        result: Box<Result<ExecuteResponse, ServerError>>,
+        finished_at: Instant,
+    },
+}
+
+/// State of a background execution
+pub enum ExecutionState {
+    Running,
+    Finished(Box<Result<ExecuteResponse, ServerError>>),
+}
+
+/// Background executions by id
+pub struct Executions {
+    max_tracked: usize,
+    entries: Mutex<HashMap<Uuid, Tracked>>,
+}
+
+impl Default for Executions {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_TRACKED)
+    }
+}
+
+impl Executions {
+    /// Track up to `max_tracked` executions at once
+    pub fn new(max_tracked: usize) -> Self {
+        Self {
+            max_tracked,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Track a new execution, returning the token that cancels it, or
+    /// `TooManyExecutions` while as many as allowed are running or waiting to be collected
+    pub fn start(&self, id: Uuid) -> Result<CancellationToken, ServerError> {
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|_, tracked| match tracked {
+            Tracked::Running(_) => true,
+            Tracked::Finished { finished_at, .. } => finished_at.elapsed() < RESULT_TTL,
+        });
+        if entries.len() >= self.max_tracked {
+            return Err(ServerError::TooManyExecutions);
+        }
+
+        let token = CancellationToken::new();
+        entries.insert(id, Tracked::Running(token.clone()));
+        Ok(token)
+    }
+
+    /// Record the result of an execution
+    pub fn finish(&self, id: Uuid, result: Result<ExecuteResponse, ServerError>) {
+        self.entries.lock().unwrap().insert(
+            id,
+            Tracked::Finished {
+                result: Box::new(result),
+                finished_at: Instant::now(),
+            },
+        );
+    }
+
+    /// Cancel a running execution, returning whether one was running under `id`
+    pub fn cancel(&self, id: Uuid) -> bool {
+        match self.entries.lock().unwrap().get(&id) {
+            Some(Tracked::Running(token)) => {
+                token.cancel();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Look up an execution, handing over its result if it has finished
+    pub fn take(&self, id: Uuid) -> Option<ExecutionState> {
+        let mut entries = self.entries.lock().unwrap();
+        match entries.get(&id)? {
+            Tracked::Running(_) => Some(ExecutionState::Running),
+            Tracked::Finished { .. } => match entries.remove(&id) {
+                Some(Tracked::Finished { result, .. }) => Some(ExecutionState::Finished(result)),
+                _ => None,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_start_is_refused_once_full() {
+        let executions = Executions::new(2);
+        let first = Uuid::new_v4();
+        executions.start(first).unwrap();
+        executions.start(Uuid::new_v4()).unwrap();
+        assert!(matches!(
+            executions.start(Uuid::new_v4()),
+            Err(ServerError::TooManyExecutions)
+        ));
+
+        // A finished result holds its place until it is collected
+        executions.finish(first, Err(ServerError::Unauthorized));
+        assert!(executions.start(Uuid::new_v4()).is_err());
+        assert!(matches!(
+            executions.take(first),
+            Some(ExecutionState::Finished(_))
+        ));
+        executions.start(Uuid::new_v4()).unwrap();
+    }
+}
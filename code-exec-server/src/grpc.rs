@@ -0,0 +1,366 @@
+//! gRPC interface, built with the `grpc` feature
+//!
+//! `Execute` and `ExecuteStream`, described in `proto/code_exec.proto`, take the same
+//! requests as `POST /execute` and run them on the service the REST API uses. API keys
+//! and rate limits apply as they do there, with the key sent as `authorization`
+//! metadata. Errors carry the REST API's JSON error body as their details.
+
+use axum::http::StatusCode;
+use code_exec::{CancellationToken, Dependency, SourceFile};
+use std::{net::SocketAddr, time::Duration};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{transport::Server, Code, Request, Response, Status};
+use tracing::info;
+use uuid::Uuid;
+
+use crate::{limit_timeout, run_execution, AppState, ExecuteRequest, ExecuteResponse, ServerError};
+
+pub mod proto {
+    tonic::include_proto!("code_exec");
+}
+
+use proto::{
+    code_exec_server::{CodeExec, CodeExecServer},
+    execute_event::Event,
+    output::Stream,
+    ExecuteEvent,
+};
+
+/// Largest piece of output sent in one `Output` event (bytes)
+const OUTPUT_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Events buffered for a client reading its stream slowly
+const EVENT_BUFFER: usize = 16;
+
+/// Serve the gRPC API on `addr`, sharing `state` with the REST API
+pub async fn run_grpc_server(state: AppState, addr: SocketAddr) -> Result<(), ServerError> {
+    info!("Starting gRPC server on {}", addr);
+    Server::builder()
+        .add_service(CodeExecServer::new(GrpcService::new(state)))
+        .serve(addr)
+        .await
+        .map_err(|e| ServerError::ServerError(e.to_string()))
+}
+
+/// Implementation of the `CodeExec` gRPC service
+pub struct GrpcService {
+    state: AppState,
+}
+
+impl GrpcService {
+    pub fn new(state: AppState) -> Self {
+        Self { state }
+    }
+
+    /// Check the API key and rate limit of a request like the REST middleware does, and
+    /// turn it into a REST request within the size limit
+    fn admit(&self, request: Request<proto::ExecuteRequest>) -> Result<ExecuteRequest, Status> {
+        let client = if self.state.api_keys.is_empty() {
+            request
+                .remote_addr()
+                .map_or_else(|| "unknown".to_string(), |addr| format!("ip:{}", addr.ip()))
+        } else {
+            let key = request
+                .metadata()
+                .get("authorization")
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.strip_prefix("Bearer "))
+                .map(str::trim)
+                .filter(|key| self.state.api_keys.contains(key));
+            match key {
+                Some(key) => format!("key:{}", key),
+                None => return Err(status(ServerError::Unauthorized)),
+            }
+        };
+        if let Some(limiter) = &self.state.rate_limiter {
+            limiter.check(&client).map_err(|wait| {
+                status(ServerError::RateLimited {
+                    retry_after: wait.as_secs_f64().ceil() as u64,
+                })
+            })?;
+        }
+
+        let mut payload = ExecuteRequest::from(request.into_inner());
+        self.state
+            .service
+            .check_source_size(
+                &payload.code,
+                payload.files.as_deref().unwrap_or_default(),
+                payload.fixtures.as_deref().unwrap_or_default(),
+                payload.input.as_deref(),
+            )
+            .map_err(|e| status(e.into()))?;
+        limit_timeout(&self.state, &mut payload).map_err(status)?;
+        Ok(payload)
+    }
+}
+
+#[tonic::async_trait]
+impl CodeExec for GrpcService {
+    async fn execute(
+        &self,
+        request: Request<proto::ExecuteRequest>,
+    ) -> Result<Response<proto::ExecuteResponse>, Status> {
+        let payload = self.admit(request)?;
+        let response = run_execution(
+            &self.state.service,
+            payload,
+            Uuid::new_v4(),
+            CancellationToken::new(),
+        )
+        .await
+        .map_err(status)?;
+        Ok(Response::new(response.into()))
+    }
+
+    type ExecuteStreamStream = ReceiverStream<Result<ExecuteEvent, Status>>;
+
+    async fn execute_stream(
+        &self,
+        request: Request<proto::ExecuteRequest>,
+    ) -> Result<Response<Self::ExecuteStreamStream>, Status> {
+        let payload = self.admit(request)?;
+        let service = self.state.service.clone();
+        let execution_id = Uuid::new_v4();
+        let (events, receiver) = mpsc::channel(EVENT_BUFFER);
+
+        tokio::spawn(async move {
+            let started = Event::Started(proto::Started {
+                execution_id: execution_id.to_string(),
+            });
+            if events.send(Ok(event(started))).await.is_err() {
+                return;
+            }
+
+            // A client that goes away cancels the execution
+            let cancel = CancellationToken::new();
+            let execution = run_execution(&service, payload, execution_id, cancel.clone());
+            tokio::pin!(execution);
+            let result = tokio::select! {
+                result = &mut execution => result,
+                _ = events.closed() => {
+                    cancel.cancel();
+                    let _ = execution.await;
+                    return;
+                }
+            };
+
+            let mut response = match result {
+                Ok(response) => response,
+                Err(e) => {
+                    let _ = events.send(Err(status(e))).await;
+                    return;
+                }
+            };
+            // Output is collected when the program ends, and sent before the result
+            let stdout = std::mem::take(&mut response.stdout);
+            let stderr = std::mem::take(&mut response.stderr);
+            let output = output_events(Stream::Stdout, &stdout)
+                .chain(output_events(Stream::Stderr, &stderr));
+            for e in output.chain([Event::Finished(response.into())]) {
+                if events.send(Ok(event(e))).await.is_err() {
+                    return;
+                }
+            }
+        });
+
+        Ok(Response::new(ReceiverStream::new(receiver)))
+    }
+}
+
+fn event(event: Event) -> ExecuteEvent {
+    ExecuteEvent { event: Some(event) }
+}
+
+/// `Output` events carrying `data` in pieces of at most [`OUTPUT_CHUNK_SIZE`] bytes
+fn output_events(stream: Stream, data: &str) -> impl Iterator<Item = Event> + '_ {
+    let mut rest = data;
+    std::iter::from_fn(move || {
+        if rest.is_empty() {
+            return None;
+        }
+        let mut end = rest.len().min(OUTPUT_CHUNK_SIZE);
+        while !rest.is_char_boundary(end) {
+            end -= 1;
+        }
+        let (chunk, tail) = rest.split_at(end);
+        rest = tail;
+        Some(Event::Output(proto::Output {
+            stream: stream as i32,
+            data: chunk.to_string(),
+        }))
+    })
+}
+
+/// gRPC status of an error, closest to the HTTP status the REST API answers with
+fn status(error: ServerError) -> Status {
+    let code = match error.status() {
+        StatusCode::BAD_REQUEST | StatusCode::PAYLOAD_TOO_LARGE => Code::InvalidArgument,
+        StatusCode::UNAUTHORIZED => Code::Unauthenticated,
+        StatusCode::FORBIDDEN => Code::PermissionDenied,
+        StatusCode::CONFLICT => Code::AlreadyExists,
+        StatusCode::TOO_MANY_REQUESTS => Code::ResourceExhausted,
+        StatusCode::GATEWAY_TIMEOUT => Code::DeadlineExceeded,
+        _ => Code::Internal,
+    };
+    let body = error.body();
+    let message = body["error"].as_str().unwrap_or_default().to_string();
+    Status::with_details(code, message, body.to_string().into_bytes().into())
+}
+
+fn millis(duration: Duration) -> u64 {
+    duration.as_millis() as u64
+}
+
+impl From<proto::ExecuteRequest> for ExecuteRequest {
+    fn from(request: proto::ExecuteRequest) -> Self {
+        let files = request
+            .files
+            .into_iter()
+            .map(|file| SourceFile {
+                path: file.path,
+                contents: file.contents,
+            })
+            .collect();
+        let fixtures = request
+            .fixtures
+            .into_iter()
+            .map(|file| SourceFile {
+                path: file.path,
+                contents: file.contents,
+            })
+            .collect();
+        let dependencies = request
+            .dependencies
+            .into_iter()
+            .map(|dependency| Dependency {
+                name: dependency.name,
+                version: dependency.version,
+                source: dependency.source,
+            })
+            .collect();
+
+        Self {
+            language: request.language,
+            code: request.code,
+            files: Some(files),
+            fixtures: Some(fixtures),
+            entrypoint: request.entrypoint,
+            input: request.input,
+            args: Some(request.args),
+            capture_outputs: Some(request.capture_outputs),
+            timeout: request.timeout,
+            dependencies: Some(dependencies),
+            env_vars: Some(request.env_vars),
+            deterministic: Some(request.deterministic),
+            ..Default::default()
+        }
+    }
+}
+
+impl From<ExecuteResponse> for proto::ExecuteResponse {
+    fn from(response: ExecuteResponse) -> Self {
+        let stats = response.process_stats;
+        Self {
+            execution_id: response.execution_id.to_string(),
+            stdout: response.stdout,
+            stderr: response.stderr,
+            status: response.status.to_string(),
+            process_stats: Some(proto::ProcessStats {
+                max_rss: stats.max_rss,
+                execution_time_ms: millis(stats.execution_time),
+                user_time_ms: millis(stats.user_time),
+                system_time_ms: millis(stats.system_time),
+                compile_time_ms: stats.compile_time.map(millis),
+                dependency_install_time_ms: stats.dependency_install_time.map(millis),
+                peak_disk_bytes: stats.peak_disk_bytes,
+            }),
+            output_files: response
+                .output_files
+                .into_iter()
+                .map(|file| proto::OutputFile {
+                    path: file.path,
+                    content_base64: file.content_base64,
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create_state, router, ServerConfig};
+    use axum::body::Body;
+    use proto::code_exec_client::CodeExecClient;
+    use tokio::net::TcpListener;
+    use tokio_stream::wrappers::TcpListenerStream;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn test_execute_matches_rest() {
+        let state = create_state(ServerConfig {
+            max_concurrent_executions: 2,
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to create state");
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = Server::builder()
+            .add_service(CodeExecServer::new(GrpcService::new(state.clone())))
+            .serve_with_incoming(TcpListenerStream::new(listener));
+        tokio::spawn(server);
+
+        let request = proto::ExecuteRequest {
+            language: "python".to_string(),
+            code: r#"print("Hello, gRPC!")"#.to_string(),
+            timeout: Some(5),
+            ..Default::default()
+        };
+
+        let mut client = CodeExecClient::connect(format!("http://{}", addr))
+            .await
+            .unwrap();
+        let grpc = client.execute(request.clone()).await.unwrap().into_inner();
+
+        let body = serde_json::to_vec(&ExecuteRequest::from(request)).unwrap();
+        let response = router(state)
+            .oneshot(
+                axum::http::Request::builder()
+                    .method("POST")
+                    .uri("/execute")
+                    .header("content-type", "application/json")
+                    .body(Body::from(body))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let rest: ExecuteResponse = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(grpc.stdout, "Hello, gRPC!\n");
+        assert_eq!(grpc.stdout, rest.stdout);
+        assert_eq!(grpc.stderr, rest.stderr);
+        assert_eq!(grpc.status, rest.status.to_string());
+    }
+
+    #[test]
+    fn test_output_events_split_on_char_boundaries() {
+        let data = "é".repeat(OUTPUT_CHUNK_SIZE);
+        let chunks: Vec<_> = output_events(Stream::Stdout, &data)
+            .map(|event| match event {
+                Event::Output(output) => output.data,
+                _ => unreachable!(),
+            })
+            .collect();
+        assert_eq!(chunks.len(), 2);
+        assert!(chunks.iter().all(|chunk| chunk.len() <= OUTPUT_CHUNK_SIZE));
+        assert_eq!(chunks.concat(), data);
+    }
+}
@@ -0,0 +1,184 @@
+//! Replay of `/execute` responses for retried requests
+//!
+//! A client sending an `Idempotency-Key` header gets the response of the first request
+//! with that key back on every retry within the TTL, instead of running the code again.
+//! Keys are scoped to the client that sent them, so one client cannot read another's
+//! responses by guessing its keys.
+
+use sha2::{Digest, Sha256};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tokio::sync::OnceCell;
+
+use crate::{ExecuteResponse, ServerError};
+
+/// Header carrying the client-chosen key
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+
+/// Keys remembered at once by default, the least recently used are dropped beyond that
+pub const DEFAULT_MAX_ENTRIES: usize = 10_000;
+
+struct Entry {
+    /// SHA-256 of the request the key was first used with
+    request_hash: [u8; 32],
+    /// Set once the first execution with this key succeeds
+    response: Arc<OnceCell<ExecuteResponse>>,
+    expires_at: Instant,
+    last_used: Instant,
+}
+
+/// Responses cached by client and idempotency key
+pub struct IdempotencyCache {
+    ttl: Duration,
+    max_entries: usize,
+    entries: Mutex<HashMap<(String, String), Entry>>,
+}
+
+impl IdempotencyCache {
+    /// Create a cache keeping responses for `ttl`, for at most `max_entries` keys
+    pub fn new(ttl: Duration, max_entries: usize) -> Self {
+        Self {
+            ttl,
+            max_entries: max_entries.max(1),
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Run `execute` once per client, key and request, returning the cached response on
+    /// repeats.
+    ///
+    /// Concurrent requests with the same key wait for the first one to finish. Failed
+    /// executions are not cached, so a retry after an error runs the code again.
+    pub async fn get_or_execute<F, Fut>(
+        &self,
+        client: &str,
+        key: &str,
+        request: &[u8],
+        execute: F,
+    ) -> Result<ExecuteResponse, ServerError>
+    where
+        F: FnOnce() -> Fut,
+        Fut: std::future::Future<Output = Result<ExecuteResponse, ServerError>>,
+    {
+        let request_hash: [u8; 32] = Sha256::digest(request).into();
+        let response = {
+            let mut entries = self.entries.lock().unwrap();
+            let now = Instant::now();
+            entries.retain(|_, entry| entry.expires_at > now);
+
+            let id = (client.to_string(), key.to_string());
+            if !entries.contains_key(&id) && entries.len() >= self.max_entries {
+                // An evicted key still in flight only loses its replay, its waiters keep
+                // their handle on the response
+                if let Some(oldest) = entries
+                    .iter()
+                    .min_by_key(|(_, entry)| entry.last_used)
+                    .map(|(id, _)| id.clone())
+                {
+                    entries.remove(&oldest);
+                }
+            }
+
+            let entry = entries.entry(id).or_insert_with(|| Entry {
+                request_hash,
+                response: Arc::new(OnceCell::new()),
+                expires_at: now + self.ttl,
+                last_used: now,
+            });
+            if entry.request_hash != request_hash {
+                return Err(ServerError::IdempotencyKeyReused(key.to_string()));
+            }
+            entry.last_used = now;
+            entry.response.clone()
+        };
+
+        response.get_or_try_init(execute).await.cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    const CLIENT: &str = "ip:127.0.0.1";
+
+    fn response(stdout: &str) -> ExecuteResponse {
+        ExecuteResponse {
+            execution_id: uuid::Uuid::new_v4(),
+            stdout: stdout.to_string(),
+            stderr: String::new(),
+            stdout_base64: None,
+            stderr_base64: None,
+            status: code_exec::ExecutionStatus::Success,
+            process_stats: Default::default(),
+            install_diagnostics: None,
+            sandbox_listing: Vec::new(),
+            output_files: Vec::new(),
+        }
+    }
+
+    async fn run(
+        cache: &IdempotencyCache,
+        runs: &AtomicUsize,
+        client: &str,
+        key: &str,
+        request: &[u8],
+    ) -> Result<ExecuteResponse, ServerError> {
+        cache
+            .get_or_execute(client, key, request, || async {
+                let run = runs.fetch_add(1, Ordering::SeqCst) + 1;
+                Ok(response(&run.to_string()))
+            })
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_keys_are_scoped_to_the_client() {
+        let cache = IdempotencyCache::new(Duration::from_secs(60), 10);
+        let runs = AtomicUsize::new(0);
+
+        let first = run(&cache, &runs, "key:a", "retry", b"body").await.unwrap();
+        let repeat = run(&cache, &runs, "key:a", "retry", b"body").await.unwrap();
+        let other = run(&cache, &runs, "key:b", "retry", b"body").await.unwrap();
+
+        assert_eq!(first.stdout, "1");
+        assert_eq!(repeat.stdout, "1");
+        assert_eq!(other.stdout, "2");
+        assert!(matches!(
+            run(&cache, &runs, "key:a", "retry", b"changed").await,
+            Err(ServerError::IdempotencyKeyReused(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_least_recently_used_key_is_evicted() {
+        let cache = IdempotencyCache::new(Duration::from_secs(60), 2);
+        let runs = AtomicUsize::new(0);
+
+        run(&cache, &runs, CLIENT, "a", b"body").await.unwrap();
+        run(&cache, &runs, CLIENT, "b", b"body").await.unwrap();
+        // Touching "a" leaves "b" as the one to go when "c" arrives
+        run(&cache, &runs, CLIENT, "a", b"body").await.unwrap();
+        run(&cache, &runs, CLIENT, "c", b"body").await.unwrap();
+        assert_eq!(runs.load(Ordering::SeqCst), 3);
+
+        let a = run(&cache, &runs, CLIENT, "a", b"body").await.unwrap();
+        let b = run(&cache, &runs, CLIENT, "b", b"body").await.unwrap();
+        assert_eq!(a.stdout, "1");
+        assert_eq!(b.stdout, "4");
+    }
+
+    #[tokio::test]
+    async fn test_expired_keys_run_again() {
+        let cache = IdempotencyCache::new(Duration::ZERO, 10);
+        let runs = AtomicUsize::new(0);
+
+        run(&cache, &runs, CLIENT, "a", b"body").await.unwrap();
+        run(&cache, &runs, CLIENT, "a", b"body").await.unwrap();
+        assert_eq!(runs.load(Ordering::SeqCst), 2);
+    }
+}
@@ -1,92 +1,558 @@
 use axum::{
-    extract::State,
-    http::StatusCode,
+    extract::{DefaultBodyLimit, Path, State},
+    http::{
+        header::{AUTHORIZATION, CONTENT_TYPE, RETRY_AFTER},
+        Extensions, HeaderMap, HeaderName, HeaderValue, Method, StatusCode,
+    },
+    middleware,
     response::{IntoResponse, Response},
     routing::{get, post},
     Json, Router,
 };
 use code_exec::{
-    CodeExecutionService, Dependency, ExecutionRequest, ExecutionResult, Language, ProcessStats,
-    ResourceLimits,
+    CancellationToken, CodeExecutionService, CustomLanguage, Dependency, DependencyCacheConfig,
+    ExecutionMode, ExecutionRequest, ExecutionStatus, InjectedCredential, InputStep, JsRuntime,
+    Language, OsvScanner, OutputEncoding, OutputFile, PolicyRule, ProcessStats, RegexPolicy,
+    ResourceLimits, SandboxEntry, ServiceStats, SourceFile, DEFAULT_MAX_SOURCE_BYTES,
 };
 use serde::{Deserialize, Serialize};
 use serde_json::json;
-use std::{collections::HashMap, net::SocketAddr, sync::Arc, time::Duration};
+use std::{collections::HashMap, net::SocketAddr, path::PathBuf, sync::Arc, time::Duration};
 use thiserror::Error;
 use tokio::net::TcpListener;
-use tower_http::{cors::CorsLayer, trace::TraceLayer};
-use tracing::{error, info};
+use tower_http::{
+    cors::{AllowOrigin, CorsLayer},
+    trace::TraceLayer,
+};
+use tracing::{error, field::Empty, info, info_span, Instrument, Span};
+use uuid::Uuid;
+
+use crate::{
+    auth::{require_api_key, ApiKeys},
+    executions::{ExecutionState, Executions},
+    idempotency::{IdempotencyCache, DEFAULT_MAX_ENTRIES, IDEMPOTENCY_KEY_HEADER},
+    ratelimit::{client_id, rate_limit, RateLimiter},
+    readiness::Readiness,
+};
+
+pub mod auth;
+pub mod cli;
+pub mod config;
+pub mod executions;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+pub mod idempotency;
+pub mod ratelimit;
+pub mod readiness;
+pub mod telemetry;
+
+/// Request body allowance for everything besides `code` and `input` (bytes)
+const REQUEST_BODY_OVERHEAD: usize = 1024 * 1024; // 1MB
+
+/// Seconds a program may run when its request sets no `timeout`
+const DEFAULT_TIMEOUT: u64 = 30;
 
 #[derive(Debug, Error)]
 pub enum ServerError {
     #[error("Invalid language: {0}")]
     InvalidLanguage(String),
+    #[error("Could not detect the language of the code")]
+    UndetectedLanguage,
+    #[error("Ambiguous language, candidates: {0:?}")]
+    AmbiguousLanguage(Vec<Language>),
+    #[error("Idempotency key {0:?} was already used with a different request")]
+    IdempotencyKeyReused(String),
+    #[error("Invalid request: {0}")]
+    InvalidRequest(String),
+    #[error("Missing or invalid API key")]
+    Unauthorized,
+    #[error("Rate limit exceeded, retry in {retry_after}s")]
+    RateLimited { retry_after: u64 },
+    #[error("Too many background executions are running or waiting to be collected")]
+    TooManyExecutions,
     #[error("Execution error: {0}")]
     ExecutionError(#[from] code_exec::Error),
     #[error("Server error: {0}")]
     ServerError(String),
 }
 
-impl IntoResponse for ServerError {
-    fn into_response(self) -> Response {
-        let (status, message) = match self {
-            ServerError::InvalidLanguage(_) => (StatusCode::BAD_REQUEST, self.to_string()),
-            ServerError::ExecutionError(_) => (StatusCode::BAD_REQUEST, self.to_string()),
-            ServerError::ServerError(_) => (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "Internal server error".to_string(),
-            ),
+impl ServerError {
+    /// Stable identifier of the kind of error, returned as `code` in the response body
+    pub fn code(&self) -> &'static str {
+        match self {
+            ServerError::InvalidLanguage(_) => "invalid_language",
+            ServerError::UndetectedLanguage => "undetected_language",
+            ServerError::AmbiguousLanguage(_) => "ambiguous_language",
+            ServerError::IdempotencyKeyReused(_) => "idempotency_key_reused",
+            ServerError::InvalidRequest(_) => "invalid_request",
+            ServerError::Unauthorized => "unauthorized",
+            ServerError::RateLimited { .. } => "rate_limited",
+            ServerError::TooManyExecutions => "too_many_executions",
+            ServerError::ExecutionError(e) => e.code(),
+            ServerError::ServerError(_) => "internal_error",
+        }
+    }
+
+    /// Fields describing the error further, returned as `details` in the response body
+    fn details(&self) -> serde_json::Map<String, serde_json::Value> {
+        let mut details = serde_json::Map::new();
+        match self {
+            ServerError::InvalidLanguage(language) => {
+                details.insert("language".into(), json!(language));
+            }
+            ServerError::AmbiguousLanguage(candidates) => {
+                details.insert("candidates".into(), json!(candidates));
+            }
+            ServerError::RateLimited { retry_after } => {
+                details.insert("retry_after".into(), json!(retry_after));
+            }
+            ServerError::ExecutionError(e) => execution_error_details(e, &mut details),
+            _ => {}
+        }
+        details
+    }
+}
+
+fn execution_error_details(
+    error: &code_exec::Error,
+    details: &mut serde_json::Map<String, serde_json::Value>,
+) {
+    if let Some(kind) = error.resource_kind() {
+        details.insert("resource".into(), json!(kind));
+    }
+    match error {
+        code_exec::Error::WithSandboxListing { error, listing } => {
+            details.insert("sandbox_listing".into(), json!(listing));
+            execution_error_details(error, details);
+        }
+        code_exec::Error::WithKeptSandbox { error, path } => {
+            details.insert("sandbox_path".into(), json!(path));
+            execution_error_details(error, details);
+        }
+        code_exec::Error::WallTimeout(seconds)
+        | code_exec::Error::InstallTimeout(seconds)
+        | code_exec::Error::CompilationTimeout(seconds) => {
+            let stage = match error {
+                code_exec::Error::InstallTimeout(_) => "install",
+                code_exec::Error::CompilationTimeout(_) => "compile",
+                _ => "run",
+            };
+            details.insert("stage".into(), json!(stage));
+            details.insert("seconds".into(), json!(seconds));
+        }
+        code_exec::Error::PolicyRejected { rule } => {
+            details.insert("rule".into(), json!(rule));
+        }
+        code_exec::Error::BannedImport { module } => {
+            details.insert("module".into(), json!(module));
+        }
+        code_exec::Error::VulnerableDependency { package, advisory } => {
+            details.insert("package".into(), json!(package));
+            details.insert("advisory".into(), json!(advisory));
+        }
+        code_exec::Error::OfflineDependencyMissing { package } => {
+            details.insert("package".into(), json!(package));
+        }
+        code_exec::Error::DependencyConflict { package, versions } => {
+            details.insert("package".into(), json!(package));
+            details.insert("versions".into(), json!(versions));
+        }
+        code_exec::Error::ResourceExceeded {
+            limit, observed, ..
+        } => {
+            details.insert("limit".into(), json!(limit));
+            details.insert("observed".into(), json!(observed));
+        }
+        code_exec::Error::SourceTooLarge { field, size, limit } => {
+            details.insert("field".into(), json!(field));
+            details.insert("size".into(), json!(size));
+            details.insert("limit".into(), json!(limit));
+        }
+        code_exec::Error::InvalidOutput { stream, offset } => {
+            details.insert("stream".into(), json!(stream));
+            details.insert("offset".into(), json!(offset));
+        }
+        _ => {}
+    }
+}
+
+impl ServerError {
+    /// HTTP status the error is answered with
+    pub fn status(&self) -> StatusCode {
+        match self {
+            ServerError::InvalidLanguage(_)
+            | ServerError::UndetectedLanguage
+            | ServerError::AmbiguousLanguage(_)
+            | ServerError::InvalidRequest(_) => StatusCode::BAD_REQUEST,
+            ServerError::Unauthorized => StatusCode::UNAUTHORIZED,
+            ServerError::RateLimited { .. } | ServerError::TooManyExecutions => {
+                StatusCode::TOO_MANY_REQUESTS
+            }
+            ServerError::IdempotencyKeyReused(_) => StatusCode::CONFLICT,
+            ServerError::ExecutionError(code_exec::Error::SourceTooLarge { .. }) => {
+                StatusCode::PAYLOAD_TOO_LARGE
+            }
+            ServerError::ExecutionError(
+                code_exec::Error::PolicyRejected { .. }
+                | code_exec::Error::BannedImport { .. }
+                | code_exec::Error::VulnerableDependency { .. },
+            ) => StatusCode::FORBIDDEN,
+            ServerError::ExecutionError(
+                code_exec::Error::InstallTimeout(_) | code_exec::Error::CompilationTimeout(_),
+            ) => StatusCode::GATEWAY_TIMEOUT,
+            ServerError::ExecutionError(_) => StatusCode::BAD_REQUEST,
+            ServerError::ServerError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// Response body, with the message, `code` and `details` if there are any
+    fn body(&self) -> serde_json::Value {
+        // Internal errors may name paths or hosts of the server
+        let message = match self {
+            ServerError::ServerError(_) => "Internal server error".to_string(),
+            _ => self.to_string(),
         };
+        let details = self.details();
+
+        let mut body = json!({ "error": message, "code": self.code() });
+        if !details.is_empty() {
+            body["details"] = details.into();
+        }
+        body
+    }
+}
 
-        (status, Json(json!({ "error": message }))).into_response()
+impl IntoResponse for ServerError {
+    fn into_response(self) -> Response {
+        let mut response = (self.status(), Json(self.body())).into_response();
+        if let ServerError::RateLimited { retry_after } = self {
+            response
+                .headers_mut()
+                .insert(RETRY_AFTER, HeaderValue::from(retry_after));
+        }
+        response
     }
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Default, Deserialize, Serialize)]
 pub struct ExecuteRequest {
+    /// Language name, or `auto` to detect it from the code
     pub language: String,
+    /// Program to run, left empty when `entrypoint` names one of `files`
+    #[serde(default)]
     pub code: String,
+    pub files: Option<Vec<SourceFile>>,
+    /// Data files written to the program's working directory, never compiled or run
+    pub fixtures: Option<Vec<SourceFile>>,
+    pub entrypoint: Option<String>,
     pub input: Option<String>,
+    /// Input written step by step, each once the program printed its prompt
+    pub input_script: Option<Vec<InputStep>>,
+    pub args: Option<Vec<String>>,
+    /// Paths or globs of files the program writes to return with the response
+    pub capture_outputs: Option<Vec<String>>,
+    /// Seconds the program may run, 30 if unset
     pub timeout: Option<u64>,
     pub dependencies: Option<Vec<Dependency>>,
     pub env_vars: Option<HashMap<String, String>>,
+    pub include_sandbox_listing: Option<bool>,
+    /// Return the package manager's output as `install_diagnostics`
+    pub include_install_diagnostics: Option<bool>,
+    pub injected_credentials: Option<Vec<InjectedCredential>>,
+    pub deterministic: Option<bool>,
+    /// `node` (default) or `deno`, for JavaScript and TypeScript
+    pub js_runtime: Option<JsRuntime>,
+    /// `run` (default), or `compile_only` to only check that the program compiles
+    pub mode: Option<ExecutionMode>,
+    /// `lossy` (default), `strict` to fail on output that is not UTF-8, or `base64`
+    pub output_encoding: Option<OutputEncoding>,
+    /// Commands to build and run the program with, for the `custom` language
+    pub custom: Option<CustomLanguage>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ExecuteResponse {
+    /// Identifier shared by every log line of this execution
+    pub execution_id: Uuid,
     pub stdout: String,
     pub stderr: String,
-    pub status: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stdout_base64: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stderr_base64: Option<String>,
+    pub status: ExecutionStatus,
     pub process_stats: ProcessStats,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub install_diagnostics: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sandbox_listing: Vec<SandboxEntry>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub output_files: Vec<OutputFile>,
+}
+
+/// Body of `POST /warmup`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WarmupRequest {
+    pub language: String,
+    pub dependencies: Option<Vec<Dependency>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WarmupResponse {
+    pub language: Language,
+    /// Whether the dependency set was already in the dependency cache
+    pub cache_hit: bool,
+    pub dependency_install_time_ms: Option<u64>,
+    pub duration_ms: u64,
 }
 
 #[derive(Clone)]
 pub struct AppState {
     service: Arc<CodeExecutionService>,
+    idempotency: Arc<IdempotencyCache>,
+    readiness: Arc<Readiness>,
+    executions: Arc<Executions>,
+    api_keys: Arc<ApiKeys>,
+    rate_limiter: Option<Arc<RateLimiter>>,
+    cors: CorsLayer,
+    max_timeout: Option<u64>,
+    reject_over_max_timeout: bool,
 }
 
-pub async fn create_app(
-    max_concurrent_executions: usize,
-    resource_limits: ResourceLimits,
-) -> Result<Router, ServerError> {
-    let service = CodeExecutionService::new(max_concurrent_executions, resource_limits)
-        .await
-        .map_err(ServerError::ExecutionError)?;
+/// Settings of the HTTP server
+pub struct ServerConfig {
+    pub max_concurrent_executions: usize,
+    /// Executions of a language that may run at once, only the global limit applies to
+    /// languages left out
+    pub language_concurrency: HashMap<Language, usize>,
+    /// Modules programs must not import, per language; importing one gets 403
+    pub banned_imports: HashMap<Language, Vec<String>>,
+    /// Directories searched for a language's commands before the system's
+    pub toolchain_paths: HashMap<Language, Vec<PathBuf>>,
+    pub resource_limits: ResourceLimits,
+    /// Cache of installed dependencies, disabled if `None`
+    pub dependency_cache: Option<DependencyCacheConfig>,
+    /// How long a response is replayed for retries with the same Idempotency-Key
+    pub idempotency_ttl: Duration,
+    /// Languages whose toolchains must be installed for `/ready` to succeed
+    pub required_languages: Vec<Language>,
+    /// Keys accepted on the execution endpoints, which are open if there are none
+    pub api_keys: ApiKeys,
+    /// Executions each client may start per minute, unlimited if `None`
+    pub requests_per_minute: Option<u32>,
+    /// Accept shell scripts
+    pub allow_shell: bool,
+    /// Programs custom language requests may run, which are rejected if there are none
+    pub custom_programs: Vec<String>,
+    /// Toolchain versions pinned per language
+    pub language_versions: HashMap<Language, String>,
+    /// Largest `code`, `fixtures` and `input` accepted, larger requests get 413
+    pub max_source_bytes: usize,
+    /// Patterns that get a submission refused with 403 before it runs
+    pub policy_rules: Vec<PolicyRule>,
+    /// OSV compatible API dependencies are checked against, unchecked if `None`
+    pub advisory_url: Option<String>,
+    /// Refuse dependencies the advisory API cannot check, such as version ranges
+    pub require_exact_versions: bool,
+    /// Never download dependencies or toolchains
+    pub offline: bool,
+    /// Cargo target directory shared by Rust builds, each sandbox builds in its own if `None`
+    pub rust_target_dir: Option<PathBuf>,
+    /// How long the sandbox of a failed execution is kept, removed right away if `None`
+    pub keep_failed_sandboxes: Option<Duration>,
+    /// Directory, relative to the sandbox, request fixtures are written to; its root if `None`
+    pub fixtures_dir: Option<PathBuf>,
+    /// Origins browsers may call the API from, `*` allowing any; none if empty
+    pub allowed_origins: Vec<String>,
+    /// Longest `timeout` a request may ask for in seconds, longer ones are lowered to it
+    pub max_timeout: Option<u64>,
+    /// Refuse requests asking for more than `max_timeout` with 400 instead
+    pub reject_over_max_timeout: bool,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            max_concurrent_executions: 10,
+            language_concurrency: HashMap::new(),
+            banned_imports: HashMap::new(),
+            toolchain_paths: HashMap::new(),
+            resource_limits: ResourceLimits::default(),
+            dependency_cache: None,
+            idempotency_ttl: Duration::from_secs(600),
+            required_languages: Vec::new(),
+            api_keys: ApiKeys::default(),
+            requests_per_minute: None,
+            allow_shell: false,
+            custom_programs: Vec::new(),
+            language_versions: HashMap::new(),
+            max_source_bytes: DEFAULT_MAX_SOURCE_BYTES,
+            policy_rules: PolicyRule::builtin(),
+            advisory_url: None,
+            require_exact_versions: false,
+            offline: false,
+            rust_target_dir: None,
+            keep_failed_sandboxes: None,
+            fixtures_dir: None,
+            allowed_origins: Vec::new(),
+            max_timeout: None,
+            reject_over_max_timeout: false,
+        }
+    }
+}
+
+pub async fn create_app(config: ServerConfig) -> Result<Router, ServerError> {
+    Ok(router(create_state(config).await?))
+}
 
-    let state = AppState {
+/// Build the state shared by the REST API and, with the `grpc` feature, the gRPC one
+pub async fn create_state(config: ServerConfig) -> Result<AppState, ServerError> {
+    let policy = RegexPolicy::new(config.policy_rules).map_err(ServerError::ExecutionError)?;
+    let mut service =
+        CodeExecutionService::new(config.max_concurrent_executions, config.resource_limits)
+            .await
+            .map_err(ServerError::ExecutionError)?
+            .with_language_concurrency(config.language_concurrency)
+            .with_banned_imports(config.banned_imports)
+            .with_toolchain_paths(config.toolchain_paths)
+            .with_shell(config.allow_shell)
+            .with_custom_programs(config.custom_programs)
+            .with_max_source_bytes(config.max_source_bytes)
+            .with_submission_policy(Arc::new(policy));
+
+    if let Some(url) = &config.advisory_url {
+        let scanner = OsvScanner::new(url).with_require_exact(config.require_exact_versions);
+        service = service.with_dependency_scanner(Arc::new(scanner));
+    }
+    if let Some(cache) = config.dependency_cache {
+        service = service
+            .with_dependency_cache(cache)
+            .await
+            .map_err(ServerError::ExecutionError)?;
+    }
+    if let Some(target_dir) = config.rust_target_dir {
+        service = service
+            .with_rust_target_dir(target_dir)
+            .map_err(ServerError::ExecutionError)?;
+    }
+    if let Some(ttl) = config.keep_failed_sandboxes {
+        service = service.with_keep_failed_sandboxes(ttl);
+    }
+    if let Some(dir) = &config.fixtures_dir {
+        service = service.with_fixtures_dir(dir);
+    }
+    let service = service
+        .with_language_versions(config.language_versions)
+        .with_offline(config.offline);
+
+    Ok(AppState {
         service: Arc::new(service),
+        idempotency: Arc::new(IdempotencyCache::new(
+            config.idempotency_ttl,
+            DEFAULT_MAX_ENTRIES,
+        )),
+        readiness: Arc::new(Readiness::new(config.required_languages)),
+        executions: Arc::new(Executions::default()),
+        api_keys: Arc::new(config.api_keys),
+        rate_limiter: config
+            .requests_per_minute
+            .map(|limit| Arc::new(RateLimiter::new(limit))),
+        cors: cors_layer(&config.allowed_origins)?,
+        max_timeout: config.max_timeout,
+        reject_over_max_timeout: config.reject_over_max_timeout,
+    })
+}
+
+/// Hold a request's timeout to `max_timeout`, lowering it or refusing the request
+fn limit_timeout(state: &AppState, payload: &mut ExecuteRequest) -> Result<(), ServerError> {
+    let Some(max_timeout) = state.max_timeout else {
+        return Ok(());
     };
+    let timeout = payload.timeout.unwrap_or(DEFAULT_TIMEOUT);
+    if timeout <= max_timeout {
+        return Ok(());
+    }
+    // Only a timeout the client asked for is refused, not the default
+    if state.reject_over_max_timeout && payload.timeout.is_some() {
+        return Err(ServerError::InvalidRequest(format!(
+            "timeout of {}s is over the limit of {}s",
+            timeout, max_timeout
+        )));
+    }
+    payload.timeout = Some(max_timeout);
+    Ok(())
+}
 
-    let cors = CorsLayer::permissive();
+/// CORS for browsers calling from `origins`, limited to the methods and headers the API
+/// uses. Cross-origin requests get no CORS headers unless their origin is listed.
+fn cors_layer(origins: &[String]) -> Result<CorsLayer, ServerError> {
+    let allow_origin = if origins.iter().any(|origin| origin == "*") {
+        AllowOrigin::any()
+    } else {
+        let origins = origins
+            .iter()
+            .map(|origin| {
+                HeaderValue::from_str(origin).map_err(|_| {
+                    ServerError::ServerError(format!("Invalid CORS origin: {}", origin))
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        AllowOrigin::list(origins)
+    };
 
-    let app = Router::new()
-        .route("/health", get(health_check))
+    Ok(CorsLayer::new()
+        .allow_origin(allow_origin)
+        .allow_methods([Method::GET, Method::POST, Method::DELETE])
+        .allow_headers([
+            CONTENT_TYPE,
+            AUTHORIZATION,
+            HeaderName::from_static(IDEMPOTENCY_KEY_HEADER),
+        ])
+        .expose_headers([RETRY_AFTER]))
+}
+
+/// Routes of the REST API over `state`
+pub fn router(state: AppState) -> Router {
+    let cors = state.cors.clone();
+
+    // Room for code, fixtures and input at their limit, plus the rest of the request,
+    // so oversized sources reach the handlers and get a 413 naming the field
+    let body_limit = state
+        .service
+        .max_source_bytes()
+        .saturating_mul(3)
+        .saturating_add(REQUEST_BODY_OVERHEAD);
+
+    // Only starting an execution counts against a client's rate limit
+    let start = Router::new()
         .route("/execute", post(execute))
+        .route("/execute/async", post(execute_async))
+        .route("/warmup", post(warmup))
+        .route_layer(middleware::from_fn_with_state(state.clone(), rate_limit))
+        .layer(DefaultBodyLimit::max(body_limit));
+
+    // Probes stay open so orchestrators can check the server without a key
+    let executions = Router::new()
+        .merge(start)
+        .route(
+            "/execute/:id",
+            get(execution_status).delete(cancel_execution),
+        )
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            require_api_key,
+        ));
+
+    Router::new()
+        .route("/health", get(health_check))
+        .route("/ready", get(readiness_check))
+        .route("/healthz", get(readiness_check))
+        .route("/stats", get(stats))
+        .route("/languages", get(languages))
+        .merge(executions)
         .layer(TraceLayer::new_for_http())
         .layer(cors)
-        .with_state(state);
-
-    Ok(app)
+        .with_state(state)
 }
 
 pub async fn run_server(app: Router, addr: SocketAddr) -> Result<(), ServerError> {
@@ -95,9 +561,12 @@ pub async fn run_server(app: Router, addr: SocketAddr) -> Result<(), ServerError
         .await
         .map_err(|e| ServerError::ServerError(e.to_string()))?;
 
-    axum::serve(listener, app)
-        .await
-        .map_err(|e| ServerError::ServerError(e.to_string()))?;
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await
+    .map_err(|e| ServerError::ServerError(e.to_string()))?;
 
     Ok(())
 }
@@ -106,36 +575,256 @@ async fn health_check() -> &'static str {
     "OK"
 }
 
-async fn execute(
+/// Ready only if every required language has its toolchain installed
+async fn readiness_check(State(state): State<AppState>) -> Response {
+    let missing = state.readiness.missing_tools();
+    if missing.is_empty() {
+        (StatusCode::OK, Json(json!({ "status": "ready" }))).into_response()
+    } else {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(json!({ "status": "unavailable", "missing_tools": missing })),
+        )
+            .into_response()
+    }
+}
+
+async fn stats(State(state): State<AppState>) -> Json<ServiceStats> {
+    Json(state.service.stats())
+}
+
+/// Install a language's toolchain and a dependency set into the dependency cache ahead
+/// of traffic, without running any code
+async fn warmup(
     State(state): State<AppState>,
-    Json(payload): Json<ExecuteRequest>,
-) -> Result<Json<ExecuteResponse>, ServerError> {
+    Json(payload): Json<WarmupRequest>,
+) -> Result<Json<WarmupResponse>, ServerError> {
     let language: Language = payload
         .language
         .parse()
-        .map_err(|_| ServerError::InvalidLanguage(payload.language))?;
-
-    let request = ExecutionRequest {
+        .map_err(|_| ServerError::InvalidLanguage(payload.language.clone()))?;
+    let result = state
+        .service
+        .warmup(language, payload.dependencies.unwrap_or_default())
+        .await?;
+    Ok(Json(WarmupResponse {
         language,
-        code: payload.code,
-        input: payload.input,
-        timeout: Duration::from_secs(payload.timeout.unwrap_or(30)),
-        dependencies: payload.dependencies.unwrap_or_default(),
-        env_vars: payload.env_vars.unwrap_or_default(),
-    };
+        cache_hit: result.cache_hit,
+        dependency_install_time_ms: result
+            .dependency_install_time
+            .map(|time| time.as_millis() as u64),
+        duration_ms: result.duration.as_millis() as u64,
+    }))
+}
 
-    let result = state
+/// Languages the server accepts, with the other names each can be requested by
+async fn languages(State(state): State<AppState>) -> Json<serde_json::Value> {
+    let languages: Vec<_> = state
         .service
-        .execute(request)
+        .accepted_languages()
+        .into_iter()
+        .map(|language| json!({ "name": language, "aliases": language.aliases() }))
+        .collect();
+    Json(json!({ "languages": languages }))
+}
+
+async fn execute(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    extensions: Extensions,
+    Json(mut payload): Json<ExecuteRequest>,
+) -> Result<Json<ExecuteResponse>, ServerError> {
+    state.service.check_source_size(
+        &payload.code,
+        payload.files.as_deref().unwrap_or_default(),
+        payload.fixtures.as_deref().unwrap_or_default(),
+        payload.input.as_deref(),
+    )?;
+    limit_timeout(&state, &mut payload)?;
+
+    let Some(key) = headers.get(IDEMPOTENCY_KEY_HEADER) else {
+        return run_execution(
+            &state.service,
+            payload,
+            Uuid::new_v4(),
+            CancellationToken::new(),
+        )
+        .await
+        .map(Json);
+    };
+
+    let key = key
+        .to_str()
+        .map_err(|_| ServerError::InvalidRequest("Idempotency-Key must be ASCII".to_string()))?;
+    let request =
+        serde_json::to_vec(&payload).map_err(|e| ServerError::ServerError(e.to_string()))?;
+    state
+        .idempotency
+        .get_or_execute(&client_id(&extensions), key, &request, || {
+            run_execution(
+                &state.service,
+                payload,
+                Uuid::new_v4(),
+                CancellationToken::new(),
+            )
+        })
         .await
-        .map_err(ServerError::ExecutionError)?;
+        .map(Json)
+}
 
-    Ok(Json(ExecuteResponse {
-        stdout: result.stdout,
-        stderr: result.stderr,
-        status: result.status.to_string(),
-        process_stats: result.process_stats,
-    }))
+/// Start an execution in the background and return its id right away
+async fn execute_async(
+    State(state): State<AppState>,
+    Json(mut payload): Json<ExecuteRequest>,
+) -> Result<(StatusCode, Json<serde_json::Value>), ServerError> {
+    state.service.check_source_size(
+        &payload.code,
+        payload.files.as_deref().unwrap_or_default(),
+        payload.fixtures.as_deref().unwrap_or_default(),
+        payload.input.as_deref(),
+    )?;
+    limit_timeout(&state, &mut payload)?;
+
+    let execution_id = Uuid::new_v4();
+    let cancel = state.executions.start(execution_id)?;
+
+    tokio::spawn(async move {
+        let result = run_execution(&state.service, payload, execution_id, cancel).await;
+        state.executions.finish(execution_id, result);
+    });
+
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(json!({ "execution_id": execution_id })),
+    ))
+}
+
+/// Report whether a background execution is still running, or hand over its result
+async fn execution_status(State(state): State<AppState>, Path(id): Path<Uuid>) -> Response {
+    match state.executions.take(id) {
+        Some(ExecutionState::Running) => (
+            StatusCode::ACCEPTED,
+            Json(json!({ "execution_id": id, "status": "running" })),
+        )
+            .into_response(),
+        Some(ExecutionState::Finished(result)) => match *result {
+            Ok(response) => Json(response).into_response(),
+            Err(e) => e.into_response(),
+        },
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+/// Cancel a background execution, killing its processes
+async fn cancel_execution(State(state): State<AppState>, Path(id): Path<Uuid>) -> StatusCode {
+    if state.executions.cancel(id) {
+        StatusCode::NO_CONTENT
+    } else {
+        StatusCode::NOT_FOUND
+    }
+}
+
+async fn run_execution(
+    service: &CodeExecutionService,
+    payload: ExecuteRequest,
+    execution_id: Uuid,
+    cancel: CancellationToken,
+) -> Result<ExecuteResponse, ServerError> {
+    // Filled in once the execution finishes, for traces exported with --otlp-endpoint
+    let span = info_span!(
+        "execution",
+        %execution_id,
+        language = %payload.language,
+        status = Empty,
+        error = Empty,
+        install_ms = Empty,
+        compile_ms = Empty,
+        run_ms = Empty,
+    );
+
+    async move {
+        let entrypoint = payload.entrypoint.as_ref().and_then(|entrypoint| {
+            payload
+                .files
+                .iter()
+                .flatten()
+                .find(|file| &file.path == entrypoint)
+        });
+        let program = entrypoint.map_or(payload.code.as_str(), |file| file.contents.as_str());
+        let language = resolve_language(&payload.language, program)?;
+
+        let request = ExecutionRequest {
+            language,
+            code: payload.code,
+            files: payload.files.unwrap_or_default(),
+            fixtures: payload.fixtures.unwrap_or_default(),
+            entrypoint: payload.entrypoint,
+            input: payload.input,
+            input_script: payload.input_script.unwrap_or_default(),
+            args: payload.args.unwrap_or_default(),
+            capture_outputs: payload.capture_outputs.unwrap_or_default(),
+            timeout: Duration::from_secs(payload.timeout.unwrap_or(DEFAULT_TIMEOUT)),
+            dependencies: payload.dependencies.unwrap_or_default(),
+            env_vars: payload.env_vars.unwrap_or_default(),
+            include_sandbox_listing: payload.include_sandbox_listing.unwrap_or(false),
+            include_install_diagnostics: payload.include_install_diagnostics.unwrap_or(false),
+            injected_credentials: payload.injected_credentials.unwrap_or_default(),
+            deterministic: payload.deterministic.unwrap_or(false),
+            js_runtime: payload.js_runtime.unwrap_or_default(),
+            mode: payload.mode.unwrap_or_default(),
+            output_encoding: payload.output_encoding.unwrap_or_default(),
+            custom: payload.custom,
+        };
+
+        let span = Span::current();
+        let result = service
+            .execute_cancellable(request, cancel)
+            .await
+            .inspect_err(|e| {
+                span.record("status", "error");
+                span.record("error", e.code());
+            })
+            .map_err(ServerError::ExecutionError)?;
+
+        let stats = &result.process_stats;
+        span.record("status", result.status.to_string());
+        if let Some(install_time) = stats.dependency_install_time {
+            span.record("install_ms", install_time.as_millis() as u64);
+        }
+        if let Some(compile_time) = stats.compile_time {
+            span.record("compile_ms", compile_time.as_millis() as u64);
+        }
+        span.record("run_ms", stats.execution_time.as_millis() as u64);
+
+        Ok(ExecuteResponse {
+            execution_id,
+            stdout: result.stdout,
+            stderr: result.stderr,
+            stdout_base64: result.stdout_base64,
+            stderr_base64: result.stderr_base64,
+            status: result.status,
+            process_stats: result.process_stats,
+            install_diagnostics: result.install_diagnostics,
+            sandbox_listing: result.sandbox_listing,
+            output_files: result.output_files,
+        })
+    }
+    .instrument(span)
+    .await
+}
+
+fn resolve_language(language: &str, code: &str) -> Result<Language, ServerError> {
+    if !language.eq_ignore_ascii_case("auto") {
+        return language
+            .parse()
+            .map_err(|_| ServerError::InvalidLanguage(language.to_string()));
+    }
+
+    match Language::detect(code).as_slice() {
+        [language] => Ok(*language),
+        [] => Err(ServerError::UndetectedLanguage),
+        candidates => Err(ServerError::AmbiguousLanguage(candidates.to_vec())),
+    }
 }
 
 #[cfg(test)]
@@ -146,9 +835,12 @@ mod tests {
 
     #[tokio::test]
     async fn test_health_check() {
-        let app = create_app(1, ResourceLimits::default())
-            .await
-            .expect("Failed to create app");
+        let app = create_app(ServerConfig {
+            max_concurrent_executions: 1,
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to create app");
 
         let response = app
             .oneshot(
@@ -164,18 +856,74 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_execute() {
-        let app = create_app(1, ResourceLimits::default())
+    async fn test_cors_allows_only_configured_origins() {
+        let app = create_app(ServerConfig {
+            max_concurrent_executions: 1,
+            allowed_origins: vec!["https://app.example.com".to_string()],
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to create app");
+
+        let preflight = |origin: &str| {
+            Request::builder()
+                .method("OPTIONS")
+                .uri("/execute")
+                .header("origin", origin)
+                .header("access-control-request-method", "POST")
+                .header("access-control-request-headers", "content-type")
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let response = app
+            .clone()
+            .oneshot(preflight("https://app.example.com"))
+            .await
+            .unwrap();
+        let headers = response.headers();
+        assert_eq!(
+            headers["access-control-allow-origin"],
+            "https://app.example.com"
+        );
+        let methods = headers["access-control-allow-methods"].to_str().unwrap();
+        assert!(methods.contains("POST"));
+        assert!(!methods.contains("PUT"));
+
+        let response = app
+            .oneshot(preflight("https://evil.example.com"))
             .await
-            .expect("Failed to create app");
+            .unwrap();
+        assert!(!response
+            .headers()
+            .contains_key("access-control-allow-origin"));
+    }
+
+    #[tokio::test]
+    async fn test_cors_rejects_invalid_origin() {
+        let result = create_app(ServerConfig {
+            max_concurrent_executions: 1,
+            allowed_origins: vec!["https://bad\norigin".to_string()],
+            ..Default::default()
+        })
+        .await;
+        assert!(matches!(result, Err(ServerError::ServerError(_))));
+    }
+
+    #[tokio::test]
+    async fn test_execute() {
+        let app = create_app(ServerConfig {
+            max_concurrent_executions: 1,
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to create app");
 
         let request = ExecuteRequest {
             language: "python".to_string(),
             code: r#"print("Hello, World!")"#.to_string(),
-            input: None,
             timeout: Some(5),
-            dependencies: None,
-            env_vars: None,
+            ..Default::default()
         };
 
         let response = app
@@ -201,6 +949,558 @@ mod tests {
 
         assert_eq!(result.stdout.trim(), "Hello, World!");
         assert!(result.stderr.is_empty());
-        assert_eq!(result.status, "success");
+        assert_eq!(result.status, ExecutionStatus::Success);
+    }
+    #[tokio::test]
+    async fn test_execute_auto_detects_python() {
+        let app = create_app(ServerConfig {
+            max_concurrent_executions: 1,
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to create app");
+
+        let request = ExecuteRequest {
+            language: "auto".to_string(),
+            code: "#!/usr/bin/env python3\nprint(\"Hello, World!\")".to_string(),
+            timeout: Some(5),
+            ..Default::default()
+        };
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/execute")
+                    .header("content-type", "application/json")
+                    .body(Body::from(serde_json::to_string(&request).unwrap()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let result: ExecuteResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(result.stdout.trim(), "Hello, World!");
+    }
+
+    async fn post_execute(app: &Router, request: &ExecuteRequest, key: &str) -> Response {
+        app.clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/execute")
+                    .header("content-type", "application/json")
+                    .header("idempotency-key", key)
+                    .body(Body::from(serde_json::to_string(request).unwrap()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_execute_idempotency_key_runs_once() {
+        let app = create_app(ServerConfig {
+            max_concurrent_executions: 1,
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to create app");
+
+        // Every run appends a line to a file outside the sandbox
+        let counter = tempfile::NamedTempFile::new().unwrap();
+        let request = ExecuteRequest {
+            language: "python".to_string(),
+            code: format!(
+                "open({:?}, 'a').write('run\\n')\nprint('done')",
+                counter.path().display().to_string()
+            ),
+            timeout: Some(5),
+            ..Default::default()
+        };
+
+        let first = post_execute(&app, &request, "retry-1").await;
+        assert_eq!(first.status(), StatusCode::OK);
+        let second = post_execute(&app, &request, "retry-1").await;
+        assert_eq!(second.status(), StatusCode::OK);
+
+        let body = axum::body::to_bytes(second.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let result: ExecuteResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(result.stdout.trim(), "done");
+        assert_eq!(std::fs::read_to_string(counter.path()).unwrap(), "run\n");
+
+        // The same key with a different body is a client bug, not a retry
+        let changed = ExecuteRequest {
+            input: Some("changed".to_string()),
+            input_script: None,
+            ..request
+        };
+        let conflict = post_execute(&app, &changed, "retry-1").await;
+        assert_eq!(conflict.status(), StatusCode::CONFLICT);
+        assert_eq!(std::fs::read_to_string(counter.path()).unwrap(), "run\n");
+    }
+
+    #[tokio::test]
+    async fn test_execute_returns_execution_id() {
+        let app = create_app(ServerConfig {
+            max_concurrent_executions: 1,
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to create app");
+
+        let request = ExecuteRequest {
+            language: "python".to_string(),
+            code: "print(1)".to_string(),
+            timeout: Some(5),
+            ..Default::default()
+        };
+        let response = post_execute(&app, &request, "execution-id").await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let execution_id = Uuid::parse_str(body["execution_id"].as_str().unwrap()).unwrap();
+        assert_eq!(execution_id.get_version_num(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_execute_rejects_oversized_code() {
+        let app = create_app(ServerConfig {
+            max_concurrent_executions: 1,
+            max_source_bytes: 1024,
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to create app");
+
+        let request = ExecuteRequest {
+            language: "python".to_string(),
+            code: format!("# {}\nprint(1)", "x".repeat(2048)),
+            timeout: Some(5),
+            ..Default::default()
+        };
+        let response = post_execute(&app, &request, "oversized").await;
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert!(body["error"].as_str().unwrap().contains("code"), "{}", body);
+        assert_eq!(body["code"], "source_too_large");
+        assert_eq!(body["details"]["field"], "code");
+
+        // Bodies far over the limit are refused before they are parsed
+        let request = ExecuteRequest {
+            code: "x".repeat(4 * 1024 * 1024),
+            ..request
+        };
+        let response = post_execute(&app, &request, "very-oversized").await;
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[tokio::test]
+    async fn test_execute_limits_timeout() {
+        let config = || ServerConfig {
+            max_concurrent_executions: 1,
+            max_timeout: Some(2),
+            ..Default::default()
+        };
+        let app = create_app(config()).await.expect("Failed to create app");
+
+        let request = ExecuteRequest {
+            language: "python".to_string(),
+            code: "import time\ntime.sleep(1.5)\nprint('done')".to_string(),
+            timeout: Some(1),
+            ..Default::default()
+        };
+        let result = |response: Response| async move {
+            assert_eq!(response.status(), StatusCode::OK);
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            serde_json::from_slice::<ExecuteResponse>(&body).unwrap()
+        };
+
+        // Under the ceiling, the request's own timeout applies
+        let response = post_execute(&app, &request, "under-ceiling").await;
+        assert_eq!(result(response).await.status, ExecutionStatus::Timeout);
+
+        // Over it, the timeout is lowered to the ceiling
+        let request = ExecuteRequest {
+            code: "import time\ntime.sleep(3)\nprint('done')".to_string(),
+            timeout: Some(600),
+            ..request
+        };
+        let started = std::time::Instant::now();
+        let response = post_execute(&app, &request, "over-ceiling").await;
+        assert_eq!(result(response).await.status, ExecutionStatus::Timeout);
+        assert!(started.elapsed() < Duration::from_secs(10));
+
+        // Unless the server is set to refuse such requests
+        let app = create_app(ServerConfig {
+            reject_over_max_timeout: true,
+            ..config()
+        })
+        .await
+        .expect("Failed to create app");
+        let response = post_execute(&app, &request, "rejected").await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["code"], "invalid_request", "{}", body);
+    }
+
+    #[tokio::test]
+    async fn test_execute_invalid_language_error_code() {
+        let app = create_app(ServerConfig {
+            max_concurrent_executions: 1,
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to create app");
+
+        let request = ExecuteRequest {
+            language: "cobol".to_string(),
+            code: "DISPLAY 'HELLO'.".to_string(),
+            timeout: Some(5),
+            ..Default::default()
+        };
+        let response = post_execute(&app, &request, "invalid-language").await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["code"], "invalid_language", "{}", body);
+        assert_eq!(body["details"]["language"], "cobol");
+    }
+
+    #[tokio::test]
+    async fn test_timeout_error_code() {
+        for (error, status, stage) in [
+            (
+                code_exec::Error::WallTimeout(5),
+                StatusCode::BAD_REQUEST,
+                "run",
+            ),
+            (
+                code_exec::Error::InstallTimeout(300),
+                StatusCode::GATEWAY_TIMEOUT,
+                "install",
+            ),
+            (
+                code_exec::Error::CompilationTimeout(300),
+                StatusCode::GATEWAY_TIMEOUT,
+                "compile",
+            ),
+        ] {
+            let response = ServerError::ExecutionError(error).into_response();
+            assert_eq!(response.status(), status);
+
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            assert_eq!(body["code"], "timeout", "{}", body);
+            assert_eq!(body["details"]["stage"], stage);
+            assert_eq!(body["details"]["resource"].is_null(), stage != "run");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_languages_lists_aliases() {
+        let app = create_app(ServerConfig {
+            max_concurrent_executions: 1,
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to create app");
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/languages")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let languages = body["languages"].as_array().unwrap();
+        let python = languages
+            .iter()
+            .find(|language| language["name"] == "python")
+            .unwrap();
+        assert!(python["aliases"].as_array().unwrap().contains(&json!("py")));
+        // Shell scripts are off by default
+        assert!(languages.iter().all(|language| language["name"] != "bash"));
+    }
+
+    #[tokio::test]
+    async fn test_warmup() {
+        let app = create_app(ServerConfig {
+            max_concurrent_executions: 1,
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to create app");
+
+        let warmup = |request: WarmupRequest| {
+            app.clone().oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/warmup")
+                    .header("content-type", "application/json")
+                    .body(Body::from(serde_json::to_string(&request).unwrap()))
+                    .unwrap(),
+            )
+        };
+
+        let response = warmup(WarmupRequest {
+            language: "py".to_string(),
+            dependencies: None,
+        })
+        .await
+        .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let result: WarmupResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(result.language, Language::Python);
+        assert!(!result.cache_hit);
+        assert_eq!(result.dependency_install_time_ms, None);
+
+        let response = warmup(WarmupRequest {
+            language: "cobol".to_string(),
+            dependencies: None,
+        })
+        .await
+        .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_ready_reports_missing_tools() {
+        let service = CodeExecutionService::new(1, ResourceLimits::default())
+            .await
+            .unwrap();
+        let state = AppState {
+            service: Arc::new(service),
+            idempotency: Arc::new(IdempotencyCache::new(Duration::from_secs(60), 10)),
+            executions: Arc::new(Executions::default()),
+            api_keys: Arc::new(ApiKeys::default()),
+            rate_limiter: None,
+            cors: CorsLayer::new(),
+            max_timeout: None,
+            reject_over_max_timeout: false,
+            readiness: Arc::new(Readiness::with_probe(
+                vec![Language::Python, Language::Rust],
+                |language| match language {
+                    Language::Rust => vec!["rustc".to_string()],
+                    _ => vec![],
+                },
+            )),
+        };
+
+        let response = router(state)
+            .oneshot(
+                Request::builder()
+                    .uri("/ready")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["missing_tools"], json!({ "rust": ["rustc"] }));
+    }
+
+    #[tokio::test]
+    async fn test_cancel_frees_slot() {
+        let app = create_app(ServerConfig {
+            max_concurrent_executions: 1,
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to create app");
+
+        let sleeper = ExecuteRequest {
+            language: "python".to_string(),
+            code: "import time\ntime.sleep(60)".to_string(),
+            timeout: Some(60),
+            ..Default::default()
+        };
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/execute/async")
+                    .header("content-type", "application/json")
+                    .body(Body::from(serde_json::to_string(&sleeper).unwrap()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let id = body["execution_id"].as_str().unwrap().to_string();
+
+        // Let the sleeper take the only slot
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("DELETE")
+                    .uri(format!("/execute/{}", id))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+        // The next execution gets the slot long before the sleeper would have finished
+        let request = ExecuteRequest {
+            language: "python".to_string(),
+            code: "print('next')".to_string(),
+            timeout: Some(5),
+            ..sleeper
+        };
+        let response = tokio::time::timeout(
+            Duration::from_secs(10),
+            post_execute(&app, &request, "after-cancel"),
+        )
+        .await
+        .expect("slot was not freed");
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri(format!("/execute/{}", id))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert!(body["error"].as_str().unwrap().contains("cancelled"));
+    }
+
+    #[tokio::test]
+    async fn test_execute_requires_api_key() {
+        let mut keys_file = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut keys_file, b"# clients\nsecret-key\n\nother-key\n").unwrap();
+        let app = create_app(ServerConfig {
+            max_concurrent_executions: 1,
+            api_keys: ApiKeys::from_file(keys_file.path()).unwrap(),
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to create app");
+
+        let request = ExecuteRequest {
+            language: "python".to_string(),
+            code: "print('authorized')".to_string(),
+            timeout: Some(5),
+            ..Default::default()
+        };
+        let post = |authorization: Option<&str>| {
+            let mut builder = Request::builder()
+                .method("POST")
+                .uri("/execute")
+                .header("content-type", "application/json");
+            if let Some(authorization) = authorization {
+                builder = builder.header("authorization", authorization);
+            }
+            app.clone().oneshot(
+                builder
+                    .body(Body::from(serde_json::to_string(&request).unwrap()))
+                    .unwrap(),
+            )
+        };
+
+        let response = post(Some("Bearer secret-key")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = post(None).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let response = post(Some("Bearer wrong-key")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        // Health checks do not need a key
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/health")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_execute_rate_limit() {
+        let app = create_app(ServerConfig {
+            max_concurrent_executions: 2,
+            requests_per_minute: Some(2),
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to create app");
+
+        let request = ExecuteRequest {
+            language: "python".to_string(),
+            code: "print('limited')".to_string(),
+            timeout: Some(5),
+            ..Default::default()
+        };
+        for key in ["first", "second"] {
+            let response = post_execute(&app, &request, key).await;
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+
+        let response = post_execute(&app, &request, "third").await;
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        let retry_after: u64 = response.headers()["retry-after"]
+            .to_str()
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!((1..=30).contains(&retry_after), "{}", retry_after);
     }
 }
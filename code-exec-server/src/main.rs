@@ -1,64 +1,458 @@
-use clap::Parser;
-use code_exec::ResourceLimits;
-use code_exec_server::{create_app, run_server};
-use std::net::SocketAddr;
+use anyhow::Context;
+use clap::{Parser, Subcommand, ValueEnum};
+use code_exec::{CodeExecutionService, DependencyCacheConfig, Language, OsvScanner, RegexPolicy};
+use code_exec_server::{
+    auth::ApiKeys,
+    cli::{run_exec, run_self_test, ExecArgs},
+    config::{ConfigFile, DependencyCacheSection},
+    create_state, router, run_server, telemetry, ServerConfig,
+};
+use std::{net::SocketAddr, path::PathBuf, sync::Arc, time::Duration};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
-    /// Server address to listen on
-    #[arg(short, long, default_value = "0.0.0.0:3000")]
-    addr: SocketAddr,
+    #[command(subcommand)]
+    command: Option<Command>,
 
-    /// Maximum number of concurrent executions
-    #[arg(short, long, default_value = "10")]
-    max_concurrent: usize,
+    /// TOML or JSON file with the server's settings, overridden by the flags below
+    #[arg(long)]
+    config: Option<PathBuf>,
 
-    /// Memory limit in bytes
-    #[arg(long, default_value = "104857600")] // 100MB
-    memory_limit: u64,
+    /// Server address to listen on [default: 0.0.0.0:3000]
+    #[arg(short, long)]
+    addr: Option<SocketAddr>,
 
-    /// CPU time limit in seconds
-    #[arg(long, default_value = "5")]
-    cpu_time_limit: u32,
+    /// Also serve the gRPC API on this address, sharing executions and limits with REST
+    #[cfg(feature = "grpc")]
+    #[arg(long)]
+    grpc_addr: Option<SocketAddr>,
 
-    /// Maximum number of processes
-    #[arg(long, default_value = "10")]
-    max_processes: u32,
+    /// Run a known program in every accepted language and exit, non-zero if any failed
+    #[arg(long)]
+    self_test: bool,
 
-    /// File size limit in bytes
-    #[arg(long, default_value = "10485760")] // 10MB
-    file_size_limit: u64,
+    /// Seconds each language gets in --self-test
+    #[arg(long, default_value = "120")]
+    self_test_timeout: u64,
 
-    /// Disk space limit in bytes
-    #[arg(long, default_value = "104857600")] // 100MB
-    disk_space_limit: u64,
+    /// Format of log lines written to stderr
+    #[arg(long, value_enum, default_value = "text")]
+    log_format: LogFormat,
+
+    /// OpenTelemetry collector spans are exported to over OTLP/gRPC, e.g.
+    /// http://localhost:4317 (not exported if unset)
+    #[arg(long)]
+    otlp_endpoint: Option<String>,
+
+    /// Maximum number of concurrent executions [default: 10]
+    #[arg(short, long)]
+    max_concurrent: Option<usize>,
+
+    /// Memory limit in bytes [default: 100MB]
+    #[arg(long)]
+    memory_limit: Option<u64>,
+
+    /// CPU time limit in seconds [default: 5]
+    #[arg(long)]
+    cpu_time_limit: Option<u32>,
+
+    /// Wall-clock time limit in seconds, capping each request's timeout [default: 60]
+    #[arg(long)]
+    wall_time_limit: Option<u64>,
+
+    /// Time limit for installing dependencies in seconds [default: 300]
+    #[arg(long)]
+    install_timeout: Option<u64>,
+
+    /// Time limit for compiling in seconds [default: 300]
+    #[arg(long)]
+    compile_timeout: Option<u64>,
+
+    /// Maximum number of processes [default: 10]
+    #[arg(long)]
+    max_processes: Option<u32>,
+
+    /// File size limit in bytes [default: 10MB]
+    #[arg(long)]
+    file_size_limit: Option<u64>,
+
+    /// Maximum number of files a program may create [default: 10000]
+    #[arg(long)]
+    max_files: Option<u64>,
+
+    /// Disk space limit in bytes [default: 100MB]
+    #[arg(long)]
+    disk_space_limit: Option<u64>,
+
+    /// Reject requests that set denied environment variables instead of stripping them
+    #[arg(long)]
+    strict_env: bool,
+
+    /// Maximum number of environment variables a request may set [default: 128]
+    #[arg(long)]
+    max_env_vars: Option<usize>,
+
+    /// Maximum bytes of environment variable names and values per request [default: 64KB]
+    #[arg(long)]
+    max_env_bytes: Option<usize>,
+
+    /// Restrict programs to an allowlist of system calls (Linux only)
+    #[arg(long)]
+    seccomp: bool,
+
+    /// Seccomp profile replacing the built-in allowlist (implies --seccomp)
+    #[arg(long)]
+    seccomp_profile: Option<PathBuf>,
+
+    /// Run programs in gVisor containers with runsc (Linux only, requires running as root)
+    #[arg(long)]
+    gvisor: bool,
+
+    /// Host path programs need to read, may be repeated; makes the host filesystem
+    /// read-only for programs (Linux only)
+    #[arg(long = "readonly-mount")]
+    readonly_mounts: Vec<PathBuf>,
+
+    /// User id programs run as, e.g. 65534 for nobody (requires running as root)
+    #[arg(long)]
+    run_as_uid: Option<u32>,
+
+    /// Group id programs run as (defaults to --run-as-uid)
+    #[arg(long)]
+    run_as_gid: Option<u32>,
+
+    /// Directory for caching installed dependencies (disabled if unset)
+    #[arg(long)]
+    dependency_cache_dir: Option<PathBuf>,
+
+    /// Maximum dependency cache size in bytes [default: 2GB]
+    #[arg(long)]
+    dependency_cache_max_size: Option<u64>,
+
+    /// Refuse dependencies with known vulnerabilities, looked up in the OSV database
+    #[arg(long)]
+    scan_dependencies: bool,
+
+    /// OSV compatible advisory API queried by --scan-dependencies [default: https://api.osv.dev]
+    #[arg(long)]
+    advisory_url: Option<String>,
+
+    /// Refuse dependencies --scan-dependencies cannot check, such as version ranges
+    #[arg(long)]
+    require_exact_versions: bool,
+
+    /// Languages whose toolchains must be installed for /ready to succeed
+    /// [default: python,javascript,typescript,rust,go]
+    #[arg(long, value_delimiter = ',')]
+    required_languages: Option<Vec<Language>>,
+
+    /// Seconds a response is replayed for retries with the same Idempotency-Key [default: 600]
+    #[arg(long)]
+    idempotency_ttl: Option<u64>,
+
+    /// File with one API key per line required as a bearer token (open if unset)
+    #[arg(long)]
+    api_keys_file: Option<PathBuf>,
+
+    /// Executions each client may start per minute, by API key or IP (unlimited if unset)
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    rate_limit: Option<u32>,
+
+    /// Accept bash scripts, which always run with the seccomp filter
+    #[arg(long)]
+    allow_shell: bool,
+
+    /// Program requests in the custom language may run, e.g. runghc; may be repeated
+    #[arg(long = "custom-program")]
+    custom_programs: Vec<String>,
+
+    /// Origin browsers may call the API from, or * for any; may be repeated [default: none]
+    #[arg(long = "allowed-origin")]
+    allowed_origins: Vec<String>,
+
+    /// Largest code and input accepted in bytes, larger requests get 413 [default: 1MB]
+    #[arg(long)]
+    max_source_bytes: Option<usize>,
+
+    /// Never download dependencies or toolchains, only use the dependency cache
+    #[arg(long)]
+    offline: bool,
+
+    /// Keep the sandbox of a failed execution for this many seconds, reporting its path
+    #[arg(long, value_name = "SECONDS")]
+    keep_failed_sandboxes: Option<u64>,
+
+    /// Cargo target directory shared by all Rust builds, so crates are compiled once
+    #[arg(long)]
+    rust_target_dir: Option<PathBuf>,
+
+    /// Directory inside the sandbox request fixtures are written to [default: its root]
+    #[arg(long)]
+    fixtures_dir: Option<PathBuf>,
+
+    /// Longest timeout a request may ask for in seconds, longer ones are lowered to it
+    #[arg(long, value_name = "SECONDS")]
+    max_timeout: Option<u64>,
+
+    /// Refuse requests over --max-timeout with 400 instead of lowering their timeout
+    #[arg(long)]
+    reject_over_max_timeout: bool,
+}
+
+impl Args {
+    /// Replace the file's settings with those given on the command line
+    fn apply_to(&self, config: &mut ConfigFile) {
+        let server = &mut config.server;
+        server.addr = self.addr.unwrap_or(server.addr);
+        server.max_concurrent = self.max_concurrent.unwrap_or(server.max_concurrent);
+        server.idempotency_ttl = self.idempotency_ttl.unwrap_or(server.idempotency_ttl);
+        if let Some(languages) = &self.required_languages {
+            server.required_languages = languages.clone();
+        }
+        server.allow_shell |= self.allow_shell;
+        if !self.custom_programs.is_empty() {
+            server.custom_programs = self.custom_programs.clone();
+        }
+        if !self.allowed_origins.is_empty() {
+            server.allowed_origins = self.allowed_origins.clone();
+        }
+        server.max_source_bytes = self.max_source_bytes.unwrap_or(server.max_source_bytes);
+        server.offline |= self.offline;
+        server.keep_failed_sandboxes = self.keep_failed_sandboxes.or(server.keep_failed_sandboxes);
+        if let Some(dir) = &self.fixtures_dir {
+            server.fixtures_dir = Some(dir.clone());
+        }
+        server.max_timeout = self.max_timeout.or(server.max_timeout);
+        server.reject_over_max_timeout |= self.reject_over_max_timeout;
+
+        let limits = &mut config.limits;
+        limits.memory = self.memory_limit.unwrap_or(limits.memory);
+        limits.cpu_time = self.cpu_time_limit.unwrap_or(limits.cpu_time);
+        limits.wall_time = self.wall_time_limit.unwrap_or(limits.wall_time);
+        limits.install_timeout = self.install_timeout.unwrap_or(limits.install_timeout);
+        limits.compile_timeout = self.compile_timeout.unwrap_or(limits.compile_timeout);
+        limits.processes = self.max_processes.unwrap_or(limits.processes);
+        limits.file_size = self.file_size_limit.unwrap_or(limits.file_size);
+        limits.max_files = self.max_files.unwrap_or(limits.max_files);
+        limits.disk_space = self.disk_space_limit.unwrap_or(limits.disk_space);
+        limits.strict_env |= self.strict_env;
+        limits.max_env_vars = self.max_env_vars.unwrap_or(limits.max_env_vars);
+        limits.max_env_bytes = self.max_env_bytes.unwrap_or(limits.max_env_bytes);
+        limits.seccomp |= self.seccomp;
+        if self.seccomp_profile.is_some() {
+            limits.seccomp_profile = self.seccomp_profile.clone();
+        }
+        limits.gvisor |= self.gvisor;
+        if !self.readonly_mounts.is_empty() {
+            limits.readonly_mounts = self.readonly_mounts.clone();
+        }
+        limits.run_as_uid = self.run_as_uid.or(limits.run_as_uid);
+        limits.run_as_gid = self.run_as_gid.or(limits.run_as_gid);
+
+        if let Some(dir) = &self.dependency_cache_dir {
+            let cache = config
+                .dependency_cache
+                .get_or_insert_with(|| DependencyCacheSection {
+                    dir: dir.clone(),
+                    max_size: DependencyCacheConfig::default().max_size,
+                });
+            cache.dir = dir.clone();
+        }
+        if let (Some(cache), Some(max_size)) =
+            (&mut config.dependency_cache, self.dependency_cache_max_size)
+        {
+            cache.max_size = max_size;
+        }
+
+        if self.scan_dependencies {
+            config.dependency_scan.get_or_insert_with(Default::default);
+        }
+        if let (Some(scan), Some(url)) = (&mut config.dependency_scan, &self.advisory_url) {
+            scan.advisory_url = url.clone();
+        }
+        if let Some(scan) = &mut config.dependency_scan {
+            scan.require_exact_versions |= self.require_exact_versions;
+        }
+
+        if let Some(target_dir) = &self.rust_target_dir {
+            let rust = config.languages.entry(Language::Rust).or_default();
+            rust.target_dir = Some(target_dir.clone());
+        }
+
+        if self.api_keys_file.is_some() {
+            config.auth.api_keys_file = self.api_keys_file.clone();
+        }
+        config.rate_limit.requests_per_minute =
+            self.rate_limit.or(config.rate_limit.requests_per_minute);
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum LogFormat {
+    /// Human-readable lines
+    Text,
+    /// One JSON object per line, including the fields of the current span
+    Json,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Execute a single source file and exit
+    Exec(ExecArgs),
 }
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
+    let args = Args::parse();
+
     // Initialize tracing
-    tracing_subscriber::registry()
+    let (otlp_layer, tracer_provider) = match &args.otlp_endpoint {
+        Some(endpoint) => {
+            let (layer, provider) = telemetry::otlp_layer(endpoint)
+                .with_context(|| format!("Failed to set up span export to {}", endpoint))?;
+            (Some(layer), Some(provider))
+        }
+        None => (None, None),
+    };
+    let registry = tracing_subscriber::registry()
         .with(
             tracing_subscriber::EnvFilter::try_from_default_env()
                 .unwrap_or_else(|_| "info,tower_http=debug".into()),
         )
-        .with(tracing_subscriber::fmt::layer())
-        .init();
+        .with(otlp_layer);
+    match args.log_format {
+        LogFormat::Text => registry
+            .with(tracing_subscriber::fmt::layer().with_writer(std::io::stderr))
+            .init(),
+        LogFormat::Json => registry
+            .with(
+                tracing_subscriber::fmt::layer()
+                    .json()
+                    .with_writer(std::io::stderr),
+            )
+            .init(),
+    }
 
-    let args = Args::parse();
+    let mut config = match &args.config {
+        Some(path) => ConfigFile::load(path)
+            .with_context(|| format!("Failed to load config from {}", path.display()))?,
+        None => ConfigFile::default(),
+    };
+    args.apply_to(&mut config);
+    config.validate()?;
+
+    let resource_limits = config.limits.resource_limits();
+    let language_versions = config.language_versions();
+    let language_concurrency = config.language_concurrency();
+    let banned_imports = config.banned_imports();
+    let toolchain_paths = config.toolchain_paths();
+    let rust_target_dir = config.rust_target_dir();
+    let dependency_cache = config.dependency_cache.map(|cache| DependencyCacheConfig {
+        dir: cache.dir,
+        max_size: cache.max_size,
+    });
+    let require_exact_versions = config
+        .dependency_scan
+        .as_ref()
+        .is_some_and(|scan| scan.require_exact_versions);
+    let advisory_url = config.dependency_scan.map(|scan| scan.advisory_url);
+
+    if args.self_test || args.command.is_some() {
+        let mut service = CodeExecutionService::new(config.server.max_concurrent, resource_limits)
+            .await?
+            .with_language_concurrency(language_concurrency)
+            .with_banned_imports(banned_imports)
+            .with_toolchain_paths(toolchain_paths)
+            .with_shell(config.server.allow_shell)
+            .with_custom_programs(config.server.custom_programs.clone())
+            .with_max_source_bytes(config.server.max_source_bytes)
+            .with_submission_policy(Arc::new(RegexPolicy::new(config.policy.rules())?));
+        if let Some(url) = &advisory_url {
+            let scanner = OsvScanner::new(url).with_require_exact(require_exact_versions);
+            service = service.with_dependency_scanner(Arc::new(scanner));
+        }
+        if let Some(config) = dependency_cache {
+            service = service.with_dependency_cache(config).await?;
+        }
+        if let Some(target_dir) = rust_target_dir {
+            service = service.with_rust_target_dir(target_dir)?;
+        }
+        if let Some(dir) = &config.server.fixtures_dir {
+            service = service.with_fixtures_dir(dir);
+        }
+        let service = service
+            .with_language_versions(language_versions)
+            .with_offline(config.server.offline);
 
-    let resource_limits = ResourceLimits {
-        memory: args.memory_limit,
-        cpu_time: args.cpu_time_limit,
-        processes: args.max_processes,
-        file_size: args.file_size_limit,
-        disk_space: args.disk_space_limit,
+        let success = match args.command {
+            Some(Command::Exec(exec_args)) => {
+                run_exec(&service, exec_args, &mut std::io::stdout()).await?
+            }
+            None => {
+                let timeout = Duration::from_secs(args.self_test_timeout);
+                run_self_test(&service, timeout, &mut std::io::stdout()).await?
+            }
+        };
+        if let Some(provider) = tracer_provider {
+            let _ = provider.shutdown();
+        }
+        std::process::exit(if success { 0 } else { 1 });
+    }
+
+    let api_keys = match &config.auth.api_keys_file {
+        Some(path) => ApiKeys::from_file(path)
+            .with_context(|| format!("Failed to read API keys from {}", path.display()))?,
+        None => ApiKeys::default(),
     };
+    if api_keys.is_empty() {
+        tracing::warn!("No API keys configured, /execute accepts unauthenticated requests");
+    }
+
+    let state = create_state(ServerConfig {
+        max_concurrent_executions: config.server.max_concurrent,
+        language_concurrency,
+        banned_imports,
+        toolchain_paths,
+        resource_limits,
+        dependency_cache,
+        idempotency_ttl: Duration::from_secs(config.server.idempotency_ttl),
+        required_languages: config.server.required_languages,
+        api_keys,
+        requests_per_minute: config.rate_limit.requests_per_minute,
+        allow_shell: config.server.allow_shell,
+        custom_programs: config.server.custom_programs,
+        language_versions,
+        max_source_bytes: config.server.max_source_bytes,
+        policy_rules: config.policy.rules(),
+        advisory_url,
+        require_exact_versions,
+        offline: config.server.offline,
+        rust_target_dir,
+        keep_failed_sandboxes: config.server.keep_failed_sandboxes.map(Duration::from_secs),
+        fixtures_dir: config.server.fixtures_dir,
+        allowed_origins: config.server.allowed_origins,
+        max_timeout: config.server.max_timeout,
+        reject_over_max_timeout: config.server.reject_over_max_timeout,
+    })
+    .await?;
+
+    #[cfg(feature = "grpc")]
+    if let Some(addr) = args.grpc_addr {
+        let state = state.clone();
+        tokio::spawn(async move {
+            if let Err(e) = code_exec_server::grpc::run_grpc_server(state, addr).await {
+                tracing::error!("gRPC server failed: {}", e);
+            }
+        });
+    }
 
-    let app = create_app(args.max_concurrent, resource_limits).await?;
-    run_server(app, args.addr).await?;
+    let served = run_server(router(state), config.server.addr).await;
+    if let Some(provider) = tracer_provider {
+        let _ = provider.shutdown();
+    }
+    served?;
 
     Ok(())
 }
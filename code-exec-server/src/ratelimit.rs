@@ -0,0 +1,99 @@
+//! Per-client rate limiting of new executions
+//!
+//! Each client gets a token bucket holding a minute's worth of requests, refilled
+//! continuously. Clients are identified by their API key when authentication is on, and
+//! by their IP address otherwise. This is independent of the concurrency limit: it stops
+//! one client from queueing more work than its share, not from running it.
+
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::Extensions,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use crate::{auth::ApiKey, AppState, ServerError};
+
+struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+}
+
+/// Token buckets by client
+pub struct RateLimiter {
+    capacity: f64,
+    /// Tokens added per second
+    refill_rate: f64,
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+impl RateLimiter {
+    /// Allow each client `requests_per_minute` requests, in bursts of up to as many
+    pub fn new(requests_per_minute: u32) -> Self {
+        let capacity = f64::from(requests_per_minute.max(1));
+        Self {
+            capacity,
+            refill_rate: capacity / 60.0,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Take a token for `client`, or return how long until one is available
+    pub fn check(&self, client: &str) -> Result<(), Duration> {
+        let mut buckets = self.buckets.lock().unwrap();
+        let now = Instant::now();
+
+        // A bucket left alone long enough to refill is the same as no bucket
+        let refill_time = Duration::from_secs_f64(self.capacity / self.refill_rate);
+        buckets.retain(|_, bucket| now.duration_since(bucket.updated_at) < refill_time);
+
+        let bucket = buckets.entry(client.to_string()).or_insert(Bucket {
+            tokens: self.capacity,
+            updated_at: now,
+        });
+        let elapsed = now.duration_since(bucket.updated_at).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.refill_rate).min(self.capacity);
+        bucket.updated_at = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64(
+                (1.0 - bucket.tokens) / self.refill_rate,
+            ))
+        }
+    }
+}
+
+/// Reject requests from clients that have used up their rate limit with 429
+pub async fn rate_limit(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let Some(limiter) = &state.rate_limiter else {
+        return next.run(request).await;
+    };
+
+    match limiter.check(&client_id(request.extensions())) {
+        Ok(()) => next.run(request).await,
+        Err(wait) => ServerError::RateLimited {
+            retry_after: wait.as_secs_f64().ceil() as u64,
+        }
+        .into_response(),
+    }
+}
+
+/// Identify the client behind a request by its API key, or else its IP address
+pub(crate) fn client_id(extensions: &Extensions) -> String {
+    if let Some(ApiKey(key)) = extensions.get::<ApiKey>() {
+        format!("key:{}", key)
+    } else if let Some(ConnectInfo(addr)) = extensions.get::<ConnectInfo<SocketAddr>>() {
+        format!("ip:{}", addr.ip())
+    } else {
+        "unknown".to_string()
+    }
+}
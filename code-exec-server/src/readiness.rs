@@ -0,0 +1,56 @@
+//! Readiness based on whether the toolchains of the required languages are installed
+
+use code_exec::Language;
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// How long a toolchain check is reused before looking at the PATH again
+const CHECK_TTL: Duration = Duration::from_secs(5);
+
+/// Missing tools per language, empty when every required language can run
+pub type MissingTools = HashMap<Language, Vec<String>>;
+
+/// Cached toolchain check for the languages the server must be able to run
+pub struct Readiness {
+    required: Vec<Language>,
+    probe: fn(Language) -> Vec<String>,
+    last_check: Mutex<Option<(Instant, MissingTools)>>,
+}
+
+impl Readiness {
+    /// Check `required` languages with [`code_exec::missing_tools`]
+    pub fn new(required: Vec<Language>) -> Self {
+        Self::with_probe(required, code_exec::missing_tools)
+    }
+
+    /// Check `required` languages with a custom lookup of missing tools
+    pub fn with_probe(required: Vec<Language>, probe: fn(Language) -> Vec<String>) -> Self {
+        Self {
+            required,
+            probe,
+            last_check: Mutex::new(None),
+        }
+    }
+
+    /// Missing tools of the required languages, from a recent check if there is one
+    pub fn missing_tools(&self) -> MissingTools {
+        let mut last_check = self.last_check.lock().unwrap();
+        if let Some((checked_at, missing)) = last_check.as_ref() {
+            if checked_at.elapsed() < CHECK_TTL {
+                return missing.clone();
+            }
+        }
+
+        let missing: MissingTools = self
+            .required
+            .iter()
+            .map(|&language| (language, (self.probe)(language)))
+            .filter(|(_, tools)| !tools.is_empty())
+            .collect();
+        *last_check = Some((Instant::now(), missing.clone()));
+        missing
+    }
+}
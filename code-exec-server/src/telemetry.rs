@@ -0,0 +1,58 @@
+//! Export of the server's spans to an OpenTelemetry collector
+
+use opentelemetry::{trace::TracerProvider as _, KeyValue};
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+use opentelemetry_sdk::{runtime, trace::TracerProvider, Resource};
+use tracing::Subscriber;
+use tracing_subscriber::{registry::LookupSpan, Layer};
+
+/// Name spans are reported under
+const SERVICE_NAME: &str = "code-exec-server";
+
+/// Spans exported over OTLP/gRPC to the collector at `endpoint`, e.g.
+/// `http://localhost:4317`.
+///
+/// Spans are sent in batches from the Tokio runtime, so this has to be called from
+/// within one. Call [`TracerProvider::shutdown`] on the returned provider before exiting
+/// to flush the last batch.
+pub fn otlp_layer<S>(endpoint: &str) -> anyhow::Result<(impl Layer<S>, TracerProvider)>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+{
+    let exporter = SpanExporter::builder()
+        .with_tonic()
+        .with_endpoint(endpoint)
+        .build()?;
+    let provider = TracerProvider::builder()
+        .with_batch_exporter(exporter, runtime::Tokio)
+        .with_resource(Resource::new([KeyValue::new("service.name", SERVICE_NAME)]))
+        .build();
+    let layer = tracing_opentelemetry::layer().with_tracer(provider.tracer(SERVICE_NAME));
+    Ok((layer, provider))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opentelemetry::trace::TraceContextExt;
+    use tracing_opentelemetry::OpenTelemetrySpanExt;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    #[tokio::test]
+    async fn test_otlp_layer_traces_spans() {
+        // Nothing listens there, spans are only dropped when exported
+        let (layer, provider) = otlp_layer("http://127.0.0.1:4317").unwrap();
+        let subscriber = tracing_subscriber::registry().with(layer);
+
+        tracing::subscriber::with_default(subscriber, || {
+            let span = tracing::info_span!("execution");
+            assert!(span.context().span().span_context().is_valid());
+        });
+
+        // Without the layer spans get no trace context
+        let span = tracing::info_span!("execution");
+        assert!(!span.context().span().span_context().is_valid());
+
+        let _ = provider.shutdown();
+    }
+}
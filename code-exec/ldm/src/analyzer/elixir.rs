@@ -0,0 +1,98 @@
+use super::{resolver::VersionResolver, DependencyAnalyzer};
+use crate::{
+    error::Result,
+    types::{Package, PackageSource},
+};
+use async_trait::async_trait;
+use regex::Regex;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, LazyLock};
+
+// Module and function definitions, which take a `do` block unlike Ruby's `def`
+static DEFINITION_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r#"(?mx)
+        ^\s*defmodule\s+[A-Z][\w.]*\s+do\b
+        |^\s*def(?:p|macrop?)?\s+[a-z_][\w?!]*\s*(?:\([^)]*\))?\s*(?:when\s+.+?)?(?:\s+do\s*$|,\s*do:)
+        "#,
+    )
+    .unwrap()
+});
+
+static KERNEL_CALL_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"\bIO\.(?:puts|inspect|write)\b"#).unwrap());
+
+// Ruby block parameters, classes, requires and attribute macros
+static RUBY_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r#"(?m)\bdo\s*\|[^|]*\||^\s*(?:class|module)\s+[A-Z]|^\s*require(?:_relative)?\s+['"]|^\s*attr_(?:accessor|reader|writer)\b"#,
+    )
+    .unwrap()
+});
+
+// The package list of `Mix.install([...])`
+static MIX_INSTALL_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"(?s)Mix\.install\(\s*\[(.*?)\]"#).unwrap());
+
+// `{:name, "requirement"}` entries
+static DEPENDENCY_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"\{\s*:([a-z_][a-z0-9_]*)\s*,\s*"([^"]+)""#).unwrap());
+
+#[derive(Default, Clone)]
+pub struct ElixirAnalyzer;
+
+impl ElixirAnalyzer {
+    fn hex_package(name: &str, requirement: &str) -> Package {
+        Package {
+            name: name.to_string(),
+            version: Some(requirement.to_string()),
+            source: PackageSource::Custom("hex".to_string()),
+        }
+    }
+}
+
+#[async_trait]
+impl DependencyAnalyzer for ElixirAnalyzer {
+    fn language(&self) -> &'static str {
+        "elixir"
+    }
+
+    /// `Mix.install` always names a requirement, so there are no defaults to replace
+    fn with_default_versions(self, _versions: HashMap<String, String>) -> Self {
+        self
+    }
+
+    /// Nothing to resolve, for the same reason
+    fn with_version_resolver(self, _resolver: Arc<dyn VersionResolver>) -> Self {
+        self
+    }
+
+    async fn analyze_dependencies(&self, source_code: &str) -> Result<Vec<Package>> {
+        let mut packages = HashSet::new();
+
+        for install in MIX_INSTALL_RE.captures_iter(source_code) {
+            for cap in DEPENDENCY_RE.captures_iter(&install[1]) {
+                packages.insert(Self::hex_package(&cap[1], &cap[2]));
+            }
+        }
+
+        Ok(packages.into_iter().collect())
+    }
+
+    fn can_handle(&self, source_code: &str) -> bool {
+        let has_elixir_patterns =
+            DEFINITION_RE.is_match(source_code) || KERNEL_CALL_RE.is_match(source_code);
+
+        has_elixir_patterns && !RUBY_RE.is_match(source_code)
+    }
+
+    fn is_dependency_line(&self, line: &str) -> bool {
+        line.contains("Mix.install") || DEPENDENCY_RE.is_match(line)
+    }
+
+    fn extract_package_info(&self, line: &str) -> Option<Package> {
+        DEPENDENCY_RE
+            .captures(line)
+            .map(|cap| Self::hex_package(&cap[1], &cap[2]))
+    }
+}
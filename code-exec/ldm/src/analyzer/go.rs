@@ -1,39 +1,120 @@
-use super::DependencyAnalyzer;
+use super::{resolver::VersionResolver, DependencyAnalyzer};
 use crate::{
-    error::Result,
+    error::{Error, Result},
     types::{Package, PackageSource},
 };
 use async_trait::async_trait;
 use regex::Regex;
+use serde::Deserialize;
 use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::{Arc, LazyLock};
+use std::time::Duration;
+use tokio::{fs, process::Command, time};
+use tracing::{debug, warn};
+
+// Single-line import, optionally named, blank (`_`) or dot-imported
+static IMPORT_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"^\s*import\s+(?:(?:[_.]|\w+)\s+)?["`]([^"`]+)["`]"#).unwrap());
+
+// Import spec on its own line inside an `import ( ... )` block
+static IMPORT_SPEC_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"^\s*(?:(?:[_.]|\w+)\s+)?["`]([^"`]+)["`]"#).unwrap());
+
+static VERSION_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"//\s*go:\s*require\s+([^\s]+)\s+v([\w\-.]+)"#).unwrap());
+
+// Time `go mod tidy` and `go list` may take, mostly spent downloading modules
+const GO_LIST_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Module as printed by `go list -m -json`
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct GoModule {
+    path: String,
+    version: Option<String>,
+    #[serde(default)]
+    main: bool,
+    #[serde(default)]
+    indirect: bool,
+}
 
 #[derive(Default, Clone)]
 pub struct GoAnalyzer {
-    import_re: Option<Regex>,
-    version_re: Option<Regex>,
     default_versions: HashMap<String, String>,
+    /// Versions set with `with_default_versions`, taking precedence over the built-ins
+    version_overrides: HashMap<String, String>,
+    /// Resolve modules with the `go` command when it is installed
+    go_list: bool,
+    /// Never let the `go` command download modules
+    offline: bool,
 }
 
 impl GoAnalyzer {
-    fn get_import_re(&mut self) -> &Regex {
-        if self.import_re.is_none() {
-            // Match both single imports and multi-line import blocks
-            self.import_re = Some(
-                Regex::new(
-                    r#"(?m)^[\s\n]*(?:import\s+"([^"]+)"|(?:import\s*\(\s*)?(?:[_.\w]+\s+)?"([^"]+)")"#
-                )
-                .unwrap(),
-            );
-        }
-        self.import_re.as_ref().unwrap()
+    /// Resolves modules with `go mod tidy` and `go list -m -json all` in a scratch module
+    /// when `go` is installed, which finds the module an import belongs to and the
+    /// version Go itself would pick. Reading the imports is the fallback.
+    pub fn with_go_list(mut self, enabled: bool) -> Self {
+        self.go_list = enabled;
+        self
+    }
+
+    /// Skips the `go` command, which would download modules, in favour of reading the
+    /// imports
+    pub fn with_offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
+        self
     }
 
-    fn get_version_re(&mut self) -> &Regex {
-        if self.version_re.is_none() {
-            self.version_re =
-                Some(Regex::new(r#"//\s*go:\s*require\s+([^\s]+)\s+v([\w\-.]+)"#).unwrap());
+    /// Modules the source requires directly, as resolved by the `go` command
+    async fn go_list_dependencies(
+        &self,
+        source_code: &str,
+        explicit_versions: &HashMap<String, String>,
+    ) -> Result<Vec<Package>> {
+        let dir = tempfile::TempDir::new().map_err(|e| {
+            Error::Environment(format!("Failed to create temporary directory: {}", e))
+        })?;
+        fs::write(dir.path().join("main.go"), source_code)
+            .await
+            .map_err(|e| Error::Environment(format!("Failed to write main.go: {}", e)))?;
+
+        run_go(dir.path(), &["mod", "init", "snippet"]).await?;
+        for (module, version) in explicit_versions {
+            let require = format!("-require={}@{}", module, version);
+            run_go(dir.path(), &["mod", "edit", &require]).await?;
+        }
+        run_go(dir.path(), &["mod", "tidy"]).await?;
+        let listing = run_go(dir.path(), &["list", "-m", "-json", "all"]).await?;
+
+        // One JSON object per module, one after the other
+        let mut packages = Vec::new();
+        for module in serde_json::Deserializer::from_slice(&listing).into_iter::<GoModule>() {
+            let module = module.map_err(|e| {
+                Error::PackageManager(format!("Failed to parse go list output: {}", e))
+            })?;
+            if module.main || module.indirect {
+                continue;
+            }
+            packages.push(Package {
+                name: module.path,
+                version: module.version,
+                source: PackageSource::Custom("go".to_string()),
+            });
         }
-        self.version_re.as_ref().unwrap()
+        Ok(packages)
+    }
+
+    fn get_import_re(&self) -> &'static Regex {
+        &IMPORT_RE
+    }
+
+    fn get_import_spec_re(&self) -> &'static Regex {
+        &IMPORT_SPEC_RE
+    }
+
+    fn get_version_re(&self) -> &'static Regex {
+        &VERSION_RE
     }
 
     fn initialize_default_versions(&mut self) {
@@ -79,8 +160,9 @@ impl GoAnalyzer {
     }
 
     fn get_default_version(&self, package_name: &str) -> String {
-        self.default_versions
+        self.version_overrides
             .get(package_name)
+            .or_else(|| self.default_versions.get(package_name))
             .cloned()
             .unwrap_or_else(|| "1.0.0".to_string())
     }
@@ -129,6 +211,38 @@ impl GoAnalyzer {
         required_matches >= 2 && !has_negative_pattern
     }
 
+    /// Paths of the packages the source imports, single imports and import blocks alike
+    fn import_paths<'a>(&self, source_code: &'a str) -> Vec<&'a str> {
+        let mut paths = Vec::new();
+        let mut in_import_block = false;
+
+        for line in source_code.lines() {
+            let line = line.trim();
+
+            // Check for import block start/end
+            if let Some(rest) = line.strip_prefix("import") {
+                if rest.trim_start().starts_with('(') {
+                    in_import_block = true;
+                    continue;
+                }
+            } else if line.starts_with(')') && in_import_block {
+                in_import_block = false;
+                continue;
+            }
+
+            // Inside a block each line is a spec, outside only `import` lines count
+            let import_re = if in_import_block {
+                self.get_import_spec_re()
+            } else {
+                self.get_import_re()
+            };
+            if let Some(import_path) = import_re.captures(line).and_then(|cap| cap.get(1)) {
+                paths.push(import_path.as_str());
+            }
+        }
+        paths
+    }
+
     fn get_base_package(&self, import_path: &str) -> String {
         // For GitHub repos, always use github.com/org/repo
         if import_path.starts_with("github.com/") {
@@ -149,12 +263,44 @@ impl GoAnalyzer {
     }
 }
 
+/// Run `go` with `args` in `dir`, returning its stdout
+async fn run_go(dir: &Path, args: &[&str]) -> Result<Vec<u8>> {
+    let output = Command::new("go")
+        .args(args)
+        .current_dir(dir)
+        .env("GOFLAGS", "-mod=mod")
+        .kill_on_drop(true)
+        .output();
+    let output = time::timeout(GO_LIST_TIMEOUT, output)
+        .await
+        .map_err(|_| Error::PackageManager(format!("go {} timed out", args.join(" "))))?
+        .map_err(|e| Error::Environment(format!("Failed to run go: {}", e)))?;
+    if !output.status.success() {
+        return Err(Error::PackageManager(format!(
+            "go {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(output.stdout)
+}
+
 #[async_trait]
 impl DependencyAnalyzer for GoAnalyzer {
     fn language(&self) -> &'static str {
         "go"
     }
 
+    fn with_default_versions(mut self, versions: HashMap<String, String>) -> Self {
+        self.version_overrides.extend(versions);
+        self
+    }
+
+    /// The Go module proxy is not queried, so unknown modules keep the fallback version
+    fn with_version_resolver(self, _resolver: Arc<dyn VersionResolver>) -> Self {
+        self
+    }
+
     async fn analyze_dependencies(&self, source_code: &str) -> Result<Vec<Package>> {
         let mut this = self.clone();
         this.initialize_default_versions();
@@ -168,63 +314,50 @@ impl DependencyAnalyzer for GoAnalyzer {
             explicit_versions.insert(name.to_string(), version);
         }
 
-        // Then process imports
-        let import_re = this.get_import_re().clone();
-        let mut in_import_block = false;
-
-        for line in source_code.lines() {
-            let line = line.trim();
+        if this.go_list && !this.offline && which::which("go").is_ok() {
+            match this
+                .go_list_dependencies(source_code, &explicit_versions)
+                .await
+            {
+                Ok(packages) => return Ok(packages),
+                Err(e) => warn!("Resolving Go modules with go list failed: {}", e),
+            }
+        } else if this.go_list {
+            debug!("Go not available or offline, reading Go imports instead");
+        }
 
-            // Check for import block start/end
-            if line.starts_with("import (") {
-                in_import_block = true;
-                continue;
-            } else if line == ")" && in_import_block {
-                in_import_block = false;
+        // Then process imports
+        for import_path in this.import_paths(source_code) {
+            if this.is_std_import(import_path) {
                 continue;
             }
 
-            // Process imports
-            if let Some(cap) = import_re.captures(line) {
-                let import_path = if in_import_block {
-                    // Inside import block, look for just the quoted path
-                    if let Some(m) = cap.get(2) {
-                        Some(m)
-                    } else {
-                        cap.get(1)
-                    }
-                } else {
-                    // Single-line import
-                    cap.get(1)
-                };
-
-                if let Some(import_path) = import_path {
-                    let import_path = import_path.as_str();
-                    if !this.is_std_import(import_path) {
-                        let base_package = this.get_base_package(import_path);
-
-                        let version = explicit_versions
-                            .get(&base_package)
-                            .cloned()
-                            .unwrap_or_else(|| this.get_default_version(&base_package));
-                        packages.insert(Package {
-                            name: base_package,
-                            version: Some(version),
-                            source: PackageSource::Custom("go".to_string()),
-                        });
-                    } else {
-                    }
-                } else {
-                }
-            }
+            let base_package = this.get_base_package(import_path);
+            let version = explicit_versions
+                .get(&base_package)
+                .cloned()
+                .unwrap_or_else(|| this.get_default_version(&base_package));
+            packages.insert(Package {
+                name: base_package,
+                version: Some(version),
+                source: PackageSource::Custom("go".to_string()),
+            });
         }
 
         Ok(packages.into_iter().collect())
     }
 
+    fn imports(&self, source_code: &str) -> Vec<String> {
+        self.import_paths(source_code)
+            .into_iter()
+            .map(str::to_string)
+            .collect()
+    }
+
     fn is_dependency_line(&self, line: &str) -> bool {
-        let mut this = self.clone();
-        this.get_import_re().is_match(line) || this.get_version_re().is_match(line)
+        self.get_import_re().is_match(line)
+            || self.get_import_spec_re().is_match(line)
+            || self.get_version_re().is_match(line)
     }
 
     fn can_handle(&self, source_code: &str) -> bool {
@@ -1,36 +1,48 @@
-use super::DependencyAnalyzer;
+use super::{
+    resolver::{resolve_guessed_versions, PackageRegistry, VersionResolver},
+    DependencyAnalyzer,
+};
 use crate::{
     error::Result,
     types::{Package, PackageSource},
 };
 use async_trait::async_trait;
-use regex::Regex;
+use regex::{Captures, Regex};
 use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, LazyLock};
+
+/// Static imports and requires at the start of a line, and dynamic `import()` anywhere.
+/// Template literals and variables are left alone, since they cannot be resolved
+/// without running the code.
+static IMPORT_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(?m)^(?:(?:const|let|var)\s*\{[^}]*\}\s*=\s*require\(['"]([^'"]+)['"]\)|(?:const|let|var)\s+\w+\s*=\s*require\(['"]([^'"]+)['"]\)|import\s+.*?from\s+['"]([^'"]+)['"])|\bimport\(\s*['"]([^'"]+)['"]\s*\)"#).unwrap()
+});
+
+// Other ways a program loads modules: `require()` anywhere, not only in declarations, and
+// side-effect imports, which bring in no package worth installing on their own
+static LOAD_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(?m)\brequire\(\s*['"]([^'"]+)['"]\s*\)|^\s*import\s*['"]([^'"]+)['"]"#).unwrap()
+});
+
+static VERSION_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"//\s*npm:\s*([@\w\-/.]+)@([\w\-~^.<>=]+)"#).unwrap());
 
 #[derive(Default, Clone)]
 pub struct JavaScriptAnalyzer {
-    import_re: Option<Regex>,
-    version_re: Option<Regex>,
     default_versions: HashMap<String, String>,
+    /// Versions set with `with_default_versions`, taking precedence over the built-ins
+    version_overrides: HashMap<String, String>,
+    /// Looks up versions for packages without a default
+    resolver: Option<Arc<dyn VersionResolver>>,
 }
 
 impl JavaScriptAnalyzer {
-    fn get_import_re(&mut self) -> &Regex {
-        if self.import_re.is_none() {
-            self.import_re = Some(
-                Regex::new(r#"(?m)^(?:(?:const|let|var)\s*\{[^}]*\}\s*=\s*require\(['"]([^'"]+)['"]\)|(?:const|let|var)\s+\w+\s*=\s*require\(['"]([^'"]+)['"]\)|import\s+.*?from\s+['"]([^'"]+)['"])"#)
-                    .unwrap(),
-            );
-        }
-        self.import_re.as_ref().unwrap()
+    fn get_import_re(&self) -> &'static Regex {
+        &IMPORT_RE
     }
 
-    fn get_version_re(&mut self) -> &Regex {
-        if self.version_re.is_none() {
-            self.version_re =
-                Some(Regex::new(r#"//\s*npm:\s*([@\w\-/.]+)@([\w\-~^.<>=]+)"#).unwrap());
-        }
-        self.version_re.as_ref().unwrap()
+    fn get_version_re(&self) -> &'static Regex {
+        &VERSION_RE
     }
 
     fn initialize_default_versions(&mut self) {
@@ -76,20 +88,57 @@ impl JavaScriptAnalyzer {
         import_path.starts_with('.') || import_path.starts_with('/')
     }
 
+    fn has_default_version(&self, package_name: &str) -> bool {
+        self.version_overrides.contains_key(package_name)
+            || self.default_versions.contains_key(package_name)
+    }
+
     fn get_default_version(&self, package_name: &str) -> String {
-        self.default_versions
+        self.version_overrides
             .get(package_name)
+            .or_else(|| self.default_versions.get(package_name))
             .cloned()
             .unwrap_or_else(|| "^1.0.0".to_string())
     }
 }
 
+/// Module specifier of an import, from whichever form matched
+fn import_path<'a>(cap: &Captures<'a>) -> &'a str {
+    cap.iter().skip(1).flatten().next().unwrap().as_str()
+}
+
+/// Modules loaded by `source_code` through `import_re` or any other form, with Node's
+/// `node:` scheme removed so `node:child_process` is reported as `child_process`
+pub(super) fn module_imports(import_re: &Regex, source_code: &str) -> Vec<String> {
+    let mut imports: Vec<String> = import_re
+        .captures_iter(source_code)
+        .chain(LOAD_RE.captures_iter(source_code))
+        .map(|cap| {
+            let path = import_path(&cap);
+            path.strip_prefix("node:").unwrap_or(path).to_string()
+        })
+        .collect();
+    imports.sort_unstable();
+    imports.dedup();
+    imports
+}
+
 #[async_trait]
 impl DependencyAnalyzer for JavaScriptAnalyzer {
     fn language(&self) -> &'static str {
         "javascript"
     }
 
+    fn with_default_versions(mut self, versions: HashMap<String, String>) -> Self {
+        self.version_overrides.extend(versions);
+        self
+    }
+
+    fn with_version_resolver(mut self, resolver: Arc<dyn VersionResolver>) -> Self {
+        self.resolver = Some(resolver);
+        self
+    }
+
     async fn analyze_dependencies(&self, source_code: &str) -> Result<Vec<Package>> {
         let mut this = self.clone();
         this.initialize_default_versions();
@@ -106,12 +155,7 @@ impl DependencyAnalyzer for JavaScriptAnalyzer {
         // Then process imports
         let import_re = this.get_import_re().clone();
         for cap in import_re.captures_iter(source_code) {
-            let import_path = cap
-                .get(1)
-                .or_else(|| cap.get(2))
-                .or_else(|| cap.get(3))
-                .unwrap()
-                .as_str();
+            let import_path = import_path(&cap);
             if !this.is_local_import(import_path) {
                 // Get the base package name (handle scoped packages)
                 let package_name = if import_path.contains('/') {
@@ -134,21 +178,36 @@ impl DependencyAnalyzer for JavaScriptAnalyzer {
             }
         }
 
-        Ok(packages.into_iter().collect())
+        let mut packages: Vec<Package> = packages.into_iter().collect();
+        resolve_guessed_versions(
+            this.resolver.as_ref(),
+            PackageRegistry::Npm,
+            &mut packages,
+            |name| !explicit_versions.contains_key(name) && !this.has_default_version(name),
+            |version| format!("^{}", version),
+        )
+        .await;
+        Ok(packages)
+    }
+
+    fn imports(&self, source_code: &str) -> Vec<String> {
+        module_imports(self.get_import_re(), source_code)
     }
 
     fn can_handle(&self, source_code: &str) -> bool {
         // JavaScript-specific patterns
-        (source_code.contains("require(") || source_code.contains("import "))
+        (source_code.contains("require(")
+            || source_code.contains("import ")
+            || source_code.contains("import("))
             && !source_code.contains("import type")  // Not TypeScript
             && !source_code.contains("fn main()")    // Not Rust
             && !source_code.contains("package main") // Not Go
             && !source_code.contains("#!/usr/bin/env python") // Not Python
+            && !source_code.contains("defmodule ") // Not Elixir
     }
 
     fn is_dependency_line(&self, line: &str) -> bool {
-        let mut this = self.clone();
-        this.get_import_re().is_match(line) || this.get_version_re().is_match(line)
+        self.get_import_re().is_match(line) || self.get_version_re().is_match(line)
     }
 
     fn extract_package_info(&self, line: &str) -> Option<Package> {
@@ -168,7 +227,7 @@ impl DependencyAnalyzer for JavaScriptAnalyzer {
 
         // Try import statement
         if let Some(cap) = this.get_import_re().captures(line) {
-            let import_path = cap.get(1).or_else(|| cap.get(2)).unwrap().as_str();
+            let import_path = import_path(&cap);
             if !this.is_local_import(import_path) {
                 let package_name = if import_path.contains('/') {
                     import_path.split('/').take(2).collect::<Vec<_>>().join("/")
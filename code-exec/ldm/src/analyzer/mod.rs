@@ -1,9 +1,16 @@
 use crate::{error::Result, types::Package};
 use async_trait::async_trait;
+use resolver::VersionResolver;
+use std::{
+    collections::HashMap,
+    sync::{Arc, OnceLock, PoisonError, RwLock},
+};
 
+pub mod elixir;
 pub mod go;
 pub mod javascript;
 pub mod python;
+pub mod resolver;
 pub mod rust;
 pub mod typescript;
 
@@ -12,9 +19,27 @@ pub trait DependencyAnalyzer: Send + Sync {
     /// Returns the name of the language this analyzer is for
     fn language(&self) -> &'static str;
 
+    /// Uses `versions`, keyed by package name, for packages the source does not pin,
+    /// falling back to the built-in defaults for packages not listed
+    fn with_default_versions(self, versions: HashMap<String, String>) -> Self
+    where
+        Self: Sized;
+
+    /// Asks `resolver` for the version of packages that are neither pinned in the source
+    /// nor have a default version, instead of guessing one
+    fn with_version_resolver(self, resolver: Arc<dyn VersionResolver>) -> Self
+    where
+        Self: Sized;
+
     /// Analyzes source code to extract dependencies
     async fn analyze_dependencies(&self, source_code: &str) -> Result<Vec<Package>>;
 
+    /// Returns every module the source code imports, the standard library's included, so
+    /// callers can refuse some. Analyzers that cannot tell return none.
+    fn imports(&self, _source_code: &str) -> Vec<String> {
+        Vec::new()
+    }
+
     /// Returns true if this analyzer can handle the given source code
     fn can_handle(&self, source_code: &str) -> bool;
 
@@ -25,24 +50,166 @@ pub trait DependencyAnalyzer: Send + Sync {
     fn extract_package_info(&self, line: &str) -> Option<Package>;
 }
 
-/// Analyzes source code to determine its language and extract dependencies
-pub async fn analyze_source_code(source_code: &str) -> Result<(String, Vec<Package>)> {
-    let analyzers: Vec<Box<dyn DependencyAnalyzer>> = vec![
-        Box::new(python::PythonAnalyzer::default()),
-        Box::new(javascript::JavaScriptAnalyzer::default()),
-        Box::new(typescript::TypeScriptAnalyzer::default()),
-        Box::new(rust::RustAnalyzer::default()),
-        Box::new(go::GoAnalyzer::default()),
-    ];
-
-    for analyzer in analyzers {
-        if analyzer.can_handle(source_code) {
-            let deps = analyzer.analyze_dependencies(source_code).await?;
-            return Ok((analyzer.language().to_string(), deps));
+/// Set of initialized analyzers, constructed once and reused across snippets
+pub struct AnalyzerRegistry {
+    analyzers: Vec<Box<dyn DependencyAnalyzer>>,
+}
+
+static SHARED_REGISTRY: OnceLock<Arc<AnalyzerRegistry>> = OnceLock::new();
+
+// Analyzers added with `register_analyzer`, tried before the built-in ones
+static REGISTERED_ANALYZERS: RwLock<Vec<Arc<dyn DependencyAnalyzer>>> = RwLock::new(Vec::new());
+
+impl Default for AnalyzerRegistry {
+    fn default() -> Self {
+        Self::with_analyzers(vec![
+            Box::new(python::PythonAnalyzer::default()),
+            Box::new(javascript::JavaScriptAnalyzer::default()),
+            Box::new(typescript::TypeScriptAnalyzer::default()),
+            Box::new(rust::RustAnalyzer::default()),
+            Box::new(go::GoAnalyzer::default()),
+            Box::new(elixir::ElixirAnalyzer),
+        ])
+    }
+}
+
+impl AnalyzerRegistry {
+    /// Creates a registry from analyzers, tried in order during detection
+    pub fn with_analyzers(analyzers: Vec<Box<dyn DependencyAnalyzer>>) -> Self {
+        Self { analyzers }
+    }
+
+    /// Adds an analyzer, tried before the ones already in the registry so it can claim
+    /// source code they would also handle
+    pub fn register(&mut self, analyzer: Box<dyn DependencyAnalyzer>) {
+        self.analyzers.insert(0, analyzer);
+    }
+
+    /// Creates the built-in analyzers with default versions replaced per language.
+    ///
+    /// `versions` maps a language name, such as `"rust"`, to package versions as taken
+    /// by [`DependencyAnalyzer::with_default_versions`].
+    pub fn with_default_versions(versions: HashMap<String, HashMap<String, String>>) -> Self {
+        Self::builtin(versions, None)
+    }
+
+    /// Creates the built-in analyzers with default versions replaced per language, asking
+    /// `resolver`, if any, for the versions of packages that still have none
+    pub fn builtin(
+        mut versions: HashMap<String, HashMap<String, String>>,
+        resolver: Option<Arc<dyn VersionResolver>>,
+    ) -> Self {
+        fn configure<A: DependencyAnalyzer + 'static>(
+            analyzer: A,
+            versions: &mut HashMap<String, HashMap<String, String>>,
+            resolver: &Option<Arc<dyn VersionResolver>>,
+        ) -> Box<dyn DependencyAnalyzer> {
+            let language = analyzer.language();
+            let analyzer =
+                analyzer.with_default_versions(versions.remove(language).unwrap_or_default());
+            match resolver {
+                Some(resolver) => Box::new(analyzer.with_version_resolver(resolver.clone())),
+                None => Box::new(analyzer),
+            }
+        }
+
+        Self::with_analyzers(vec![
+            configure(python::PythonAnalyzer::default(), &mut versions, &resolver),
+            configure(
+                javascript::JavaScriptAnalyzer::default(),
+                &mut versions,
+                &resolver,
+            ),
+            configure(
+                typescript::TypeScriptAnalyzer::default(),
+                &mut versions,
+                &resolver,
+            ),
+            configure(rust::RustAnalyzer::default(), &mut versions, &resolver),
+            configure(go::GoAnalyzer::default(), &mut versions, &resolver),
+            configure(elixir::ElixirAnalyzer, &mut versions, &resolver),
+        ])
+    }
+
+    /// Returns the process-wide registry of built-in analyzers
+    pub fn shared() -> Arc<Self> {
+        SHARED_REGISTRY
+            .get_or_init(|| Arc::new(Self::default()))
+            .clone()
+    }
+
+    /// Returns the analyzer for `language`, such as `"python"`
+    pub fn analyzer(&self, language: &str) -> Option<&dyn DependencyAnalyzer> {
+        self.analyzers
+            .iter()
+            .find(|analyzer| analyzer.language() == language)
+            .map(|analyzer| analyzer.as_ref())
+    }
+
+    /// Returns the language of every analyzer that can handle the source code
+    pub fn detect_languages(&self, source_code: &str) -> Vec<&'static str> {
+        self.analyzers
+            .iter()
+            .filter(|analyzer| analyzer.can_handle(source_code))
+            .map(|analyzer| analyzer.language())
+            .collect()
+    }
+
+    /// Determines the language of the source code and extracts its dependencies
+    pub async fn detect_and_analyze(&self, source_code: &str) -> Result<(String, Vec<Package>)> {
+        for analyzer in &self.analyzers {
+            if analyzer.can_handle(source_code) {
+                let deps = analyzer.analyze_dependencies(source_code).await?;
+                return Ok((analyzer.language().to_string(), deps));
+            }
         }
+
+        Err(crate::error::Error::Validation(
+            "Could not determine language of source code".into(),
+        ))
     }
+}
 
-    Err(crate::error::Error::Validation(
-        "Could not determine language of source code".into(),
-    ))
+/// Adds an analyzer to every later [`analyze_source_code`] call, ahead of the built-in
+/// ones, so languages from internal package ecosystems can be supported without changes
+/// to this crate. Analyzers registered later are tried after earlier ones.
+pub fn register_analyzer(analyzer: Box<dyn DependencyAnalyzer>) {
+    REGISTERED_ANALYZERS
+        .write()
+        .unwrap_or_else(PoisonError::into_inner)
+        .push(Arc::from(analyzer));
+}
+
+/// Analyzes source code to determine its language and extract dependencies.
+///
+/// Analyzers added with [`register_analyzer`] are tried first. `default_versions`
+/// replaces the built-in versions of unpinned packages, keyed by language and then
+/// package name as for [`AnalyzerRegistry::with_default_versions`].
+pub async fn analyze_source_code(
+    source_code: &str,
+    default_versions: Option<&HashMap<String, HashMap<String, String>>>,
+) -> Result<(String, Vec<Package>)> {
+    let registered = REGISTERED_ANALYZERS
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .iter()
+        .find(|analyzer| analyzer.can_handle(source_code))
+        .cloned();
+    if let Some(analyzer) = registered {
+        let deps = analyzer.analyze_dependencies(source_code).await?;
+        return Ok((analyzer.language().to_string(), deps));
+    }
+
+    match default_versions {
+        Some(versions) => {
+            AnalyzerRegistry::with_default_versions(versions.clone())
+                .detect_and_analyze(source_code)
+                .await
+        }
+        None => {
+            AnalyzerRegistry::shared()
+                .detect_and_analyze(source_code)
+                .await
+        }
+    }
 }
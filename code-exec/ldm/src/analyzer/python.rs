@@ -1,43 +1,75 @@
-use super::DependencyAnalyzer;
+use super::{
+    resolver::{resolve_guessed_versions, PackageRegistry, VersionResolver},
+    DependencyAnalyzer,
+};
 use crate::{
     error::Result,
     types::{Package, PackageSource},
 };
 use async_trait::async_trait;
-use regex::Regex;
+use regex::{Captures, Regex};
 use std::collections::HashMap;
 use std::collections::HashSet;
+use std::sync::{Arc, LazyLock};
+
+static IMPORT_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?m)^\s*(?:from\s+([a-zA-Z0-9_.-]+)(?:\s+import\s+(.*))?|import\s+([a-zA-Z0-9_.-]+)(?:\s+as\s+.*)?)",
+    )
+    .unwrap()
+});
+
+static PIP_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"#\s*pip\s*:\s*([a-zA-Z0-9_-]+)(?:>=|==|<=|>|<|~=|!=)?([0-9a-zA-Z.-]*)").unwrap()
+});
+
+/// Distributions installing a module under a different name, keyed by module path. A key
+/// also covers its submodules, and the longest matching key wins.
+static MODULE_PACKAGES: &[(&str, &str)] = &[
+    ("attr", "attrs"),
+    ("bs4", "beautifulsoup4"),
+    ("Crypto", "pycryptodome"),
+    ("cv2", "opencv-python"),
+    ("dateutil", "python-dateutil"),
+    ("docx", "python-docx"),
+    ("dotenv", "python-dotenv"),
+    ("fitz", "pymupdf"),
+    ("google.cloud.pubsub_v1", "google-cloud-pubsub"),
+    ("google.generativeai", "google-generativeai"),
+    ("google.protobuf", "protobuf"),
+    ("jwt", "pyjwt"),
+    ("magic", "python-magic"),
+    ("MySQLdb", "mysqlclient"),
+    ("OpenSSL", "pyopenssl"),
+    ("PIL", "pillow"),
+    ("pptx", "python-pptx"),
+    ("serial", "pyserial"),
+    ("skimage", "scikit-image"),
+    ("sklearn", "scikit-learn"),
+    ("yaml", "pyyaml"),
+    ("zmq", "pyzmq"),
+];
+
+/// Namespace packages whose subpackages are distributed separately, with the prefix of
+/// their distribution names, e.g. `google.cloud.storage` comes from `google-cloud-storage`
+static NAMESPACE_PACKAGES: &[(&str, &str)] = &[("google.cloud", "google-cloud-")];
 
 #[derive(Default, Clone)]
 pub struct PythonAnalyzer {
-    import_re: Option<Regex>,
-    pip_re: Option<Regex>,
     default_versions: HashMap<String, String>,
+    /// Versions set with `with_default_versions`, taking precedence over the built-ins
+    version_overrides: HashMap<String, String>,
+    /// Looks up versions for packages without a default
+    resolver: Option<Arc<dyn VersionResolver>>,
 }
 
 impl PythonAnalyzer {
-    fn get_import_re(&mut self) -> &Regex {
-        if self.import_re.is_none() {
-            self.import_re = Some(
-                Regex::new(
-                    r"(?m)^\s*(?:from\s+([a-zA-Z0-9_.-]+)(?:\s+import\s+.*)?|import\s+([a-zA-Z0-9_.-]+)(?:\s+as\s+.*)?)"
-                )
-                .unwrap(),
-            );
-        }
-        self.import_re.as_ref().unwrap()
+    fn get_import_re(&self) -> &'static Regex {
+        &IMPORT_RE
     }
 
-    fn get_pip_re(&mut self) -> &Regex {
-        if self.pip_re.is_none() {
-            self.pip_re = Some(
-                Regex::new(
-                    r"#\s*pip\s*:\s*([a-zA-Z0-9_-]+)(?:>=|==|<=|>|<|~=|!=)?([0-9a-zA-Z.-]*)",
-                )
-                .unwrap(),
-            );
-        }
-        self.pip_re.as_ref().unwrap()
+    fn get_pip_re(&self) -> &'static Regex {
+        &PIP_RE
     }
 
     fn is_stdlib_module(&self, module: &str) -> bool {
@@ -103,9 +135,68 @@ impl PythonAnalyzer {
         }
     }
 
+    /// Modules an import line brings in. `from google.cloud import storage` imports
+    /// `google.cloud.storage`, which matters for namespace packages only.
+    fn imported_modules(cap: &Captures) -> Vec<String> {
+        let Some(module) = cap.get(1).or_else(|| cap.get(3)).map(|m| m.as_str()) else {
+            return Vec::new();
+        };
+        let names = cap.get(2).map(|m| m.as_str()).unwrap_or("");
+
+        if !NAMESPACE_PACKAGES.iter().any(|(ns, _)| *ns == module) {
+            return vec![module.to_string()];
+        }
+        names
+            .split(',')
+            .filter_map(|name| {
+                name.trim_matches(|c: char| c.is_whitespace() || c == '(' || c == ')')
+                    .split_whitespace()
+                    .next()
+            })
+            .map(|name| format!("{}.{}", module, name))
+            .collect()
+    }
+
+    /// Distribution providing a module, `None` for the standard library
+    fn package_for_module(&self, module: &str) -> Option<String> {
+        let covers = |key: &str| {
+            module == key
+                || module
+                    .strip_prefix(key)
+                    .is_some_and(|rest| rest.starts_with('.'))
+        };
+
+        if let Some((_, package)) = MODULE_PACKAGES
+            .iter()
+            .filter(|(key, _)| covers(key))
+            .max_by_key(|(key, _)| key.len())
+        {
+            return Some(package.to_string());
+        }
+
+        for (namespace, prefix) in NAMESPACE_PACKAGES {
+            if let Some(rest) = module
+                .strip_prefix(namespace)
+                .and_then(|rest| rest.strip_prefix('.'))
+            {
+                let subpackage = rest.split('.').next().unwrap_or(rest);
+                return Some(format!("{}{}", prefix, subpackage.replace('_', "-")));
+            }
+        }
+
+        let base_module = module.split('.').next().unwrap_or(module);
+        (!self.is_stdlib_module(base_module)).then(|| base_module.to_string())
+    }
+
+    fn has_default_version(&self, package_name: &str) -> bool {
+        self.version_overrides.contains_key(package_name)
+            || self.default_versions.contains_key(package_name)
+    }
+
     fn get_default_version(&self, package_name: &str) -> String {
-        self.default_versions
+        self.version_overrides
             .get(package_name)
+            .or_else(|| self.default_versions.get(package_name))
             .cloned()
             .unwrap_or_else(|| ">=1.0.0".to_string())
     }
@@ -117,6 +208,16 @@ impl DependencyAnalyzer for PythonAnalyzer {
         "python"
     }
 
+    fn with_default_versions(mut self, versions: HashMap<String, String>) -> Self {
+        self.version_overrides.extend(versions);
+        self
+    }
+
+    fn with_version_resolver(mut self, resolver: Arc<dyn VersionResolver>) -> Self {
+        self.resolver = Some(resolver);
+        self
+    }
+
     async fn analyze_dependencies(&self, source_code: &str) -> Result<Vec<Package>> {
         let mut this = self.clone();
         this.initialize_default_versions();
@@ -134,29 +235,19 @@ impl DependencyAnalyzer for PythonAnalyzer {
 
             // Check for imports
             if let Some(cap) = this.get_import_re().captures(line) {
-                let module = cap
-                    .get(1)
-                    .or_else(|| cap.get(2))
-                    .map(|m| m.as_str())
-                    .unwrap_or("");
-
-                let base_module = module.split('.').next().unwrap_or(module);
-
-                if !this.is_stdlib_module(base_module) {
-                    // Map certain module names to their package names
-                    let package_name = match base_module {
-                        "PIL" => "pillow",
-                        _ => base_module,
+                for module in Self::imported_modules(&cap) {
+                    let Some(package_name) = this.package_for_module(&module) else {
+                        continue;
                     };
 
                     let version = explicit_versions
-                        .get(package_name)
+                        .get(&package_name)
                         .cloned()
                         .flatten()
-                        .unwrap_or_else(|| this.get_default_version(package_name));
+                        .unwrap_or_else(|| this.get_default_version(&package_name));
 
                     packages.insert(Package {
-                        name: package_name.to_string(),
+                        name: package_name,
                         version: Some(version),
                         source: PackageSource::Custom("pip".to_string()),
                     });
@@ -164,7 +255,55 @@ impl DependencyAnalyzer for PythonAnalyzer {
             }
         }
 
-        Ok(packages.into_iter().collect())
+        let mut packages: Vec<Package> = packages.into_iter().collect();
+        resolve_guessed_versions(
+            this.resolver.as_ref(),
+            PackageRegistry::PyPI,
+            &mut packages,
+            |name| {
+                !explicit_versions.get(name).is_some_and(Option::is_some)
+                    && !this.has_default_version(name)
+            },
+            |version| format!(">={}", version),
+        )
+        .await;
+        Ok(packages)
+    }
+
+    fn imports(&self, source_code: &str) -> Vec<String> {
+        let mut imports = Vec::new();
+        // Statements separated by `;` share a line
+        for statement in source_code.lines().flat_map(|line| line.split(';')) {
+            let Some(cap) = self.get_import_re().captures(statement) else {
+                continue;
+            };
+            if let Some(module) = cap.get(1) {
+                // `from m import a, b` also takes `m.a` and `m.b`
+                let module = module.as_str();
+                imports.push(module.to_string());
+                let names = cap.get(2).map_or("", |m| m.as_str());
+                imports.extend(
+                    names
+                        .split(',')
+                        .filter_map(|name| {
+                            name.trim_matches(|c: char| c.is_whitespace() || c == '(' || c == ')')
+                                .split_whitespace()
+                                .next()
+                        })
+                        .filter(|name| *name != "*")
+                        .map(|name| format!("{}.{}", module, name)),
+                );
+            } else {
+                // `import a, b as c` imports every listed module
+                let listed = statement.trim_start().trim_start_matches("import");
+                imports.extend(
+                    listed
+                        .split(',')
+                        .filter_map(|item| item.split_whitespace().next().map(str::to_string)),
+                );
+            }
+        }
+        imports
     }
 
     fn can_handle(&self, source_code: &str) -> bool {
@@ -174,7 +313,8 @@ impl DependencyAnalyzer for PythonAnalyzer {
 
         let has_python_imports = source_code.contains("import ") 
             && !source_code.contains("from '")  // Not JS/TS
-            && !source_code.contains("package main"); // Not Go
+            && !source_code.contains("package main") // Not Go
+            && !source_code.contains("defmodule "); // Not Elixir
 
         let has_python_def = source_code.contains("def ")
             && source_code.contains(":")
@@ -184,15 +324,12 @@ impl DependencyAnalyzer for PythonAnalyzer {
     }
 
     fn is_dependency_line(&self, line: &str) -> bool {
-        let mut this = self.clone();
-        this.get_import_re().is_match(line) || this.get_pip_re().is_match(line)
+        self.get_import_re().is_match(line) || self.get_pip_re().is_match(line)
     }
 
     fn extract_package_info(&self, line: &str) -> Option<Package> {
-        let mut this = self.clone();
-
         // Try pip comment first
-        if let Some(cap) = this.get_pip_re().captures(line) {
+        if let Some(cap) = self.get_pip_re().captures(line) {
             let name = cap.get(1).unwrap().as_str();
             let version = cap.get(2).map(|m| m.as_str().to_string());
             return Some(Package {
@@ -203,22 +340,15 @@ impl DependencyAnalyzer for PythonAnalyzer {
         }
 
         // Try import statement
-        if let Some(cap) = this.get_import_re().captures(line) {
-            let module = cap
-                .get(1)
-                .or_else(|| cap.get(2))
-                .map(|m| m.as_str())
-                .unwrap_or("");
-
-            let base_module = module.split('.').next().unwrap_or(module);
-
-            if !this.is_stdlib_module(base_module) {
-                return Some(Package {
-                    name: base_module.to_string(),
-                    version: None,
-                    source: PackageSource::Custom("pip".to_string()),
-                });
-            }
+        if let Some(cap) = self.get_import_re().captures(line) {
+            let package_name = Self::imported_modules(&cap)
+                .iter()
+                .find_map(|module| self.package_for_module(module))?;
+            return Some(Package {
+                name: package_name,
+                version: None,
+                source: PackageSource::Custom("pip".to_string()),
+            });
         }
 
         None
@@ -0,0 +1,160 @@
+use crate::types::Package;
+use async_trait::async_trait;
+use serde_json::Value;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tracing::warn;
+
+/// Package registries versions can be looked up in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PackageRegistry {
+    CratesIo,
+    Npm,
+    PyPI,
+}
+
+impl PackageRegistry {
+    fn default_url(&self) -> &'static str {
+        match self {
+            PackageRegistry::CratesIo => "https://crates.io",
+            PackageRegistry::Npm => "https://registry.npmjs.org",
+            PackageRegistry::PyPI => "https://pypi.org",
+        }
+    }
+}
+
+/// Looks up versions for packages an analyzer has no default version for
+#[async_trait]
+pub trait VersionResolver: Send + Sync {
+    /// Returns the latest stable version of the package, or `None` if it is unknown
+    async fn latest_version(&self, registry: PackageRegistry, package: &str) -> Option<String>;
+}
+
+/// Resolver querying the public registries over HTTP, caching their answers
+pub struct RegistryResolver {
+    client: reqwest::Client,
+    urls: HashMap<PackageRegistry, String>,
+    offline: bool,
+    cache: Mutex<HashMap<(PackageRegistry, String), Option<String>>>,
+}
+
+impl RegistryResolver {
+    pub fn new() -> Self {
+        Self {
+            client: reqwest::Client::builder()
+                .user_agent(concat!("ldm/", env!("CARGO_PKG_VERSION")))
+                .timeout(Duration::from_secs(10))
+                .build()
+                .expect("HTTP client configuration is valid"),
+            urls: HashMap::new(),
+            offline: false,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Queries `url` instead of the public instance of `registry`
+    pub fn with_registry_url(mut self, registry: PackageRegistry, url: &str) -> Self {
+        self.urls
+            .insert(registry, url.trim_end_matches('/').to_string());
+        self
+    }
+
+    /// Never queries a registry, leaving analyzers with their fallback versions
+    pub fn with_offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
+        self
+    }
+
+    fn url(&self, registry: PackageRegistry) -> &str {
+        self.urls
+            .get(&registry)
+            .map(String::as_str)
+            .unwrap_or_else(|| registry.default_url())
+    }
+
+    /// Fetches the latest version, `Ok(None)` meaning the registry has no such package
+    async fn fetch(
+        &self,
+        registry: PackageRegistry,
+        package: &str,
+    ) -> reqwest::Result<Option<String>> {
+        let base = self.url(registry);
+        let (url, pointer) = match registry {
+            PackageRegistry::CratesIo => (
+                format!("{}/api/v1/crates/{}", base, package),
+                "/crate/max_stable_version",
+            ),
+            // Scoped packages keep their `@` but escape the `/`
+            PackageRegistry::Npm => (
+                format!("{}/{}/latest", base, package.replace('/', "%2F")),
+                "/version",
+            ),
+            PackageRegistry::PyPI => (format!("{}/pypi/{}/json", base, package), "/info/version"),
+        };
+
+        let response = self.client.get(url).send().await?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let body: Value = response.error_for_status()?.json().await?;
+        Ok(body
+            .pointer(pointer)
+            .and_then(Value::as_str)
+            .filter(|version| !version.is_empty())
+            .map(str::to_string))
+    }
+}
+
+impl Default for RegistryResolver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl VersionResolver for RegistryResolver {
+    async fn latest_version(&self, registry: PackageRegistry, package: &str) -> Option<String> {
+        if self.offline {
+            return None;
+        }
+
+        let key = (registry, package.to_string());
+        if let Some(cached) = self.cache.lock().unwrap().get(&key) {
+            return cached.clone();
+        }
+
+        match self.fetch(registry, package).await {
+            Ok(version) => {
+                self.cache.lock().unwrap().insert(key, version.clone());
+                version
+            }
+            // Failed lookups are retried next time rather than cached
+            Err(e) => {
+                warn!("Failed to look up the latest version of {}: {}", package, e);
+                None
+            }
+        }
+    }
+}
+
+/// Replaces the fallback version of every package `guessed` selects with the registry's
+/// latest version, written as a requirement by `requirement`
+pub(crate) async fn resolve_guessed_versions(
+    resolver: Option<&Arc<dyn VersionResolver>>,
+    registry: PackageRegistry,
+    packages: &mut [Package],
+    guessed: impl Fn(&str) -> bool,
+    requirement: impl Fn(&str) -> String,
+) {
+    let Some(resolver) = resolver else {
+        return;
+    };
+    for package in packages.iter_mut().filter(|package| guessed(&package.name)) {
+        if let Some(version) = resolver.latest_version(registry, &package.name).await {
+            package.version = Some(requirement(&version));
+        }
+    }
+}
@@ -1,4 +1,7 @@
-use super::DependencyAnalyzer;
+use super::{
+    resolver::{resolve_guessed_versions, PackageRegistry, VersionResolver},
+    DependencyAnalyzer,
+};
 use crate::{
     error::Result,
     types::{Package, PackageSource},
@@ -6,41 +9,58 @@ use crate::{
 use async_trait::async_trait;
 use regex::Regex;
 use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, LazyLock};
+
+// Match both simple and complex use statements
+static USE_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r#"(?mx)
+        ^[\s\n]*use\s+
+        ([a-zA-Z0-9_]+)  # Base crate name
+        (?:::[a-zA-Z0-9_:,\s{}\[\]]+)?  # Optional path components
+        \s*;  # Ending semicolon
+        |^[\s\n]*extern\s+crate\s+([a-zA-Z0-9_]+)\s*;  # extern crate form
+        "#,
+    )
+    .unwrap()
+});
+
+static VERSION_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"//\s*cargo-version:\s*([a-zA-Z0-9_-]+)\s*=\s*"([0-9.]+)""#).unwrap()
+});
+
+// Syntax only Rust has: `fn` and `impl` items, `let mut`, `use` paths, `extern crate`
+// and attributes. A `use` and a `::` anywhere in the source also show up in C++ and prose.
+static RUST_SYNTAX_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r#"(?mx)
+        ^\s*(?:pub(?:\([^)]*\))?\s+)?(?:const\s+)?(?:async\s+)?(?:unsafe\s+)?fn\s+\w+\s*[<(]
+        |^\s*(?:unsafe\s+)?impl\b(?:<[^>]*>)?\s+\w
+        |\blet\s+mut\s+\w
+        |^\s*(?:pub(?:\([^)]*\))?\s+)?use\s+(?:::)?[a-zA-Z_]\w*::
+        |^\s*extern\s+crate\s+\w+
+        |^\s*\#!?\[[a-zA-Z_]\w*(?:::\w+)*\s*[(\]=]
+        "#,
+    )
+    .unwrap()
+});
 
 #[derive(Default, Clone)]
 pub struct RustAnalyzer {
-    use_re: Option<Regex>,
-    version_re: Option<Regex>,
     default_versions: HashMap<String, String>,
+    /// Versions set with `with_default_versions`, taking precedence over the built-ins
+    version_overrides: HashMap<String, String>,
+    /// Looks up versions for packages without a default
+    resolver: Option<Arc<dyn VersionResolver>>,
 }
 
 impl RustAnalyzer {
-    fn get_use_re(&mut self) -> &Regex {
-        if self.use_re.is_none() {
-            // Match both simple and complex use statements
-            self.use_re = Some(
-                Regex::new(
-                    r#"(?mx)
-                    ^[\s\n]*use\s+
-                    ([a-zA-Z0-9_]+)  # Base crate name
-                    (?:::[a-zA-Z0-9_:,\s{}\[\]]+)?  # Optional path components
-                    \s*;  # Ending semicolon
-                    |^[\s\n]*extern\s+crate\s+([a-zA-Z0-9_]+)\s*;  # extern crate form
-                    "#,
-                )
-                .unwrap(),
-            );
-        }
-        self.use_re.as_ref().unwrap()
+    fn get_use_re(&self) -> &'static Regex {
+        &USE_RE
     }
 
-    fn get_version_re(&mut self) -> &Regex {
-        if self.version_re.is_none() {
-            self.version_re = Some(
-                Regex::new(r#"//\s*cargo-version:\s*([a-zA-Z0-9_-]+)\s*=\s*"([0-9.]+)""#).unwrap(),
-            );
-        }
-        self.version_re.as_ref().unwrap()
+    fn get_version_re(&self) -> &'static Regex {
+        &VERSION_RE
     }
 
     fn get_canonical_crate_name(&self, name: &str) -> String {
@@ -68,7 +88,7 @@ impl RustAnalyzer {
         }
 
         // Check if it's in default versions
-        if self.default_versions.contains_key(name) {
+        if self.default_versions.contains_key(name) || self.version_overrides.contains_key(name) {
             return name.to_string();
         }
 
@@ -128,9 +148,15 @@ impl RustAnalyzer {
         import_path.starts_with("std::")
     }
 
+    fn has_default_version(&self, package_name: &str) -> bool {
+        self.version_overrides.contains_key(package_name)
+            || self.default_versions.contains_key(package_name)
+    }
+
     fn get_default_version(&self, package_name: &str) -> String {
-        self.default_versions
+        self.version_overrides
             .get(package_name)
+            .or_else(|| self.default_versions.get(package_name))
             .cloned()
             .unwrap_or_else(|| "0.1".to_string())
     }
@@ -151,6 +177,16 @@ impl DependencyAnalyzer for RustAnalyzer {
         "rust"
     }
 
+    fn with_default_versions(mut self, versions: HashMap<String, String>) -> Self {
+        self.version_overrides.extend(versions);
+        self
+    }
+
+    fn with_version_resolver(mut self, resolver: Arc<dyn VersionResolver>) -> Self {
+        self.resolver = Some(resolver);
+        self
+    }
+
     async fn analyze_dependencies(&self, source_code: &str) -> Result<Vec<Package>> {
         let mut this = self.clone();
         this.initialize_default_versions();
@@ -186,19 +222,24 @@ impl DependencyAnalyzer for RustAnalyzer {
             }
         }
 
-        Ok(packages.into_iter().collect())
+        let mut packages: Vec<Package> = packages.into_iter().collect();
+        resolve_guessed_versions(
+            this.resolver.as_ref(),
+            PackageRegistry::CratesIo,
+            &mut packages,
+            |name| !explicit_versions.contains_key(name) && !this.has_default_version(name),
+            |version| version.to_string(),
+        )
+        .await;
+        Ok(packages)
     }
 
     fn can_handle(&self, source_code: &str) -> bool {
-        // Rust-specific patterns
-        source_code.contains("fn main()")
-            || source_code.contains("#[derive")
-            || (source_code.contains("use ") && source_code.contains("::"))
+        RUST_SYNTAX_RE.is_match(source_code)
     }
 
     fn is_dependency_line(&self, line: &str) -> bool {
-        let mut this = self.clone();
-        this.get_use_re().is_match(line) || this.get_version_re().is_match(line)
+        self.get_use_re().is_match(line) || self.get_version_re().is_match(line)
     }
 
     fn extract_package_info(&self, line: &str) -> Option<Package> {
@@ -1,4 +1,7 @@
-use super::DependencyAnalyzer;
+use super::{
+    resolver::{resolve_guessed_versions, PackageRegistry, VersionResolver},
+    DependencyAnalyzer,
+};
 use crate::{
     error::Result,
     types::{Package, PackageSource},
@@ -6,43 +9,46 @@ use crate::{
 use async_trait::async_trait;
 use regex::Regex;
 use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, LazyLock};
+
+static IMPORT_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r#"(?mx)
+        ^[\s\n]*import\s+
+        (?:type\s+)?
+        (?:
+            \{[^}]*\}\s+from\s+|
+            [^{}\s]+\s*,\s*\{[^}]*\}\s+from\s+|
+            [^{}\s]+\s+from\s+|
+            \*\s+as\s+[^{}\s]+\s+from\s+|
+            type\s+\{[^}]*\}\s+from\s+
+        )
+        ['"]([^'"]+)['"]
+        # Dynamic import() with a string literal, template literals cannot be resolved
+        |\bimport\(\s*['"]([^'"]+)['"]\s*\)"#,
+    )
+    .unwrap()
+});
+
+static VERSION_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"//\s*npm:\s*([@\w\-/.]+)@([\w\-~^.<>=]+)"#).unwrap());
 
 #[derive(Default, Clone)]
 pub struct TypeScriptAnalyzer {
-    import_re: Option<Regex>,
-    version_re: Option<Regex>,
     default_versions: HashMap<String, String>,
+    /// Versions set with `with_default_versions`, taking precedence over the built-ins
+    version_overrides: HashMap<String, String>,
+    /// Looks up versions for packages without a default
+    resolver: Option<Arc<dyn VersionResolver>>,
 }
 
 impl TypeScriptAnalyzer {
-    fn get_import_re(&mut self) -> &Regex {
-        if self.import_re.is_none() {
-            self.import_re = Some(
-                Regex::new(
-                    r#"(?mx)
-                    ^[\s\n]*import\s+
-                    (?:type\s+)?
-                    (?:
-                        \{[^}]*\}\s+from\s+|
-                        [^{}\s]+\s*,\s*\{[^}]*\}\s+from\s+|
-                        [^{}\s]+\s+from\s+|
-                        \*\s+as\s+[^{}\s]+\s+from\s+|
-                        type\s+\{[^}]*\}\s+from\s+
-                    )
-                    ['"]([^'"]+)['"]"#,
-                )
-                .unwrap(),
-            );
-        }
-        self.import_re.as_ref().unwrap()
+    fn get_import_re(&self) -> &'static Regex {
+        &IMPORT_RE
     }
 
-    fn get_version_re(&mut self) -> &Regex {
-        if self.version_re.is_none() {
-            self.version_re =
-                Some(Regex::new(r#"//\s*npm:\s*([@\w\-/.]+)@([\w\-~^.<>=]+)"#).unwrap());
-        }
-        self.version_re.as_ref().unwrap()
+    fn get_version_re(&self) -> &'static Regex {
+        &VERSION_RE
     }
 
     fn initialize_default_versions(&mut self) {
@@ -99,9 +105,15 @@ impl TypeScriptAnalyzer {
         import_path.starts_with('.') || import_path.starts_with('/')
     }
 
+    fn has_default_version(&self, package_name: &str) -> bool {
+        self.version_overrides.contains_key(package_name)
+            || self.default_versions.contains_key(package_name)
+    }
+
     fn get_default_version(&self, package_name: &str) -> String {
-        self.default_versions
+        self.version_overrides
             .get(package_name)
+            .or_else(|| self.default_versions.get(package_name))
             .cloned()
             .unwrap_or_else(|| "^1.0.0".to_string())
     }
@@ -113,6 +125,16 @@ impl DependencyAnalyzer for TypeScriptAnalyzer {
         "typescript"
     }
 
+    fn with_default_versions(mut self, versions: HashMap<String, String>) -> Self {
+        self.version_overrides.extend(versions);
+        self
+    }
+
+    fn with_version_resolver(mut self, resolver: Arc<dyn VersionResolver>) -> Self {
+        self.resolver = Some(resolver);
+        self
+    }
+
     async fn analyze_dependencies(&self, source_code: &str) -> Result<Vec<Package>> {
         let mut this = self.clone();
         this.initialize_default_versions();
@@ -156,7 +178,7 @@ impl DependencyAnalyzer for TypeScriptAnalyzer {
         // Then process imports
         let import_re = this.get_import_re().clone();
         for cap in import_re.captures_iter(source_code) {
-            let import_path = cap.get(1).unwrap().as_str();
+            let import_path = cap.get(1).or_else(|| cap.get(2)).unwrap().as_str();
             if !this.is_local_import(import_path) {
                 // Get the base package name (handle scoped packages)
                 let package_name = if import_path.contains('/') {
@@ -179,7 +201,20 @@ impl DependencyAnalyzer for TypeScriptAnalyzer {
             }
         }
 
-        Ok(packages.into_iter().collect())
+        let mut packages: Vec<Package> = packages.into_iter().collect();
+        resolve_guessed_versions(
+            this.resolver.as_ref(),
+            PackageRegistry::Npm,
+            &mut packages,
+            |name| !explicit_versions.contains_key(name) && !this.has_default_version(name),
+            |version| format!("^{}", version),
+        )
+        .await;
+        Ok(packages)
+    }
+
+    fn imports(&self, source_code: &str) -> Vec<String> {
+        super::javascript::module_imports(self.get_import_re(), source_code)
     }
 
     fn can_handle(&self, source_code: &str) -> bool {
@@ -202,8 +237,7 @@ impl DependencyAnalyzer for TypeScriptAnalyzer {
     }
 
     fn is_dependency_line(&self, line: &str) -> bool {
-        let mut this = self.clone();
-        this.get_import_re().is_match(line) || this.get_version_re().is_match(line)
+        self.get_import_re().is_match(line) || self.get_version_re().is_match(line)
     }
 
     fn extract_package_info(&self, line: &str) -> Option<Package> {
@@ -223,7 +257,7 @@ impl DependencyAnalyzer for TypeScriptAnalyzer {
 
         // Try import statement
         if let Some(cap) = this.get_import_re().captures(line) {
-            let import_path = cap.get(1).unwrap().as_str();
+            let import_path = cap.get(1).or_else(|| cap.get(2)).unwrap().as_str();
             if !this.is_local_import(import_path) {
                 let package_name = if import_path.contains('/') {
                     import_path.split('/').take(2).collect::<Vec<_>>().join("/")
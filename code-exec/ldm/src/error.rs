@@ -31,6 +31,15 @@ pub enum Error {
 
     #[error("Validation error: {0}")]
     Validation(String),
+
+    #[error("Package {package} is not installed and cannot be downloaded offline")]
+    OfflineDependencyMissing { package: String },
+
+    #[error("Conflicting versions of {package} requested: {}", .versions.join(", "))]
+    DependencyConflict {
+        package: String,
+        versions: Vec<String>,
+    },
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
@@ -1,4 +1,4 @@
-use super::LanguageProvider;
+use super::{remove_temp_dir, LanguageProvider};
 use crate::{
     error::{Error, Result},
     types::{Package, PackageSource, Tool},
@@ -120,27 +120,10 @@ impl LanguageProvider for GoProvider {
         ])
     }
 
-    async fn cleanup(&self) -> Result<()> {
+    async fn cleanup(&mut self, keep_artifacts: bool) -> Result<()> {
         info!("Cleaning up Go environment");
 
-        // Clean up GOPATH if it exists
-        if let Some(ref go_path) = self.go_path {
-            if go_path.exists() {
-                debug!("Removing GOPATH directory: {}", go_path.display());
-                std::fs::remove_dir_all(go_path).map_err(|e| {
-                    Error::Environment(format!("Failed to remove GOPATH directory: {}", e))
-                })?;
-            }
-        }
-
-        // The TempDir will be automatically cleaned up when dropped
-        if let Some(project_dir) = &self.project_dir {
-            debug!(
-                "Removing project directory: {}",
-                project_dir.path().display()
-            );
-        }
-
-        Ok(())
+        self.go_path = None;
+        remove_temp_dir(self.project_dir.take(), keep_artifacts).await
     }
 }
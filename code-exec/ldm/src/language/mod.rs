@@ -1,4 +1,6 @@
 use async_trait::async_trait;
+use tempfile::TempDir;
+use tracing::{debug, info};
 
 pub mod go;
 pub mod javascript;
@@ -7,7 +9,7 @@ pub mod rust;
 pub mod typescript;
 
 use crate::{
-    error::Result,
+    error::{Error, Result},
     types::{Package, Tool},
 };
 
@@ -34,6 +36,29 @@ pub trait LanguageProvider: Send + Sync {
     /// Returns the command to compile a file in this language (if applicable)
     fn get_compile_command(&self, file_path: &str) -> Option<Vec<String>>;
 
-    /// Cleans up any temporary files or environment setup
-    async fn cleanup(&self) -> Result<()>;
+    /// Removes the temporary files and environment created by `setup_environment` right
+    /// away instead of when the provider is dropped. Calling it again, or before any
+    /// setup, does nothing.
+    ///
+    /// With `keep_artifacts`, the files are left on disk for inspection instead.
+    async fn cleanup(&mut self, keep_artifacts: bool) -> Result<()>;
+}
+
+/// Remove a provider's temporary directory, or keep it and log where it is
+pub(crate) async fn remove_temp_dir(dir: Option<TempDir>, keep_artifacts: bool) -> Result<()> {
+    let Some(dir) = dir else {
+        return Ok(());
+    };
+    // Detached either way, so dropping it cannot delete it again or keep a kept one
+    let path = dir.into_path();
+
+    if keep_artifacts {
+        info!("Keeping installation artifacts in {}", path.display());
+        return Ok(());
+    }
+
+    debug!("Removing temporary directory: {}", path.display());
+    tokio::fs::remove_dir_all(&path)
+        .await
+        .map_err(|e| Error::Environment(format!("Failed to remove {}: {}", path.display(), e)))
 }
@@ -1,4 +1,4 @@
-use super::LanguageProvider;
+use super::{remove_temp_dir, LanguageProvider};
 use crate::{
     error::{Error, Result},
     types::{Package, PackageSource, Tool},
@@ -154,17 +154,10 @@ impl LanguageProvider for PythonProvider {
         None // Python is an interpreted language
     }
 
-    async fn cleanup(&self) -> Result<()> {
+    async fn cleanup(&mut self, keep_artifacts: bool) -> Result<()> {
         info!("Cleaning up Python environment");
 
-        // The TempDir will be automatically cleaned up when dropped
-        if let Some(venv_dir) = &self.venv_dir {
-            debug!(
-                "Removing virtualenv directory: {}",
-                venv_dir.path().display()
-            );
-        }
-
-        Ok(())
+        self.python_path = None;
+        remove_temp_dir(self.venv_dir.take(), keep_artifacts).await
     }
 }
@@ -1,4 +1,4 @@
-use super::LanguageProvider;
+use super::{remove_temp_dir, LanguageProvider};
 use crate::{
     error::{Error, Result},
     types::{Package, PackageSource, Tool},
@@ -136,27 +136,50 @@ impl LanguageProvider for RustProvider {
         }
     }
 
-    async fn cleanup(&self) -> Result<()> {
+    async fn cleanup(&mut self, keep_artifacts: bool) -> Result<()> {
         info!("Cleaning up Rust environment");
 
-        // Clean up target directory if it exists
-        if let Some(ref target_dir) = self.target_dir {
-            if target_dir.exists() {
-                debug!("Removing target directory: {}", target_dir.display());
-                std::fs::remove_dir_all(target_dir).map_err(|e| {
-                    Error::Environment(format!("Failed to remove target directory: {}", e))
-                })?;
-            }
-        }
+        self.target_dir = None;
+        remove_temp_dir(self.project_dir.take(), keep_artifacts).await
+    }
+}
 
-        // The TempDir will be automatically cleaned up when dropped
-        if let Some(project_dir) = &self.project_dir {
-            debug!(
-                "Removing project directory: {}",
-                project_dir.path().display()
-            );
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Provider as left by `setup_environment`, without needing cargo
+    fn set_up_provider() -> (RustProvider, PathBuf) {
+        let project_dir = TempDir::new().unwrap();
+        let path = project_dir.path().to_path_buf();
+        std::fs::write(path.join("Cargo.toml"), "[package]").unwrap();
+        let provider = RustProvider {
+            target_dir: Some(path.join("target")),
+            project_dir: Some(project_dir),
+        };
+        (provider, path)
+    }
 
-        Ok(())
+    #[tokio::test]
+    async fn test_cleanup_removes_project_dir() {
+        let (mut provider, project_dir) = set_up_provider();
+
+        provider.cleanup(false).await.unwrap();
+        assert!(!project_dir.exists());
+        assert_eq!(provider.get_compile_command("main.rs").unwrap()[0], "rustc");
+
+        // Already torn down
+        provider.cleanup(false).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_cleanup_keeps_artifacts() {
+        let (mut provider, project_dir) = set_up_provider();
+
+        provider.cleanup(true).await.unwrap();
+        drop(provider);
+        assert!(project_dir.join("Cargo.toml").exists());
+
+        std::fs::remove_dir_all(project_dir).unwrap();
     }
 }
@@ -1,4 +1,4 @@
-use super::LanguageProvider;
+use super::{remove_temp_dir, LanguageProvider};
 use crate::{
     error::{Error, Result},
     types::{Package, PackageSource, Tool},
@@ -171,17 +171,10 @@ impl LanguageProvider for TypeScriptProvider {
         Some(vec!["tsc".to_string(), file_path.to_string()])
     }
 
-    async fn cleanup(&self) -> Result<()> {
+    async fn cleanup(&mut self, keep_artifacts: bool) -> Result<()> {
         info!("Cleaning up TypeScript environment");
 
-        // The TempDir will be automatically cleaned up when dropped
-        if let Some(project_dir) = &self.project_dir {
-            debug!(
-                "Removing project directory: {}",
-                project_dir.path().display()
-            );
-        }
-
-        Ok(())
+        self.node_modules = None;
+        remove_temp_dir(self.project_dir.take(), keep_artifacts).await
     }
 }
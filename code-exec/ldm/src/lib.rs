@@ -14,7 +14,11 @@ mod types;
 #[cfg(test)]
 mod tests;
 
-pub use analyzer::{analyze_source_code, DependencyAnalyzer};
+pub use analyzer::{
+    analyze_source_code, register_analyzer,
+    resolver::{PackageRegistry, RegistryResolver, VersionResolver},
+    AnalyzerRegistry, DependencyAnalyzer,
+};
 pub use error::{Error, Result};
 pub use language::LanguageProvider;
 pub use manager::InstallationManager;
@@ -30,4 +34,6 @@ pub use language::{
 };
 
 // Re-export commonly used package managers
-pub use package_manager::{apt::AptPackageManager, brew::HomebrewPackageManager};
+pub use package_manager::{
+    apk::ApkPackageManager, apt::AptPackageManager, brew::HomebrewPackageManager,
+};
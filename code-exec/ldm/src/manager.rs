@@ -2,7 +2,7 @@ use crate::{
     error::{Error, Result},
     language::LanguageProvider,
     package_manager::PackageManager,
-    types::{InstallationConfig, InstallationProgress, InstallationStatus},
+    types::{InstallationConfig, InstallationProgress, InstallationStatus, Package},
 };
 use std::{env, sync::Arc};
 use tokio::sync::Mutex;
@@ -15,19 +15,23 @@ pub enum OsType {
 }
 
 impl OsType {
-    pub fn current() -> Self {
+    /// The OS this was built for, or an error on one without supported package managers
+    pub fn try_current() -> Result<Self> {
         match env::consts::OS {
-            "linux" => OsType::Linux,
-            "macos" => OsType::MacOS,
-            _ => panic!("Unsupported OS"),
+            "linux" => Ok(OsType::Linux),
+            "macos" => Ok(OsType::MacOS),
+            os => Err(Error::System(format!("Unsupported OS: {}", os))),
         }
     }
 
+    /// Package managers that may be found on this OS, in order of preference. Which one
+    /// is used depends on [`PackageManager::is_available`], so Alpine images get apk.
     pub fn get_supported_package_managers(&self) -> Vec<Arc<dyn PackageManager>> {
         match self {
-            OsType::Linux => vec![Arc::new(
-                crate::package_manager::apt::AptPackageManager::default(),
-            )],
+            OsType::Linux => vec![
+                Arc::new(crate::package_manager::apt::AptPackageManager::default()),
+                Arc::new(crate::package_manager::apk::ApkPackageManager::default()),
+            ],
             OsType::MacOS => vec![Arc::new(
                 crate::package_manager::brew::HomebrewPackageManager::default(),
             )],
@@ -39,6 +43,8 @@ pub struct InstallationManager {
     package_managers: Vec<Arc<dyn PackageManager>>,
     progress: Arc<Mutex<InstallationProgress>>,
     quiet_mode: bool,
+    offline: bool,
+    keep_artifacts: bool,
 }
 
 impl InstallationManager {
@@ -52,13 +58,15 @@ impl InstallationManager {
                 current_step_index: 0,
             })),
             quiet_mode: config.quiet_mode,
+            offline: config.offline,
+            keep_artifacts: config.keep_artifacts,
         }
     }
 
-    pub fn new_for_current_os(config: InstallationConfig) -> Self {
-        let os = OsType::current();
+    pub fn new_for_current_os(config: InstallationConfig) -> Result<Self> {
+        let os = OsType::try_current()?;
         let package_managers = os.get_supported_package_managers();
-        Self::new(config, package_managers)
+        Ok(Self::new(config, package_managers))
     }
 
     pub async fn install_dependencies(
@@ -72,6 +80,8 @@ impl InstallationManager {
         );
         let _enter = span.enter();
 
+        Self::check_conflicts(&language_provider.required_packages())?;
+
         if !self.quiet_mode {
             info!("Installing dependencies for {}", language_provider.name());
         }
@@ -89,10 +99,14 @@ impl InstallationManager {
 
         // Update package list
         self.update_progress("Updating package list".into()).await;
-        if !self.quiet_mode {
-            debug!("Updating package list");
+        if self.offline {
+            debug!("Offline, not updating package list");
+        } else {
+            if !self.quiet_mode {
+                debug!("Updating package list");
+            }
+            package_manager.update_package_list().await?;
         }
-        package_manager.update_package_list().await?;
 
         // Install required packages
         for package in language_provider.required_packages() {
@@ -101,6 +115,12 @@ impl InstallationManager {
             }
 
             if !package_manager.is_installed(&package).await? {
+                if self.offline {
+                    self.progress.lock().await.status = InstallationStatus::Failed;
+                    return Err(Error::OfflineDependencyMissing {
+                        package: package.name,
+                    });
+                }
                 self.update_progress(format!("Installing package: {}", package.name))
                     .await;
                 if !self.quiet_mode {
@@ -137,11 +157,33 @@ impl InstallationManager {
         Ok(())
     }
 
-    pub async fn cleanup(&self, language_provider: &dyn LanguageProvider) -> Result<()> {
+    /// Fails with [`Error::DependencyConflict`] if a package is asked for at more than one
+    /// version, which the package manager would otherwise report halfway through the
+    /// installation. Packages without a version never conflict.
+    pub fn check_conflicts(packages: &[Package]) -> Result<()> {
+        for package in packages {
+            let mut versions: Vec<&str> = Vec::new();
+            let same_name = packages.iter().filter(|other| other.name == package.name);
+            for version in same_name.filter_map(|other| other.version.as_deref()) {
+                if !versions.contains(&version) {
+                    versions.push(version);
+                }
+            }
+            if versions.len() > 1 {
+                return Err(Error::DependencyConflict {
+                    package: package.name.clone(),
+                    versions: versions.into_iter().map(String::from).collect(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn cleanup(&self, language_provider: &mut dyn LanguageProvider) -> Result<()> {
         info!("Cleaning up installation for {}", language_provider.name());
 
         // Clean up language-specific resources
-        language_provider.cleanup().await?;
+        language_provider.cleanup(self.keep_artifacts).await?;
 
         // Clean up package manager resources
         if let Some(package_manager) = self.find_available_package_manager() {
@@ -188,7 +230,7 @@ mod tests {
             let progress = manager.get_progress().await;
             assert_eq!(progress.status, InstallationStatus::Complete);
 
-            let cleanup_result = manager.cleanup(&python_provider).await;
+            let cleanup_result = manager.cleanup(&mut python_provider).await;
             assert!(cleanup_result.is_ok());
         }
     }
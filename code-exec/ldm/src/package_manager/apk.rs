@@ -0,0 +1,150 @@
+use super::PackageManager;
+use crate::{
+    error::{Error, Result},
+    types::Package,
+};
+use async_trait::async_trait;
+use std::{collections::HashMap, process::Command};
+use tracing::{debug, error, info};
+use which::which;
+
+/// Alpine's package manager, found on musl based images instead of apt
+pub struct ApkPackageManager {
+    package_map: HashMap<&'static str, &'static str>,
+}
+
+impl Default for ApkPackageManager {
+    fn default() -> Self {
+        let mut package_map = HashMap::new();
+        package_map.insert("python", "python3");
+        package_map.insert("pip", "py3-pip");
+        package_map.insert("node", "nodejs");
+        package_map.insert("npm", "npm");
+        package_map.insert("java", "openjdk17-jdk");
+        package_map.insert("javac", "openjdk17-jdk");
+        package_map.insert("mvn", "maven");
+        package_map.insert("php", "php83");
+        package_map.insert("composer", "composer");
+        package_map.insert("g++", "g++");
+        package_map.insert("make", "make");
+        package_map.insert("cmake", "cmake");
+        package_map.insert("go", "go");
+        package_map.insert("kotlinc", "kotlin");
+        package_map.insert("elixir", "elixir");
+        package_map.insert("mix", "elixir");
+
+        Self { package_map }
+    }
+}
+
+#[async_trait]
+impl PackageManager for ApkPackageManager {
+    fn is_available(&self) -> bool {
+        which("apk").is_ok()
+    }
+
+    fn get_package_name(&self, tool: &str) -> String {
+        self.package_map
+            .get(tool)
+            .map(|&s| s.to_string())
+            .unwrap_or_else(|| tool.to_string())
+    }
+
+    fn get_package_map(&self) -> HashMap<&'static str, &'static str> {
+        self.package_map.clone()
+    }
+
+    async fn is_installed(&self, package: &Package) -> Result<bool> {
+        let output = Command::new("apk")
+            .args(["info", "-e", &package.name])
+            .output()
+            .map_err(|e| Error::PackageManager(format!("Failed to check package status: {}", e)))?;
+
+        Ok(output.status.success())
+    }
+
+    async fn install(&self, package: &Package) -> Result<()> {
+        info!("Installing package: {}", package.name);
+
+        let status = Command::new("apk")
+            .args(["add", "--no-cache", "-q", &package.name])
+            .status()
+            .map_err(|e| Error::PackageManager(format!("Failed to install package: {}", e)))?;
+
+        if !status.success() {
+            error!("Failed to install package: {}", package.name);
+            return Err(Error::InstallationFailed(format!(
+                "Package installation failed: {}",
+                package.name
+            )));
+        }
+
+        debug!("Successfully installed package: {}", package.name);
+        Ok(())
+    }
+
+    async fn uninstall(&self, package: &Package) -> Result<()> {
+        info!("Uninstalling package: {}", package.name);
+
+        let status = Command::new("apk")
+            .args(["del", "-q", &package.name])
+            .status()
+            .map_err(|e| Error::PackageManager(format!("Failed to uninstall package: {}", e)))?;
+
+        if !status.success() {
+            error!("Failed to uninstall package: {}", package.name);
+            return Err(Error::PackageManager(format!(
+                "Failed to uninstall package: {}",
+                package.name
+            )));
+        }
+
+        debug!("Successfully uninstalled package: {}", package.name);
+        Ok(())
+    }
+
+    async fn update(&self, package: &Package) -> Result<()> {
+        info!("Updating package: {}", package.name);
+
+        let status = Command::new("apk")
+            .args(["upgrade", "--no-cache", "-q", &package.name])
+            .status()
+            .map_err(|e| Error::PackageManager(format!("Failed to update package: {}", e)))?;
+
+        if !status.success() {
+            error!("Failed to update package: {}", package.name);
+            return Err(Error::PackageManager(format!(
+                "Failed to update package: {}",
+                package.name
+            )));
+        }
+
+        debug!("Successfully updated package: {}", package.name);
+        Ok(())
+    }
+
+    async fn update_package_list(&self) -> Result<()> {
+        info!("Updating package list");
+
+        let status = Command::new("apk")
+            .args(["update", "-q"])
+            .status()
+            .map_err(|e| Error::PackageManager(format!("Failed to update package list: {}", e)))?;
+
+        if !status.success() {
+            error!("Failed to update package list");
+            return Err(Error::PackageManager(
+                "Failed to update package list".into(),
+            ));
+        }
+
+        debug!("Successfully updated package list");
+        Ok(())
+    }
+
+    async fn cleanup(&self) -> Result<()> {
+        // Packages are installed with --no-cache, so apk keeps no downloads around
+        debug!("Nothing to clean up for apk");
+        Ok(())
+    }
+}
@@ -28,6 +28,9 @@ impl Default for AptPackageManager {
         package_map.insert("make", "make");
         package_map.insert("cmake", "cmake");
         package_map.insert("go", "golang");
+        package_map.insert("kotlinc", "kotlin");
+        package_map.insert("elixir", "elixir");
+        package_map.insert("mix", "elixir");
 
         Self { package_map }
     }
@@ -28,6 +28,10 @@ impl Default for HomebrewPackageManager {
         package_map.insert("make", "make");
         package_map.insert("cmake", "cmake");
         package_map.insert("go", "go");
+        package_map.insert("kotlinc", "kotlin");
+        package_map.insert("elixir", "elixir");
+        package_map.insert("mix", "elixir");
+        package_map.insert("sqlite3", "sqlite");
         package_map.insert("rustc", "rust");
         package_map.insert("cargo", "rust");
 
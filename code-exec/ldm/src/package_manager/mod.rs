@@ -1,6 +1,7 @@
 use async_trait::async_trait;
 use std::collections::HashMap;
 
+pub mod apk;
 pub mod apt;
 pub mod brew;
 
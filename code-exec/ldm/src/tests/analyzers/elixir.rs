@@ -0,0 +1,119 @@
+use crate::{
+    analyzer::{elixir::ElixirAnalyzer, DependencyAnalyzer},
+    AnalyzerRegistry, PackageSource,
+};
+
+#[test]
+fn test_elixir_detection() {
+    let analyzer = ElixirAnalyzer;
+
+    let elixir = [
+        // Module with a do block
+        r#"
+        defmodule Greeter do
+          def hello(name) do
+            "Hello, #{name}"
+          end
+        end
+        "#,
+        // Keyword-list function bodies and private functions
+        r#"
+        defmodule Math do
+          def double(x), do: x * 2
+          defp half(x) when is_number(x), do: x / 2
+        end
+        "#,
+        // Script without a module
+        r#"IO.puts("Hello from Elixir!")"#,
+    ];
+    for code in elixir {
+        assert!(analyzer.can_handle(code), "Should detect Elixir: {}", code);
+    }
+
+    let ruby = [
+        // Methods without do blocks
+        r#"
+        def greet(name)
+          puts "Hello, #{name}"
+        end
+        "#,
+        // Classes and modules
+        r#"
+        module Greeting
+          class Greeter
+            attr_accessor :name
+            def hello do_something end
+          end
+        end
+        "#,
+        // Blocks with parameters
+        r#"
+        require 'json'
+        [1, 2, 3].each do |n|
+          puts n
+        end
+        "#,
+    ];
+    for code in ruby {
+        assert!(
+            !analyzer.can_handle(code),
+            "Should not detect Ruby: {}",
+            code
+        );
+    }
+}
+
+#[test]
+fn test_registry_detects_only_elixir() {
+    let code = r#"
+defmodule Report do
+  import Enum
+
+  def run do
+    [1, 2, 3] |> map(&(&1 * 2)) |> IO.inspect()
+  end
+end
+
+Report.run()
+"#;
+    assert_eq!(
+        AnalyzerRegistry::default().detect_languages(code),
+        vec!["elixir"]
+    );
+}
+
+#[tokio::test]
+async fn test_elixir_mix_install_dependencies() {
+    let analyzer = ElixirAnalyzer;
+    let code = r#"
+Mix.install([
+  {:jason, "~> 1.4"},
+  {:req, "~> 0.4.0"}
+])
+
+IO.puts(Jason.encode!(%{ok: true}))
+"#;
+
+    let mut deps = analyzer.analyze_dependencies(code).await.unwrap();
+    deps.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let found: Vec<_> = deps
+        .iter()
+        .map(|p| (p.name.as_str(), p.version.as_deref()))
+        .collect();
+    assert_eq!(
+        found,
+        vec![("jason", Some("~> 1.4")), ("req", Some("~> 0.4.0"))]
+    );
+    assert!(deps
+        .iter()
+        .all(|p| p.source == PackageSource::Custom("hex".to_string())));
+
+    // Tuples outside of Mix.install are ordinary data
+    let data = r#"IO.inspect({:ok, "value"})"#;
+    assert!(analyzer
+        .analyze_dependencies(data)
+        .await
+        .unwrap()
+        .is_empty());
+}
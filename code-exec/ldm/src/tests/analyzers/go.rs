@@ -1,4 +1,5 @@
 use crate::analyze_source_code;
+use crate::analyzer::{go::GoAnalyzer, DependencyAnalyzer};
 
 #[tokio::test]
 async fn test_go_dependency_analysis() {
@@ -133,7 +134,7 @@ func main() {
 }
 "#;
 
-    let (lang, packages) = analyze_source_code(source_code).await.unwrap();
+    let (lang, packages) = analyze_source_code(source_code, None).await.unwrap();
     assert_eq!(lang, "go");
 
     let find_package = |name: &str| packages.iter().find(|p| p.name == name).cloned();
@@ -159,3 +160,108 @@ func main() {
         );
     }
 }
+
+#[tokio::test]
+async fn test_go_aliased_and_blank_imports() {
+    let source_code = r#"
+package main
+
+import (
+    "fmt"
+    str "strings"
+    . "math"
+
+    mux "github.com/gorilla/mux"
+    _ "github.com/lib/pq"
+    . "github.com/onsi/gomega/format"
+    "go.uber.org/zap" // logging
+)
+
+import log "github.com/sirupsen/logrus/hooks/writer"
+
+func main() {
+    fmt.Println(str.ToUpper("hi"), Pi, mux.NewRouter(), zap.L())
+}
+"#;
+
+    let (lang, packages) = analyze_source_code(source_code, None).await.unwrap();
+    assert_eq!(lang, "go");
+
+    let mut names: Vec<_> = packages.iter().map(|p| p.name.as_str()).collect();
+    names.sort();
+    assert_eq!(
+        names,
+        [
+            "github.com/gorilla/mux",
+            "github.com/lib/pq",
+            "github.com/onsi/gomega",
+            "github.com/sirupsen/logrus",
+            "go.uber.org/zap",
+        ]
+    );
+
+    let pq = packages.iter().find(|p| p.name == "github.com/lib/pq");
+    assert_eq!(pq.unwrap().version.as_deref(), Some("1.10.9"));
+}
+
+#[tokio::test]
+async fn test_go_list_resolves_modules() {
+    // Needs the go command and access to the module proxy
+    if which::which("go").is_err() {
+        return;
+    }
+
+    let source_code = r#"
+package main
+
+// go: require github.com/google/uuid v1.6.0
+
+import (
+    "fmt"
+
+    "github.com/aws/aws-sdk-go-v2/config"
+    "github.com/aws/aws-sdk-go-v2/service/sqs"
+    "github.com/google/uuid"
+)
+
+func main() {
+    fmt.Println(uuid.New(), config.LoadDefaultConfig, sqs.NewFromConfig)
+}
+"#;
+
+    let analyzer = GoAnalyzer::default().with_go_list(true);
+    let packages = analyzer.analyze_dependencies(source_code).await.unwrap();
+    let version = |name: &str| {
+        packages
+            .iter()
+            .find(|p| p.name == name)
+            .and_then(|p| p.version.clone())
+    };
+
+    // Nested modules of the v2 SDK are told apart from the SDK itself
+    assert!(version("github.com/aws/aws-sdk-go-v2/config").is_some());
+    assert!(version("github.com/aws/aws-sdk-go-v2/service/sqs").is_some());
+    assert_eq!(version("github.com/google/uuid").as_deref(), Some("v1.6.0"));
+    assert!(packages
+        .iter()
+        .all(|p| p.version.as_deref().is_some_and(|v| v.starts_with('v'))));
+}
+
+#[tokio::test]
+async fn test_go_list_falls_back_offline() {
+    let source_code = r#"
+package main
+
+import "github.com/google/uuid"
+
+func main() {
+    println(uuid.New().String())
+}
+"#;
+
+    let analyzer = GoAnalyzer::default().with_go_list(true).with_offline(true);
+    let packages = analyzer.analyze_dependencies(source_code).await.unwrap();
+    assert_eq!(packages.len(), 1);
+    assert_eq!(packages[0].name, "github.com/google/uuid");
+    assert_eq!(packages[0].version.as_deref(), Some("1.3.0"));
+}
@@ -1,4 +1,5 @@
 use crate::analyze_source_code;
+use crate::analyzer::{javascript::JavaScriptAnalyzer, DependencyAnalyzer};
 
 #[tokio::test]
 async fn test_javascript_dependency_analysis() {
@@ -93,7 +94,7 @@ exports.handler = async (event) => {
 };
 "#;
 
-    let (lang, packages) = analyze_source_code(source_code).await.unwrap();
+    let (lang, packages) = analyze_source_code(source_code, None).await.unwrap();
     assert_eq!(lang, "javascript");
 
     let find_package = |name: &str| packages.iter().find(|p| p.name == name).cloned();
@@ -110,3 +111,42 @@ exports.handler = async (event) => {
     // Make sure we don't have any unexpected dependencies
     assert_eq!(packages.len(), 5);
 }
+
+#[tokio::test]
+async fn test_javascript_dynamic_imports() {
+    let source_code = r#"
+const name = process.env.PLUGIN;
+
+async function main() {
+    const { default: axios } = await import('axios');
+    const plugin = require(`./plugins/${name}`);
+    const other = require(`${name}`);
+    const locale = await import(`date-fns/locale/${name}`);
+    const helper = await import('./helper.js');
+    console.log(axios, plugin, other, locale, helper);
+}
+"#;
+
+    let analyzer = JavaScriptAnalyzer::default();
+    assert!(analyzer.can_handle(source_code));
+    let packages = analyzer.analyze_dependencies(source_code).await.unwrap();
+    let names: Vec<_> = packages.iter().map(|p| p.name.as_str()).collect();
+    assert_eq!(names, ["axios"]);
+
+    let line = "const { default: axios } = await import('axios');";
+    assert_eq!(analyzer.extract_package_info(line).unwrap().name, "axios");
+    assert!(!analyzer.is_dependency_line("const plugin = require(`${name}`);"));
+}
+
+#[test]
+fn test_javascript_imports_include_builtins() {
+    let source_code = r#"
+const { exec } = require('child_process');
+import fs from 'node:fs';
+import './setup.js';
+require("net").connect(80);
+"#;
+
+    let imports = JavaScriptAnalyzer::default().imports(source_code);
+    assert_eq!(imports, vec!["./setup.js", "child_process", "fs", "net"]);
+}
@@ -1,5 +1,8 @@
+pub mod elixir;
 pub mod go;
 pub mod javascript;
 pub mod python;
+pub mod registry;
+pub mod resolver;
 pub mod rust;
 pub mod typescript;
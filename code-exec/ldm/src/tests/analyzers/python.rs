@@ -1,4 +1,7 @@
-use crate::analyze_source_code;
+use crate::{
+    analyze_source_code,
+    analyzer::{python::PythonAnalyzer, DependencyAnalyzer},
+};
 
 #[tokio::test]
 async fn test_python_dependency_analysis() {
@@ -66,7 +69,7 @@ def lambda_handler(event, context):
         }
 "#;
 
-    let (lang, deps) = analyze_source_code(source_code).await.unwrap();
+    let (lang, deps) = analyze_source_code(source_code, None).await.unwrap();
     assert_eq!(lang, "python");
 
     let dep_names: Vec<_> = deps.iter().map(|d| d.name.as_str()).collect();
@@ -79,3 +82,72 @@ def lambda_handler(event, context):
     let numpy_dep = deps.iter().find(|d| d.name == "numpy").unwrap();
     assert_eq!(numpy_dep.version.as_deref(), Some(">=1.24.0"));
 }
+
+#[tokio::test]
+async fn test_python_module_package_names() {
+    let source_code = r#"
+import cv2
+import sklearn.linear_model
+from yaml import safe_load
+from bs4 import BeautifulSoup
+from google.cloud import storage, secret_manager as sm
+from google.cloud.pubsub_v1 import PublisherClient
+import google.protobuf.json_format
+"#;
+
+    let analyzer = PythonAnalyzer::default();
+    let deps = analyzer.analyze_dependencies(source_code).await.unwrap();
+    let mut names: Vec<_> = deps.iter().map(|d| d.name.as_str()).collect();
+    names.sort();
+    assert_eq!(
+        names,
+        [
+            "beautifulsoup4",
+            "google-cloud-pubsub",
+            "google-cloud-secret-manager",
+            "google-cloud-storage",
+            "opencv-python",
+            "protobuf",
+            "pyyaml",
+            "scikit-learn",
+        ]
+    );
+
+    let sklearn = deps.iter().find(|d| d.name == "scikit-learn").unwrap();
+    assert_eq!(sklearn.version.as_deref(), Some(">=1.2.0"));
+    assert_eq!(
+        analyzer.extract_package_info("import cv2").unwrap().name,
+        "opencv-python"
+    );
+}
+
+#[test]
+fn test_python_imports_include_stdlib() {
+    let source_code = r#"
+import math, subprocess as sp
+from os import system, path
+import json; import socket
+def main():
+    from collections import *
+"#;
+
+    let imports = PythonAnalyzer::default().imports(source_code);
+    for module in [
+        "math",
+        "subprocess",
+        "os",
+        "os.system",
+        "os.path",
+        "json",
+        "socket",
+        "collections",
+    ] {
+        assert!(
+            imports.contains(&module.to_string()),
+            "{} in {:?}",
+            module,
+            imports
+        );
+    }
+    assert!(!imports.iter().any(|module| module.contains('*')));
+}
@@ -0,0 +1,151 @@
+use crate::{
+    analyze_source_code, analyzer::python::PythonAnalyzer, register_analyzer, AnalyzerRegistry,
+    DependencyAnalyzer, Package, PackageSource, Result, VersionResolver,
+};
+use async_trait::async_trait;
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+static CONSTRUCTED: AtomicUsize = AtomicUsize::new(0);
+
+/// Python analyzer that counts how many times it has been constructed
+struct CountingAnalyzer(PythonAnalyzer);
+
+impl CountingAnalyzer {
+    fn new() -> Self {
+        CONSTRUCTED.fetch_add(1, Ordering::SeqCst);
+        Self(PythonAnalyzer::default())
+    }
+}
+
+#[async_trait]
+impl DependencyAnalyzer for CountingAnalyzer {
+    fn language(&self) -> &'static str {
+        self.0.language()
+    }
+
+    fn with_default_versions(self, versions: HashMap<String, String>) -> Self {
+        Self(self.0.with_default_versions(versions))
+    }
+
+    fn with_version_resolver(self, resolver: Arc<dyn VersionResolver>) -> Self {
+        Self(self.0.with_version_resolver(resolver))
+    }
+
+    async fn analyze_dependencies(&self, source_code: &str) -> Result<Vec<Package>> {
+        self.0.analyze_dependencies(source_code).await
+    }
+
+    fn can_handle(&self, source_code: &str) -> bool {
+        self.0.can_handle(source_code)
+    }
+
+    fn is_dependency_line(&self, line: &str) -> bool {
+        self.0.is_dependency_line(line)
+    }
+
+    fn extract_package_info(&self, line: &str) -> Option<Package> {
+        self.0.extract_package_info(line)
+    }
+}
+
+#[tokio::test]
+async fn test_registry_reuses_analyzers() {
+    let registry = AnalyzerRegistry::with_analyzers(vec![Box::new(CountingAnalyzer::new())]);
+
+    let snippets = [
+        "import numpy as np\n\ndef main():\n    print(np.zeros(3))\n",
+        "import pandas as pd\n\ndef load():\n    return pd.DataFrame()\n",
+        "import requests\n\ndef fetch():\n    return requests.get('https://example.com')\n",
+    ];
+
+    for snippet in snippets {
+        let (lang, deps) = registry.detect_and_analyze(snippet).await.unwrap();
+        assert_eq!(lang, "python");
+        assert_eq!(deps.len(), 1);
+    }
+
+    assert_eq!(CONSTRUCTED.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn test_shared_registry_is_singleton() {
+    let first = AnalyzerRegistry::shared();
+    let second = AnalyzerRegistry::shared();
+    assert!(std::sync::Arc::ptr_eq(&first, &second));
+}
+
+/// Analyzer for a made-up language, recognised by its `#lang fake` header
+struct FakeAnalyzer;
+
+#[async_trait]
+impl DependencyAnalyzer for FakeAnalyzer {
+    fn language(&self) -> &'static str {
+        "fake"
+    }
+
+    fn with_default_versions(self, _versions: HashMap<String, String>) -> Self {
+        self
+    }
+
+    fn with_version_resolver(self, _resolver: Arc<dyn VersionResolver>) -> Self {
+        self
+    }
+
+    async fn analyze_dependencies(&self, source_code: &str) -> Result<Vec<Package>> {
+        Ok(source_code
+            .lines()
+            .filter_map(|line| self.extract_package_info(line))
+            .collect())
+    }
+
+    fn can_handle(&self, source_code: &str) -> bool {
+        source_code.starts_with("#lang fake")
+    }
+
+    fn is_dependency_line(&self, line: &str) -> bool {
+        line.starts_with("use ")
+    }
+
+    fn extract_package_info(&self, line: &str) -> Option<Package> {
+        let name = line.strip_prefix("use ")?.trim();
+        Some(Package {
+            name: name.to_string(),
+            version: None,
+            source: PackageSource::Custom("fake".to_string()),
+        })
+    }
+}
+
+#[tokio::test]
+async fn test_registered_analyzer_is_used() {
+    register_analyzer(Box::new(FakeAnalyzer));
+
+    // Would be detected as Rust without the registered analyzer
+    let source = "#lang fake\nuse widgets\n\nfn main() {}\n";
+    let (lang, deps) = analyze_source_code(source, None).await.unwrap();
+    assert_eq!(lang, "fake");
+    assert_eq!(deps.len(), 1);
+    assert_eq!(deps[0].name, "widgets");
+
+    // Other source still reaches the built-in analyzers
+    let (lang, _) = analyze_source_code("import numpy\n", None).await.unwrap();
+    assert_eq!(lang, "python");
+}
+
+#[tokio::test]
+async fn test_register_takes_precedence() {
+    let mut registry = AnalyzerRegistry::default();
+    registry.register(Box::new(FakeAnalyzer));
+
+    let (lang, _) = registry
+        .detect_and_analyze("#lang fake\nuse widgets\n\nfn main() {}\n")
+        .await
+        .unwrap();
+    assert_eq!(lang, "fake");
+}
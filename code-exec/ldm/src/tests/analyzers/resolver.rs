@@ -0,0 +1,107 @@
+use crate::{
+    analyzer::{rust::RustAnalyzer, DependencyAnalyzer},
+    PackageRegistry, RegistryResolver, VersionResolver,
+};
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+};
+
+/// Serves a crates.io API that only knows `obscure_crate`, counting the requests it gets
+async fn mock_crates_io() -> (String, Arc<AtomicUsize>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let requests = Arc::new(AtomicUsize::new(0));
+
+    let counter = requests.clone();
+    tokio::spawn(async move {
+        loop {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            counter.fetch_add(1, Ordering::SeqCst);
+
+            let mut request = Vec::new();
+            let mut buf = [0; 1024];
+            while !request.ends_with(b"\r\n\r\n") {
+                let n = stream.read(&mut buf).await.unwrap();
+                if n == 0 {
+                    break;
+                }
+                request.extend_from_slice(&buf[..n]);
+            }
+
+            let request = String::from_utf8_lossy(&request);
+            let (status, body) = if request.starts_with("GET /api/v1/crates/obscure_crate ") {
+                ("200 OK", r#"{"crate":{"max_stable_version":"2.3.4"}}"#)
+            } else {
+                ("404 Not Found", r#"{"errors":[]}"#)
+            };
+            let response = format!(
+                "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            );
+            stream.write_all(response.as_bytes()).await.unwrap();
+        }
+    });
+
+    (url, requests)
+}
+
+#[tokio::test]
+async fn test_resolver_supplies_unknown_versions() {
+    let (url, requests) = mock_crates_io().await;
+    let resolver: Arc<dyn VersionResolver> =
+        Arc::new(RegistryResolver::new().with_registry_url(PackageRegistry::CratesIo, &url));
+    let analyzer = RustAnalyzer::default().with_version_resolver(resolver);
+
+    let source = r#"
+use obscure_crate::Thing;
+use missing_crate::Other;
+use tokio::runtime::Runtime;
+"#;
+    let version_of = |packages: &[crate::Package], name: &str| {
+        packages
+            .iter()
+            .find(|p| p.name == name)
+            .and_then(|p| p.version.clone())
+    };
+
+    let packages = analyzer.analyze_dependencies(source).await.unwrap();
+    assert_eq!(
+        version_of(&packages, "obscure_crate").as_deref(),
+        Some("2.3.4")
+    );
+    // Unknown to the registry as well, so the fallback stays
+    assert_eq!(
+        version_of(&packages, "missing_crate").as_deref(),
+        Some("0.1")
+    );
+    // Crates with a default version are not looked up
+    assert_eq!(version_of(&packages, "tokio").as_deref(), Some("1.28"));
+    assert_eq!(requests.load(Ordering::SeqCst), 2);
+
+    // Answers are cached
+    analyzer.analyze_dependencies(source).await.unwrap();
+    assert_eq!(requests.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn test_offline_resolver_does_not_query() {
+    let (url, requests) = mock_crates_io().await;
+    let resolver = RegistryResolver::new()
+        .with_registry_url(PackageRegistry::CratesIo, &url)
+        .with_offline(true);
+
+    assert_eq!(
+        resolver
+            .latest_version(PackageRegistry::CratesIo, "obscure_crate")
+            .await,
+        None
+    );
+    assert_eq!(requests.load(Ordering::SeqCst), 0);
+}
@@ -1,7 +1,9 @@
 use crate::{
+    analyze_source_code,
     analyzer::{rust::RustAnalyzer, DependencyAnalyzer},
-    PackageSource,
+    AnalyzerRegistry, PackageSource,
 };
+use std::collections::HashMap;
 
 #[tokio::test]
 async fn test_rust_dependency_analysis() {
@@ -123,6 +125,40 @@ async fn test_rust_language_detection() {
     assert!(!analyzer.can_handle(python_code));
 }
 
+#[tokio::test]
+async fn test_rust_detection_ignores_cpp() {
+    let analyzer = RustAnalyzer::default();
+
+    let cpp_code = r#"
+    #include <iostream>
+    #include <vector>
+
+    // Prefer to use std::vector over raw arrays
+    using namespace std;
+
+    int main() {
+        std::vector<int> values{1, 2, 3};
+        for (auto v : values) std::cout << v << std::endl;
+        return 0;
+    }
+    "#;
+    let prose = "You can use the :: operator to name std::string explicitly.";
+
+    assert!(!analyzer.can_handle(cpp_code));
+    assert!(!analyzer.can_handle(prose));
+    let languages = AnalyzerRegistry::shared().detect_languages(cpp_code);
+    assert!(!languages.contains(&"rust"), "{:?}", languages);
+
+    // Rust with none of its use statements is still recognized
+    for rust_code in [
+        "#[derive(Debug)]\nstruct Point { x: i32 }",
+        "impl Point {\n    pub fn new() -> Self { Point { x: 0 } }\n}",
+        "let mut total = 0;",
+    ] {
+        assert!(analyzer.can_handle(rust_code), "Should detect Rust: {}", rust_code);
+    }
+}
+
 #[tokio::test]
 async fn test_rust_version_parsing() {
     let analyzer = RustAnalyzer::default();
@@ -145,3 +181,43 @@ async fn test_rust_version_parsing() {
     assert_eq!(tokio_pkg.version.as_ref().unwrap(), "1.25");
     assert_eq!(serde_pkg.version.as_ref().unwrap(), "2.0");
 }
+
+#[tokio::test]
+async fn test_rust_default_version_override() {
+    let source = r#"
+use tokio::runtime::Runtime;
+use serde::Deserialize;
+
+fn main() {
+    let runtime = Runtime::new().unwrap();
+}
+"#;
+    let overrides = HashMap::from([("tokio".to_string(), "1.40".to_string())]);
+
+    let packages = RustAnalyzer::default()
+        .with_default_versions(overrides.clone())
+        .analyze_dependencies(source)
+        .await
+        .unwrap();
+    let version_of = |packages: &[crate::Package], name: &str| {
+        packages
+            .iter()
+            .find(|p| p.name == name)
+            .and_then(|p| p.version.clone())
+    };
+    assert_eq!(version_of(&packages, "tokio").as_deref(), Some("1.40"));
+    // Packages without an override keep the built-in default
+    assert_eq!(version_of(&packages, "serde").as_deref(), Some("1.0"));
+
+    // Overrides passed to analyze_source_code only apply to their language
+    let versions = HashMap::from([
+        ("rust".to_string(), overrides),
+        (
+            "python".to_string(),
+            HashMap::from([("tokio".to_string(), "0.0.1".to_string())]),
+        ),
+    ]);
+    let (language, packages) = analyze_source_code(source, Some(&versions)).await.unwrap();
+    assert_eq!(language, "rust");
+    assert_eq!(version_of(&packages, "tokio").as_deref(), Some("1.40"));
+}
@@ -1,4 +1,8 @@
-use crate::{analyze_source_code, Package, PackageSource};
+use crate::{
+    analyze_source_code,
+    analyzer::{typescript::TypeScriptAnalyzer, DependencyAnalyzer},
+    Package, PackageSource,
+};
 
 #[tokio::test]
 async fn test_typescript_dependency_analysis() {
@@ -39,7 +43,7 @@ async fn test_typescript_dependency_analysis() {
         export default MyComponent;
     "#;
 
-    let (lang, deps) = analyze_source_code(source_code).await.unwrap();
+    let (lang, deps) = analyze_source_code(source_code, None).await.unwrap();
     assert_eq!(lang, "typescript");
 
     // Helper function to find package by name
@@ -75,3 +79,19 @@ async fn test_typescript_dependency_analysis() {
     assert!(find_package("./components", &deps).is_none());
     assert!(find_package("../types", &deps).is_none());
 }
+
+#[tokio::test]
+async fn test_typescript_dynamic_imports() {
+    let source_code = r#"
+        export async function load(name: string): Promise<void> {
+            const { default: axios } = await import("axios");
+            const mod = await import(`./modules/${name}`);
+            console.log(axios, mod);
+        }
+    "#;
+
+    let analyzer = TypeScriptAnalyzer::default();
+    let deps = analyzer.analyze_dependencies(source_code).await.unwrap();
+    let names: Vec<_> = deps.iter().map(|p| p.name.as_str()).collect();
+    assert_eq!(names, ["axios"]);
+}
@@ -3,7 +3,7 @@ use crate::*;
 
 async fn try_install<T: LanguageProvider>(provider: &mut T) -> bool {
     let config = InstallationConfig::default();
-    let manager = InstallationManager::new_for_current_os(config);
+    let manager = InstallationManager::new_for_current_os(config).unwrap();
 
     // Skip test if no package manager is available
     if let Some(pm) = manager.find_available_package_manager() {
@@ -66,8 +66,8 @@ async fn test_go_installation() {
 async fn test_os_detection() {
     use crate::manager::OsType;
 
-    let os = OsType::current();
-    let manager = InstallationManager::new_for_current_os(InstallationConfig::default());
+    let os = OsType::try_current().unwrap();
+    let manager = InstallationManager::new_for_current_os(InstallationConfig::default()).unwrap();
 
     match os {
         OsType::Linux => {
@@ -86,3 +86,127 @@ async fn test_os_detection() {
         }
     }
 }
+
+/// Package manager with nothing installed that records the calls reaching the network
+#[derive(Default)]
+struct RecordingPackageManager {
+    network_calls: std::sync::Mutex<Vec<String>>,
+}
+
+#[async_trait::async_trait]
+impl PackageManager for RecordingPackageManager {
+    fn is_available(&self) -> bool {
+        true
+    }
+
+    fn get_package_name(&self, tool: &str) -> String {
+        tool.to_string()
+    }
+
+    fn get_package_map(&self) -> std::collections::HashMap<&'static str, &'static str> {
+        Default::default()
+    }
+
+    async fn is_installed(&self, _package: &Package) -> Result<bool> {
+        Ok(false)
+    }
+
+    async fn install(&self, package: &Package) -> Result<()> {
+        let call = format!("install {}", package.name);
+        self.network_calls.lock().unwrap().push(call);
+        Ok(())
+    }
+
+    async fn uninstall(&self, _package: &Package) -> Result<()> {
+        Ok(())
+    }
+
+    async fn update(&self, package: &Package) -> Result<()> {
+        let call = format!("update {}", package.name);
+        self.network_calls.lock().unwrap().push(call);
+        Ok(())
+    }
+
+    async fn update_package_list(&self) -> Result<()> {
+        let call = "update_package_list".to_string();
+        self.network_calls.lock().unwrap().push(call);
+        Ok(())
+    }
+
+    async fn cleanup(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_offline_missing_package_is_not_downloaded() {
+    let package_manager = std::sync::Arc::new(RecordingPackageManager::default());
+    let config = InstallationConfig {
+        offline: true,
+        ..Default::default()
+    };
+    let manager = InstallationManager::new(config, vec![package_manager.clone()]);
+
+    let result = manager
+        .install_dependencies(&mut PythonProvider::default())
+        .await;
+    assert!(
+        matches!(&result, Err(Error::OfflineDependencyMissing { package }) if package == "python3"),
+        "{:?}",
+        result
+    );
+    assert!(package_manager.network_calls.lock().unwrap().is_empty());
+    assert_eq!(
+        manager.get_progress().await.status,
+        InstallationStatus::Failed
+    );
+}
+
+#[test]
+fn test_conflicting_versions_are_rejected() {
+    let package = |name: &str, version: Option<&str>| Package {
+        name: name.to_string(),
+        version: version.map(String::from),
+        source: PackageSource::Custom("npm".to_string()),
+    };
+
+    let result = InstallationManager::check_conflicts(&[
+        package("lodash", Some("^4")),
+        package("express", None),
+        package("lodash", Some("^3")),
+    ]);
+    assert!(
+        matches!(&result, Err(Error::DependencyConflict { package, versions })
+            if package == "lodash" && versions == &["^4", "^3"]),
+        "{:?}",
+        result
+    );
+
+    // Repeating a package at the same version, or without one, is fine
+    assert!(InstallationManager::check_conflicts(&[
+        package("lodash", Some("^4")),
+        package("lodash", Some("^4")),
+        package("lodash", None),
+    ])
+    .is_ok());
+}
+
+#[test]
+fn test_apk_package_names() {
+    let apk = ApkPackageManager::default();
+    assert_eq!(apk.get_package_name("python"), "python3");
+    assert_eq!(apk.get_package_name("pip"), "py3-pip");
+    assert_eq!(apk.get_package_name("node"), "nodejs");
+    assert_eq!(apk.get_package_name("javac"), "openjdk17-jdk");
+    assert_eq!(apk.get_package_name("go"), "go");
+    assert_eq!(apk.get_package_name("mix"), "elixir");
+    // Tools without a mapping are installed under their own name
+    assert_eq!(apk.get_package_name("git"), "git");
+
+    // Every tool apt knows has an apk package
+    let apt = AptPackageManager::default().get_package_map();
+    let apk_map = apk.get_package_map();
+    for tool in apt.keys() {
+        assert!(apk_map.contains_key(tool), "no apk package for {}", tool);
+    }
+}
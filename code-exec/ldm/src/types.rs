@@ -30,6 +30,12 @@ pub struct InstallationConfig {
     pub retry_attempts: u32,
     pub timeout_secs: u64,
     pub quiet_mode: bool,
+    /// Never reach out to package mirrors: the package list is not refreshed and
+    /// missing packages fail with `Error::OfflineDependencyMissing`
+    pub offline: bool,
+    /// Leave providers' temporary directories on disk on cleanup, to debug failed
+    /// installations
+    pub keep_artifacts: bool,
 }
 
 impl Default for InstallationConfig {
@@ -43,6 +49,8 @@ impl Default for InstallationConfig {
             retry_attempts: 3,
             timeout_secs: 300,
             quiet_mode: true,
+            offline: false,
+            keep_artifacts: false,
         }
     }
 }
@@ -0,0 +1,238 @@
+//! Content-addressed cache for installed dependencies
+//!
+//! Entries are keyed by a hash of the language and the sorted dependency set, so
+//! sandboxes that request the same dependencies can reuse installed packages such as
+//! `node_modules/`, or the dependency crates compiled into `target/`, instead of
+//! reinstalling them from scratch. Entries are stored as soon as the dependencies are
+//! installed, before the program is compiled, so they never hold a program's build output.
+
+use sha2::{Digest, Sha256};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+use tracing::{debug, warn};
+use uuid::Uuid;
+
+use crate::{
+    error::Error,
+    types::{Dependency, Language},
+};
+
+/// Marker file touched whenever an entry is used, for least-recently-used eviction
+const LAST_USED_MARKER: &str = ".last-used";
+
+/// Configuration for the dependency cache
+#[derive(Debug, Clone)]
+pub struct DependencyCacheConfig {
+    /// Directory holding cache entries
+    pub dir: PathBuf,
+    /// Maximum total size of all entries (bytes)
+    pub max_size: u64,
+}
+
+impl Default for DependencyCacheConfig {
+    fn default() -> Self {
+        Self {
+            dir: std::env::temp_dir().join("code-exec-cache"),
+            max_size: 2 * 1024 * 1024 * 1024, // 2GB
+        }
+    }
+}
+
+/// Cache of dependency artifacts shared between sandboxes
+#[derive(Debug, Clone)]
+pub struct DependencyCache {
+    config: DependencyCacheConfig,
+}
+
+impl DependencyCache {
+    /// Create a new dependency cache, creating its directory if needed
+    pub fn new(config: DependencyCacheConfig) -> Result<Self, Error> {
+        fs::create_dir_all(&config.dir)
+            .map_err(|e| Error::System(format!("Failed to create cache directory: {}", e)))?;
+        Ok(Self { config })
+    }
+
+    /// Compute the cache key for a language and dependency set
+    pub fn key(language: Language, dependencies: &[Dependency]) -> String {
+        let mut entries: Vec<_> = dependencies
+            .iter()
+            .map(|dep| {
+                (
+                    dep.name.as_str(),
+                    dep.version.as_str(),
+                    dep.source.as_deref().unwrap_or(""),
+                )
+            })
+            .collect();
+        entries.sort();
+
+        let mut hasher = Sha256::new();
+        hasher.update(format!("{:?}", language));
+        for (name, version, source) in entries {
+            hasher.update([0]);
+            hasher.update(name);
+            hasher.update([0]);
+            hasher.update(version);
+            hasher.update([0]);
+            hasher.update(source);
+        }
+        format!("{:x}", hasher.finalize())
+    }
+
+    /// Copy cached directories for `key` into the sandbox.
+    ///
+    /// Returns `true` on a cache hit.
+    pub async fn restore(
+        &self,
+        key: &str,
+        sandbox_dir: &Path,
+        dirs: &[&str],
+    ) -> Result<bool, Error> {
+        let entry = self.config.dir.join(key);
+        let sandbox_dir = sandbox_dir.to_path_buf();
+        let dirs: Vec<String> = dirs.iter().map(|d| d.to_string()).collect();
+
+        let hit = tokio::task::spawn_blocking(move || -> std::io::Result<bool> {
+            if !entry.is_dir() {
+                return Ok(false);
+            }
+            for dir in &dirs {
+                let cached = entry.join(dir);
+                if cached.exists() {
+                    copy_dir_all(&cached, &sandbox_dir.join(dir))?;
+                }
+            }
+            fs::write(entry.join(LAST_USED_MARKER), b"")?;
+            Ok(true)
+        })
+        .await
+        .map_err(|e| Error::System(format!("Cache restore task failed: {}", e)))?
+        .map_err(|e| Error::System(format!("Failed to restore cache entry {}: {}", key, e)))?;
+
+        debug!(
+            "Dependency cache {} for {}",
+            if hit { "hit" } else { "miss" },
+            key
+        );
+        Ok(hit)
+    }
+
+    /// Populate the cache entry for `key` from the sandbox, then evict old entries.
+    ///
+    /// Existing entries are left untouched.
+    pub async fn store(&self, key: &str, sandbox_dir: &Path, dirs: &[&str]) -> Result<(), Error> {
+        let cache = self.clone();
+        let key = key.to_string();
+        let sandbox_dir = sandbox_dir.to_path_buf();
+        let dirs: Vec<String> = dirs.iter().map(|d| d.to_string()).collect();
+
+        tokio::task::spawn_blocking(move || -> std::io::Result<()> {
+            let entry = cache.config.dir.join(&key);
+            if entry.exists() {
+                return Ok(());
+            }
+
+            // Stage into a unique directory so concurrent stores never see a partial entry
+            let staging = cache
+                .config
+                .dir
+                .join(format!(".staging-{}", Uuid::new_v4()));
+            fs::create_dir_all(&staging)?;
+            for dir in &dirs {
+                let source = sandbox_dir.join(dir);
+                if source.exists() {
+                    copy_dir_all(&source, &staging.join(dir))?;
+                }
+            }
+            fs::write(staging.join(LAST_USED_MARKER), b"")?;
+
+            if fs::rename(&staging, &entry).is_err() {
+                // Another sandbox stored the same entry first
+                fs::remove_dir_all(&staging)?;
+            }
+
+            cache.evict()
+        })
+        .await
+        .map_err(|e| Error::System(format!("Cache store task failed: {}", e)))?
+        .map_err(|e| Error::System(format!("Failed to store cache entry: {}", e)))
+    }
+
+    /// Remove least recently used entries until the cache fits in `max_size`
+    fn evict(&self) -> std::io::Result<()> {
+        let mut entries = Vec::new();
+        let mut total = 0;
+
+        for item in fs::read_dir(&self.config.dir)? {
+            let path = item?.path();
+            let is_entry = path
+                .file_name()
+                .map(|name| !name.to_string_lossy().starts_with('.'))
+                .unwrap_or(false);
+            if !is_entry || !path.is_dir() {
+                continue;
+            }
+
+            let size = dir_size(&path)?;
+            let last_used = fs::metadata(path.join(LAST_USED_MARKER))
+                .and_then(|m| m.modified())
+                .unwrap_or(SystemTime::UNIX_EPOCH);
+            total += size;
+            entries.push((last_used, size, path));
+        }
+
+        entries.sort_by_key(|(last_used, _, _)| *last_used);
+        for (_, size, path) in entries {
+            if total <= self.config.max_size {
+                break;
+            }
+            if let Err(e) = fs::remove_dir_all(&path) {
+                warn!("Failed to evict cache entry {}: {}", path.display(), e);
+                continue;
+            }
+            debug!("Evicted cache entry {}", path.display());
+            total -= size;
+        }
+
+        Ok(())
+    }
+}
+
+/// Recursively copy a directory, preserving symlinks
+fn copy_dir_all(source: &Path, target: &Path) -> std::io::Result<()> {
+    fs::create_dir_all(target)?;
+    for item in fs::read_dir(source)? {
+        let item = item?;
+        let file_type = item.file_type()?;
+        let destination = target.join(item.file_name());
+
+        if file_type.is_dir() {
+            copy_dir_all(&item.path(), &destination)?;
+        } else if file_type.is_symlink() {
+            let link = fs::read_link(item.path())?;
+            let _ = fs::remove_file(&destination);
+            std::os::unix::fs::symlink(link, &destination)?;
+        } else {
+            fs::copy(item.path(), &destination)?;
+        }
+    }
+    Ok(())
+}
+
+/// Total size in bytes of the regular files under a directory
+fn dir_size(path: &Path) -> std::io::Result<u64> {
+    let mut size = 0;
+    for item in fs::read_dir(path)? {
+        let item = item?;
+        let file_type = item.file_type()?;
+        if file_type.is_dir() {
+            size += dir_size(&item.path())?;
+        } else if file_type.is_file() {
+            size += item.metadata()?.len();
+        }
+    }
+    Ok(size)
+}
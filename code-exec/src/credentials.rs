@@ -0,0 +1,171 @@
+//! Per-execution credentials that only exist while the program runs
+//!
+//! Credentials are handed over right before the run phase, wiped from the sandbox as
+//! soon as the program exits and scrubbed from everything returned to the caller.
+
+use std::path::{Path, PathBuf};
+use tokio::{fs, io::AsyncWriteExt};
+
+use crate::{
+    error::Error,
+    types::{CredentialDelivery, InjectedCredential},
+};
+
+/// Replacement for credential values found in captured output
+pub const REDACTED: &str = "[REDACTED]";
+
+/// Sandbox directory holding file-delivered credentials
+const CREDENTIALS_DIR: &str = ".credentials";
+
+/// Make credentials available in the sandbox.
+///
+/// Returns the environment variables the program should be started with.
+pub(crate) async fn install(
+    sandbox_dir: &Path,
+    credentials: &[InjectedCredential],
+) -> Result<Vec<(String, String)>, Error> {
+    let mut env = Vec::new();
+
+    for credential in credentials {
+        validate_name(&credential.name)?;
+
+        match credential.delivery {
+            CredentialDelivery::Env => {
+                env.push((credential.name.clone(), credential.value.clone()));
+            }
+            CredentialDelivery::File => {
+                let path = write_credential_file(sandbox_dir, credential).await?;
+                env.push((
+                    format!("{}_FILE", credential.name),
+                    path.display().to_string(),
+                ));
+            }
+        }
+    }
+
+    Ok(env)
+}
+
+/// Overwrite and delete any credential files left in the sandbox
+pub(crate) async fn remove(sandbox_dir: &Path) -> Result<(), Error> {
+    let dir = sandbox_dir.join(CREDENTIALS_DIR);
+    if !dir.exists() {
+        return Ok(());
+    }
+
+    let mut entries = fs::read_dir(&dir)
+        .await
+        .map_err(|e| Error::System(format!("Failed to read credentials directory: {}", e)))?;
+    while let Some(entry) = entries
+        .next_entry()
+        .await
+        .map_err(|e| Error::System(format!("Failed to read credentials directory: {}", e)))?
+    {
+        let path = entry.path();
+        // Zero the contents first so the secret does not linger in freed blocks
+        if let Ok(metadata) = entry.metadata().await {
+            if metadata.is_file() {
+                let _ = fs::write(&path, vec![0u8; metadata.len() as usize]).await;
+            }
+        }
+    }
+
+    fs::remove_dir_all(&dir)
+        .await
+        .map_err(|e| Error::System(format!("Failed to remove credentials: {}", e)))
+}
+
+/// Replace every credential value in `text` with [`REDACTED`]
+pub(crate) fn scrub(text: &str, credentials: &[InjectedCredential]) -> String {
+    let mut values: Vec<&str> = credentials
+        .iter()
+        .map(|c| c.value.as_str())
+        .filter(|v| !v.is_empty())
+        .collect();
+    // Longest first, so a value containing another is not partially redacted
+    values.sort_by_key(|v| std::cmp::Reverse(v.len()));
+
+    values.into_iter().fold(text.to_string(), |text, value| {
+        text.replace(value, REDACTED)
+    })
+}
+
+/// Scrub credential values from output that may not be text
+pub(crate) fn scrub_bytes(bytes: &[u8], credentials: &[InjectedCredential]) -> Vec<u8> {
+    let mut values: Vec<&[u8]> = credentials
+        .iter()
+        .map(|c| c.value.as_bytes())
+        .filter(|v| !v.is_empty())
+        .collect();
+    values.sort_by_key(|v| std::cmp::Reverse(v.len()));
+
+    values.into_iter().fold(bytes.to_vec(), |bytes, value| {
+        let mut scrubbed = Vec::with_capacity(bytes.len());
+        let mut rest = bytes.as_slice();
+        while let Some(start) = rest.windows(value.len()).position(|w| w == value) {
+            scrubbed.extend_from_slice(&rest[..start]);
+            scrubbed.extend_from_slice(REDACTED.as_bytes());
+            rest = &rest[start + value.len()..];
+        }
+        scrubbed.extend_from_slice(rest);
+        scrubbed
+    })
+}
+
+/// Scrub credential values from the message of an execution error
+pub(crate) fn scrub_error(error: Error, credentials: &[InjectedCredential]) -> Error {
+    if credentials.is_empty() {
+        return error;
+    }
+
+    match error {
+        Error::ExecutionError(msg) => Error::ExecutionError(scrub(&msg, credentials)),
+        Error::Sandbox(msg) => Error::Sandbox(scrub(&msg, credentials)),
+        Error::System(msg) => Error::System(scrub(&msg, credentials)),
+        error => error,
+    }
+}
+
+fn validate_name(name: &str) -> Result<(), Error> {
+    let valid = name
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+
+    if valid {
+        Ok(())
+    } else {
+        Err(Error::Validation(format!(
+            "Invalid credential name: {:?}",
+            name
+        )))
+    }
+}
+
+async fn write_credential_file(
+    sandbox_dir: &Path,
+    credential: &InjectedCredential,
+) -> Result<PathBuf, Error> {
+    let dir = sandbox_dir.join(CREDENTIALS_DIR);
+    fs::DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(&dir)
+        .await
+        .map_err(|e| Error::System(format!("Failed to create credentials directory: {}", e)))?;
+
+    let path = dir.join(&credential.name);
+    let mut file = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(&path)
+        .await
+        .map_err(|e| Error::System(format!("Failed to create credential file: {}", e)))?;
+    file.write_all(credential.value.as_bytes())
+        .await
+        .map_err(|e| Error::System(format!("Failed to write credential file: {}", e)))?;
+
+    Ok(path)
+}
@@ -1,5 +1,8 @@
+use std::path::PathBuf;
 use thiserror::Error;
 
+use crate::types::{ResourceKind, SandboxEntry};
+
 #[derive(Error, Debug)]
 pub enum Error {
     #[error("Language not supported: {0}")]
@@ -11,27 +14,143 @@ pub enum Error {
     #[error("Execution failed: {0}")]
     ExecutionError(String),
 
-    #[error("Timeout after {0} seconds")]
-    Timeout(u64),
+    /// The program ran longer than its wall-clock budget and was killed
+    #[error("Wall-clock timeout after {0} seconds")]
+    WallTimeout(u64),
+
+    /// Installing dependencies took longer than its budget and was killed
+    #[error("Dependency installation timed out after {0} seconds")]
+    InstallTimeout(u64),
+
+    /// Compiling the program took longer than its budget and was killed
+    #[error("Compilation timed out after {0} seconds")]
+    CompilationTimeout(u64),
+
+    /// The program used up its CPU time limit (`RLIMIT_CPU`)
+    #[error("CPU time limit of {0} seconds exceeded")]
+    CpuTimeout(u32),
+
+    /// The execution was cancelled before it finished and its processes were killed
+    #[error("Execution cancelled")]
+    Cancelled,
 
     #[error("System error: {0}")]
     System(String),
 
+    #[error("Validation error: {0}")]
+    Validation(String),
+
+    /// The submission policy refused to run the code
+    #[error("Submission rejected by policy rule {rule}")]
+    PolicyRejected { rule: String },
+
+    /// The program imports a module the service bans for its language
+    #[error("Import of {module} is not allowed")]
+    BannedImport { module: String },
+
     #[error("Invalid dependency specification: {0}")]
     InvalidDependency(String),
 
+    /// A requested dependency version has a known vulnerability
+    #[error("Dependency {package} is affected by advisory {advisory}")]
+    VulnerableDependency { package: String, advisory: String },
+
+    /// The request asks for the same dependency at different versions
+    #[error("Conflicting versions of {package} requested: {}", .versions.join(", "))]
+    DependencyConflict {
+        package: String,
+        versions: Vec<String>,
+    },
+
+    /// Offline mode is on and a requested dependency has not been installed before
+    #[error("Dependency {package} is not available offline")]
+    OfflineDependencyMissing { package: String },
+
     #[error("Resource limit exceeded: {0}")]
     ResourceLimit(String),
 
+    /// The request's `code` or `input` is larger than the service accepts
+    #[error("Request {field} is {size} bytes, over the limit of {limit} bytes")]
+    SourceTooLarge {
+        field: &'static str,
+        size: usize,
+        limit: usize,
+    },
+
+    /// The program wrote output that is not UTF-8, with [`crate::OutputEncoding::Strict`]
+    #[error("Program wrote invalid UTF-8 to {stream} at byte {offset}")]
+    InvalidOutput { stream: String, offset: usize },
+
     #[error("Sandbox error: {0}")]
     Sandbox(String),
 
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
 
-    #[error("Resource exceeded: {0}")]
-    ResourceExceeded(String),
+    /// A resource limit was hit; `limit` and `observed` use the unit of `resource`
+    #[error("Resource exceeded: {resource} usage of {observed} is over the limit of {limit}")]
+    ResourceExceeded {
+        resource: ResourceKind,
+        limit: u64,
+        observed: u64,
+    },
 
     #[error("Resource limit error: {0}")]
     ResourceLimitError(String),
+
+    #[error("{error}")]
+    WithSandboxListing {
+        error: Box<Error>,
+        listing: Vec<SandboxEntry>,
+    },
+
+    /// The execution failed and its sandbox directory was kept at `path` for inspection
+    #[error("{error}")]
+    WithKeptSandbox { error: Box<Error>, path: PathBuf },
+}
+
+impl Error {
+    /// The resource whose limit caused this error, if any
+    pub fn resource_kind(&self) -> Option<ResourceKind> {
+        match self {
+            Error::ResourceExceeded { resource, .. } => Some(*resource),
+            Error::CpuTimeout(_) => Some(ResourceKind::CpuTime),
+            Error::WallTimeout(_) => Some(ResourceKind::WallTime),
+            Error::WithSandboxListing { error, .. } | Error::WithKeptSandbox { error, .. } => {
+                error.resource_kind()
+            }
+            _ => None,
+        }
+    }
+
+    /// Stable identifier of the kind of error, for clients that handle errors by kind
+    /// rather than by message
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::UnsupportedLanguage(_) => "unsupported_language",
+            Error::CompilationError(_) => "compilation_error",
+            Error::ExecutionError(_) => "execution_error",
+            Error::WallTimeout(_) | Error::InstallTimeout(_) | Error::CompilationTimeout(_) => {
+                "timeout"
+            }
+            Error::Cancelled => "cancelled",
+            Error::Validation(_) => "validation_error",
+            Error::PolicyRejected { .. } => "policy_rejected",
+            Error::BannedImport { .. } => "banned_import",
+            Error::InvalidDependency(_) => "invalid_dependency",
+            Error::VulnerableDependency { .. } => "vulnerable_dependency",
+            Error::DependencyConflict { .. } => "dependency_conflict",
+            Error::OfflineDependencyMissing { .. } => "dependency_unavailable",
+            Error::CpuTimeout(_) | Error::ResourceLimit(_) | Error::ResourceExceeded { .. } => {
+                "resource_exceeded"
+            }
+            Error::SourceTooLarge { .. } => "source_too_large",
+            Error::InvalidOutput { .. } => "invalid_output",
+            Error::Sandbox(_) => "sandbox_error",
+            Error::System(_) | Error::Io(_) | Error::ResourceLimitError(_) => "system_error",
+            Error::WithSandboxListing { error, .. } | Error::WithKeptSandbox { error, .. } => {
+                error.code()
+            }
+        }
+    }
 }
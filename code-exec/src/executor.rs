@@ -1,15 +1,35 @@
 use async_trait::async_trait;
-use std::path::PathBuf;
-use tokio::fs;
-use tracing::debug;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use std::{
+    collections::HashMap,
+    path::{Component, Path, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::{fs, time};
+use tracing::{debug, info_span, warn, Instrument};
 
 use crate::{
+    cache::{DependencyCache, DependencyCacheConfig},
+    credentials,
     error::Error,
-    languages::{GoExecutor, JavaScriptExecutor, PythonExecutor, RustExecutor, TypeScriptExecutor},
-    sandbox::Sandbox,
-    types::{ExecutionRequest, ExecutionResult, ExecutionStatus, Language},
+    languages::{
+        diagnostic_output, rust::SharedTargetDir, BashExecutor, CustomExecutor, DenoExecutor,
+        ElixirExecutor, GoExecutor, JavaScriptExecutor, KotlinExecutor, PythonExecutor,
+        RustExecutor, SqlExecutor, TypeScriptExecutor,
+    },
+    outputs,
+    sandbox::{RunOutput, Sandbox, MAX_OUTPUT_SIZE},
+    types::{
+        ExecutionMode, ExecutionRequest, ExecutionResult, ExecutionStatus, JsRuntime, Language,
+        OutputEncoding, SandboxBackend, WarmupResult,
+    },
+    ProcessStats,
 };
 
+#[cfg(feature = "wasm")]
+use crate::wasm::WasmSandbox;
+
 /// Trait for language-specific code executors
 #[async_trait]
 pub trait LanguageExecutor: Send + Sync {
@@ -24,19 +44,94 @@ pub trait LanguageExecutor: Send + Sync {
         Vec::new()
     }
 
+    /// Get environment variables the run command needs besides the request's own
+    fn run_env(&self) -> Vec<(String, String)> {
+        Vec::new()
+    }
+
+    /// Whether the program can receive the request's command-line arguments
+    fn accepts_args(&self) -> bool {
+        true
+    }
+
+    /// Get the arguments for the run command in deterministic mode, which may seed the
+    /// runtime's random number generator. Implementations build on
+    /// [`run_args`](Self::run_args), so both modes run the program the same way
+    fn deterministic_run_args(&self) -> Vec<String> {
+        self.run_args()
+    }
+
+    /// Get environment variables fixing the runtime's hash and random seeds in
+    /// deterministic mode, on top of [`DETERMINISTIC_ENV`]
+    fn deterministic_env(&self) -> Vec<(String, String)> {
+        Vec::new()
+    }
+
+    /// Get the arguments for the run command that check the program's syntax without
+    /// running it, in compile-only mode, for languages [`compile`](Self::compile) does
+    /// not check
+    fn check_args(&self) -> Option<Vec<String>> {
+        None
+    }
+
+    /// Get the directory, relative to the sandbox, the program's source is moved to by
+    /// [`compile`](Self::compile); the request's other files are written there
+    fn source_dir(&self) -> &str {
+        ""
+    }
+
+    /// Get the sandbox directories holding installed dependencies, reused across
+    /// sandboxes by the dependency cache
+    fn cache_dirs(&self) -> Vec<&str> {
+        Vec::new()
+    }
+
+    /// Get the sandbox paths the executor manages itself, which request files must not
+    /// be written to, so they cannot end up in the dependency cache or change what gets
+    /// installed
+    fn reserved_paths(&self) -> Vec<&str> {
+        self.cache_dirs()
+    }
+
+    /// Get commands run in the sandbox under the execution's limits, like the program
+    /// itself, once [`setup_environment`](Self::setup_environment) and the dependency
+    /// install are done. For setup steps the request supplies, which must never run on
+    /// the host
+    fn sandboxed_setup_commands(&self) -> Vec<Vec<String>> {
+        Vec::new()
+    }
+
+    /// Get commands run in the sandbox under the execution's limits after
+    /// [`compile`](Self::compile), for compile steps the request supplies
+    fn sandboxed_compile_commands(&self) -> Vec<Vec<String>> {
+        Vec::new()
+    }
+
     /// Set up the execution environment
     async fn setup_environment(&self, _sandbox_dir: &PathBuf) -> Result<(), Error>;
 
-    /// Install required dependencies
+    /// Install required dependencies, returning what the package manager printed, or
+    /// `None` if it printed nothing or its output is not captured
     async fn install_dependencies(
         &self,
         _sandbox_dir: &PathBuf,
         _dependencies: &[crate::types::Dependency],
-    ) -> Result<(), Error>;
+    ) -> Result<Option<String>, Error>;
 
     /// Compile the code if needed
     async fn compile(&self, _sandbox_dir: &PathBuf, _source_file: &PathBuf) -> Result<(), Error>;
 
+    /// Compile the code to a WASI module for the WebAssembly backend, returning its path
+    async fn compile_wasm(
+        &self,
+        _sandbox_dir: &Path,
+        _source_file: &Path,
+    ) -> Result<PathBuf, Error> {
+        Err(Error::UnsupportedLanguage(
+            "language cannot target the WebAssembly backend".to_string(),
+        ))
+    }
+
     /// Check if all required tools are available
     async fn check_tools(&self) -> Result<(), Error>;
 
@@ -47,13 +142,94 @@ pub trait LanguageExecutor: Send + Sync {
     async fn ensure_directories(&self, _sandbox_dir: &PathBuf) -> Result<(), Error>;
 }
 
+/// Environment of every program run in deterministic mode
+pub const DETERMINISTIC_ENV: &[(&str, &str)] = &[("TZ", "UTC"), ("SOURCE_DATE_EPOCH", "0")];
+
+/// Maximum number of entries in a sandbox listing attached to errors
+const MAX_SANDBOX_LISTING_ENTRIES: usize = 500;
+
+/// Dependencies of a sandbox after [`CodeExecutor::install_environment`]
+struct InstalledDependencies {
+    /// Dependency cache entry of the set, and whether the sandbox was seeded from it;
+    /// `None` if the cache was not consulted
+    cache_key: Option<(String, bool)>,
+    /// Time spent installing, `None` if there was nothing to install
+    install_time: Option<Duration>,
+    /// What the package manager printed, if anything
+    diagnostics: Option<String>,
+}
+
 /// Generic code executor that uses a sandbox
-pub struct CodeExecutor {}
+#[derive(Clone)]
+pub struct CodeExecutor {
+    /// Cache of installed dependencies shared between sandboxes
+    dependency_cache: Option<DependencyCache>,
+    /// Toolchain versions pinned per language, executors pick their own otherwise
+    versions: HashMap<Language, String>,
+    /// Take dependencies from the cache only, never from package registries
+    offline: bool,
+    /// Cargo target directory shared by Rust builds
+    rust_target_dir: Option<Arc<SharedTargetDir>>,
+    /// Directory, relative to the sandbox, request fixtures are written to
+    fixtures_dir: PathBuf,
+}
 
 impl CodeExecutor {
     /// Create a new code executor
     pub async fn new() -> Result<Self, Error> {
-        Ok(Self {})
+        Ok(Self {
+            dependency_cache: None,
+            versions: HashMap::new(),
+            offline: false,
+            rust_target_dir: None,
+            fixtures_dir: PathBuf::new(),
+        })
+    }
+
+    /// Create a new code executor that reuses installed dependencies across sandboxes
+    pub async fn with_dependency_cache(config: DependencyCacheConfig) -> Result<Self, Error> {
+        Ok(Self {
+            dependency_cache: Some(DependencyCache::new(config)?),
+            versions: HashMap::new(),
+            offline: false,
+            rust_target_dir: None,
+            fixtures_dir: PathBuf::new(),
+        })
+    }
+
+    /// Pin the toolchain version each language's executor is created with
+    pub fn with_language_versions(mut self, versions: HashMap<Language, String>) -> Self {
+        self.versions = versions;
+        self
+    }
+
+    /// Never download dependencies, for hosts without access to package registries.
+    ///
+    /// Requests with dependencies only run if an earlier execution left the same
+    /// dependency set in the cache, and fail with [`Error::OfflineDependencyMissing`]
+    /// before any package manager is started otherwise. Executors without cacheable
+    /// artifacts, like Go's, refuse dependencies with [`Error::InvalidDependency`].
+    pub fn with_offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
+        self
+    }
+
+    /// Build Rust programs in one Cargo target directory, so crates are compiled once
+    /// rather than in every sandbox. Builds of the same dependency set take turns using
+    /// its subdirectory.
+    pub fn with_rust_target_dir(mut self, target_dir: PathBuf) -> Self {
+        self.rust_target_dir = Some(Arc::new(SharedTargetDir::new(target_dir)));
+        self
+    }
+
+    /// Write request fixtures to `dir` inside the sandbox instead of its root. Only the
+    /// plain names in `dir` are used, so it cannot point outside the sandbox.
+    pub fn with_fixtures_dir(mut self, dir: &Path) -> Self {
+        self.fixtures_dir = dir
+            .components()
+            .filter(|component| matches!(component, Component::Normal(_)))
+            .collect();
+        self
     }
 
     /// Execute code in a specific sandbox
@@ -62,57 +238,442 @@ impl CodeExecutor {
         request: ExecutionRequest,
         sandbox: &mut Sandbox,
     ) -> Result<ExecutionResult, Error> {
-        let executor = self.create_executor(request.language)?;
+        let include_listing = request.include_sandbox_listing;
 
-        // Check/install tools only if needed (shared across executions)
-        if let Err(e) = executor.check_tools().await {
-            executor.install_missing_tools().await?;
+        match self.run_in_sandbox(request, sandbox).await {
+            Err(error) if include_listing => Err(Error::WithSandboxListing {
+                error: Box::new(error),
+                listing: sandbox.listing(MAX_SANDBOX_LISTING_ENTRIES),
+            }),
+            Ok(mut result) if include_listing && result.status != ExecutionStatus::Success => {
+                result.sandbox_listing = sandbox.listing(MAX_SANDBOX_LISTING_ENTRIES);
+                Ok(result)
+            }
+            result => result,
+        }
+    }
+
+    async fn run_in_sandbox(
+        &self,
+        request: ExecutionRequest,
+        sandbox: &mut Sandbox,
+    ) -> Result<ExecutionResult, Error> {
+        let executor = self.create_executor(&request)?;
+        if request.output_encoding != OutputEncoding::Lossy
+            && sandbox.limits().backend == SandboxBackend::Wasm
+        {
+            return Err(Error::Validation(
+                "The WebAssembly backend only decodes output lossily".to_string(),
+            ));
+        }
+        if !request.args.is_empty() && !executor.accepts_args() {
+            return Err(Error::Validation(format!(
+                "{} programs do not take command-line arguments",
+                request.language
+            )));
         }
 
+        self.ensure_tools(executor.as_ref()).await?;
+
         let source_file = self
             .write_source_file(&sandbox, &request, executor.file_extension())
             .await?;
+        self.write_files(sandbox, &request, executor.as_ref())
+            .await?;
 
-        // Setup sandbox environment
-        executor.ensure_directories(&sandbox.root_dir).await?;
-        executor.setup_environment(&sandbox.root_dir).await?;
+        let installed = self
+            .install_environment(executor.as_ref(), sandbox, &request)
+            .await?;
+        // Stored before anything is compiled, so the cache never holds build output
+        self.store_dependencies(executor.as_ref(), sandbox, &installed.cache_key)
+            .await;
+        let install_timeout = Duration::from_secs(sandbox.limits().install_timeout);
+        for command in executor.sandboxed_setup_commands() {
+            self.run_sandboxed_step(
+                executor.as_ref(),
+                sandbox,
+                &command,
+                install_timeout,
+                Error::InstallTimeout,
+            )
+            .instrument(info_span!("setup"))
+            .await?;
+        }
+        let dependency_install_time = installed.install_time;
+        let dependency_cache_hit = installed.cache_key.as_ref().map(|(_, hit)| *hit);
+        let install_diagnostics = installed
+            .diagnostics
+            .filter(|_| request.include_install_diagnostics);
 
-        if !request.dependencies.is_empty() {
-            executor
-                .install_dependencies(&sandbox.root_dir, &request.dependencies)
-                .await?;
+        // Dropping a timed out step kills its compiler
+        let compile_timeout = Duration::from_secs(sandbox.limits().compile_timeout);
+
+        let started = Instant::now();
+        let compile = async {
+            match sandbox.limits().backend {
+                SandboxBackend::Native | SandboxBackend::Gvisor => executor
+                    .compile(&sandbox.root_dir, &source_file)
+                    .await
+                    .map(|_| None),
+                SandboxBackend::Wasm => executor
+                    .compile_wasm(&sandbox.root_dir, &source_file)
+                    .await
+                    .map(Some),
+            }
+        };
+        let mut compiled = time::timeout(compile_timeout, sandbox.watch_step(compile))
+            .instrument(info_span!("compile"))
+            .await
+            .map_err(|_| Error::CompilationTimeout(compile_timeout.as_secs()))??;
+        if compiled.is_ok() {
+            for command in executor.sandboxed_compile_commands() {
+                let step = self
+                    .run_sandboxed_step(
+                        executor.as_ref(),
+                        sandbox,
+                        &command,
+                        compile_timeout,
+                        Error::CompilationTimeout,
+                    )
+                    .instrument(info_span!("compile"))
+                    .await;
+                if let Err(e) = step {
+                    compiled = Err(e);
+                    break;
+                }
+            }
         }
+        let compile_time = Some(started.elapsed());
+        let wasm_module = match compiled {
+            Ok(module) => module,
+            Err(Error::CompilationError(diagnostics)) => {
+                return Ok(ExecutionResult {
+                    status: ExecutionStatus::CompilationError,
+                    stdout: String::new(),
+                    stderr: diagnostics,
+                    stdout_base64: None,
+                    stderr_base64: None,
+                    process_stats: ProcessStats {
+                        compile_time,
+                        dependency_install_time,
+                        dependency_cache_hit,
+                        peak_disk_bytes: sandbox.peak_disk_bytes().await,
+                        ..Default::default()
+                    },
+                    install_diagnostics,
+                    sandbox_listing: Vec::new(),
+                    output_files: Vec::new(),
+                })
+            }
+            Err(error) => return Err(error),
+        };
 
-        executor.compile(&sandbox.root_dir, &source_file).await?;
+        if request.mode == ExecutionMode::CompileOnly {
+            // Interpreted programs compile to nothing, only their syntax is checked
+            let check = match (executor.check_args(), &wasm_module) {
+                (Some(args), None) => {
+                    let args: Vec<_> = args.iter().map(|s| s.as_str()).collect();
+                    let env = executor.run_env();
+                    let output = sandbox
+                        .run(executor.run_command(), &args, &env, None, compile_timeout)
+                        .instrument(info_span!("check"))
+                        .await?;
+                    Some(output)
+                }
+                _ => None,
+            };
+            let (status, stderr) = match check {
+                Some(output) => match output.status {
+                    ExecutionStatus::RuntimeError(_) => {
+                        (ExecutionStatus::CompilationError, output.stderr)
+                    }
+                    status => (status, output.stderr),
+                },
+                None => (ExecutionStatus::Success, String::new()),
+            };
+            return Ok(ExecutionResult {
+                status,
+                stdout: String::new(),
+                stderr,
+                stdout_base64: None,
+                stderr_base64: None,
+                process_stats: ProcessStats {
+                    compile_time: Some(started.elapsed()),
+                    dependency_install_time,
+                    dependency_cache_hit,
+                    peak_disk_bytes: sandbox.peak_disk_bytes().await,
+                    ..Default::default()
+                },
+                install_diagnostics,
+                sandbox_listing: Vec::new(),
+                output_files: Vec::new(),
+            });
+        }
 
-        let env_vars: Vec<(String, String)> = request
-            .env_vars
-            .iter()
-            .map(|(k, v)| (k.clone(), v.clone()))
-            .collect();
+        self.write_fixtures(sandbox, &request).await?;
 
-        let (stdout, stderr, process_stats) = sandbox
-            .execute(
-                executor.run_command(),
-                &executor
-                    .run_args()
+        let mut env_vars = executor.run_env();
+        let mut run_args = if request.deterministic {
+            env_vars.extend(
+                DETERMINISTIC_ENV
                     .iter()
-                    .map(|s| s.as_str())
-                    .collect::<Vec<_>>(),
-                &env_vars,
-                request.input.as_deref(),
-                request.timeout,
-            )
-            .await?;
+                    .map(|(k, v)| (k.to_string(), v.to_string())),
+            );
+            env_vars.extend(executor.deterministic_env());
+            executor.deterministic_run_args()
+        } else {
+            executor.run_args()
+        };
+        // Passed to the program as is, never through a shell
+        run_args.extend(request.args.iter().cloned());
+        env_vars.extend(request.env_vars.iter().map(|(k, v)| (k.clone(), v.clone())));
+        let credentials = &request.injected_credentials;
+        env_vars.extend(credentials::install(&sandbox.root_dir, credentials).await?);
+
+        sandbox.set_output_encoding(request.output_encoding);
+        let run = async {
+            let run_args: Vec<_> = run_args.iter().map(|s| s.as_str()).collect();
+            match wasm_module {
+                // Wasm modules read stdin from memory, so a script's input is sent at once
+                Some(module) => RunOutput::from_result(
+                    execute_wasm(
+                        sandbox,
+                        &module,
+                        &request.args,
+                        &env_vars,
+                        request.stdin().as_deref(),
+                        request.timeout,
+                    )
+                    .await,
+                ),
+                None if !request.input_script.is_empty() => {
+                    sandbox
+                        .run_script(
+                            executor.run_command(),
+                            &run_args,
+                            &env_vars,
+                            &request.input_script,
+                            request.timeout,
+                        )
+                        .await
+                }
+                None => {
+                    sandbox
+                        .run(
+                            executor.run_command(),
+                            &run_args,
+                            &env_vars,
+                            request.input.as_deref(),
+                            request.timeout,
+                        )
+                        .await
+                }
+            }
+        };
+        let result = run.instrument(info_span!("run")).await;
+
+        credentials::remove(&sandbox.root_dir).await?;
+        let output = result.map_err(|e| credentials::scrub_error(e, credentials))?;
+        // Base64 output replaces the text, credentials are scrubbed from either
+        let text = |text: &str| match request.output_encoding {
+            OutputEncoding::Base64 => String::new(),
+            _ => credentials::scrub(text, credentials),
+        };
+        let encode = |bytes: Option<Vec<u8>>| {
+            bytes.map(|bytes| STANDARD.encode(credentials::scrub_bytes(&bytes, credentials)))
+        };
+
+        let (output_files, truncated) = match output.status {
+            ExecutionStatus::Success if !request.capture_outputs.is_empty() => outputs::collect(
+                &sandbox.root_dir,
+                &request.capture_outputs,
+                MAX_OUTPUT_SIZE,
+                credentials,
+            )?,
+            _ => (Vec::new(), false),
+        };
 
         Ok(ExecutionResult {
-            status: ExecutionStatus::Success,
-            stdout,
-            stderr,
-            process_stats,
+            status: if truncated {
+                ExecutionStatus::OutputTruncated
+            } else {
+                output.status
+            },
+            stdout: text(&output.stdout),
+            stderr: text(&output.stderr),
+            stdout_base64: encode(output.stdout_bytes),
+            stderr_base64: encode(output.stderr_bytes),
+            process_stats: ProcessStats {
+                compile_time,
+                dependency_install_time,
+                dependency_cache_hit,
+                peak_disk_bytes: sandbox.peak_disk_bytes().await,
+                ..output.stats
+            },
+            install_diagnostics,
+            sandbox_listing: Vec::new(),
+            output_files,
+        })
+    }
+
+    /// Install a request's toolchain and dependencies into `sandbox` without running
+    /// anything, leaving the dependency set in the dependency cache for later requests
+    pub async fn warmup(
+        &self,
+        request: &ExecutionRequest,
+        sandbox: &Sandbox,
+    ) -> Result<WarmupResult, Error> {
+        let started = Instant::now();
+        let executor = self.create_executor(request)?;
+        let cacheable = self.dependency_cache.is_some() && !executor.cache_dirs().is_empty();
+        if !request.dependencies.is_empty() && !cacheable {
+            return Err(Error::Validation(format!(
+                "{} dependencies cannot be warmed up without a dependency cache for them",
+                request.language
+            )));
+        }
+
+        self.ensure_tools(executor.as_ref()).await?;
+        let installed = self
+            .install_environment(executor.as_ref(), sandbox, request)
+            .await?;
+        self.store_dependencies(executor.as_ref(), sandbox, &installed.cache_key)
+            .await;
+
+        Ok(WarmupResult {
+            cache_hit: matches!(installed.cache_key, Some((_, true))),
+            dependency_install_time: installed.install_time,
+            duration: started.elapsed(),
+        })
+    }
+
+    /// Install the language's toolchain if it is missing, shared across executions
+    async fn ensure_tools(&self, executor: &dyn LanguageExecutor) -> Result<(), Error> {
+        if let Err(e) = executor.check_tools().await {
+            // Installing toolchains downloads them from the system's package mirrors
+            if self.offline {
+                return Err(e);
+            }
+            executor.install_missing_tools().await?;
+        }
+        Ok(())
+    }
+
+    /// Set up the language's environment in the sandbox and install the request's
+    /// dependencies, seeding the sandbox from the dependency cache first
+    async fn install_environment(
+        &self,
+        executor: &dyn LanguageExecutor,
+        sandbox: &Sandbox,
+        request: &ExecutionRequest,
+    ) -> Result<InstalledDependencies, Error> {
+        executor.ensure_directories(&sandbox.root_dir).await?;
+
+        // Seed the sandbox with artifacts from an identical dependency set
+        let cache_key = match &self.dependency_cache {
+            Some(cache)
+                if !request.dependencies.is_empty() && !executor.cache_dirs().is_empty() =>
+            {
+                let key = DependencyCache::key(request.language, &request.dependencies);
+                let hit = cache
+                    .restore(&key, &sandbox.root_dir, &executor.cache_dirs())
+                    .await?;
+                Some((key, hit))
+            }
+            _ => None,
+        };
+        if self.offline && !request.dependencies.is_empty() && executor.cache_dirs().is_empty() {
+            return Err(Error::InvalidDependency(format!(
+                "Dependencies of {} programs cannot be installed offline, as the dependency \
+                 cache does not keep them",
+                request.language
+            )));
+        }
+        if self.offline && !request.dependencies.is_empty() && !matches!(cache_key, Some((_, true)))
+        {
+            return Err(Error::OfflineDependencyMissing {
+                package: request.dependencies[0].name.clone(),
+            });
+        }
+
+        // Dropping a timed out step kills its package manager. Setting up can download
+        // packages too, such as TypeScript's compiler, so it runs under the same limit.
+        let install_timeout = Duration::from_secs(sandbox.limits().install_timeout);
+        let setup = executor.setup_environment(&sandbox.root_dir);
+        time::timeout(install_timeout, sandbox.watch_step(setup))
+            .instrument(info_span!("setup"))
+            .await
+            .map_err(|_| Error::InstallTimeout(install_timeout.as_secs()))???;
+
+        let mut install_time = None;
+        let mut diagnostics = None;
+        if !request.dependencies.is_empty() {
+            let started = Instant::now();
+            let install = executor.install_dependencies(&sandbox.root_dir, &request.dependencies);
+            diagnostics = time::timeout(install_timeout, sandbox.watch_step(install))
+                .instrument(info_span!("install"))
+                .await
+                .map_err(|_| Error::InstallTimeout(install_timeout.as_secs()))???;
+            install_time = Some(started.elapsed());
+        }
+
+        Ok(InstalledDependencies {
+            cache_key,
+            install_time,
+            diagnostics,
         })
     }
 
+    /// Run a build command in the sandbox under the execution's limits, like the program
+    /// itself, failing with its diagnostics if it does not succeed and with `timed_out`
+    /// if it runs out of time
+    async fn run_sandboxed_step(
+        &self,
+        executor: &dyn LanguageExecutor,
+        sandbox: &mut Sandbox,
+        command: &[String],
+        timeout: Duration,
+        timed_out: fn(u64) -> Error,
+    ) -> Result<(), Error> {
+        let Some((program, args)) = command.split_first() else {
+            return Err(Error::Validation(
+                "Build commands must not be empty".to_string(),
+            ));
+        };
+        let args: Vec<_> = args.iter().map(String::as_str).collect();
+
+        let output = sandbox
+            .run(program, &args, &executor.run_env(), None, timeout)
+            .await?;
+        match output.status {
+            ExecutionStatus::Success => Ok(()),
+            ExecutionStatus::Timeout => Err(timed_out(timeout.as_secs())),
+            _ => Err(Error::CompilationError(format!(
+                "{} failed: {}",
+                command.join(" "),
+                diagnostic_output(output.stderr.as_bytes())
+            ))),
+        }
+    }
+
+    /// Copy a dependency set missing from the dependency cache into it
+    async fn store_dependencies(
+        &self,
+        executor: &dyn LanguageExecutor,
+        sandbox: &Sandbox,
+        cache_key: &Option<(String, bool)>,
+    ) {
+        if let (Some(cache), Some((key, false))) = (&self.dependency_cache, cache_key) {
+            // A failed store only costs a future cache miss
+            if let Err(e) = cache
+                .store(key, &sandbox.root_dir, &executor.cache_dirs())
+                .await
+            {
+                warn!("Failed to populate dependency cache: {}", e);
+            }
+        }
+    }
+
     async fn write_source_file(
         &self,
         sandbox: &Sandbox,
@@ -133,18 +694,150 @@ impl CodeExecutor {
             fs::create_dir_all(parent).await.map_err(Error::Io)?;
         }
 
-        fs::write(&path, &request.code).await.map_err(Error::Io)?;
+        fs::write(&path, request.program()?)
+            .await
+            .map_err(Error::Io)?;
         debug!("Created source file at: {}", path.display());
         Ok(path)
     }
 
-    fn create_executor(&self, language: Language) -> Result<Box<dyn LanguageExecutor>, Error> {
-        match language {
-            Language::Python => Ok(Box::new(PythonExecutor::new(None))),
-            Language::JavaScript => Ok(Box::new(JavaScriptExecutor::new(None))),
-            Language::TypeScript => Ok(Box::new(TypeScriptExecutor::new(None, None))),
-            Language::Rust => Ok(Box::new(RustExecutor::new(None))),
-            Language::Go => Ok(Box::new(GoExecutor::new(None))),
+    /// Write the request's files, except the entrypoint, under `source_dir`
+    async fn write_files(
+        &self,
+        sandbox: &Sandbox,
+        request: &ExecutionRequest,
+        executor: &dyn LanguageExecutor,
+    ) -> Result<(), Error> {
+        request.validate_files()?;
+
+        let source_dir = Path::new(executor.source_dir());
+        let reserved = executor.reserved_paths();
+        if let Some(file) = request.files.iter().find(|file| {
+            let relative = source_dir.join(&file.path);
+            reserved.iter().any(|path| relative.starts_with(path))
+        }) {
+            return Err(Error::Validation(format!(
+                "File path is reserved for installed dependencies and build files: {}",
+                file.path
+            )));
+        }
+
+        let dir = sandbox.root_dir.join(source_dir);
+        for file in &request.files {
+            if request.entrypoint.as_ref() == Some(&file.path) {
+                continue;
+            }
+            let path = dir.join(&file.path);
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent).await.map_err(Error::Io)?;
+            }
+            fs::write(&path, &file.contents).await.map_err(Error::Io)?;
+            debug!("Created source file at: {}", path.display());
+        }
+        Ok(())
+    }
+
+    /// Write the request's fixtures under the fixtures directory, once the program is
+    /// built so none of them is taken for source
+    async fn write_fixtures(
+        &self,
+        sandbox: &Sandbox,
+        request: &ExecutionRequest,
+    ) -> Result<(), Error> {
+        let dir = sandbox.root_dir.join(&self.fixtures_dir);
+        for fixture in &request.fixtures {
+            let path = dir.join(&fixture.path);
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent).await.map_err(Error::Io)?;
+            }
+            fs::write(&path, &fixture.contents)
+                .await
+                .map_err(Error::Io)?;
+            debug!("Created fixture at: {}", path.display());
+        }
+        Ok(())
+    }
+
+    pub(crate) fn create_executor(
+        &self,
+        request: &ExecutionRequest,
+    ) -> Result<Box<dyn LanguageExecutor>, Error> {
+        if request.js_runtime == JsRuntime::Deno {
+            return match request.language {
+                Language::JavaScript | Language::TypeScript => Ok(Box::new(
+                    DenoExecutor::new(request.language).with_offline(self.offline),
+                )),
+                language => Err(Error::Validation(format!(
+                    "The Deno runtime cannot run {} programs",
+                    language
+                ))),
+            };
+        }
+
+        let version = self.versions.get(&request.language).cloned();
+        match request.language {
+            Language::Python => Ok(Box::new(
+                PythonExecutor::new(version).with_offline(self.offline),
+            )),
+            Language::JavaScript => Ok(Box::new(
+                JavaScriptExecutor::new(version).with_offline(self.offline),
+            )),
+            Language::TypeScript => Ok(Box::new(
+                TypeScriptExecutor::new(version, None).with_offline(self.offline),
+            )),
+            Language::Rust => Ok(Box::new(
+                RustExecutor::new(version)
+                    .with_offline(self.offline)
+                    .with_shared_target_dir(self.rust_target_dir.clone()),
+            )),
+            Language::Go => Ok(Box::new(
+                // `go run main.go` would leave out the other files
+                GoExecutor::new(version)
+                    .with_go_run(request.dependencies.is_empty() && request.files.is_empty())
+                    .with_offline(self.offline),
+            )),
+            Language::Kotlin => Ok(Box::new(KotlinExecutor::new(version))),
+            Language::Bash => Ok(Box::new(BashExecutor::new())),
+            Language::Sql => Ok(Box::new(SqlExecutor::new())),
+            Language::Elixir => Ok(Box::new(
+                ElixirExecutor::new(version)
+                    .with_mix(!request.dependencies.is_empty())
+                    .with_offline(self.offline),
+            )),
+            Language::Custom => match &request.custom {
+                Some(custom) => Ok(Box::new(CustomExecutor::new(custom.clone()))),
+                None => Err(Error::Validation(
+                    "Custom language requests need the commands to run them with".to_string(),
+                )),
+            },
         }
     }
 }
+
+#[cfg(feature = "wasm")]
+async fn execute_wasm(
+    sandbox: &Sandbox,
+    module: &Path,
+    args: &[String],
+    env: &[(String, String)],
+    input: Option<&str>,
+    timeout: Duration,
+) -> Result<(String, String, ProcessStats), Error> {
+    WasmSandbox::new(sandbox.limits().clone())
+        .execute(module, &sandbox.root_dir, args, env, input, timeout)
+        .await
+}
+
+#[cfg(not(feature = "wasm"))]
+async fn execute_wasm(
+    _sandbox: &Sandbox,
+    _module: &Path,
+    _args: &[String],
+    _env: &[(String, String)],
+    _input: Option<&str>,
+    _timeout: Duration,
+) -> Result<(String, String, ProcessStats), Error> {
+    Err(Error::Sandbox(
+        "the WebAssembly backend requires the `wasm` feature".to_string(),
+    ))
+}
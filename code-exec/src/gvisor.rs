@@ -0,0 +1,211 @@
+//! gVisor backend, running programs in a `runsc` container
+//!
+//! The container's root filesystem is the host's, mounted read-only, with a private
+//! `/tmp` and the sandbox directory bound writable at its usual path, so toolchains and
+//! the paths executors use work unchanged. The container has no network, and resource
+//! limits are set in its OCI spec rather than on the host process, since they would
+//! otherwise also count gVisor's own usage.
+
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+use tokio::{fs, process::Command};
+use tracing::debug;
+
+use crate::{error::Error, sandbox::PROGRAM_UMASK, types::ResourceLimits};
+
+/// Locate `runsc`, failing with installation hints if it is missing
+pub fn runsc_path() -> Result<PathBuf, Error> {
+    which::which("runsc").map_err(|_| {
+        Error::Sandbox(
+            "The gVisor backend needs runsc on the PATH, see https://gvisor.dev/docs/user_guide/install/"
+                .to_string(),
+        )
+    })
+}
+
+/// Container running a single program
+pub struct Container {
+    runsc: PathBuf,
+    /// Holds the OCI spec and runsc's state, outside the sandbox so the program
+    /// cannot tamper with either
+    bundle_dir: PathBuf,
+    id: String,
+}
+
+/// What the container runs
+pub struct ContainerProcess<'a> {
+    pub command: &'a Path,
+    pub args: &'a [&'a str],
+    pub env: &'a [(String, String)],
+    /// Sandbox directory, which is also the working directory
+    pub root_dir: &'a Path,
+    pub uid: u32,
+    pub gid: u32,
+}
+
+impl Container {
+    /// Write the bundle for `process` next to the sandbox directory
+    pub async fn create(
+        id: &str,
+        process: &ContainerProcess<'_>,
+        limits: &ResourceLimits,
+    ) -> Result<Self, Error> {
+        let runsc = runsc_path()?;
+        let bundle_dir = process.root_dir.with_extension("runsc");
+        fs::create_dir_all(bundle_dir.join("state"))
+            .await
+            .map_err(|e| Error::Sandbox(format!("Failed to create gVisor bundle: {}", e)))?;
+
+        let spec = oci_spec(process, limits);
+        fs::write(bundle_dir.join("config.json"), spec.to_string())
+            .await
+            .map_err(|e| Error::Sandbox(format!("Failed to write OCI spec: {}", e)))?;
+
+        Ok(Self {
+            runsc,
+            bundle_dir,
+            id: format!("code-exec-{}", id),
+        })
+    }
+
+    /// Command running the container in the foreground, passing its stdio through
+    pub fn command(&self) -> Command {
+        let mut command = Command::new(&self.runsc);
+        command
+            .arg("--network=none")
+            .arg("--root")
+            .arg(self.bundle_dir.join("state"))
+            .arg("run")
+            .arg("--bundle")
+            .arg(&self.bundle_dir)
+            .arg(&self.id)
+            .env_clear();
+        command
+    }
+
+    /// Kill whatever still runs in the container and remove its bundle
+    pub async fn destroy(self) {
+        let result = Command::new(&self.runsc)
+            .arg("--root")
+            .arg(self.bundle_dir.join("state"))
+            .args(["delete", "--force", &self.id])
+            .output()
+            .await;
+        if let Err(e) = result {
+            debug!("Failed to delete gVisor container {}: {}", self.id, e);
+        }
+        if let Err(e) = fs::remove_dir_all(&self.bundle_dir).await {
+            debug!("Failed to remove gVisor bundle: {}", e);
+        }
+    }
+}
+
+/// OCI runtime spec for `process` under `limits`
+fn oci_spec(process: &ContainerProcess<'_>, limits: &ResourceLimits) -> Value {
+    let args: Vec<String> = std::iter::once(process.command.display().to_string())
+        .chain(process.args.iter().map(|arg| arg.to_string()))
+        .collect();
+    let home = process.root_dir.join("home");
+    let env: Vec<String> = process
+        .env
+        .iter()
+        .map(|(k, v)| format!("{}={}", k, v))
+        .chain([
+            format!("PATH={}", limits.program_path()),
+            format!("HOME={}", home.display()),
+        ])
+        .collect();
+
+    json!({
+        "ociVersion": "1.0.2",
+        "process": {
+            "terminal": false,
+            "user": {
+                "uid": process.uid,
+                "gid": process.gid,
+                "umask": PROGRAM_UMASK,
+            },
+            "args": args,
+            "env": env,
+            "cwd": process.root_dir,
+            "rlimits": [
+                {
+                    "type": "RLIMIT_CPU",
+                    "soft": limits.cpu_time as u64,
+                    "hard": limits.cpu_time as u64 + 1,
+                },
+                {
+                    "type": "RLIMIT_FSIZE",
+                    "soft": limits.file_size,
+                    "hard": limits.file_size,
+                },
+            ],
+            "noNewPrivileges": true,
+        },
+        "root": { "path": "/", "readonly": true },
+        "hostname": "sandbox",
+        "mounts": [
+            { "destination": "/proc", "type": "proc", "source": "proc" },
+            // Hides the other sandboxes, which live in the host's /tmp
+            { "destination": "/tmp", "type": "tmpfs", "source": "tmpfs" },
+            {
+                "destination": process.root_dir,
+                "type": "bind",
+                "source": process.root_dir,
+                "options": ["rbind", "rw"],
+            },
+        ],
+        "linux": {
+            "namespaces": [
+                { "type": "pid" },
+                { "type": "network" },
+                { "type": "ipc" },
+                { "type": "uts" },
+                { "type": "mount" },
+            ],
+            "resources": {
+                "memory": { "limit": limits.memory },
+                "pids": { "limit": limits.processes },
+            },
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_oci_spec() {
+        let root_dir = PathBuf::from("/tmp/sandbox-test");
+        let env = [("NAME".to_string(), "value".to_string())];
+        let process = ContainerProcess {
+            command: Path::new("/usr/bin/python3"),
+            args: &["source.py"],
+            env: &env,
+            root_dir: &root_dir,
+            uid: 65534,
+            gid: 65534,
+        };
+        let limits = ResourceLimits::default();
+        let spec = oci_spec(&process, &limits);
+
+        assert_eq!(
+            spec["process"]["args"],
+            json!(["/usr/bin/python3", "source.py"])
+        );
+        assert_eq!(spec["process"]["cwd"], json!("/tmp/sandbox-test"));
+        assert_eq!(spec["process"]["user"]["uid"], json!(65534));
+        assert!(spec["process"]["env"]
+            .as_array()
+            .unwrap()
+            .contains(&json!("NAME=value")));
+        assert_eq!(spec["root"]["readonly"], json!(true));
+        assert_eq!(
+            spec["linux"]["resources"]["memory"]["limit"],
+            json!(limits.memory)
+        );
+        // The sandbox is mounted after the tmpfs that would hide it
+        assert_eq!(spec["mounts"][2]["destination"], json!("/tmp/sandbox-test"));
+    }
+}
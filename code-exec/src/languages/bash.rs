@@ -0,0 +1,115 @@
+use async_trait::async_trait;
+use std::path::PathBuf;
+use tokio::fs;
+use which::which;
+
+use crate::{error::Error, executor::LanguageExecutor, languages::ToolCheck};
+
+pub struct BashExecutor;
+
+impl BashExecutor {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Default for BashExecutor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ToolCheck for BashExecutor {
+    fn required_tools(&self) -> Vec<&str> {
+        vec!["bash"]
+    }
+}
+
+#[async_trait]
+impl LanguageExecutor for BashExecutor {
+    fn file_extension(&self) -> &str {
+        "sh"
+    }
+
+    fn run_command(&self) -> &str {
+        "bash"
+    }
+
+    fn run_args(&self) -> Vec<String> {
+        // Skip ~/.bashrc and /etc/profile, which are not the sandbox's to run. A failing
+        // command, an unset variable or a failure inside a pipeline stops the script
+        // instead of being ignored, and line numbers in errors still match the script.
+        vec![
+            "--noprofile".to_string(),
+            "--norc".to_string(),
+            "-e".to_string(),
+            "-u".to_string(),
+            "-o".to_string(),
+            "pipefail".to_string(),
+            "main.sh".to_string(),
+        ]
+    }
+
+    fn check_args(&self) -> Option<Vec<String>> {
+        // Parses the script without running any of it
+        Some(vec![
+            "--noprofile".to_string(),
+            "--norc".to_string(),
+            "-n".to_string(),
+            "main.sh".to_string(),
+        ])
+    }
+
+    async fn setup_environment(&self, _sandbox_dir: &PathBuf) -> Result<(), Error> {
+        Ok(())
+    }
+
+    async fn install_dependencies(
+        &self,
+        _sandbox_dir: &PathBuf,
+        dependencies: &[crate::types::Dependency],
+    ) -> Result<Option<String>, Error> {
+        if dependencies.is_empty() {
+            return Ok(None);
+        }
+
+        Err(Error::InvalidDependency(
+            "Shell scripts cannot declare dependencies".to_string(),
+        ))
+    }
+
+    async fn compile(&self, sandbox_dir: &PathBuf, source_file: &PathBuf) -> Result<(), Error> {
+        fs::rename(source_file, sandbox_dir.join("main.sh"))
+            .await
+            .map_err(|e| Error::System(format!("Failed to move source file: {}", e)))?;
+        Ok(())
+    }
+
+    async fn check_tools(&self) -> Result<(), Error> {
+        let missing: Vec<_> = self
+            .required_tools()
+            .iter()
+            .filter(|tool| which(tool).is_err())
+            .map(|s| (*s).to_string())
+            .collect();
+
+        if !missing.is_empty() {
+            return Err(Error::System(format!(
+                "Missing required tools: {}",
+                missing.join(", ")
+            )));
+        }
+        Ok(())
+    }
+
+    async fn install_missing_tools(&self) -> Result<(), Error> {
+        ToolCheck::install_missing_tools(self).await
+    }
+
+    async fn ensure_directories(&self, sandbox_dir: &PathBuf) -> Result<(), Error> {
+        tokio::fs::create_dir_all(sandbox_dir.join("tmp"))
+            .await
+            .map_err(|e| Error::System(format!("Failed to create tmp directory: {}", e)))?;
+        Ok(())
+    }
+}
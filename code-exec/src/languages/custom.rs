@@ -0,0 +1,125 @@
+use async_trait::async_trait;
+use std::path::PathBuf;
+use tokio::fs;
+use which::which;
+
+use crate::{
+    error::Error, executor::LanguageExecutor, languages::ToolCheck, types::CustomLanguage,
+};
+
+/// Runs programs in languages without an executor of their own, with the commands of a
+/// [`CustomLanguage`].
+///
+/// The commands are not checked here; [`CodeExecutionService`](crate::CodeExecutionService)
+/// only accepts programs the operator allowed. As the request supplies them, setup and
+/// compile commands run in the sandbox like the program itself, never on the host.
+pub struct CustomExecutor {
+    language: CustomLanguage,
+    /// Program and arguments of the run command
+    run: Vec<String>,
+}
+
+impl CustomExecutor {
+    pub fn new(language: CustomLanguage) -> Self {
+        let run = language.argv(&language.run_command);
+        Self { language, run }
+    }
+}
+
+impl ToolCheck for CustomExecutor {
+    fn required_tools(&self) -> Vec<&str> {
+        let mut tools: Vec<&str> = self
+            .language
+            .commands()
+            .filter_map(|command| command.split_whitespace().next())
+            .collect();
+        tools.sort_unstable();
+        tools.dedup();
+        tools
+    }
+}
+
+#[async_trait]
+impl LanguageExecutor for CustomExecutor {
+    fn file_extension(&self) -> &str {
+        &self.language.file_extension
+    }
+
+    fn run_command(&self) -> &str {
+        self.run.first().map_or("", String::as_str)
+    }
+
+    fn run_args(&self) -> Vec<String> {
+        self.run.iter().skip(1).cloned().collect()
+    }
+
+    fn sandboxed_setup_commands(&self) -> Vec<Vec<String>> {
+        self.language
+            .setup_commands
+            .iter()
+            .map(|command| self.language.argv(command))
+            .collect()
+    }
+
+    fn sandboxed_compile_commands(&self) -> Vec<Vec<String>> {
+        self.language
+            .compile_command
+            .iter()
+            .map(|command| self.language.argv(command))
+            .collect()
+    }
+
+    async fn setup_environment(&self, _sandbox_dir: &PathBuf) -> Result<(), Error> {
+        Ok(())
+    }
+
+    async fn install_dependencies(
+        &self,
+        _sandbox_dir: &PathBuf,
+        dependencies: &[crate::types::Dependency],
+    ) -> Result<Option<String>, Error> {
+        if dependencies.is_empty() {
+            return Ok(None);
+        }
+
+        Err(Error::InvalidDependency(
+            "Custom language programs cannot declare dependencies".to_string(),
+        ))
+    }
+
+    async fn compile(&self, sandbox_dir: &PathBuf, source_file: &PathBuf) -> Result<(), Error> {
+        fs::rename(source_file, sandbox_dir.join(self.language.source_file()))
+            .await
+            .map_err(|e| Error::System(format!("Failed to move source file: {}", e)))?;
+        Ok(())
+    }
+
+    async fn check_tools(&self) -> Result<(), Error> {
+        let missing: Vec<_> = self
+            .required_tools()
+            .iter()
+            .filter(|tool| which(tool).is_err())
+            .map(|s| (*s).to_string())
+            .collect();
+
+        if !missing.is_empty() {
+            return Err(Error::System(format!(
+                "Missing required tools: {}",
+                missing.join(", ")
+            )));
+        }
+        Ok(())
+    }
+
+    async fn install_missing_tools(&self) -> Result<(), Error> {
+        // The operator provides the allowed programs, there is no package to install
+        LanguageExecutor::check_tools(self).await
+    }
+
+    async fn ensure_directories(&self, sandbox_dir: &PathBuf) -> Result<(), Error> {
+        tokio::fs::create_dir_all(sandbox_dir.join("tmp"))
+            .await
+            .map_err(|e| Error::System(format!("Failed to create tmp directory: {}", e)))?;
+        Ok(())
+    }
+}
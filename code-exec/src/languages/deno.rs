@@ -0,0 +1,184 @@
+use async_trait::async_trait;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+use which::which;
+
+use crate::{
+    error::Error,
+    executor::LanguageExecutor,
+    languages::ToolCheck,
+    types::{Dependency, Language},
+};
+
+/// Runs JavaScript and TypeScript with Deno instead of Node.
+///
+/// Deno runs TypeScript as is and fetches imports the first time they are loaded, so
+/// there is no `npm install` or `tsc` step and no `node_modules` directory. Requested
+/// dependencies become `npm:` entries of an import map, which keeps bare imports such
+/// as `import _ from "lodash"` working.
+pub struct DenoExecutor {
+    /// Absolute path of `deno`, which is often installed outside the system directories
+    /// the sandbox searches
+    deno: String,
+    extension: &'static str,
+    offline: bool,
+}
+
+impl DenoExecutor {
+    pub fn new(language: Language) -> Self {
+        Self {
+            deno: which("deno")
+                .map(|path| path.to_string_lossy().into_owned())
+                .unwrap_or_else(|_| "deno".to_string()),
+            extension: match language {
+                Language::TypeScript => "ts",
+                _ => "js",
+            },
+            offline: false,
+        }
+    }
+
+    /// Only load modules already in Deno's cache
+    pub fn with_offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
+        self
+    }
+
+    /// Import map target of a dependency, which may already be a full specifier
+    fn specifier(dependency: &Dependency) -> String {
+        let name = dependency.name.trim_start_matches("npm:");
+        match &dependency.source {
+            Some(source)
+                if ["npm:", "jsr:", "http://", "https://"]
+                    .iter()
+                    .any(|prefix| source.starts_with(prefix)) =>
+            {
+                source.clone()
+            }
+            Some(source) => format!("npm:{}@{}", name, source),
+            None => format!("npm:{}@{}", name, dependency.version),
+        }
+    }
+
+    /// Write `deno.json`, mapping each dependency's bare name to its specifier
+    async fn write_config(
+        &self,
+        sandbox_dir: &Path,
+        dependencies: &[Dependency],
+    ) -> Result<(), Error> {
+        let imports: serde_json::Map<String, serde_json::Value> = dependencies
+            .iter()
+            .map(|dep| {
+                let name = dep.name.trim_start_matches("npm:").to_string();
+                (name, Self::specifier(dep).into())
+            })
+            .collect();
+        let config = serde_json::json!({
+            "imports": imports,
+            // npm packages stay in Deno's global cache
+            "nodeModulesDir": "none",
+        });
+
+        fs::write(
+            sandbox_dir.join("deno.json"),
+            serde_json::to_string_pretty(&config).unwrap(),
+        )
+        .await
+        .map_err(|e| Error::System(format!("Failed to write deno.json: {}", e)))
+    }
+
+    fn run_args_with(&self, extra: &[&str]) -> Vec<String> {
+        let source = format!("source.{}", self.extension);
+        // Permission prompts would read the program's stdin, so missing permissions
+        // fail instead
+        ["run", "--no-prompt", "--allow-read=.", "--allow-write=."]
+            .into_iter()
+            .chain(self.offline.then_some("--cached-only"))
+            .chain(extra.iter().copied())
+            .chain([source.as_str()])
+            .map(str::to_string)
+            .collect()
+    }
+}
+
+impl ToolCheck for DenoExecutor {
+    fn required_tools(&self) -> Vec<&str> {
+        vec!["deno"]
+    }
+}
+
+#[async_trait]
+impl LanguageExecutor for DenoExecutor {
+    fn file_extension(&self) -> &str {
+        self.extension
+    }
+
+    fn run_command(&self) -> &str {
+        &self.deno
+    }
+
+    fn run_args(&self) -> Vec<String> {
+        self.run_args_with(&[])
+    }
+
+    fn deterministic_run_args(&self) -> Vec<String> {
+        self.run_args_with(&["--seed=42"])
+    }
+
+    fn run_env(&self) -> Vec<(String, String)> {
+        vec![
+            ("DENO_NO_UPDATE_CHECK".to_string(), "1".to_string()),
+            ("NO_COLOR".to_string(), "1".to_string()),
+        ]
+    }
+
+    fn reserved_paths(&self) -> Vec<&str> {
+        vec!["deno.json", "deno.lock"]
+    }
+
+    async fn setup_environment(&self, sandbox_dir: &PathBuf) -> Result<(), Error> {
+        self.write_config(sandbox_dir, &[]).await
+    }
+
+    async fn install_dependencies(
+        &self,
+        sandbox_dir: &PathBuf,
+        dependencies: &[Dependency],
+    ) -> Result<Option<String>, Error> {
+        // Fetched when the program first imports them
+        self.write_config(sandbox_dir, dependencies).await?;
+        Ok(None)
+    }
+
+    async fn compile(&self, _sandbox_dir: &PathBuf, _source_file: &PathBuf) -> Result<(), Error> {
+        Ok(())
+    }
+
+    async fn check_tools(&self) -> Result<(), Error> {
+        let missing: Vec<_> = self
+            .required_tools()
+            .iter()
+            .filter(|tool| which(tool).is_err())
+            .map(|s| (*s).to_string())
+            .collect();
+
+        if !missing.is_empty() {
+            return Err(Error::System(format!(
+                "Missing required tools: {}",
+                missing.join(", ")
+            )));
+        }
+        Ok(())
+    }
+
+    async fn install_missing_tools(&self) -> Result<(), Error> {
+        ToolCheck::install_missing_tools(self).await
+    }
+
+    async fn ensure_directories(&self, sandbox_dir: &PathBuf) -> Result<(), Error> {
+        tokio::fs::create_dir_all(sandbox_dir.join("tmp"))
+            .await
+            .map_err(|e| Error::System(format!("Failed to create tmp directory: {}", e)))?;
+        Ok(())
+    }
+}
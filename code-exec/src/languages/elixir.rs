@@ -0,0 +1,255 @@
+use async_trait::async_trait;
+use std::path::{Path, PathBuf};
+use tokio::{fs, process::Command};
+use tracing::debug;
+use which::which;
+
+use crate::{
+    error::Error,
+    executor::LanguageExecutor,
+    languages::{diagnostic_output, run_output, ToolCheck},
+    types::Dependency,
+};
+
+pub struct ElixirExecutor {
+    elixir_version: String,
+    /// Run the script inside a generated Mix project, needed to load hex packages
+    mix: bool,
+    offline: bool,
+}
+
+impl ElixirExecutor {
+    pub fn new(version: Option<String>) -> Self {
+        Self {
+            elixir_version: version.unwrap_or_else(|| "1.15".to_string()),
+            mix: false,
+            offline: false,
+        }
+    }
+
+    /// Generate a Mix project for the script and `mix run` it instead of running it with
+    /// plain `elixir`.
+    ///
+    /// Only needed for programs with dependencies, since starting Mix is much slower.
+    pub fn with_mix(mut self, mix: bool) -> Self {
+        self.mix = mix;
+        self
+    }
+
+    /// Fetch packages from Hex's local cache only, and never install Hex or Rebar
+    pub fn with_offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
+        self
+    }
+
+    /// Mix dependency tuple for a requested package, e.g. `{:jason, "~> 1.4"}`
+    fn dependency_spec(dep: &Dependency) -> Result<String, Error> {
+        let valid_name = dep.name.starts_with(|c: char| c.is_ascii_lowercase())
+            && dep
+                .name
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+        if !valid_name {
+            return Err(Error::InvalidDependency(format!(
+                "Invalid hex package name: {}",
+                dep.name
+            )));
+        }
+
+        // Values are spliced into mix.exs, so nothing that could end the string literal
+        // or start an interpolation is allowed through
+        let unsafe_value = |value: &str| value.contains(['"', '\\', '#', '\n']);
+        if unsafe_value(&dep.version) || dep.source.as_deref().is_some_and(unsafe_value) {
+            return Err(Error::InvalidDependency(format!(
+                "Invalid version or source for {}",
+                dep.name
+            )));
+        }
+
+        if let Some(source) = &dep.source {
+            return Ok(format!("{{:{}, git: \"{}\"}}", dep.name, source));
+        }
+
+        // A bare version allows compatible releases, like a caret requirement in Cargo
+        let requirement = match dep.version.trim() {
+            "" | "*" | "latest" => ">= 0.0.0".to_string(),
+            version if version.starts_with(['~', '>', '<', '=']) => version.to_string(),
+            version => format!("~> {}", version),
+        };
+        Ok(format!("{{:{}, \"{}\"}}", dep.name, requirement))
+    }
+
+    async fn write_mix_exs(
+        &self,
+        sandbox_dir: &Path,
+        dependencies: &[Dependency],
+    ) -> Result<(), Error> {
+        let deps = dependencies
+            .iter()
+            .map(Self::dependency_spec)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let content = format!(
+            r#"defmodule CodeExecution.MixProject do
+  use Mix.Project
+
+  def project do
+    [app: :code_execution, version: "0.1.0", deps: deps()]
+  end
+
+  defp deps do
+    [
+      {}
+    ]
+  end
+end
+"#,
+            deps.join(",\n      ")
+        );
+
+        fs::write(sandbox_dir.join("mix.exs"), &content)
+            .await
+            .map_err(|e| Error::System(format!("Failed to write mix.exs: {}", e)))?;
+
+        debug!("Created mix.exs with content:\n{}", content);
+        Ok(())
+    }
+
+    /// Run a Mix task in the sandbox directory, with Mix and Hex state kept in the
+    /// sandbox's home directory where the program finds it when it runs
+    async fn mix(&self, sandbox_dir: &Path, args: &[&str]) -> Result<(), Error> {
+        let home = sandbox_dir.join("home");
+        let mut command = Command::new("mix");
+        if self.offline {
+            command.env("HEX_OFFLINE", "1");
+        }
+        command
+            .args(args)
+            .env("MIX_HOME", home.join(".mix"))
+            .env("HEX_HOME", home.join(".hex"))
+            .current_dir(sandbox_dir);
+        let output = run_output(&mut command)
+            .await
+            .map_err(|e| Error::System(format!("Failed to run mix {}: {}", args[0], e)))?;
+
+        if !output.status.success() {
+            return Err(Error::System(format!(
+                "mix {} failed: {}",
+                args.join(" "),
+                diagnostic_output(&output.stderr)
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl ToolCheck for ElixirExecutor {
+    fn required_tools(&self) -> Vec<&str> {
+        if self.mix {
+            vec!["elixir", "mix"]
+        } else {
+            vec!["elixir"]
+        }
+    }
+}
+
+#[async_trait]
+impl LanguageExecutor for ElixirExecutor {
+    fn file_extension(&self) -> &str {
+        "exs"
+    }
+
+    fn run_command(&self) -> &str {
+        if self.mix {
+            "mix"
+        } else {
+            "elixir"
+        }
+    }
+
+    fn run_args(&self) -> Vec<String> {
+        if self.mix {
+            vec!["run".to_string(), "main.exs".to_string()]
+        } else {
+            vec!["main.exs".to_string()]
+        }
+    }
+
+    fn cache_dirs(&self) -> Vec<&str> {
+        vec!["deps", "_build"]
+    }
+
+    fn reserved_paths(&self) -> Vec<&str> {
+        vec!["deps", "_build", "mix.exs", "mix.lock"]
+    }
+
+    async fn setup_environment(&self, _sandbox_dir: &PathBuf) -> Result<(), Error> {
+        debug!("Using Elixir {}", self.elixir_version);
+        Ok(())
+    }
+
+    async fn install_dependencies(
+        &self,
+        sandbox_dir: &PathBuf,
+        dependencies: &[Dependency],
+    ) -> Result<Option<String>, Error> {
+        if dependencies.is_empty() {
+            return Ok(None);
+        }
+        if !self.mix {
+            return Err(Error::InvalidDependency(
+                "Elixir dependencies need a Mix project".to_string(),
+            ));
+        }
+
+        self.write_mix_exs(sandbox_dir, dependencies).await?;
+
+        if !self.offline {
+            self.mix(sandbox_dir, &["local.hex", "--force", "--if-missing"])
+                .await?;
+            self.mix(sandbox_dir, &["local.rebar", "--force", "--if-missing"])
+                .await?;
+        }
+        self.mix(sandbox_dir, &["deps.get"]).await?;
+        // Build the packages now rather than under the sandbox limits on first run
+        self.mix(sandbox_dir, &["deps.compile"]).await?;
+
+        debug!("Installed dependencies: {:?}", dependencies);
+        Ok(None)
+    }
+
+    async fn compile(&self, sandbox_dir: &PathBuf, source_file: &PathBuf) -> Result<(), Error> {
+        fs::rename(source_file, sandbox_dir.join("main.exs"))
+            .await
+            .map_err(|e| Error::System(format!("Failed to move source file: {}", e)))?;
+        Ok(())
+    }
+
+    async fn check_tools(&self) -> Result<(), Error> {
+        let missing: Vec<_> = self
+            .required_tools()
+            .iter()
+            .filter(|tool| which(tool).is_err())
+            .map(|s| (*s).to_string())
+            .collect();
+
+        if !missing.is_empty() {
+            return Err(Error::System(format!(
+                "Missing required tools: {}",
+                missing.join(", ")
+            )));
+        }
+        Ok(())
+    }
+
+    async fn install_missing_tools(&self) -> Result<(), Error> {
+        ToolCheck::install_missing_tools(self).await
+    }
+
+    async fn ensure_directories(&self, sandbox_dir: &PathBuf) -> Result<(), Error> {
+        tokio::fs::create_dir_all(sandbox_dir.join("tmp"))
+            .await
+            .map_err(|e| Error::System(format!("Failed to create tmp directory: {}", e)))?;
+        Ok(())
+    }
+}
@@ -4,19 +4,59 @@ use tokio::{fs, process::Command};
 use tracing::debug;
 use which::which;
 
-use crate::{error::Error, executor::LanguageExecutor, languages::ToolCheck};
+use crate::{
+    error::Error,
+    executor::LanguageExecutor,
+    languages::{diagnostic_output, install_output, run_output, ToolCheck},
+};
 
 pub struct GoExecutor {
     go_version: String,
+    /// Skip module management and the separate build, and `go run` the source instead
+    go_run: bool,
+    offline: bool,
 }
 
 impl GoExecutor {
     pub fn new(version: Option<String>) -> Self {
         Self {
             go_version: version.unwrap_or_else(|| "1.21".to_string()),
+            go_run: false,
+            offline: false,
         }
     }
 
+    /// Never reach the module proxy or download a toolchain the `go` line of `go.mod`
+    /// asks for. Dependencies are refused before this matters, as Go modules are not
+    /// kept in the dependency cache.
+    pub fn with_offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
+        self
+    }
+
+    /// Environment of every `go` command, keeping it off the network in offline mode
+    fn go_env(&self) -> Vec<(String, String)> {
+        if !self.offline {
+            return Vec::new();
+        }
+        vec![
+            ("GOPROXY".to_string(), "off".to_string()),
+            ("GOTOOLCHAIN".to_string(), "local".to_string()),
+        ]
+    }
+
+    /// Use `go run main.go` inside the sandbox instead of writing `go.mod` and building
+    /// a binary up front.
+    ///
+    /// Only suitable for programs without dependencies. Compilation then happens under
+    /// the sandbox limits, and compile errors surface as execution errors. The build
+    /// cache is the default one under the sandbox's home directory, as a program could
+    /// tamper with a cache it shares with other runs.
+    pub fn with_go_run(mut self, go_run: bool) -> Self {
+        self.go_run = go_run;
+        self
+    }
+
     async fn write_go_mod(
         &self,
         sandbox_dir: &PathBuf,
@@ -57,14 +97,45 @@ impl LanguageExecutor for GoExecutor {
     }
 
     fn run_command(&self) -> &str {
-        "./code-execution"
+        if self.go_run {
+            "go"
+        } else {
+            "./code-execution"
+        }
     }
 
     fn run_args(&self) -> Vec<String> {
-        Vec::new()
+        if self.go_run {
+            vec!["run".to_string(), "main.go".to_string()]
+        } else {
+            Vec::new()
+        }
+    }
+
+    fn run_env(&self) -> Vec<(String, String)> {
+        // `go run` builds in the sandbox
+        if self.go_run {
+            self.go_env()
+        } else {
+            Vec::new()
+        }
+    }
+
+    fn deterministic_env(&self) -> Vec<(String, String)> {
+        // The top-level math/rand functions go back to the fixed seed of Go 1.19 and
+        // earlier. Map iteration order stays random.
+        vec![("GODEBUG".to_string(), "randautoseed=0".to_string())]
+    }
+
+    fn reserved_paths(&self) -> Vec<&str> {
+        vec!["go.mod", "go.sum"]
     }
 
     async fn setup_environment(&self, sandbox_dir: &PathBuf) -> Result<(), Error> {
+        if self.go_run {
+            return Ok(());
+        }
+
         // Create an empty go.mod file - we'll update it during dependency installation
         self.write_go_mod(sandbox_dir, &[]).await?;
         Ok(())
@@ -74,21 +145,23 @@ impl LanguageExecutor for GoExecutor {
         &self,
         sandbox_dir: &PathBuf,
         dependencies: &[crate::types::Dependency],
-    ) -> Result<(), Error> {
+    ) -> Result<Option<String>, Error> {
         if dependencies.is_empty() {
-            return Ok(());
+            return Ok(None);
         }
 
         // Update go.mod with dependencies
         self.write_go_mod(sandbox_dir, dependencies).await?;
 
         // Run go mod tidy to download dependencies and create go.sum
-        let output = Command::new("go")
-            .args(["mod", "tidy"])
-            .current_dir(sandbox_dir)
-            .output()
-            .await
-            .map_err(|e| Error::System(format!("Failed to run go mod tidy: {}", e)))?;
+        let output = run_output(
+            Command::new("go")
+                .args(["mod", "tidy"])
+                .envs(self.go_env())
+                .current_dir(sandbox_dir),
+        )
+        .await
+        .map_err(|e| Error::System(format!("Failed to run go mod tidy: {}", e)))?;
 
         if !output.status.success() {
             return Err(Error::System(format!(
@@ -96,14 +169,17 @@ impl LanguageExecutor for GoExecutor {
                 String::from_utf8_lossy(&output.stderr)
             )));
         }
+        let diagnostics = install_output(&output);
 
         // Verify dependencies
-        let output = Command::new("go")
-            .args(["mod", "verify"])
-            .current_dir(sandbox_dir)
-            .output()
-            .await
-            .map_err(|e| Error::System(format!("Failed to verify dependencies: {}", e)))?;
+        let output = run_output(
+            Command::new("go")
+                .args(["mod", "verify"])
+                .envs(self.go_env())
+                .current_dir(sandbox_dir),
+        )
+        .await
+        .map_err(|e| Error::System(format!("Failed to verify dependencies: {}", e)))?;
 
         if !output.status.success() {
             return Err(Error::System(format!(
@@ -113,7 +189,7 @@ impl LanguageExecutor for GoExecutor {
         }
 
         println!("Successfully installed Go dependencies");
-        Ok(())
+        Ok(diagnostics)
     }
 
     async fn compile(&self, sandbox_dir: &PathBuf, source_file: &PathBuf) -> Result<(), Error> {
@@ -122,13 +198,19 @@ impl LanguageExecutor for GoExecutor {
             .await
             .map_err(|e| Error::System(format!("Failed to move source file: {}", e)))?;
 
+        if self.go_run {
+            return Ok(());
+        }
+
         // Build with verbose output to help diagnose issues
-        let output = Command::new("go")
-            .args(["build", "-v", "-o", "code-execution"])
-            .current_dir(sandbox_dir)
-            .output()
-            .await
-            .map_err(|e| Error::CompilationError(e.to_string()))?;
+        let output = run_output(
+            Command::new("go")
+                .args(["build", "-v", "-o", "code-execution"])
+                .envs(self.go_env())
+                .current_dir(sandbox_dir),
+        )
+        .await
+        .map_err(|e| Error::CompilationError(e.to_string()))?;
 
         if !output.status.success() {
             println!(
@@ -141,7 +223,7 @@ impl LanguageExecutor for GoExecutor {
             );
             return Err(Error::CompilationError(format!(
                 "Go compilation failed: {}",
-                String::from_utf8_lossy(&output.stderr)
+                diagnostic_output(&output.stderr)
             )));
         }
 
@@ -4,19 +4,27 @@ use tokio::{fs, process::Command};
 use tracing::debug;
 use which::which;
 
-use crate::languages::ToolCheck;
+use crate::languages::{diagnostic_output, install_output, run_output, ToolCheck};
 use crate::{error::Error, executor::LanguageExecutor};
 
 pub struct JavaScriptExecutor {
     node_version: String,
+    offline: bool,
 }
 
 impl JavaScriptExecutor {
     pub fn new(version: Option<String>) -> Self {
         Self {
             node_version: version.unwrap_or_else(|| "18".to_string()),
+            offline: false,
         }
     }
+
+    /// Install packages from npm's local cache only, failing instead of downloading
+    pub fn with_offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
+        self
+    }
 }
 
 impl ToolCheck for JavaScriptExecutor {
@@ -39,6 +47,25 @@ impl LanguageExecutor for JavaScriptExecutor {
         vec!["source.js".to_string()]
     }
 
+    fn check_args(&self) -> Option<Vec<String>> {
+        Some(vec!["--check".to_string(), "source.js".to_string()])
+    }
+
+    fn deterministic_run_args(&self) -> Vec<String> {
+        // V8 seeds Math.random from this instead of the system when it is non-zero
+        let mut args = vec!["--random-seed=42".to_string()];
+        args.extend(self.run_args());
+        args
+    }
+
+    fn cache_dirs(&self) -> Vec<&str> {
+        vec!["node_modules"]
+    }
+
+    fn reserved_paths(&self) -> Vec<&str> {
+        vec!["node_modules", "package.json", "package-lock.json", ".npmrc"]
+    }
+
     async fn setup_environment(&self, sandbox_dir: &PathBuf) -> Result<(), Error> {
         // Initialize npm project
         let package_json = sandbox_dir.join("package.json");
@@ -63,12 +90,15 @@ impl LanguageExecutor for JavaScriptExecutor {
         &self,
         sandbox_dir: &PathBuf,
         dependencies: &[crate::types::Dependency],
-    ) -> Result<(), Error> {
+    ) -> Result<Option<String>, Error> {
         if dependencies.is_empty() {
-            return Ok(());
+            return Ok(None);
         }
 
         let mut install_args = vec!["install"];
+        if self.offline {
+            install_args.push("--offline");
+        }
         let dep_specs: Vec<String> = dependencies
             .iter()
             .map(|dep| match &dep.source {
@@ -79,20 +109,24 @@ impl LanguageExecutor for JavaScriptExecutor {
 
         install_args.extend(dep_specs.iter().map(|s| s.as_str()));
 
-        // Install dependencies
-        let status = Command::new("npm")
-            .args(&install_args)
-            .current_dir(sandbox_dir)
-            .status()
-            .await
-            .map_err(|e| Error::System(format!("Failed to install dependencies: {}", e)))?;
+        // Install dependencies, npm reporting its audit findings with them
+        let output = run_output(
+            Command::new("npm")
+                .args(&install_args)
+                .current_dir(sandbox_dir),
+        )
+        .await
+        .map_err(|e| Error::System(format!("Failed to install dependencies: {}", e)))?;
 
-        if !status.success() {
-            return Err(Error::System("Failed to install dependencies".to_string()));
+        if !output.status.success() {
+            return Err(Error::System(format!(
+                "Failed to install dependencies: {}",
+                diagnostic_output(&output.stderr)
+            )));
         }
 
         debug!("Installed dependencies: {:?}", dep_specs);
-        Ok(())
+        Ok(install_output(&output))
     }
 
     async fn compile(&self, sandbox_dir: &PathBuf, source_file: &PathBuf) -> Result<(), Error> {
@@ -0,0 +1,130 @@
+use async_trait::async_trait;
+use std::path::PathBuf;
+use tokio::{fs, process::Command};
+use tracing::debug;
+use which::which;
+
+use crate::{
+    error::Error,
+    executor::LanguageExecutor,
+    languages::{diagnostic_output, run_output, ToolCheck},
+};
+
+pub struct KotlinExecutor {
+    kotlin_version: String,
+}
+
+impl KotlinExecutor {
+    pub fn new(version: Option<String>) -> Self {
+        Self {
+            kotlin_version: version.unwrap_or_else(|| "1.9".to_string()),
+        }
+    }
+}
+
+impl ToolCheck for KotlinExecutor {
+    fn required_tools(&self) -> Vec<&str> {
+        vec!["kotlinc", "java"]
+    }
+}
+
+#[async_trait]
+impl LanguageExecutor for KotlinExecutor {
+    fn file_extension(&self) -> &str {
+        "kt"
+    }
+
+    fn run_command(&self) -> &str {
+        "java"
+    }
+
+    fn run_args(&self) -> Vec<String> {
+        vec![
+            // The serial collector keeps the JVM small enough for the sandbox memory limit
+            "-XX:+UseSerialGC".to_string(),
+            "-jar".to_string(),
+            "app.jar".to_string(),
+        ]
+    }
+
+    fn source_dir(&self) -> &str {
+        "src"
+    }
+
+    async fn setup_environment(&self, _sandbox_dir: &PathBuf) -> Result<(), Error> {
+        debug!("Using Kotlin {}", self.kotlin_version);
+        Ok(())
+    }
+
+    async fn install_dependencies(
+        &self,
+        _sandbox_dir: &PathBuf,
+        dependencies: &[crate::types::Dependency],
+    ) -> Result<Option<String>, Error> {
+        if dependencies.is_empty() {
+            return Ok(None);
+        }
+
+        Err(Error::InvalidDependency(
+            "Kotlin programs cannot declare dependencies yet".to_string(),
+        ))
+    }
+
+    async fn compile(&self, sandbox_dir: &PathBuf, source_file: &PathBuf) -> Result<(), Error> {
+        let src_dir = sandbox_dir.join("src");
+        fs::create_dir_all(&src_dir)
+            .await
+            .map_err(|e| Error::System(format!("Failed to create src directory: {}", e)))?;
+
+        fs::rename(source_file, src_dir.join("main.kt"))
+            .await
+            .map_err(|e| Error::System(format!("Failed to move source file: {}", e)))?;
+
+        // Compile every file under src, and bundle the Kotlin runtime so the jar runs
+        // with plain `java`
+        let output = run_output(
+            Command::new("kotlinc")
+                .args(["src", "-include-runtime", "-d", "app.jar"])
+                .current_dir(sandbox_dir),
+        )
+        .await
+        .map_err(|e| Error::CompilationError(e.to_string()))?;
+
+        if !output.status.success() {
+            return Err(Error::CompilationError(format!(
+                "Kotlin compilation failed: {}",
+                diagnostic_output(&output.stderr)
+            )));
+        }
+
+        Ok(())
+    }
+
+    async fn check_tools(&self) -> Result<(), Error> {
+        let missing: Vec<_> = self
+            .required_tools()
+            .iter()
+            .filter(|tool| which(tool).is_err())
+            .map(|s| (*s).to_string())
+            .collect();
+
+        if !missing.is_empty() {
+            return Err(Error::System(format!(
+                "Missing required tools: {}",
+                missing.join(", ")
+            )));
+        }
+        Ok(())
+    }
+
+    async fn install_missing_tools(&self) -> Result<(), Error> {
+        ToolCheck::install_missing_tools(self).await
+    }
+
+    async fn ensure_directories(&self, sandbox_dir: &PathBuf) -> Result<(), Error> {
+        tokio::fs::create_dir_all(sandbox_dir.join("tmp"))
+            .await
+            .map_err(|e| Error::System(format!("Failed to create tmp directory: {}", e)))?;
+        Ok(())
+    }
+}
@@ -1,28 +1,126 @@
 //! Language-specific executor implementations
 
+pub mod bash;
+pub mod custom;
+pub mod deno;
+pub mod elixir;
 pub mod go;
 pub mod javascript;
+pub mod kotlin;
 pub mod python;
 pub mod rust;
+pub mod sql;
 pub mod typescript;
 
+pub use bash::BashExecutor;
+pub use custom::CustomExecutor;
+pub use deno::DenoExecutor;
+pub use elixir::ElixirExecutor;
 pub use go::GoExecutor;
 pub use javascript::JavaScriptExecutor;
+pub use kotlin::KotlinExecutor;
 pub use python::PythonExecutor;
 pub use rust::RustExecutor;
+pub use sql::SqlExecutor;
 pub use typescript::TypeScriptExecutor;
 
-use crate::error::Error;
-use ldm::{analyze_source_code, InstallationConfig, InstallationManager, Package};
+use crate::{error::Error, types::Language};
+use ldm::{AnalyzerRegistry, InstallationConfig, InstallationManager, Package};
+use nix::{
+    sys::signal::{killpg, Signal},
+    unistd::Pid,
+};
+use std::{
+    process::{ExitStatus, Output, Stdio},
+    sync::Arc,
+};
+use tokio::process::Command;
 use which::which;
 
+/// Maximum length of compiler diagnostics included in errors
+const MAX_DIAGNOSTIC_LEN: usize = 8 * 1024;
+
+/// Decode compiler output for an error message, keeping the head where the first
+/// diagnostics are
+pub(crate) fn diagnostic_output(output: &[u8]) -> String {
+    let text = String::from_utf8_lossy(output);
+    let text = text.trim();
+    if text.len() <= MAX_DIAGNOSTIC_LEN {
+        return text.to_string();
+    }
+
+    let mut end = MAX_DIAGNOSTIC_LEN;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}\n... (truncated)", &text[..end])
+}
+
+/// What a package manager printed on stdout and stderr, each cut down like
+/// [`diagnostic_output`]; `None` if it printed nothing
+pub(crate) fn install_output(output: &Output) -> Option<String> {
+    let text = [&output.stdout, &output.stderr]
+        .into_iter()
+        .map(|stream| diagnostic_output(stream))
+        .filter(|text| !text.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n");
+    (!text.is_empty()).then_some(text)
+}
+
+/// Process group of a toolchain command, killed if dropped before the command exits
+struct ProcessGroup(Option<u32>);
+
+impl ProcessGroup {
+    fn exited(mut self) {
+        self.0 = None;
+    }
+}
+
+impl Drop for ProcessGroup {
+    fn drop(&mut self) {
+        if let Some(id) = self.0 {
+            let _ = killpg(Pid::from_raw(id as i32), Signal::SIGKILL);
+        }
+    }
+}
+
+/// Run a package manager or compiler to completion, capturing its output.
+///
+/// The command leads its own process group, which is killed with everything in it if
+/// the returned future is dropped, as it is when an install or compile step times out.
+/// Killing only the command would leave e.g. the `rustc` processes under `cargo` or a
+/// `git` fetch running.
+pub(crate) async fn run_output(command: &mut Command) -> std::io::Result<Output> {
+    let child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .process_group(0)
+        .spawn()?;
+    let group = ProcessGroup(child.id());
+    let output = child.wait_with_output().await;
+    group.exited();
+    output
+}
+
+/// Like [`run_output`], but with the command's output going to the service's own
+pub(crate) async fn run_status(command: &mut Command) -> std::io::Result<ExitStatus> {
+    let mut child = command.stdin(Stdio::null()).process_group(0).spawn()?;
+    let group = ProcessGroup(child.id());
+    let status = child.wait().await;
+    group.exited();
+    status
+}
+
 /// Trait for checking and installing required tools
 pub trait ToolCheck {
     fn required_tools(&self) -> Vec<&str>;
 
     async fn install_missing_tools(&self) -> Result<(), Error> {
         let config = InstallationConfig::default();
-        let manager = InstallationManager::new_for_current_os(config);
+        let manager = InstallationManager::new_for_current_os(config)
+            .map_err(|e| Error::System(e.to_string()))?;
 
         // Convert tools to packages
         let packages: Vec<Package> = self
@@ -56,13 +154,17 @@ pub trait ToolCheck {
         }
     }
 
-    fn check_tools(&self) -> Result<(), Error> {
-        let missing: Vec<_> = self
-            .required_tools()
+    /// Required tools that are not on the PATH
+    fn missing_tools(&self) -> Vec<String> {
+        self.required_tools()
             .iter()
             .filter(|tool| which(tool).is_err())
             .map(|s| (*s).to_string())
-            .collect();
+            .collect()
+    }
+
+    fn check_tools(&self) -> Result<(), Error> {
+        let missing = self.missing_tools();
 
         if !missing.is_empty() {
             return Err(Error::System(format!(
@@ -79,9 +181,16 @@ pub trait DependencyManager {
     /// Returns the language name for dependency analysis
     fn get_language(&self) -> &'static str;
 
+    /// Returns the analyzer registry used to detect and analyze source code
+    fn analyzer_registry(&self) -> Arc<AnalyzerRegistry> {
+        AnalyzerRegistry::shared()
+    }
+
     /// Analyzes source code to extract dependencies
     async fn analyze_dependencies(&self, source_code: &str) -> Result<Vec<Package>, Error> {
-        let (lang, deps) = analyze_source_code(source_code)
+        let (lang, deps) = self
+            .analyzer_registry()
+            .detect_and_analyze(source_code)
             .await
             .map_err(|e| Error::System(e.to_string()))?;
 
@@ -100,7 +209,8 @@ pub trait DependencyManager {
     async fn install_dependencies(&self, source_code: &str) -> Result<(), Error> {
         let deps = self.analyze_dependencies(source_code).await?;
         let config = InstallationConfig::default();
-        let manager = InstallationManager::new_for_current_os(config);
+        let manager = InstallationManager::new_for_current_os(config)
+            .map_err(|e| Error::System(e.to_string()))?;
 
         if let Some(pm) = manager.find_available_package_manager() {
             for package in deps {
@@ -126,7 +236,8 @@ pub trait DependencyManager {
     async fn validate_dependencies(&self, source_code: &str) -> Result<(), Error> {
         let deps = self.analyze_dependencies(source_code).await?;
         let config = InstallationConfig::default();
-        let manager = InstallationManager::new_for_current_os(config);
+        let manager = InstallationManager::new_for_current_os(config)
+            .map_err(|e| Error::System(e.to_string()))?;
 
         if let Some(pm) = manager.find_available_package_manager() {
             for package in deps {
@@ -150,6 +261,23 @@ pub trait DependencyManager {
     }
 }
 
+/// Tools needed to run `language` that are not installed
+pub fn missing_tools(language: Language) -> Vec<String> {
+    match language {
+        Language::Python => ToolCheck::missing_tools(&PythonExecutor::new(None)),
+        Language::JavaScript => ToolCheck::missing_tools(&JavaScriptExecutor::new(None)),
+        Language::TypeScript => ToolCheck::missing_tools(&TypeScriptExecutor::new(None, None)),
+        Language::Rust => ToolCheck::missing_tools(&RustExecutor::new(None)),
+        Language::Go => ToolCheck::missing_tools(&GoExecutor::new(None)),
+        Language::Kotlin => ToolCheck::missing_tools(&KotlinExecutor::new(None)),
+        Language::Bash => ToolCheck::missing_tools(&BashExecutor::new()),
+        Language::Sql => ToolCheck::missing_tools(&SqlExecutor::new()),
+        Language::Elixir => ToolCheck::missing_tools(&ElixirExecutor::new(None)),
+        // Each request brings its own commands
+        Language::Custom => Vec::new(),
+    }
+}
+
 #[allow(dead_code)]
 pub async fn check_requirements<T: ToolCheck>(executor: &T) -> Result<(), Error> {
     if let Err(_) = executor.check_tools() {
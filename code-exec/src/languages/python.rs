@@ -4,18 +4,31 @@ use tokio::{fs, process::Command};
 use tracing::debug;
 use which::which;
 
-use crate::{error::Error, executor::LanguageExecutor, languages::ToolCheck};
+use crate::{
+    error::Error,
+    executor::LanguageExecutor,
+    languages::{diagnostic_output, install_output, run_output, run_status, ToolCheck},
+};
 
 pub struct PythonExecutor {
     python_version: String,
+    offline: bool,
 }
 
 impl PythonExecutor {
     pub fn new(version: Option<String>) -> Self {
         Self {
             python_version: version.unwrap_or_else(|| "3.10".to_string()),
+            offline: false,
         }
     }
+
+    /// Install with `--no-index`, so only packages the dependency cache restored into
+    /// the virtualenv satisfy the requirements
+    pub fn with_offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
+        self
+    }
 }
 
 impl ToolCheck for PythonExecutor {
@@ -31,27 +44,65 @@ impl LanguageExecutor for PythonExecutor {
     }
 
     fn run_command(&self) -> &str {
-        "python3"
+        // The sandbox's own virtualenv, so installed dependencies never touch the
+        // system Python or another execution
+        "./venv/bin/python"
     }
 
     fn run_args(&self) -> Vec<String> {
         vec!["source.py".to_string()]
     }
 
+    fn cache_dirs(&self) -> Vec<&str> {
+        // Installed packages only, the virtualenv itself is recreated around them
+        vec!["venv/lib"]
+    }
+
+    fn reserved_paths(&self) -> Vec<&str> {
+        vec!["venv"]
+    }
+
+    fn check_args(&self) -> Option<Vec<String>> {
+        Some(vec![
+            "-m".to_string(),
+            "py_compile".to_string(),
+            "source.py".to_string(),
+        ])
+    }
+
+    fn deterministic_run_args(&self) -> Vec<String> {
+        // Seed `random`, then run the usual arguments as if they were the command line;
+        // runpy keeps `__name__ == "__main__"`
+        let mut args = vec![
+            "-c".to_string(),
+            "import random, runpy, sys; random.seed(0); sys.argv = sys.argv[1:]; \
+             runpy.run_path(sys.argv[0], run_name='__main__')"
+                .to_string(),
+        ];
+        args.extend(self.run_args());
+        args
+    }
+
+    fn deterministic_env(&self) -> Vec<(String, String)> {
+        // Stable str and bytes hashes, and with them set iteration order
+        vec![("PYTHONHASHSEED".to_string(), "0".to_string())]
+    }
+
     async fn setup_environment(&self, sandbox_dir: &PathBuf) -> Result<(), Error> {
         // Create virtual environment with minimal output
-        let status = Command::new("virtualenv")
-            .args([
-                "venv",
-                "--quiet",
-                "--no-download",
-                "--no-periodic-update",
-                "--no-vcs-ignore",
-            ])
-            .current_dir(sandbox_dir)
-            .status()
-            .await
-            .map_err(|e| Error::System(format!("Failed to create virtualenv: {}", e)))?;
+        let status = run_status(
+            Command::new("virtualenv")
+                .args([
+                    "venv",
+                    "--quiet",
+                    "--no-download",
+                    "--no-periodic-update",
+                    "--no-vcs-ignore",
+                ])
+                .current_dir(sandbox_dir),
+        )
+        .await
+        .map_err(|e| Error::System(format!("Failed to create virtualenv: {}", e)))?;
 
         if !status.success() {
             return Err(Error::System("Failed to create virtualenv".to_string()));
@@ -68,19 +119,23 @@ impl LanguageExecutor for PythonExecutor {
         &self,
         sandbox_dir: &PathBuf,
         dependencies: &[crate::types::Dependency],
-    ) -> Result<(), Error> {
+    ) -> Result<Option<String>, Error> {
         if dependencies.is_empty() {
-            return Ok(());
+            return Ok(None);
         }
 
         // Activate virtualenv and install dependencies quietly
         let pip_path = sandbox_dir.join("venv/bin/pip");
+        // Not quiet, so what pip resolved and warned about can be reported
         let mut install_args = vec![
             "install",
-            "--quiet",
             "--no-cache-dir",
             "--no-warn-script-location",
+            "--disable-pip-version-check",
         ];
+        if self.offline {
+            install_args.push("--no-index");
+        }
         let dep_specs: Vec<String> = dependencies
             .iter()
             .map(|dep| match &dep.source {
@@ -91,19 +146,23 @@ impl LanguageExecutor for PythonExecutor {
 
         install_args.extend(dep_specs.iter().map(|s| s.as_str()));
 
-        let status = Command::new(pip_path)
-            .args(&install_args)
-            .current_dir(sandbox_dir)
-            .status()
-            .await
-            .map_err(|e| Error::System(format!("Failed to install dependencies: {}", e)))?;
+        let output = run_output(
+            Command::new(pip_path)
+                .args(&install_args)
+                .current_dir(sandbox_dir),
+        )
+        .await
+        .map_err(|e| Error::System(format!("Failed to install dependencies: {}", e)))?;
 
-        if !status.success() {
-            return Err(Error::System("Failed to install dependencies".to_string()));
+        if !output.status.success() {
+            return Err(Error::System(format!(
+                "Failed to install dependencies: {}",
+                diagnostic_output(&output.stderr)
+            )));
         }
 
         debug!("Installed dependencies: {:?}", dep_specs);
-        Ok(())
+        Ok(install_output(&output))
     }
 
     async fn compile(&self, sandbox_dir: &PathBuf, source_file: &PathBuf) -> Result<(), Error> {
@@ -1,21 +1,92 @@
 use async_trait::async_trait;
-use std::path::PathBuf;
-use tokio::{fs, process::Command};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+use tokio::{
+    fs,
+    process::Command,
+    sync::{Mutex, OwnedMutexGuard},
+};
 use tracing::info;
 
-use crate::{error::Error, executor::LanguageExecutor, languages::ToolCheck};
+use crate::{
+    error::Error,
+    executor::LanguageExecutor,
+    languages::{diagnostic_output, install_output, run_output, run_status, ToolCheck},
+};
+
+/// Rust target used for the WebAssembly sandbox backend
+const WASM_TARGET: &str = "wasm32-wasip1";
+
+/// Cargo target directory shared by the builds of all sandboxes, so dependency crates are
+/// only compiled once.
+///
+/// Each dependency set builds in a subdirectory of its own. Every program is built as
+/// the same crate, whose binary would be overwritten by a concurrent build, so builds of
+/// the same set take turns and copy their binary out before releasing the subdirectory,
+/// while builds of other sets go ahead.
+pub struct SharedTargetDir {
+    path: PathBuf,
+    locks: std::sync::Mutex<HashMap<String, Arc<Mutex<()>>>>,
+}
+
+impl SharedTargetDir {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            locks: std::sync::Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    /// Wait for the subdirectory of the dependency set declared by `manifest`, returning
+    /// it with the guard that holds it
+    async fn lock(&self, manifest: &[u8]) -> (PathBuf, OwnedMutexGuard<()>) {
+        let key = format!("{:x}", Sha256::digest(manifest));
+        let lock = self
+            .locks
+            .lock()
+            .unwrap()
+            .entry(key.clone())
+            .or_default()
+            .clone();
+        (self.path.join(key), lock.lock_owned().await)
+    }
+}
 
 pub struct RustExecutor {
     toolchain: String,
+    offline: bool,
+    shared_target_dir: Option<Arc<SharedTargetDir>>,
 }
 
 impl RustExecutor {
     pub fn new(toolchain: Option<String>) -> Self {
         Self {
             toolchain: toolchain.unwrap_or_else(|| "stable".to_string()),
+            offline: false,
+            shared_target_dir: None,
         }
     }
 
+    /// Build in `target_dir` instead of the sandbox's own `target/`
+    pub fn with_shared_target_dir(mut self, target_dir: Option<Arc<SharedTargetDir>>) -> Self {
+        self.shared_target_dir = target_dir;
+        self
+    }
+
+    /// Build with `--offline`, resolving crates from the local registry cache only
+    pub fn with_offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
+        self
+    }
+
     async fn create_cargo_toml(
         &self,
         sandbox_dir: &PathBuf,
@@ -55,6 +126,75 @@ impl RustExecutor {
 
         Ok(())
     }
+
+    /// Move the source into the crate and build it in release mode, leaving `artifact`
+    /// (relative to the target directory) in the sandbox's `target/`
+    async fn build(
+        &self,
+        sandbox_dir: &Path,
+        source_file: &Path,
+        extra_args: &[&str],
+        artifact: &str,
+    ) -> Result<(), Error> {
+        // Move source to src/main.rs
+        let src_dir = sandbox_dir.join("src");
+        fs::create_dir_all(&src_dir)
+            .await
+            .map_err(|e| Error::System(format!("Failed to create src directory: {}", e)))?;
+
+        fs::rename(source_file, src_dir.join("main.rs"))
+            .await
+            .map_err(|e| Error::System(format!("Failed to move source file: {}", e)))?;
+
+        // Held until the artifact is copied out of the shared directory
+        let mut shared_build = None;
+        let mut command = Command::new("cargo");
+        if let Some(shared) = &self.shared_target_dir {
+            let manifest = fs::read(sandbox_dir.join("Cargo.toml"))
+                .await
+                .map_err(|e| Error::System(format!("Failed to read Cargo.toml: {}", e)))?;
+            let (target_dir, guard) = shared.lock(&manifest).await;
+            command.env("CARGO_TARGET_DIR", &target_dir);
+            shared_build = Some((target_dir, guard));
+        }
+
+        // Build the code
+        command
+            .args([
+                "build",
+                "--release",
+                "--quiet",
+                "--color=never",
+                "--message-format=short",
+            ])
+            .args(extra_args)
+            .args(self.offline.then_some("--offline"))
+            .current_dir(sandbox_dir);
+        let output = run_output(&mut command)
+            .await
+            .map_err(|e| Error::CompilationError(e.to_string()))?;
+
+        if !output.status.success() {
+            return Err(Error::CompilationError(format!(
+                "Rust compilation failed: {}",
+                diagnostic_output(&output.stderr)
+            )));
+        }
+
+        if let Some((target_dir, _guard)) = &shared_build {
+            let target = sandbox_dir.join("target").join(artifact);
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent).await.map_err(|e| {
+                    Error::System(format!("Failed to create {}: {}", parent.display(), e))
+                })?;
+            }
+            fs::copy(target_dir.join(artifact), &target)
+                .await
+                .map_err(|e| Error::System(format!("Failed to copy build output: {}", e)))?;
+        }
+
+        Ok(())
+    }
 }
 
 impl ToolCheck for RustExecutor {
@@ -73,6 +213,14 @@ impl LanguageExecutor for RustExecutor {
         "./target/release/code-execution"
     }
 
+    fn source_dir(&self) -> &str {
+        "src"
+    }
+
+    fn cache_dirs(&self) -> Vec<&str> {
+        vec!["target"]
+    }
+
     async fn setup_environment(&self, sandbox_dir: &PathBuf) -> Result<(), Error> {
         // Create Cargo.toml
         let cargo_toml = sandbox_dir.join("Cargo.toml");
@@ -89,12 +237,13 @@ edition = "2021"
             .map_err(|e| Error::System(format!("Failed to create Cargo.toml: {}", e)))?;
 
         // Initialize toolchain with minimal output
-        let status = Command::new("rustup")
-            .args(["default", "stable"])
-            .current_dir(sandbox_dir)
-            .status()
-            .await
-            .map_err(|e| Error::System(format!("Failed to set Rust toolchain: {}", e)))?;
+        let status = run_status(
+            Command::new("rustup")
+                .args(["default", "stable"])
+                .current_dir(sandbox_dir),
+        )
+        .await
+        .map_err(|e| Error::System(format!("Failed to set Rust toolchain: {}", e)))?;
 
         if !status.success() {
             return Err(Error::System("Failed to set Rust toolchain".to_string()));
@@ -107,42 +256,68 @@ edition = "2021"
         &self,
         sandbox_dir: &PathBuf,
         dependencies: &[crate::types::Dependency],
-    ) -> Result<(), Error> {
+    ) -> Result<Option<String>, Error> {
         self.create_cargo_toml(sandbox_dir, dependencies).await?;
-        Ok(())
-    }
 
-    async fn compile(&self, sandbox_dir: &PathBuf, source_file: &PathBuf) -> Result<(), Error> {
-        // Move source to src/main.rs
-        let src_dir = sandbox_dir.join("src");
-        fs::create_dir_all(&src_dir)
-            .await
-            .map_err(|e| Error::System(format!("Failed to create src directory: {}", e)))?;
+        // Cargo only resolves a manifest with a target; the program replaces this
+        // placeholder when it is compiled
+        let main_rs = sandbox_dir.join("src/main.rs");
+        if !main_rs.exists() {
+            fs::write(&main_rs, "fn main() {}\n")
+                .await
+                .map_err(|e| Error::System(format!("Failed to write src/main.rs: {}", e)))?;
+        }
 
-        fs::rename(source_file, src_dir.join("main.rs"))
-            .await
-            .map_err(|e| Error::System(format!("Failed to move source file: {}", e)))?;
+        // Download the crates up front, so the build only compiles
+        let output = run_output(
+            Command::new("cargo")
+                .args(["fetch", "--quiet", "--color=never"])
+                .args(self.offline.then_some("--offline"))
+                .current_dir(sandbox_dir),
+        )
+        .await
+        .map_err(|e| Error::System(format!("Failed to run cargo fetch: {}", e)))?;
 
-        // Build the code
-        let status = Command::new("cargo")
-            .args([
-                "build",
-                "--release",
-                "--quiet",
-                "--color=never",
-                "--message-format=short",
-            ])
-            .current_dir(sandbox_dir)
-            .status()
-            .await
-            .map_err(|e| Error::CompilationError(e.to_string()))?;
+        if !output.status.success() {
+            return Err(Error::System(format!(
+                "Failed to fetch dependencies: {}",
+                diagnostic_output(&output.stderr)
+            )));
+        }
 
-        if !status.success() {
-            return Err(Error::CompilationError(
-                "Rust compilation failed".to_string(),
-            ));
+        // Compile the crates into the sandbox's target/, which the dependency cache keeps,
+        // then drop the placeholder's own artifacts so only dependencies are cached and
+        // the program is always rebuilt from its source
+        if self.shared_target_dir.is_none() {
+            for args in [
+                &["build", "--release", "--quiet", "--color=never"][..],
+                &["clean", "--release", "--quiet", "-p", "code-execution"],
+            ] {
+                let output = run_output(
+                    Command::new("cargo")
+                        .args(args)
+                        .args(self.offline.then_some("--offline"))
+                        .current_dir(sandbox_dir),
+                )
+                .await
+                .map_err(|e| Error::System(format!("Failed to run cargo {}: {}", args[0], e)))?;
+
+                if !output.status.success() {
+                    return Err(Error::System(format!(
+                        "Failed to build dependencies: {}",
+                        diagnostic_output(&output.stderr)
+                    )));
+                }
+            }
         }
 
+        Ok(install_output(&output))
+    }
+
+    async fn compile(&self, sandbox_dir: &PathBuf, source_file: &PathBuf) -> Result<(), Error> {
+        self.build(sandbox_dir, source_file, &[], "release/code-execution")
+            .await?;
+
         // Copy binary to root directory
         fs::copy(
             sandbox_dir.join("target/release/code-execution"),
@@ -154,6 +329,36 @@ edition = "2021"
         Ok(())
     }
 
+    async fn compile_wasm(&self, sandbox_dir: &Path, source_file: &Path) -> Result<PathBuf, Error> {
+        let status = run_status(
+            Command::new("rustup")
+                .args(["target", "add", WASM_TARGET])
+                .current_dir(sandbox_dir),
+        )
+        .await
+        .map_err(|e| Error::System(format!("Failed to add {} target: {}", WASM_TARGET, e)))?;
+
+        if !status.success() {
+            return Err(Error::System(format!(
+                "Failed to add {} target",
+                WASM_TARGET
+            )));
+        }
+
+        let artifact = format!("{}/release/code-execution.wasm", WASM_TARGET);
+        self.build(
+            sandbox_dir,
+            source_file,
+            &["--target", WASM_TARGET],
+            &artifact,
+        )
+        .await?;
+        Ok(sandbox_dir.join(format!(
+            "target/{}/release/code-execution.wasm",
+            WASM_TARGET
+        )))
+    }
+
     async fn check_tools(&self) -> Result<(), Error> {
         let missing: Vec<_> = self
             .required_tools()
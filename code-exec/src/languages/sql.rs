@@ -0,0 +1,117 @@
+use async_trait::async_trait;
+use std::path::PathBuf;
+use tokio::fs;
+use which::which;
+
+use crate::{error::Error, executor::LanguageExecutor, languages::ToolCheck};
+
+/// Runs SQL against a SQLite database created fresh in the sandbox.
+///
+/// The request's input, if any, is read as SQL before the program, which lets callers
+/// seed the database separately from the statements under test. `sqlite3` runs in safe
+/// mode, so dot-commands such as `.shell` or `.open` that reach outside the database are
+/// refused; as that includes `.read`, both are piped in on its standard input.
+pub struct SqlExecutor {
+    /// Absolute path of `sqlite3`, since the sandbox only searches the system directories
+    sqlite3: String,
+}
+
+impl SqlExecutor {
+    pub fn new() -> Self {
+        Self {
+            sqlite3: which("sqlite3")
+                .map(|path| path.to_string_lossy().into_owned())
+                .unwrap_or_else(|_| "sqlite3".to_string()),
+        }
+    }
+}
+
+impl Default for SqlExecutor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ToolCheck for SqlExecutor {
+    fn required_tools(&self) -> Vec<&str> {
+        vec!["sqlite3"]
+    }
+}
+
+#[async_trait]
+impl LanguageExecutor for SqlExecutor {
+    fn file_extension(&self) -> &str {
+        "sql"
+    }
+
+    fn run_command(&self) -> &str {
+        "sh"
+    }
+
+    /// The program is SQL read by `sqlite3`, which has no use for arguments
+    fn accepts_args(&self) -> bool {
+        false
+    }
+
+    fn run_args(&self) -> Vec<String> {
+        // -bail stops at the first failing statement and exits non-zero
+        let script = format!(
+            "cat - main.sql | '{}' -safe -bail -batch -header -column database.db",
+            self.sqlite3.replace('\'', "'\\''")
+        );
+        vec!["-c".to_string(), script]
+    }
+
+    async fn setup_environment(&self, _sandbox_dir: &PathBuf) -> Result<(), Error> {
+        Ok(())
+    }
+
+    async fn install_dependencies(
+        &self,
+        _sandbox_dir: &PathBuf,
+        dependencies: &[crate::types::Dependency],
+    ) -> Result<Option<String>, Error> {
+        if dependencies.is_empty() {
+            return Ok(None);
+        }
+
+        Err(Error::InvalidDependency(
+            "SQL programs cannot declare dependencies".to_string(),
+        ))
+    }
+
+    async fn compile(&self, sandbox_dir: &PathBuf, source_file: &PathBuf) -> Result<(), Error> {
+        fs::rename(source_file, sandbox_dir.join("main.sql"))
+            .await
+            .map_err(|e| Error::System(format!("Failed to move source file: {}", e)))?;
+        Ok(())
+    }
+
+    async fn check_tools(&self) -> Result<(), Error> {
+        let missing: Vec<_> = self
+            .required_tools()
+            .iter()
+            .filter(|tool| which(tool).is_err())
+            .map(|s| (*s).to_string())
+            .collect();
+
+        if !missing.is_empty() {
+            return Err(Error::System(format!(
+                "Missing required tools: {}",
+                missing.join(", ")
+            )));
+        }
+        Ok(())
+    }
+
+    async fn install_missing_tools(&self) -> Result<(), Error> {
+        ToolCheck::install_missing_tools(self).await
+    }
+
+    async fn ensure_directories(&self, sandbox_dir: &PathBuf) -> Result<(), Error> {
+        tokio::fs::create_dir_all(sandbox_dir.join("tmp"))
+            .await
+            .map_err(|e| Error::System(format!("Failed to create tmp directory: {}", e)))?;
+        Ok(())
+    }
+}
@@ -5,13 +5,16 @@ use tracing::debug;
 use which::which;
 
 use crate::{
-    error::Error, executor::LanguageExecutor, languages::ToolCheck, ExecutionResult,
-    ExecutionStatus,
+    error::Error,
+    executor::LanguageExecutor,
+    languages::{diagnostic_output, install_output, run_output, run_status, ToolCheck},
+    ExecutionResult, ExecutionStatus,
 };
 
 pub struct TypeScriptExecutor {
     node_version: String,
     ts_version: String,
+    offline: bool,
 }
 
 impl TypeScriptExecutor {
@@ -19,8 +22,25 @@ impl TypeScriptExecutor {
         Self {
             node_version: node_version.unwrap_or_else(|| "18".to_string()),
             ts_version: ts_version.unwrap_or_else(|| "5.0".to_string()),
+            offline: false,
         }
     }
+
+    /// Install packages from npm's local cache only, failing instead of downloading
+    pub fn with_offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
+        self
+    }
+
+    /// `npm install` with the given arguments, from the local cache only in offline mode
+    fn npm_install(&self, args: &[&str]) -> Command {
+        let mut command = Command::new("npm");
+        command.arg("install").args(args);
+        if self.offline {
+            command.arg("--offline");
+        }
+        command
+    }
 }
 
 impl ToolCheck for TypeScriptExecutor {
@@ -43,6 +63,21 @@ impl LanguageExecutor for TypeScriptExecutor {
         vec!["dist/index.js".to_string()]
     }
 
+    fn deterministic_run_args(&self) -> Vec<String> {
+        // V8 seeds Math.random from this instead of the system when it is non-zero
+        let mut args = vec!["--random-seed=42".to_string()];
+        args.extend(self.run_args());
+        args
+    }
+
+    fn source_dir(&self) -> &str {
+        "src"
+    }
+
+    fn cache_dirs(&self) -> Vec<&str> {
+        vec!["node_modules"]
+    }
+
     async fn setup_environment(&self, sandbox_dir: &PathBuf) -> Result<(), Error> {
         // Initialize npm project
         let package_json = sandbox_dir.join("package.json");
@@ -84,12 +119,12 @@ impl LanguageExecutor for TypeScriptExecutor {
         .map_err(|e| Error::System(format!("Failed to create tsconfig.json: {}", e)))?;
 
         // Install TypeScript and @types/node
-        let status = Command::new("npm")
-            .args(["install", "--quiet", "typescript", "@types/node"])
-            .current_dir(sandbox_dir)
-            .status()
-            .await
-            .map_err(|e| Error::System(format!("Failed to install TypeScript: {}", e)))?;
+        let status = run_status(
+            self.npm_install(&["--quiet", "typescript", "@types/node"])
+                .current_dir(sandbox_dir),
+        )
+        .await
+        .map_err(|e| Error::System(format!("Failed to install TypeScript: {}", e)))?;
 
         if !status.success() {
             return Err(Error::System("Failed to install TypeScript".to_string()));
@@ -103,12 +138,11 @@ impl LanguageExecutor for TypeScriptExecutor {
         &self,
         sandbox_dir: &PathBuf,
         dependencies: &[crate::types::Dependency],
-    ) -> Result<(), Error> {
+    ) -> Result<Option<String>, Error> {
         if dependencies.is_empty() {
-            return Ok(());
+            return Ok(None);
         }
 
-        let mut install_args = vec!["install"];
         let dep_specs: Vec<String> = dependencies
             .iter()
             .map(|dep| match &dep.source {
@@ -117,21 +151,21 @@ impl LanguageExecutor for TypeScriptExecutor {
             })
             .collect();
 
-        install_args.extend(dep_specs.iter().map(|s| s.as_str()));
+        let install_args: Vec<&str> = dep_specs.iter().map(|s| s.as_str()).collect();
 
         // Install dependencies
-        let status = Command::new("npm")
-            .args(&install_args)
-            .current_dir(sandbox_dir)
-            .status()
+        let output = run_output(self.npm_install(&install_args).current_dir(sandbox_dir))
             .await
             .map_err(|e| Error::System(format!("Failed to install dependencies: {}", e)))?;
 
-        if !status.success() {
-            return Err(Error::System("Failed to install dependencies".to_string()));
+        if !output.status.success() {
+            return Err(Error::System(format!(
+                "Failed to install dependencies: {}",
+                diagnostic_output(&output.stderr)
+            )));
         }
 
-        Ok(())
+        Ok(install_output(&output))
     }
 
     async fn compile(&self, sandbox_dir: &PathBuf, source_file: &PathBuf) -> Result<(), Error> {
@@ -145,29 +179,33 @@ impl LanguageExecutor for TypeScriptExecutor {
             .map_err(|e| Error::System(format!("Failed to move source file: {}", e)))?;
 
         // Install typescript locally first
-        let status = Command::new("npm")
-            .args(["install", "--save-dev", "typescript"])
-            .current_dir(sandbox_dir)
-            .status()
-            .await
-            .map_err(|e| Error::System(format!("Failed to install typescript: {}", e)))?;
+        let status = run_status(
+            self.npm_install(&["--save-dev", "typescript"])
+                .current_dir(sandbox_dir),
+        )
+        .await
+        .map_err(|e| Error::System(format!("Failed to install typescript: {}", e)))?;
 
         if !status.success() {
             return Err(Error::System("Failed to install typescript".to_string()));
         }
 
         // Use local tsc from node_modules
-        let status = Command::new("npx")
-            .args(["tsc"])
-            .current_dir(sandbox_dir)
-            .status()
-            .await
-            .map_err(|e| Error::CompilationError(e.to_string()))?;
-
-        if !status.success() {
-            return Err(Error::CompilationError(
-                "TypeScript compilation failed".to_string(),
-            ));
+        let output = run_output(
+            Command::new("npx")
+                .args(["tsc", "--pretty", "false"])
+                .current_dir(sandbox_dir),
+        )
+        .await
+        .map_err(|e| Error::CompilationError(e.to_string()))?;
+
+        if !output.status.success() {
+            // tsc reports diagnostics on stdout
+            let diagnostics = [output.stdout, output.stderr].concat();
+            return Err(Error::CompilationError(format!(
+                "TypeScript compilation failed: {}",
+                diagnostic_output(&diagnostics)
+            )));
         }
 
         Ok(())
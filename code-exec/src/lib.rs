@@ -3,22 +3,42 @@
 //! A secure code execution service that runs arbitrary code snippets in TEE environments.
 //! Supports multiple programming languages with secure isolation and resource limits.
 
+pub mod cache;
+pub mod credentials;
 pub mod error;
 pub mod executor;
+pub mod gvisor;
 pub mod languages;
+pub mod outputs;
+pub mod policy;
 pub mod sandbox;
+pub mod scanner;
+#[cfg(target_os = "linux")]
+pub mod seccomp;
 pub mod service;
 pub mod types;
+#[cfg(feature = "wasm")]
+pub mod wasm;
 
 pub use crate::languages::*;
+pub use cache::{DependencyCache, DependencyCacheConfig};
 pub use error::Error;
 pub use executor::{CodeExecutor, LanguageExecutor};
-pub use service::CodeExecutionService;
+pub use policy::{PolicyDecision, PolicyRule, RegexPolicy, SubmissionPolicy};
+pub use scanner::{Advisory, DependencyScanner, OsvScanner, OSV_API_URL};
+pub use service::{
+    CodeExecutionService, ServiceStats, DEFAULT_MAX_SOURCE_BYTES, IMPORT_CHECKED_LANGUAGES,
+};
 pub use types::{
-    Dependency, ExecutionRequest, ExecutionResult, ExecutionStatus, Language, ProcessStats,
-    ResourceLimits,
+    CredentialDelivery, CustomLanguage, Dependency, EnvPolicy, ExecutionMode, ExecutionRequest,
+    ExecutionResult, ExecutionStatus, InjectedCredential, InputStep, JsRuntime, Language,
+    OutputEncoding, OutputFile, ProcessStats, ResourceKind, ResourceLimits, SandboxBackend,
+    SandboxEntry, SourceFile, WarmupResult, DEFAULT_COMPILE_TIMEOUT, DEFAULT_INSTALL_TIMEOUT,
+    DEFAULT_MAX_ENV_BYTES, DEFAULT_MAX_ENV_VARS, INPUT_STEP_TIMEOUT,
 };
 
+pub use tokio_util::sync::CancellationToken;
+
 /// Result type for code execution operations
 pub type Result<T> = std::result::Result<T, Error>;
 
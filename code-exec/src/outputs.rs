@@ -0,0 +1,145 @@
+//! Files a program writes that are returned with its result
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use regex::Regex;
+use std::{
+    collections::VecDeque,
+    path::{Component, Path, PathBuf},
+};
+
+use crate::{
+    credentials,
+    error::Error,
+    types::{InjectedCredential, OutputFile},
+};
+
+/// Check that a capture pattern is relative and cannot reach outside the sandbox
+pub fn validate_pattern(pattern: &str) -> Result<(), Error> {
+    let relative = !pattern.is_empty()
+        && Path::new(pattern)
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)));
+    if !relative {
+        return Err(Error::Validation(format!(
+            "Output pattern must be relative and must not contain '..': {}",
+            pattern
+        )));
+    }
+    Ok(())
+}
+
+/// Regular expression matching the paths selected by a glob.
+///
+/// `*` and `?` match within one path segment, and `**` matches any number of segments.
+fn glob_regex(pattern: &str) -> Regex {
+    let mut regex = String::from("^");
+    let mut chars = pattern.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                if chars.peek() == Some(&'/') {
+                    chars.next();
+                    regex.push_str("(?:.*/)?");
+                } else {
+                    regex.push_str(".*");
+                }
+            }
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    regex.push('$');
+    Regex::new(&regex).expect("escaped glob is a valid regex")
+}
+
+/// Read the files under `root` matching any of `patterns`, in path order.
+///
+/// Only regular files are returned; symlinks are neither read nor followed, so a
+/// program cannot use them to hand out files from outside the sandbox. Files that would
+/// take the total past `limit` bytes are left out, which the returned flag reports.
+/// The values of `credentials` are scrubbed from the returned contents.
+pub fn collect(
+    root: &Path,
+    patterns: &[String],
+    limit: usize,
+    credentials: &[InjectedCredential],
+) -> Result<(Vec<OutputFile>, bool), Error> {
+    let globs: Vec<Regex> = patterns
+        .iter()
+        .map(|pattern| {
+            validate_pattern(pattern)?;
+            Ok(glob_regex(pattern))
+        })
+        .collect::<Result<_, Error>>()?;
+
+    let mut files = Vec::new();
+    let mut total = 0;
+    let mut truncated = false;
+    let mut pending = VecDeque::from([PathBuf::new()]);
+
+    while let Some(relative) = pending.pop_front() {
+        let Ok(read_dir) = std::fs::read_dir(root.join(&relative)) else {
+            continue;
+        };
+        let mut children: Vec<_> = read_dir.filter_map(|item| item.ok()).collect();
+        children.sort_by_key(|item| item.file_name());
+
+        for child in children {
+            // Does not follow symlinks
+            let Ok(metadata) = child.metadata() else {
+                continue;
+            };
+            let path = relative.join(child.file_name());
+            if metadata.is_dir() {
+                pending.push_back(path);
+                continue;
+            }
+
+            let path = path.to_string_lossy().into_owned();
+            if !metadata.is_file() || !globs.iter().any(|glob| glob.is_match(&path)) {
+                continue;
+            }
+            if total + metadata.len() as usize > limit {
+                truncated = true;
+                continue;
+            }
+
+            let content = std::fs::read(child.path())?;
+            total += content.len();
+            files.push(OutputFile {
+                path,
+                content_base64: STANDARD.encode(credentials::scrub_bytes(&content, credentials)),
+            });
+        }
+    }
+
+    Ok((files, truncated))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_regex() {
+        assert!(glob_regex("out.csv").is_match("out.csv"));
+        assert!(!glob_regex("out.csv").is_match("outxcsv"));
+        assert!(glob_regex("*.png").is_match("plot.png"));
+        assert!(!glob_regex("*.png").is_match("plots/plot.png"));
+        assert!(glob_regex("plots/**/*.png").is_match("plots/plot.png"));
+        assert!(glob_regex("plots/**/*.png").is_match("plots/a/b/plot.png"));
+        assert!(glob_regex("**").is_match("a/b/c.txt"));
+        assert!(glob_regex("data?.txt").is_match("data1.txt"));
+    }
+
+    #[test]
+    fn test_validate_pattern() {
+        assert!(validate_pattern("out/*.csv").is_ok());
+        assert!(validate_pattern("../secret").is_err());
+        assert!(validate_pattern("/etc/passwd").is_err());
+        assert!(validate_pattern("out/../../secret").is_err());
+        assert!(validate_pattern("").is_err());
+    }
+}
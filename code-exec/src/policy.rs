@@ -0,0 +1,109 @@
+//! Pre-flight checks that reject abusive submissions before a sandbox is spent on them
+//!
+//! This is a cheap first filter, not a security boundary: the sandbox limits still
+//! apply to everything that gets through, and a determined author can always obfuscate
+//! code past a pattern match.
+
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::{error::Error, types::Language};
+
+/// Outcome of checking a submission
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PolicyDecision {
+    Allow,
+    /// Refuse to run the submission, naming the rule it broke
+    Reject {
+        rule: String,
+    },
+}
+
+/// Check run on every submission before it is executed
+pub trait SubmissionPolicy: Send + Sync {
+    fn check(&self, language: Language, code: &str) -> PolicyDecision;
+}
+
+/// Regular expression a submission must not match
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PolicyRule {
+    /// Name reported when the rule rejects a submission
+    pub name: String,
+    pub pattern: String,
+    /// Languages the rule applies to, all of them if empty
+    #[serde(default)]
+    pub languages: Vec<Language>,
+}
+
+impl PolicyRule {
+    pub fn new(name: &str, pattern: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            pattern: pattern.to_string(),
+            languages: Vec::new(),
+        }
+    }
+
+    /// Rules for fork bombs, wiping the root directory and cryptocurrency miners
+    pub fn builtin() -> Vec<Self> {
+        vec![
+            Self::new("fork_bomb", r":\(\)\s*\{\s*:\s*\|\s*:\s*&\s*\}\s*;\s*:"),
+            Self::new(
+                "fork_bomb",
+                r"(?s)while\s*\(?\s*(?:True|true|1)\s*\)?\s*[:{]?.{0,200}?\bfork\(\)",
+            ),
+            Self::new(
+                "rm_root",
+                r#"\brm\s+(?:-{1,2}[\w-]+\s+)*/\*?(?:\s|$|["';)])"#,
+            ),
+            Self::new(
+                "crypto_miner",
+                r"(?i)stratum\+(?:tcp|ssl|tls)://|\bxmrig\b|\bcoinhive\b|\bcryptonight\b|\bminerd\b",
+            ),
+        ]
+    }
+}
+
+/// Policy rejecting submissions that match any of its rules
+pub struct RegexPolicy {
+    rules: Vec<(PolicyRule, Regex)>,
+}
+
+impl RegexPolicy {
+    /// Compile the rules, failing on the first invalid pattern
+    pub fn new(rules: Vec<PolicyRule>) -> Result<Self, Error> {
+        let rules = rules
+            .into_iter()
+            .map(|rule| {
+                let regex = Regex::new(&rule.pattern).map_err(|e| {
+                    Error::Validation(format!("Invalid pattern for rule {}: {}", rule.name, e))
+                })?;
+                Ok((rule, regex))
+            })
+            .collect::<Result<_, Error>>()?;
+        Ok(Self { rules })
+    }
+}
+
+impl Default for RegexPolicy {
+    fn default() -> Self {
+        Self::new(PolicyRule::builtin()).expect("built-in policy rules are valid")
+    }
+}
+
+impl SubmissionPolicy for RegexPolicy {
+    fn check(&self, language: Language, code: &str) -> PolicyDecision {
+        let broken = self.rules.iter().find(|(rule, regex)| {
+            (rule.languages.is_empty() || rule.languages.contains(&language))
+                && regex.is_match(code)
+        });
+
+        match broken {
+            Some((rule, _)) => PolicyDecision::Reject {
+                rule: rule.name.clone(),
+            },
+            None => PolicyDecision::Allow,
+        }
+    }
+}
@@ -1,16 +1,223 @@
-use crate::{error::Error, types::ResourceLimits, ProcessStats};
-use nix::sys::resource::{getrusage, setrlimit, Resource, Usage, UsageWho};
-use std::{path::PathBuf, process::Stdio, time::Instant};
-use tokio::process::Child;
+use crate::{
+    error::Error,
+    gvisor::{self, Container, ContainerProcess},
+    types::{
+        ExecutionStatus, InputStep, OutputEncoding, ResourceKind, ResourceLimits, SandboxBackend,
+        SandboxEntry, INPUT_STEP_TIMEOUT,
+    },
+    ProcessStats,
+};
+use nix::libc;
+#[cfg(not(target_os = "linux"))]
+use nix::sys::resource::{getrusage, UsageWho};
+use nix::sys::stat::{umask, Mode};
+use nix::sys::{
+    resource::{setrlimit, Resource},
+    signal::Signal,
+};
+#[cfg(target_os = "linux")]
+use nix::unistd::setgroups;
+use nix::unistd::{setgid, setpgid, setuid, Gid, Pid, Uid};
+#[cfg(target_os = "linux")]
+use nix::{
+    mount::{mount, MsFlags},
+    sched::{unshare, CloneFlags},
+};
+#[cfg(target_os = "linux")]
+use std::ffi::{CStr, CString};
+use std::{
+    collections::VecDeque,
+    future::Future,
+    os::unix::fs::MetadataExt,
+    path::PathBuf,
+    process::Stdio,
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::Instant,
+};
+use tokio::process::{Child, ChildStdin};
 use tokio::{
     fs,
-    io::AsyncWriteExt,
+    io::{AsyncRead, AsyncReadExt, AsyncWriteExt},
     process::Command,
     time::{self, Duration},
 };
+use tokio_util::sync::CancellationToken;
 use tracing::{debug, error, warn};
 use uuid::Uuid;
 
+/// How often the sandbox is scanned for the number of files the program created and
+/// the disk space they take
+const FILE_COUNT_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How often the sandbox's disk usage is checked while dependencies are installed or the
+/// program compiled, which write far more files than programs usually do
+const BUILD_DISK_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How often stdout is checked for the prompt an input script step waits for
+const PROMPT_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Permissions masked from files the program creates, keeping them private to its user,
+/// under either backend
+pub(crate) const PROGRAM_UMASK: u32 = 0o077;
+
+/// Maximum bytes captured from each of stdout and stderr
+pub const MAX_OUTPUT_SIZE: usize = 10 * 1024 * 1024;
+
+/// How long output still buffered in the pipes is read after a program is killed
+const OUTPUT_DRAIN_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// How many times [`Sandbox::cleanup`] tries to remove the sandbox directory
+const CLEANUP_ATTEMPTS: u32 = 3;
+
+/// Pause before the first cleanup retry, doubled for each following one
+const CLEANUP_RETRY_DELAY: Duration = Duration::from_millis(100);
+
+/// Sandbox directories left on disk because removing them failed
+static LEAKED_SANDBOXES: AtomicUsize = AtomicUsize::new(0);
+
+/// Number of sandbox directories this process failed to remove
+pub fn leaked_sandboxes() -> usize {
+    LEAKED_SANDBOXES.load(Ordering::Relaxed)
+}
+
+/// Longest time between two checks for expired [`KeptSandboxes`]
+const KEPT_SANDBOX_SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Sandbox directories kept for inspection after their execution failed.
+///
+/// A background task removes each directory once it has been kept for the TTL, for as
+/// long as the set exists.
+pub struct KeptSandboxes {
+    ttl: Duration,
+    dirs: Mutex<Vec<(PathBuf, Instant)>>,
+}
+
+impl KeptSandboxes {
+    /// Keep directories for `ttl`. Must be called within a Tokio runtime, which runs the
+    /// task removing expired directories.
+    pub fn new(ttl: Duration) -> Arc<Self> {
+        let kept = Arc::new(Self {
+            ttl,
+            dirs: Mutex::new(Vec::new()),
+        });
+
+        let weak = Arc::downgrade(&kept);
+        let period = ttl.clamp(Duration::from_secs(1), KEPT_SANDBOX_SWEEP_INTERVAL);
+        tokio::spawn(async move {
+            let mut sweep = time::interval(period);
+            loop {
+                sweep.tick().await;
+                let Some(kept) = weak.upgrade() else {
+                    return;
+                };
+                kept.remove_expired().await;
+            }
+        });
+        kept
+    }
+
+    /// Keep the directory of `sandbox` instead of removing it, returning its path
+    pub fn keep(&self, sandbox: Sandbox) -> PathBuf {
+        let path = sandbox.keep();
+        self.dirs
+            .lock()
+            .unwrap()
+            .push((path.clone(), Instant::now()));
+        path
+    }
+
+    /// Paths of the directories kept and not yet removed
+    pub fn paths(&self) -> Vec<PathBuf> {
+        let dirs = self.dirs.lock().unwrap();
+        dirs.iter().map(|(path, _)| path.clone()).collect()
+    }
+
+    /// Remove the directories kept for longer than the TTL
+    pub async fn remove_expired(&self) {
+        let expired: Vec<_> = {
+            let mut dirs = self.dirs.lock().unwrap();
+            let (expired, kept) = dirs
+                .drain(..)
+                .partition(|(_, kept_at)| kept_at.elapsed() >= self.ttl);
+            *dirs = kept;
+            expired
+        };
+
+        for (path, _) in expired {
+            match fs::remove_dir_all(&path).await {
+                Ok(()) => debug!("Removed kept sandbox {}", path.display()),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => {
+                    LEAKED_SANDBOXES.fetch_add(1, Ordering::Relaxed);
+                    warn!("Failed to remove kept sandbox {}: {}", path.display(), e);
+                }
+            }
+        }
+    }
+}
+
+/// Outcome of a program run by [`Sandbox::run`]
+#[derive(Debug)]
+pub struct RunOutput {
+    /// How the program ended
+    pub status: ExecutionStatus,
+    /// Captured stdout, at most [`MAX_OUTPUT_SIZE`] bytes
+    pub stdout: String,
+    /// Captured stderr, at most [`MAX_OUTPUT_SIZE`] bytes
+    pub stderr: String,
+    /// Captured stdout as the program wrote it, kept with [`OutputEncoding::Base64`]
+    pub stdout_bytes: Option<Vec<u8>>,
+    /// Captured stderr as the program wrote it, kept with [`OutputEncoding::Base64`]
+    pub stderr_bytes: Option<Vec<u8>>,
+    /// Resource usage
+    pub stats: ProcessStats,
+    /// Error [`Sandbox::execute`] reports for an unsuccessful status
+    failure: Option<Error>,
+}
+
+impl RunOutput {
+    /// Outcome of a program another backend ran, with `error` mapped to its status if
+    /// it stands for one
+    pub(crate) fn from_result(
+        result: Result<(String, String, ProcessStats), Error>,
+    ) -> Result<Self, Error> {
+        match result {
+            Ok((stdout, stderr, stats)) => Ok(Self {
+                status: ExecutionStatus::Success,
+                stdout,
+                stderr,
+                stdout_bytes: None,
+                stderr_bytes: None,
+                stats,
+                failure: None,
+            }),
+            Err(error) => match ExecutionStatus::from_error(&error) {
+                Some(status) => Ok(Self {
+                    status,
+                    stdout: String::new(),
+                    stderr: String::new(),
+                    stdout_bytes: None,
+                    stderr_bytes: None,
+                    stats: ProcessStats::default(),
+                    failure: Some(error),
+                }),
+                None => Err(error),
+            },
+        }
+    }
+}
+
+/// What a program run in the sandbox reads from stdin
+#[derive(Clone, Copy)]
+enum Stdin<'a> {
+    Closed,
+    Input(&'a str),
+    Script(&'a [InputStep]),
+}
+
 /// Sandbox environment for secure code execution
 pub struct Sandbox {
     /// Root directory for the sandbox
@@ -21,11 +228,24 @@ pub struct Sandbox {
     id: String,
     /// Start time of current execution
     start_time: Option<Instant>,
+    /// Kills the running program or build step when cancelled
+    cancel: CancellationToken,
+    /// Set once [`Sandbox::cleanup`] or [`Sandbox::keep`] has run, so dropping does not
+    /// remove the directory
+    cleaned_up: bool,
+    /// Largest disk space the sandbox took in any check so far
+    peak_disk_bytes: AtomicU64,
+    /// How [`Sandbox::run`] decodes the program's output
+    output_encoding: OutputEncoding,
 }
 
 impl Sandbox {
     /// Create a new sandbox environment
     pub async fn new(limits: ResourceLimits) -> Result<Self, Error> {
+        if limits.backend == SandboxBackend::Gvisor {
+            gvisor::runsc_path()?;
+        }
+
         let id = Uuid::new_v4().to_string();
         let root_dir = PathBuf::from("/tmp").join(format!("sandbox-{}", id));
 
@@ -46,38 +266,90 @@ impl Sandbox {
             limits,
             id: id.to_string(),
             start_time: None,
+            cancel: CancellationToken::new(),
+            cleaned_up: false,
+            peak_disk_bytes: AtomicU64::new(0),
+            output_encoding: OutputEncoding::default(),
         };
 
         Ok(sandbox)
     }
 
-    /// Check if resource limits have been exceeded
-    fn check_resource_usage(&self) -> Result<(), Error> {
-        let usage = getrusage(UsageWho::RUSAGE_CHILDREN)
-            .map_err(|e| Error::Sandbox(format!("Failed to get resource usage: {}", e)))?;
-
-        // Check CPU time
-        if let Some(start_time) = self.start_time {
-            let elapsed = start_time.elapsed();
-            if elapsed.as_secs() as u32 > self.limits.cpu_time {
-                return Err(Error::ResourceExceeded(format!(
-                    "CPU time limit exceeded: {} > {}",
-                    elapsed.as_secs(),
-                    self.limits.cpu_time
-                )));
+    /// Kill whatever runs in this sandbox, and fail later commands, once `token` is
+    /// cancelled
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancel = token;
+        self
+    }
+
+    /// Decode the output of later runs as `encoding` asks, instead of lossily
+    pub fn set_output_encoding(&mut self, encoding: OutputEncoding) {
+        self.output_encoding = encoding;
+    }
+
+    /// Remove the sandbox directory.
+    ///
+    /// Removal is retried a few times, since processes the program left behind may still
+    /// be writing to the directory while they are killed. Directories that cannot be
+    /// removed are counted in [`leaked_sandboxes`]. Dropping a sandbox without calling
+    /// this removes the directory too, but blocks and can only log failures.
+    pub async fn cleanup(mut self) -> Result<(), Error> {
+        self.cleaned_up = true;
+
+        let mut delay = CLEANUP_RETRY_DELAY;
+        let mut attempt = 1;
+        loop {
+            match fs::remove_dir_all(&self.root_dir).await {
+                Ok(()) => return Ok(()),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+                Err(e) if attempt < CLEANUP_ATTEMPTS => {
+                    debug!(
+                        "Failed to remove sandbox directory (attempt {}): {}",
+                        attempt, e
+                    );
+                    time::sleep(delay).await;
+                    delay *= 2;
+                    attempt += 1;
+                }
+                Err(e) => {
+                    LEAKED_SANDBOXES.fetch_add(1, Ordering::Relaxed);
+                    return Err(Error::Sandbox(format!(
+                        "Failed to clean up sandbox directory {}: {}",
+                        self.root_dir.display(),
+                        e
+                    )));
+                }
             }
         }
+    }
 
-        // On macOS, getrusage reports unreliable memory values so we skip the check
+    /// Leave the sandbox directory on disk, for inspecting what an execution left behind,
+    /// and return its path
+    pub fn keep(mut self) -> PathBuf {
+        self.cleaned_up = true;
+        std::mem::take(&mut self.root_dir)
+    }
+
+    /// Resource limits programs in this sandbox run under
+    pub fn limits(&self) -> &ResourceLimits {
+        &self.limits
+    }
+
+    /// Check if the program's processes, with `usage`, exceeded the resource limits
+    fn check_resource_usage(&self, usage: &libc::rusage) -> Result<(), Error> {
+        // On macOS, getrusage reports unreliable memory values so we skip the check.
+        // Under gVisor the usage includes the user-space kernel, and the container's
+        // cgroup enforces the limit instead.
         #[cfg(target_os = "linux")]
-        {
+        if self.limits.backend != SandboxBackend::Gvisor {
             // Check memory usage (RSS)
-            let memory_kb = usage.max_rss() as u64 * 1024; // Convert KB to bytes
+            let memory_kb = usage.ru_maxrss as u64 * 1024; // Convert KB to bytes
             if memory_kb > self.limits.memory {
-                return Err(Error::ResourceExceeded(format!(
-                    "Memory limit exceeded: {} > {}",
-                    memory_kb, self.limits.memory
-                )));
+                return Err(Error::ResourceExceeded {
+                    resource: ResourceKind::Memory,
+                    limit: self.limits.memory,
+                    observed: memory_kb,
+                });
             }
         }
 
@@ -90,6 +362,10 @@ impl Sandbox {
     }
 
     /// Execute a command in the sandbox with proper resource limits and monitoring
+    ///
+    /// The process is killed once `timeout` or the configured `wall_time` elapses,
+    /// whichever is shorter. Any outcome other than a successful exit is an error; use
+    /// [`Sandbox::run`] to get the output and [`ExecutionStatus`] of failed programs.
     pub async fn execute(
         &mut self,
         cmd: &str,
@@ -98,64 +374,199 @@ impl Sandbox {
         input: Option<&str>,
         timeout: Duration,
     ) -> Result<(String, String, ProcessStats), Error> {
+        let output = self.run(cmd, args, env, input, timeout).await?;
+        match output.failure {
+            Some(error) => Err(error),
+            None => Ok((output.stdout, output.stderr, output.stats)),
+        }
+    }
+
+    /// Run a command in the sandbox, reporting how it ended instead of failing when it
+    /// exits unsuccessfully or is killed by a limit.
+    ///
+    /// Errors are left for problems that leave no program outcome to report, such as
+    /// failing to spawn, cancellation or exceeding `max_files`.
+    pub async fn run(
+        &mut self,
+        cmd: &str,
+        args: &[&str],
+        env: &[(String, String)],
+        input: Option<&str>,
+        timeout: Duration,
+    ) -> Result<RunOutput, Error> {
+        let stdin = match input {
+            Some(input) => Stdin::Input(input),
+            None => Stdin::Closed,
+        };
+        self.run_with_stdin(cmd, args, env, stdin, timeout).await
+    }
+
+    /// Like [`Sandbox::run`], writing each step of `script` to the program's stdin once it
+    /// printed the step's prompt
+    pub async fn run_script(
+        &mut self,
+        cmd: &str,
+        args: &[&str],
+        env: &[(String, String)],
+        script: &[InputStep],
+        timeout: Duration,
+    ) -> Result<RunOutput, Error> {
+        self.run_with_stdin(cmd, args, env, Stdin::Script(script), timeout)
+            .await
+    }
+
+    async fn run_with_stdin(
+        &mut self,
+        cmd: &str,
+        args: &[&str],
+        env: &[(String, String)],
+        stdin: Stdin<'_>,
+        timeout: Duration,
+    ) -> Result<RunOutput, Error> {
+        if self.cancel.is_cancelled() {
+            return Err(Error::Cancelled);
+        }
         self.start_time = Some(Instant::now());
+        let timeout = timeout.min(Duration::from_secs(self.limits.wall_time));
 
         debug!("Sandbox execute - Command: {}", cmd);
         debug!("Sandbox execute - Args: {:?}", args);
-        debug!("Sandbox execute - Env: {:?}", env);
+        // Values may hold credentials, so only the names are logged
+        debug!(
+            "Sandbox execute - Env: {:?}",
+            env.iter().map(|(k, _)| k).collect::<Vec<_>>()
+        );
         debug!("Sandbox execute - Root dir: {:?}", self.root_dir);
 
         // For system commands, use their absolute path directly
         let cmd_path = if cmd.starts_with("./") {
             PathBuf::from(cmd)
+        } else if let Some(path) = self.limits.find_toolchain_command(cmd) {
+            path
         } else if let Ok(path) = which::which(cmd) {
             path
         } else {
             return Err(Error::Sandbox(format!("Command not found: {}", cmd)));
         };
 
-        let mut command = Command::new(&cmd_path);
+        let env = self.limits.env_policy.apply(env)?;
+        let run_as = self.run_as().await?;
+
+        let container = match self.limits.backend {
+            SandboxBackend::Gvisor => {
+                let (uid, gid) = run_as.unwrap_or((Uid::effective(), Gid::effective()));
+                let process = ContainerProcess {
+                    command: &cmd_path,
+                    args,
+                    env: &env,
+                    root_dir: &self.root_dir,
+                    uid: uid.as_raw(),
+                    gid: gid.as_raw(),
+                };
+                Some(Container::create(&self.id, &process, &self.limits).await?)
+            }
+            _ => None,
+        };
+        // gVisor applies the limits and the user inside the container instead
+        let host_limits = container.is_none();
+        let run_as = run_as.filter(|_| host_limits);
+
+        let mut command = match &container {
+            Some(container) => container.command(),
+            None => {
+                let mut command = Command::new(&cmd_path);
+                command
+                    .args(args)
+                    .env_clear()
+                    .envs(env.iter().map(|(k, v)| (k, v)))
+                    .env("PATH", self.limits.program_path()) // Toolchains, then system commands
+                    .env("HOME", self.root_dir.join("home"))
+                    .env("TMPDIR", self.root_dir.join("tmp"));
+                command
+            }
+        };
         command
-            .args(args)
-            .env_clear()
-            .envs(env.iter().map(|(k, v)| (k, v)))
-            .env("PATH", "/usr/bin:/bin:/usr/sbin:/sbin") // Set minimal PATH for system commands
-            .env("HOME", self.root_dir.join("home"))
             .current_dir(&self.root_dir)
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
-            .stdin(if input.is_some() {
-                Stdio::piped()
-            } else {
-                Stdio::null()
+            .stdin(match stdin {
+                Stdin::Closed => Stdio::null(),
+                Stdin::Input(_) | Stdin::Script(_) => Stdio::piped(),
             });
 
-        // Store limits in stack-allocated variables to avoid closure lifetime issues
+        // Store limits in stack-allocated variables to avoid closure lifetime issues.
+        // The soft CPU limit delivers SIGXCPU, the hard limit a second later SIGKILL
+        // in case the program ignores it.
         let file_size = self.limits.file_size;
         let cpu_time = self.limits.cpu_time;
+        // RLIMIT_NPROC counts every thread of the user the program runs as, so the
+        // threads it has already, e.g. the server's own without `run_as_uid`, are added
+        // to the program's allowance. Root is exempt from the limit.
+        #[cfg(target_os = "linux")]
+        let processes = {
+            let uid = run_as.map_or_else(Uid::effective, |(uid, _)| uid);
+            self.limits.processes as u64 + user_thread_count(uid)
+        };
+
+        // Checked and resolved up front, as the child must not allocate after forking. The
+        // container's root filesystem is read-only already.
+        #[cfg(target_os = "linux")]
+        let private_mounts = host_limits && !self.limits.readonly_mounts.is_empty();
+        #[cfg(target_os = "linux")]
+        if private_mounts {
+            self.check_readonly_mounts()?;
+        }
+        #[cfg(target_os = "linux")]
+        let root_dir = path_to_cstring(&self.root_dir)?;
+
+        // Compiled up front so the child only has to install it
+        #[cfg(target_os = "linux")]
+        let seccomp_filter = if self.limits.seccomp && host_limits {
+            Some(crate::seccomp::load_filter(
+                self.limits.seccomp_profile.as_deref(),
+            )?)
+        } else {
+            None
+        };
 
         unsafe {
             command.pre_exec(move || {
+                // Lead a new process group so a timeout can kill every descendant
+                setpgid(Pid::from_raw(0), Pid::from_raw(0)).map_err(|e| {
+                    std::io::Error::other(format!("Failed to create process group: {}", e))
+                })?;
+
                 #[cfg(target_os = "linux")]
-                {
+                if host_limits {
                     if let Err(e) = setrlimit(Resource::RLIMIT_FSIZE, file_size, file_size) {
                         return Err(std::io::Error::new(
                             std::io::ErrorKind::Other,
                             format!("Failed to set file size limit: {}", e),
                         ));
                     }
-                    if let Err(e) = setrlimit(Resource::RLIMIT_CPU, cpu_time as u64, cpu_time as u64) {
+                    if let Err(e) = setrlimit(Resource::RLIMIT_CPU, cpu_time as u64, cpu_time as u64 + 1) {
                         return Err(std::io::Error::new(
                             std::io::ErrorKind::Other,
                             format!("Failed to set CPU time limit: {}", e),
                         ));
                     }
+                    setrlimit(Resource::RLIMIT_NPROC, processes, processes).map_err(|e| {
+                        std::io::Error::other(format!("Failed to set process limit: {}", e))
+                    })?;
+                    if private_mounts {
+                        setup_mounts(&root_dir).map_err(|e| {
+                            std::io::Error::other(format!(
+                                "Failed to set up read-only mounts (requires CAP_SYS_ADMIN): {}",
+                                e
+                            ))
+                        })?;
+                    }
                 }
 
                 #[cfg(target_os = "macos")]
-                {
+                if host_limits {
                     warn!("Resource limits are limited on macOS. For full resource limiting, use Linux.");
-                    if let Err(e) = setrlimit(Resource::RLIMIT_CPU, cpu_time as u64, cpu_time as u64) {
+                    if let Err(e) = setrlimit(Resource::RLIMIT_CPU, cpu_time as u64, cpu_time as u64 + 1) {
                         return Err(std::io::Error::new(
                             std::io::ErrorKind::Other,
                             format!("Failed to set CPU time limit: {}", e),
@@ -163,6 +574,21 @@ impl Sandbox {
                     }
                 }
 
+                umask(Mode::from_bits_truncate(PROGRAM_UMASK));
+
+                // Dropped after the limits and mounts, which need the server's privileges
+                if let Some((uid, gid)) = run_as {
+                    drop_privileges(uid, gid).map_err(|e| {
+                        std::io::Error::other(format!("Failed to drop privileges: {}", e))
+                    })?;
+                }
+
+                // Installed last, as it may deny the calls made above
+                #[cfg(target_os = "linux")]
+                if let Some(filter) = &seccomp_filter {
+                    crate::seccomp::apply(filter)?;
+                }
+
                 Ok(())
             });
         }
@@ -171,129 +597,697 @@ impl Sandbox {
             .spawn()
             .map_err(|e| Error::Sandbox(format!("Failed to spawn process: {}", e)))?;
 
-        // Write input if provided
-        if let Some(input_str) = input {
-            if let Some(mut stdin) = child.stdin.take() {
-                stdin
-                    .write_all(input_str.as_bytes())
-                    .await
-                    .map_err(|e| Error::Sandbox(format!("Failed to write input: {}", e)))?;
-                // Explicitly close stdin to signal EOF
-                drop(stdin);
+        // Read output as it is written, so whatever a program printed before it was
+        // killed is still there
+        let stdout = Arc::new(Mutex::new(Vec::new()));
+        let stderr = Arc::new(Mutex::new(Vec::new()));
+
+        // Write input from a task, as a program that never reads it would otherwise block
+        // the write once the pipe is full, before the timeout below even starts
+        let stdin_writer = match (stdin, child.stdin.take()) {
+            (Stdin::Input(input), Some(mut pipe)) => {
+                let input = input.as_bytes().to_vec();
+                Some(tokio::spawn(async move {
+                    // Dropping stdin afterwards closes it to signal EOF
+                    pipe.write_all(&input).await
+                }))
             }
-        }
+            (Stdin::Script(script), Some(pipe)) => Some(tokio::spawn(write_script(
+                pipe,
+                script.to_vec(),
+                stdout.clone(),
+            ))),
+            _ => None,
+        };
+        let mut stdout_reader = tokio::spawn(read_pipe(child.stdout.take(), stdout.clone()));
+        let mut stderr_reader = tokio::spawn(read_pipe(child.stderr.take(), stderr.clone()));
 
-        // Wait for completion with timeout
+        // Wait for completion with timeout, watching how many files the program creates,
+        // the disk space the sandbox takes and for cancellation
         let child_id = child.id();
-        let output = match time::timeout(timeout, child.wait_with_output()).await {
-            Ok(Ok(output)) => Ok((output.status, output.stdout, output.stderr)),
-            Ok(Err(e)) => Err(Error::Sandbox(format!("Process error: {}", e))),
-            Err(_) => {
-                if let Some(id) = child_id {
-                    // Send SIGTERM and wait briefly for graceful shutdown
-                    let _ = Command::new("kill").arg(id.to_string()).status().await;
-
-                    // Give a very short grace period
-                    tokio::time::sleep(Duration::from_millis(10)).await;
-
-                    // Force kill with SIGKILL
-                    let _ = Command::new("kill")
-                        .arg("-9")
-                        .arg(id.to_string())
-                        .status()
-                        .await;
+        let baseline_files = self.disk_usage().await.entries;
+        let waited = {
+            // Descendants may keep the pipes open after the program exits, so the
+            // timeout also covers reading them to the end
+            let wait = time::timeout(timeout, async {
+                let exited = wait_with_usage(&mut child).await?;
+                let _ = tokio::join!(&mut stdout_reader, &mut stderr_reader);
+                Ok::<_, std::io::Error>(exited)
+            });
+            tokio::pin!(wait);
+            let mut file_check = time::interval(FILE_COUNT_INTERVAL);
+
+            loop {
+                tokio::select! {
+                    result = &mut wait => break match result {
+                        Ok(Ok(exited)) => Ok(exited),
+                        Ok(Err(e)) => Err(Error::Sandbox(format!("Process error: {}", e))),
+                        Err(_) => {
+                            if let Some(id) = child_id {
+                                kill_process_group(id).await;
+                            }
+                            Err(Error::WallTimeout(timeout.as_secs()))
+                        }
+                    },
+                    _ = self.cancel.cancelled() => {
+                        if let Some(id) = child_id {
+                            kill_process_group(id).await;
+                        }
+                        break Err(Error::Cancelled);
+                    }
+                    _ = file_check.tick() => {
+                        if let Err(e) = self.check_disk_usage(baseline_files).await {
+                            if let Some(id) = child_id {
+                                kill_process_group(id).await;
+                            }
+                            break Err(e);
+                        }
+                    }
                 }
-                Err(Error::Timeout(timeout.as_secs()))
             }
-        }?;
+        };
 
-        // Check resource usage after execution
-        if let Err(e) = self.check_resource_usage() {
-            return Err(e);
+        if let Some(container) = container {
+            container.destroy().await;
         }
 
-        if !output.0.success() {
-            // Check if the process was killed by a signal
-            #[cfg(unix)]
-            {
-                use std::os::unix::process::ExitStatusExt;
-                if let Some(signal) = output.0.signal() {
-                    if signal == 9 || signal == 15 {
-                        // SIGKILL or SIGTERM
-                        return Err(Error::Timeout(timeout.as_secs()));
+        if let Some(writer) = stdin_writer {
+            writer.abort();
+            if let Ok(Err(e)) = writer.await {
+                debug!("Program did not read all of its input: {}", e);
+            }
+        }
+
+        if waited.is_err() {
+            // Killed, so the pipes close unless a process escaped the group; pick up
+            // what is still buffered in them, but don't wait on such a process
+            let _ = time::timeout(OUTPUT_DRAIN_TIMEOUT, async {
+                for reader in [&mut stdout_reader, &mut stderr_reader] {
+                    // A finished reader may already have been awaited above
+                    if !reader.is_finished() {
+                        let _ = reader.await;
                     }
                 }
+            })
+            .await;
+            stdout_reader.abort();
+            stderr_reader.abort();
+        }
+        let stdout = capture_output(&stdout.lock().unwrap(), self.output_encoding, "stdout")?;
+        let stderr = capture_output(&stderr.lock().unwrap(), self.output_encoding, "stderr")?;
+
+        let (exit_status, usage) = match waited {
+            Ok(exited) => exited,
+            Err(error @ Error::WallTimeout(_)) => {
+                return Ok(RunOutput {
+                    status: ExecutionStatus::Timeout,
+                    stdout: stdout.text,
+                    stderr: stderr.text,
+                    stdout_bytes: stdout.raw,
+                    stderr_bytes: stderr.raw,
+                    stats: ProcessStats {
+                        execution_time: self.start_time.unwrap().elapsed(),
+                        ..Default::default()
+                    },
+                    failure: Some(error),
+                })
             }
+            Err(error) => return Err(error),
+        };
 
-            return Err(Error::Sandbox(format!(
-                "Process exited with status: {} (stderr: {})",
-                output.0,
-                String::from_utf8_lossy(&output.2)
-            )));
+        // Catch programs that create too many files or fill the disk and exit between checks
+        self.check_disk_usage(baseline_files).await?;
+
+        let stats = self.process_stats(&usage);
+
+        // Check resource usage after execution
+        let (status, failure) = if let Err(e) = self.check_resource_usage(&usage) {
+            (ExecutionStatus::MemoryExceeded, Some(e))
+        } else if !exit_status.success() {
+            let cpu_used = stats.user_time + stats.system_time;
+            self.classify_failure(exit_status, &stderr.text, cpu_used)
+        } else if stdout.truncated || stderr.truncated {
+            (ExecutionStatus::OutputTruncated, None)
+        } else {
+            (ExecutionStatus::Success, None)
+        };
+
+        Ok(RunOutput {
+            status,
+            stdout: stdout.text,
+            stderr: stderr.text,
+            stdout_bytes: stdout.raw,
+            stderr_bytes: stderr.raw,
+            stats,
+            failure,
+        })
+    }
+
+    /// Status of a program that exited unsuccessfully, with the error [`Sandbox::execute`]
+    /// reports for it. `cpu_used` is the CPU time the program's processes took.
+    fn classify_failure(
+        &self,
+        exit_status: std::process::ExitStatus,
+        stderr: &str,
+        cpu_used: Duration,
+    ) -> (ExecutionStatus, Option<Error>) {
+        use std::os::unix::process::ExitStatusExt;
+
+        // runsc reports a container process killed by a signal as exit code 128 + signal
+        let signal = exit_status.signal().or_else(|| match exit_status.code() {
+            Some(code) if code > 128 && self.limits.backend == SandboxBackend::Gvisor => {
+                Some(code - 128)
+            }
+            _ => None,
+        });
+        if let Some(signal) = signal {
+            // Wall-clock timeouts are handled above, so SIGXCPU comes from RLIMIT_CPU. A
+            // SIGKILL only does if the CPU time limit was used up, otherwise the kernel's
+            // OOM killer or another process sent it.
+            let cpu_exhausted = cpu_used >= Duration::from_secs(self.limits.cpu_time.into());
+            if signal == Signal::SIGXCPU as i32
+                || (signal == Signal::SIGKILL as i32 && cpu_exhausted)
+            {
+                return (
+                    ExecutionStatus::CpuExceeded,
+                    Some(Error::CpuTimeout(self.limits.cpu_time)),
+                );
+            }
+            #[cfg(target_os = "linux")]
+            if signal == Signal::SIGSYS as i32 && self.limits.seccomp {
+                return (
+                    ExecutionStatus::RuntimeError(128 + signal),
+                    Some(Error::Sandbox(format!(
+                        "Process was killed for making a system call denied by the seccomp filter (stderr: {})",
+                        stderr
+                    ))),
+                );
+            }
         }
 
+        let code = exit_status
+            .code()
+            .or_else(|| exit_status.signal().map(|signal| 128 + signal))
+            .unwrap_or(-1);
+        (
+            ExecutionStatus::RuntimeError(code),
+            Some(Error::Sandbox(format!(
+                "Process exited with status: {} (stderr: {})",
+                exit_status, stderr
+            ))),
+        )
+    }
+
+    /// Statistics of the program's processes, with `usage`
+    fn process_stats(&self, usage: &libc::rusage) -> ProcessStats {
         let execution_time = self.start_time.unwrap().elapsed();
-        let usage = getrusage(UsageWho::RUSAGE_CHILDREN)
-            .map_err(|e| Error::Sandbox(format!("Failed to get resource usage: {}", e)))?;
-
-        Ok((
-            String::from_utf8_lossy(&output.1).to_string(),
-            String::from_utf8_lossy(&output.2).to_string(),
-            ProcessStats {
-                max_rss: usage.max_rss() as u64,
-                minor_page_faults: usage.minor_page_faults() as u64,
-                major_page_faults: usage.major_page_faults() as u64,
-                block_reads: usage.block_reads() as u64,
-                block_writes: usage.block_writes() as u64,
-                voluntary_context_switches: usage.voluntary_context_switches() as u64,
-                involuntary_context_switches: usage.involuntary_context_switches() as u64,
-                user_time: Duration::from_micros(
-                    (usage.user_time().tv_sec() as i64 * 1_000_000
-                        + usage.user_time().tv_usec() as i64) as u64,
-                ),
-                system_time: Duration::from_micros(
-                    (usage.system_time().tv_sec() as i64 * 1_000_000
-                        + usage.system_time().tv_usec() as i64) as u64,
-                ),
-                execution_time,
-            },
-        ))
+
+        ProcessStats {
+            max_rss: usage.ru_maxrss as u64,
+            minor_page_faults: usage.ru_minflt as u64,
+            major_page_faults: usage.ru_majflt as u64,
+            block_reads: usage.ru_inblock as u64,
+            block_writes: usage.ru_oublock as u64,
+            voluntary_context_switches: usage.ru_nvcsw as u64,
+            involuntary_context_switches: usage.ru_nivcsw as u64,
+            user_time: timeval_duration(usage.ru_utime),
+            system_time: timeval_duration(usage.ru_stime),
+            execution_time,
+            ..Default::default()
+        }
     }
 
-    /// Copy a system binary into the sandbox
-    async fn copy_binary(&self, cmd: &str) -> Result<PathBuf, Error> {
-        if let Ok(system_path) = which::which(cmd) {
-            let sandbox_bin = self.root_dir.join("bin").join(cmd);
-            fs::copy(&system_path, &sandbox_bin)
-                .await
-                .map_err(|e| Error::Sandbox(format!("Failed to copy binary {}: {}", cmd, e)))?;
+    /// Count the files and directories in the sandbox and the disk space they take
+    async fn disk_usage(&self) -> DiskUsage {
+        let root_dir = self.root_dir.clone();
+        let usage = tokio::task::spawn_blocking(move || disk_usage(&root_dir))
+            .await
+            .unwrap_or_default();
+        self.peak_disk_bytes
+            .fetch_max(usage.bytes, Ordering::Relaxed);
+        usage
+    }
 
-            // Make the binary executable
-            #[cfg(unix)]
-            {
-                use std::os::unix::fs::PermissionsExt;
-                let mut perms = fs::metadata(&sandbox_bin)
-                    .await
-                    .map_err(|e| Error::Sandbox(format!("Failed to get permissions: {}", e)))?
-                    .permissions();
-                perms.set_mode(0o755);
-                fs::set_permissions(&sandbox_bin, perms)
-                    .await
-                    .map_err(|e| Error::Sandbox(format!("Failed to set permissions: {}", e)))?;
+    /// Largest disk space the sandbox took while installing, compiling and running,
+    /// sampled by the disk space checks and once more now
+    pub async fn peak_disk_bytes(&self) -> u64 {
+        self.disk_usage().await;
+        self.peak_disk_bytes.load(Ordering::Relaxed)
+    }
+
+    /// Check that the program has not created more than `max_files` entries, counted
+    /// from `baseline_files`, and that the sandbox fits in `disk_space`
+    async fn check_disk_usage(&self, baseline_files: u64) -> Result<(), Error> {
+        let usage = self.disk_usage().await;
+        let created = usage.entries.saturating_sub(baseline_files);
+        if created > self.limits.max_files {
+            return Err(Error::ResourceExceeded {
+                resource: ResourceKind::Files,
+                limit: self.limits.max_files,
+                observed: created,
+            });
+        }
+        self.check_disk_space(usage)
+    }
+
+    fn check_disk_space(&self, usage: DiskUsage) -> Result<(), Error> {
+        if usage.bytes > self.limits.disk_space {
+            return Err(Error::ResourceExceeded {
+                resource: ResourceKind::Disk,
+                limit: self.limits.disk_space,
+                observed: usage.bytes,
+            });
+        }
+        Ok(())
+    }
+
+    /// Run a step writing to the sandbox outside of [`Sandbox::run`], such as installing
+    /// dependencies or compiling, failing it once the sandbox outgrows `disk_space` or
+    /// with [`Error::Cancelled`] once the execution is cancelled.
+    ///
+    /// `RLIMIT_FSIZE` only caps single files, so a build writing many files under that
+    /// size would otherwise fill the host's disk. The step is dropped when it fails,
+    /// which kills toolchain commands started with
+    /// [`run_output`](crate::languages::run_output).
+    pub(crate) async fn watch_step<F: Future>(&self, step: F) -> Result<F::Output, Error> {
+        tokio::pin!(step);
+        let mut disk_check = time::interval(BUILD_DISK_CHECK_INTERVAL);
+        loop {
+            tokio::select! {
+                output = &mut step => {
+                    self.check_disk_space(self.disk_usage().await)?;
+                    return Ok(output);
+                }
+                _ = disk_check.tick() => self.check_disk_space(self.disk_usage().await)?,
+                _ = self.cancel.cancelled() => return Err(Error::Cancelled),
             }
+        }
+    }
 
-            Ok(sandbox_bin)
-        } else {
-            Err(Error::Sandbox(format!("Command not found: {}", cmd)))
+    /// List the sandbox tree breadth-first, so shallow entries survive the bound
+    pub fn listing(&self, max_entries: usize) -> Vec<SandboxEntry> {
+        let mut entries = Vec::new();
+        let mut pending = VecDeque::from([PathBuf::new()]);
+
+        while let Some(relative) = pending.pop_front() {
+            let Ok(read_dir) = std::fs::read_dir(self.root_dir.join(&relative)) else {
+                continue;
+            };
+            let mut children: Vec<_> = read_dir.filter_map(|item| item.ok()).collect();
+            children.sort_by_key(|item| item.file_name());
+
+            for child in children {
+                if entries.len() >= max_entries {
+                    return entries;
+                }
+                let Ok(metadata) = child.metadata() else {
+                    continue;
+                };
+                let path = relative.join(child.file_name());
+                if metadata.is_dir() {
+                    entries.push(SandboxEntry {
+                        path: format!("{}/", path.display()),
+                        size: 0,
+                    });
+                    pending.push_back(path);
+                } else {
+                    entries.push(SandboxEntry {
+                        path: path.display().to_string(),
+                        size: metadata.len(),
+                    });
+                }
+            }
+        }
+
+        entries
+    }
+
+    /// User and group to run the program as, handing the sandbox over to them.
+    ///
+    /// Without root the server cannot switch users, so the program keeps the server's
+    /// user and a warning is logged.
+    async fn run_as(&self) -> Result<Option<(Uid, Gid)>, Error> {
+        let Some(uid) = self.limits.run_as_uid else {
+            return Ok(None);
+        };
+        let gid = self.limits.run_as_gid.unwrap_or(uid);
+        if !Uid::effective().is_root() {
+            warn!(
+                "Not running as root, so programs keep the server's user instead of uid {}",
+                uid
+            );
+            return Ok(None);
+        }
+
+        let (uid, gid) = (Uid::from_raw(uid), Gid::from_raw(gid));
+        let root_dir = self.root_dir.clone();
+        tokio::task::spawn_blocking(move || chown_tree(&root_dir, uid, gid))
+            .await
+            .map_err(|e| Error::Sandbox(format!("Failed to hand over sandbox: {}", e)))?
+            .map_err(|e| Error::Sandbox(format!("Failed to hand over sandbox: {}", e)))?;
+        Ok(Some((uid, gid)))
+    }
+
+    /// Check that the configured read-only mounts exist
+    #[cfg(target_os = "linux")]
+    fn check_readonly_mounts(&self) -> Result<(), Error> {
+        for path in &self.limits.readonly_mounts {
+            path.canonicalize().map_err(|e| {
+                Error::Sandbox(format!("Invalid read-only mount {}: {}", path.display(), e))
+            })?;
+        }
+        Ok(())
+    }
+}
+
+/// Length of a `timeval` from `rusage`
+fn timeval_duration(time: libc::timeval) -> Duration {
+    Duration::from_secs(time.tv_sec as u64) + Duration::from_micros(time.tv_usec as u64)
+}
+
+/// Wait for a program to exit, returning the resource usage of it and the descendants it
+/// waited for.
+///
+/// `getrusage(RUSAGE_CHILDREN)` would also count concurrent executions and earlier setup
+/// and build steps, so on Linux the exited child is read with `waitid(WNOWAIT)` first,
+/// which leaves it for `Child::wait` to reap.
+async fn wait_with_usage(
+    child: &mut Child,
+) -> std::io::Result<(std::process::ExitStatus, libc::rusage)> {
+    #[cfg(target_os = "linux")]
+    {
+        let pid = child
+            .id()
+            .ok_or_else(|| std::io::Error::other("Process was already reaped"))?;
+        let usage = tokio::task::spawn_blocking(move || exited_child_usage(pid))
+            .await
+            .map_err(std::io::Error::other)??;
+        Ok((child.wait().await?, usage))
+    }
+
+    // Only the usage of every child of this process together is available here
+    #[cfg(not(target_os = "linux"))]
+    {
+        let status = child.wait().await?;
+        let usage = getrusage(UsageWho::RUSAGE_CHILDREN)?;
+        Ok((status, *usage.as_ref()))
+    }
+}
+
+/// Block until the child `pid` exits and return its resource usage, without reaping it
+#[cfg(target_os = "linux")]
+fn exited_child_usage(pid: u32) -> std::io::Result<libc::rusage> {
+    let mut info: libc::siginfo_t = unsafe { std::mem::zeroed() };
+    let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+    loop {
+        // The libc wrapper of waitid has no rusage argument
+        let result = unsafe {
+            libc::syscall(
+                libc::SYS_waitid,
+                libc::P_PID as libc::c_long,
+                pid as libc::c_long,
+                &mut info as *mut libc::siginfo_t,
+                (libc::WEXITED | libc::WNOWAIT) as libc::c_long,
+                &mut usage as *mut libc::rusage,
+            )
+        };
+        match nix::errno::Errno::result(result) {
+            Ok(_) => return Ok(usage),
+            Err(nix::errno::Errno::EINTR) => continue,
+            Err(e) => return Err(e.into()),
         }
     }
 }
 
+/// Kill a process and all of its descendants.
+///
+/// The child leads its own process group, so signalling the group also takes down
+/// grandchildren such as the binary under `cargo run`.
+async fn kill_process_group(id: u32) {
+    let group = format!("-{}", id);
+
+    // Send SIGTERM and wait briefly for graceful shutdown
+    let _ = Command::new("kill")
+        .args(["-TERM", "--", &group])
+        .status()
+        .await;
+
+    // Give a very short grace period
+    tokio::time::sleep(Duration::from_millis(10)).await;
+
+    // Force kill with SIGKILL
+    let _ = Command::new("kill")
+        .args(["-KILL", "--", &group])
+        .status()
+        .await;
+}
+
+/// Switch the calling process to `uid` and `gid`, leaving no supplementary groups.
+///
+/// Runs between fork and exec, so it only makes system calls.
+fn drop_privileges(uid: Uid, gid: Gid) -> nix::Result<()> {
+    #[cfg(target_os = "linux")]
+    setgroups(&[gid])?;
+    setgid(gid)?;
+    setuid(uid)
+}
+
+/// Recursively change the owner of a directory and everything in it, without following
+/// symlinks
+fn chown_tree(path: &std::path::Path, uid: Uid, gid: Gid) -> std::io::Result<()> {
+    std::os::unix::fs::lchown(path, Some(uid.as_raw()), Some(gid.as_raw()))?;
+    if std::fs::symlink_metadata(path)?.is_dir() {
+        for entry in std::fs::read_dir(path)? {
+            chown_tree(&entry?.path(), uid, gid)?;
+        }
+    }
+    Ok(())
+}
+
+/// Give the calling process a private mount namespace in which every host mount is
+/// read-only, and the sandbox root is the only writable mount, without setuid or device
+/// files.
+///
+/// Runs between fork and exec, so it only makes system calls.
+#[cfg(target_os = "linux")]
+fn setup_mounts(root_dir: &CStr) -> nix::Result<()> {
+    unshare(CloneFlags::CLONE_NEWNS)?;
+    // Keep the mounts below from propagating back to the host
+    mount(
+        None::<&str>,
+        "/",
+        None::<&str>,
+        MsFlags::MS_REC | MsFlags::MS_PRIVATE,
+        None::<&str>,
+    )?;
+
+    // A read-only remount only applies to a single mount, mount_setattr also covers the
+    // ones below it, such as /tmp or /dev/shm
+    let attr = libc::mount_attr {
+        attr_set: libc::MOUNT_ATTR_RDONLY,
+        attr_clr: 0,
+        propagation: 0,
+        userns_fd: 0,
+    };
+    let result = unsafe {
+        libc::syscall(
+            libc::SYS_mount_setattr,
+            libc::AT_FDCWD,
+            c"/".as_ptr(),
+            libc::AT_RECURSIVE,
+            &attr as *const libc::mount_attr,
+            std::mem::size_of::<libc::mount_attr>(),
+        )
+    };
+    nix::errno::Errno::result(result)?;
+
+    // The bind mount starts out read-only like its parent, the remount makes it writable
+    mount(
+        Some(root_dir),
+        root_dir,
+        None::<&str>,
+        MsFlags::MS_BIND,
+        None::<&str>,
+    )?;
+    mount(
+        None::<&str>,
+        root_dir,
+        None::<&str>,
+        MsFlags::MS_BIND | MsFlags::MS_REMOUNT | MsFlags::MS_NOSUID | MsFlags::MS_NODEV,
+        None::<&str>,
+    )?;
+    // The working directory was entered before the bind mount and still points below it
+    nix::unistd::chdir(root_dir)
+}
+
+#[cfg(target_os = "linux")]
+fn path_to_cstring(path: &std::path::Path) -> Result<CString, Error> {
+    use std::os::unix::ffi::OsStrExt;
+    CString::new(path.as_os_str().as_bytes())
+        .map_err(|_| Error::Sandbox(format!("Invalid path: {}", path.display())))
+}
+
+/// Write each step of `script` to `stdin` once `stdout`, as read so far, shows its prompt
+async fn write_script(
+    mut stdin: ChildStdin,
+    script: Vec<InputStep>,
+    stdout: Arc<Mutex<Vec<u8>>>,
+) -> std::io::Result<()> {
+    // Prompts are looked for after the previous one, so a repeated prompt is waited for again
+    let mut searched = 0;
+    for step in script {
+        if let Some(prompt) = &step.wait_for {
+            let deadline = Instant::now() + INPUT_STEP_TIMEOUT;
+            loop {
+                let found = find_prompt(&stdout.lock().unwrap()[searched..], prompt.as_bytes());
+                match found {
+                    Some(end) => {
+                        searched += end;
+                        break;
+                    }
+                    None if Instant::now() >= deadline => {
+                        debug!("Prompt {:?} not printed, sending input anyway", prompt);
+                        break;
+                    }
+                    None => time::sleep(PROMPT_POLL_INTERVAL).await,
+                }
+            }
+        }
+        stdin.write_all(step.send.as_bytes()).await?;
+        stdin.flush().await?;
+    }
+    // Dropping stdin closes it to signal EOF
+    Ok(())
+}
+
+/// Offset in `output` just past the first occurrence of `prompt`
+fn find_prompt(output: &[u8], prompt: &[u8]) -> Option<usize> {
+    if prompt.is_empty() {
+        return Some(0);
+    }
+    output
+        .windows(prompt.len())
+        .position(|window| window == prompt)
+        .map(|start| start + prompt.len())
+}
+
+/// Read a pipe to its end into `buffer`, keeping one byte more than [`MAX_OUTPUT_SIZE`]
+/// so that truncation can be detected
+async fn read_pipe<R: AsyncRead + Unpin>(pipe: Option<R>, buffer: Arc<Mutex<Vec<u8>>>) {
+    let Some(mut pipe) = pipe else {
+        return;
+    };
+    let mut chunk = [0u8; 8192];
+    loop {
+        match pipe.read(&mut chunk).await {
+            Ok(0) | Err(_) => break,
+            Ok(n) => {
+                let mut buffer = buffer.lock().unwrap();
+                let room = (MAX_OUTPUT_SIZE + 1).saturating_sub(buffer.len());
+                buffer.extend_from_slice(&chunk[..n.min(room)]);
+            }
+        }
+    }
+}
+
+/// A stream's output, as [`capture_output`] decoded it
+struct CapturedOutput {
+    text: String,
+    /// The bytes as written, kept for [`OutputEncoding::Base64`]
+    raw: Option<Vec<u8>>,
+    /// Whether the output was cut off at [`MAX_OUTPUT_SIZE`] bytes
+    truncated: bool,
+}
+
+/// Decode captured output, cutting it off at [`MAX_OUTPUT_SIZE`] bytes.
+///
+/// With [`OutputEncoding::Strict`], output that is not UTF-8 fails with
+/// [`Error::InvalidOutput`], unless only a character cut off by the truncation is.
+fn capture_output(
+    bytes: &[u8],
+    encoding: OutputEncoding,
+    stream: &str,
+) -> Result<CapturedOutput, Error> {
+    let truncated = bytes.len() > MAX_OUTPUT_SIZE;
+    let bytes = &bytes[..bytes.len().min(MAX_OUTPUT_SIZE)];
+
+    let text = match (encoding, std::str::from_utf8(bytes)) {
+        (_, Ok(text)) => text.to_string(),
+        (OutputEncoding::Strict, Err(e)) if !(truncated && e.error_len().is_none()) => {
+            return Err(Error::InvalidOutput {
+                stream: stream.to_string(),
+                offset: e.valid_up_to(),
+            })
+        }
+        _ => String::from_utf8_lossy(bytes).to_string(),
+    };
+    Ok(CapturedOutput {
+        text,
+        raw: (encoding == OutputEncoding::Base64).then(|| bytes.to_vec()),
+        truncated,
+    })
+}
+
+/// Threads of every process `uid` runs, which the kernel counts against `RLIMIT_NPROC`
+#[cfg(target_os = "linux")]
+fn user_thread_count(uid: Uid) -> u64 {
+    let Ok(processes) = std::fs::read_dir("/proc") else {
+        return 0;
+    };
+    processes
+        .filter_map(|item| item.ok())
+        .filter(|item| {
+            item.file_name()
+                .to_str()
+                .is_some_and(|name| name.bytes().all(|b| b.is_ascii_digit()))
+        })
+        .filter(|item| item.metadata().is_ok_and(|m| m.uid() == uid.as_raw()))
+        .map(|item| std::fs::read_dir(item.path().join("task")).map_or(0, |t| t.count() as u64))
+        .sum()
+}
+
+/// Entries under a directory and the disk space they take
+#[derive(Debug, Clone, Copy, Default)]
+struct DiskUsage {
+    entries: u64,
+    /// Bytes allocated on disk, so sparse files only count the blocks they use
+    bytes: u64,
+}
+
+/// Recursively measure a directory, without following symlinks
+fn disk_usage(path: &std::path::Path) -> DiskUsage {
+    let mut usage = DiskUsage::default();
+    let Ok(read_dir) = std::fs::read_dir(path) else {
+        return usage;
+    };
+    for item in read_dir.filter_map(|item| item.ok()) {
+        usage.entries += 1;
+        let Ok(metadata) = item.metadata() else {
+            continue;
+        };
+        usage.bytes += metadata.blocks() * 512;
+        if metadata.is_dir() {
+            let nested = disk_usage(&item.path());
+            usage.entries += nested.entries;
+            usage.bytes += nested.bytes;
+        }
+    }
+    usage
+}
+
 impl Drop for Sandbox {
     fn drop(&mut self) {
-        // Clean up sandbox directory
-        if let Err(e) = std::fs::remove_dir_all(&self.root_dir) {
-            error!("Failed to clean up sandbox directory: {}", e);
+        if self.cleaned_up {
+            return;
+        }
+        // Best effort for sandboxes that were not cleaned up explicitly
+        match std::fs::remove_dir_all(&self.root_dir) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => {
+                LEAKED_SANDBOXES.fetch_add(1, Ordering::Relaxed);
+                error!("Failed to clean up sandbox directory: {}", e);
+            }
         }
     }
 }
@@ -0,0 +1,190 @@
+//! Vulnerability checks for requested dependencies, run before anything is installed
+
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::json;
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    error::Error,
+    types::{Dependency, Language},
+};
+
+/// Public OSV API
+pub const OSV_API_URL: &str = "https://api.osv.dev";
+
+/// How long an answer is reused before the API is asked again, so advisories published
+/// in the meantime are picked up
+const DEFAULT_CACHE_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// Known vulnerability of a package version
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Advisory {
+    /// Advisory identifier, e.g. `GHSA-x84v-xcm2-53pg` or `PYSEC-2018-28`
+    pub id: String,
+    #[serde(default)]
+    pub summary: String,
+}
+
+/// Check run on every dependency of a request before it is installed
+#[async_trait]
+pub trait DependencyScanner: Send + Sync {
+    /// Advisory affecting the requested version of `dependency`, if any
+    async fn scan(
+        &self,
+        language: Language,
+        dependency: &Dependency,
+    ) -> Result<Option<Advisory>, Error>;
+}
+
+/// Language, package name and version an advisory was looked up for
+type AdvisoryKey = (Language, String, String);
+
+/// Answer of the advisory API, kept until the cache TTL passes
+struct CachedAdvisory {
+    advisory: Option<Advisory>,
+    checked_at: Instant,
+}
+
+/// Scanner querying an [OSV](https://osv.dev) compatible API, caching its answers
+///
+/// OSV matches exact versions only, so dependencies requested with a range or without a
+/// version are not checked, and neither are git sources, unless
+/// [`with_require_exact`](Self::with_require_exact) refuses them instead.
+pub struct OsvScanner {
+    client: reqwest::Client,
+    url: String,
+    require_exact: bool,
+    cache_ttl: Duration,
+    cache: Mutex<HashMap<AdvisoryKey, CachedAdvisory>>,
+}
+
+#[derive(Deserialize)]
+struct OsvResponse {
+    #[serde(default)]
+    vulns: Vec<Advisory>,
+}
+
+impl OsvScanner {
+    /// Query the API at `url`, usually [`OSV_API_URL`]
+    pub fn new(url: &str) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            url: url.trim_end_matches('/').to_string(),
+            require_exact: false,
+            cache_ttl: DEFAULT_CACHE_TTL,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Fail dependencies that cannot be checked, because they request a range, no
+    /// version or a git source, with [`Error::InvalidDependency`] instead of letting
+    /// them through
+    pub fn with_require_exact(mut self, require_exact: bool) -> Self {
+        self.require_exact = require_exact;
+        self
+    }
+
+    /// Reuse answers for `ttl` instead of an hour
+    pub fn with_cache_ttl(mut self, ttl: Duration) -> Self {
+        self.cache_ttl = ttl;
+        self
+    }
+
+    /// OSV ecosystem the language's packages are published in
+    fn ecosystem(language: Language) -> Option<&'static str> {
+        match language {
+            Language::Python => Some("PyPI"),
+            Language::JavaScript | Language::TypeScript => Some("npm"),
+            Language::Rust => Some("crates.io"),
+            Language::Go => Some("Go"),
+            Language::Kotlin => Some("Maven"),
+            Language::Elixir => Some("Hex"),
+            Language::Bash | Language::Sql | Language::Custom => None,
+        }
+    }
+
+    /// Exact version requested, stripped of a leading `=`, `==` or `v`
+    fn exact_version(dependency: &Dependency) -> Option<&str> {
+        if dependency.source.is_some() {
+            return None;
+        }
+        let version = dependency
+            .version
+            .trim()
+            .trim_start_matches('=')
+            .trim_start_matches('v');
+        let exact = version.starts_with(|c: char| c.is_ascii_digit())
+            && version
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '+'));
+        exact.then_some(version)
+    }
+}
+
+impl Default for OsvScanner {
+    fn default() -> Self {
+        Self::new(OSV_API_URL)
+    }
+}
+
+#[async_trait]
+impl DependencyScanner for OsvScanner {
+    async fn scan(
+        &self,
+        language: Language,
+        dependency: &Dependency,
+    ) -> Result<Option<Advisory>, Error> {
+        let Some(ecosystem) = Self::ecosystem(language) else {
+            return Ok(None);
+        };
+        let Some(version) = Self::exact_version(dependency) else {
+            if self.require_exact {
+                return Err(Error::InvalidDependency(format!(
+                    "{} must request an exact version from the registry to be checked for \
+                     advisories",
+                    dependency.name
+                )));
+            }
+            return Ok(None);
+        };
+
+        let key = (language, dependency.name.clone(), version.to_string());
+        if let Some(cached) = self.cache.lock().unwrap().get(&key) {
+            if cached.checked_at.elapsed() < self.cache_ttl {
+                return Ok(cached.advisory.clone());
+            }
+        }
+
+        let response = self
+            .client
+            .post(format!("{}/v1/query", self.url))
+            .json(&json!({
+                "version": version,
+                "package": { "name": dependency.name, "ecosystem": ecosystem },
+            }))
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| Error::System(format!("Failed to query advisories: {}", e)))?
+            .json::<OsvResponse>()
+            .await
+            .map_err(|e| Error::System(format!("Invalid advisory response: {}", e)))?;
+
+        let advisory = response.vulns.into_iter().next();
+        let mut cache = self.cache.lock().unwrap();
+        cache.retain(|_, cached| cached.checked_at.elapsed() < self.cache_ttl);
+        cache.insert(
+            key,
+            CachedAdvisory {
+                advisory: advisory.clone(),
+                checked_at: Instant::now(),
+            },
+        );
+        Ok(advisory)
+    }
+}
@@ -0,0 +1,297 @@
+//! Seccomp-bpf system call filtering for native sandboxes
+//!
+//! The built-in profile denies every system call that is not on an allowlist covering
+//! what interpreters, compilers and their output need to run. Programs making any other
+//! call are killed with `SIGSYS`. A custom profile can be loaded from a file in the
+//! [seccompiler JSON format](https://github.com/rust-vmm/seccompiler/blob/main/docs/json_format.md).
+
+use nix::libc;
+use seccompiler::{
+    BpfProgram, SeccompAction, SeccompCmpArgLen, SeccompCmpOp, SeccompCondition, SeccompFilter,
+    SeccompRule, TargetArch,
+};
+use std::{collections::BTreeMap, path::Path};
+
+use crate::error::Error;
+
+/// System calls allowed on every architecture
+const ALLOWED_SYSCALLS: &[i64] = &[
+    // File descriptors and filesystem
+    libc::SYS_read,
+    libc::SYS_write,
+    libc::SYS_readv,
+    libc::SYS_writev,
+    libc::SYS_pread64,
+    libc::SYS_pwrite64,
+    libc::SYS_preadv,
+    libc::SYS_pwritev,
+    libc::SYS_lseek,
+    libc::SYS_openat,
+    libc::SYS_close,
+    libc::SYS_close_range,
+    libc::SYS_fstat,
+    libc::SYS_newfstatat,
+    libc::SYS_statx,
+    libc::SYS_statfs,
+    libc::SYS_fstatfs,
+    libc::SYS_faccessat,
+    libc::SYS_faccessat2,
+    libc::SYS_readlinkat,
+    libc::SYS_getdents64,
+    libc::SYS_getcwd,
+    libc::SYS_chdir,
+    libc::SYS_fchdir,
+    libc::SYS_mkdirat,
+    libc::SYS_unlinkat,
+    libc::SYS_renameat,
+    libc::SYS_renameat2,
+    libc::SYS_linkat,
+    libc::SYS_symlinkat,
+    libc::SYS_fchmod,
+    libc::SYS_fchmodat,
+    libc::SYS_fchown,
+    libc::SYS_fchownat,
+    libc::SYS_truncate,
+    libc::SYS_ftruncate,
+    libc::SYS_fallocate,
+    libc::SYS_fsync,
+    libc::SYS_fdatasync,
+    libc::SYS_flock,
+    libc::SYS_fcntl,
+    libc::SYS_ioctl,
+    libc::SYS_dup,
+    libc::SYS_dup3,
+    libc::SYS_pipe2,
+    libc::SYS_utimensat,
+    libc::SYS_fadvise64,
+    libc::SYS_sendfile,
+    libc::SYS_copy_file_range,
+    libc::SYS_memfd_create,
+    libc::SYS_umask,
+    // Memory
+    libc::SYS_brk,
+    libc::SYS_mmap,
+    libc::SYS_munmap,
+    libc::SYS_mremap,
+    libc::SYS_mprotect,
+    libc::SYS_madvise,
+    libc::SYS_msync,
+    libc::SYS_mincore,
+    libc::SYS_membarrier,
+    // Processes and threads
+    libc::SYS_execve,
+    libc::SYS_execveat,
+    libc::SYS_clone,
+    libc::SYS_clone3,
+    libc::SYS_exit,
+    libc::SYS_exit_group,
+    libc::SYS_wait4,
+    libc::SYS_waitid,
+    libc::SYS_getpid,
+    libc::SYS_getppid,
+    libc::SYS_gettid,
+    libc::SYS_getuid,
+    libc::SYS_geteuid,
+    libc::SYS_getgid,
+    libc::SYS_getegid,
+    libc::SYS_getresuid,
+    libc::SYS_getresgid,
+    libc::SYS_getgroups,
+    libc::SYS_getpgid,
+    libc::SYS_setpgid,
+    libc::SYS_getsid,
+    libc::SYS_setsid,
+    libc::SYS_set_tid_address,
+    libc::SYS_set_robust_list,
+    libc::SYS_get_robust_list,
+    libc::SYS_rseq,
+    libc::SYS_prctl,
+    libc::SYS_prlimit64,
+    libc::SYS_getrusage,
+    libc::SYS_capget,
+    libc::SYS_getpriority,
+    libc::SYS_sched_yield,
+    libc::SYS_sched_getaffinity,
+    libc::SYS_sched_getparam,
+    libc::SYS_sched_getscheduler,
+    libc::SYS_uname,
+    libc::SYS_sysinfo,
+    libc::SYS_getrandom,
+    libc::SYS_futex,
+    libc::SYS_futex_waitv,
+    // Time
+    libc::SYS_nanosleep,
+    libc::SYS_clock_nanosleep,
+    libc::SYS_clock_gettime,
+    libc::SYS_clock_getres,
+    libc::SYS_gettimeofday,
+    libc::SYS_times,
+    // Signals
+    libc::SYS_rt_sigaction,
+    libc::SYS_rt_sigprocmask,
+    libc::SYS_rt_sigreturn,
+    libc::SYS_rt_sigsuspend,
+    libc::SYS_rt_sigtimedwait,
+    libc::SYS_sigaltstack,
+    libc::SYS_kill,
+    libc::SYS_tgkill,
+    libc::SYS_tkill,
+    // Event loops
+    libc::SYS_epoll_create1,
+    libc::SYS_epoll_ctl,
+    libc::SYS_epoll_pwait,
+    libc::SYS_epoll_pwait2,
+    libc::SYS_ppoll,
+    libc::SYS_pselect6,
+    libc::SYS_eventfd2,
+    libc::SYS_signalfd4,
+    libc::SYS_timerfd_create,
+    libc::SYS_timerfd_settime,
+    libc::SYS_timerfd_gettime,
+    libc::SYS_inotify_init1,
+    libc::SYS_inotify_add_watch,
+    libc::SYS_inotify_rm_watch,
+    // Sockets, see `socket_rules` for which kinds may be created
+    libc::SYS_socketpair,
+    libc::SYS_connect,
+    libc::SYS_bind,
+    libc::SYS_listen,
+    libc::SYS_accept4,
+    libc::SYS_getsockname,
+    libc::SYS_getpeername,
+    libc::SYS_sendto,
+    libc::SYS_recvfrom,
+    libc::SYS_sendmsg,
+    libc::SYS_recvmsg,
+    libc::SYS_sendmmsg,
+    libc::SYS_recvmmsg,
+    libc::SYS_setsockopt,
+    libc::SYS_getsockopt,
+    libc::SYS_shutdown,
+];
+
+/// Legacy system calls only present on x86_64, still used by older libcs and runtimes
+#[cfg(target_arch = "x86_64")]
+const ALLOWED_LEGACY_SYSCALLS: &[i64] = &[
+    libc::SYS_open,
+    libc::SYS_creat,
+    libc::SYS_stat,
+    libc::SYS_lstat,
+    libc::SYS_access,
+    libc::SYS_readlink,
+    libc::SYS_getdents,
+    libc::SYS_mkdir,
+    libc::SYS_rmdir,
+    libc::SYS_unlink,
+    libc::SYS_rename,
+    libc::SYS_link,
+    libc::SYS_symlink,
+    libc::SYS_chmod,
+    libc::SYS_chown,
+    libc::SYS_lchown,
+    libc::SYS_utimes,
+    libc::SYS_pipe,
+    libc::SYS_dup2,
+    libc::SYS_poll,
+    libc::SYS_select,
+    libc::SYS_epoll_create,
+    libc::SYS_epoll_wait,
+    libc::SYS_eventfd,
+    libc::SYS_signalfd,
+    libc::SYS_inotify_init,
+    libc::SYS_fork,
+    libc::SYS_vfork,
+    libc::SYS_getpgrp,
+    libc::SYS_getrlimit,
+    libc::SYS_arch_prctl,
+    libc::SYS_alarm,
+    libc::SYS_pause,
+    libc::SYS_time,
+    libc::SYS_accept,
+];
+
+#[cfg(not(target_arch = "x86_64"))]
+const ALLOWED_LEGACY_SYSCALLS: &[i64] = &[];
+
+/// Compile the filter for `profile`, or the built-in profile when none is given.
+///
+/// Filters are compiled before the program is spawned, so that installing one in the
+/// child only takes a couple of system calls.
+pub fn load_filter(profile: Option<&Path>) -> Result<BpfProgram, Error> {
+    let arch = target_arch()?;
+
+    let Some(path) = profile else {
+        return default_filter(arch);
+    };
+
+    let file = std::fs::File::open(path).map_err(|e| {
+        Error::Sandbox(format!(
+            "Failed to open seccomp profile {}: {}",
+            path.display(),
+            e
+        ))
+    })?;
+    let filters = seccompiler::compile_from_json(file, arch)
+        .map_err(|e| Error::Sandbox(format!("Invalid seccomp profile: {}", e)))?;
+
+    if filters.len() != 1 {
+        return Err(Error::Sandbox(format!(
+            "Seccomp profile must define exactly one filter, found {}",
+            filters.len()
+        )));
+    }
+    Ok(filters.into_values().next().unwrap_or_default())
+}
+
+/// Install a compiled filter on the calling process.
+///
+/// Meant to be called from `pre_exec`, so the filter is inherited across `execve` by
+/// the program and everything it spawns.
+pub(crate) fn apply(filter: &BpfProgram) -> std::io::Result<()> {
+    seccompiler::apply_filter(filter)
+        .map_err(|e| std::io::Error::other(format!("Failed to install seccomp filter: {}", e)))
+}
+
+/// Built-in default-deny profile
+fn default_filter(arch: TargetArch) -> Result<BpfProgram, Error> {
+    let mut rules: BTreeMap<i64, Vec<SeccompRule>> = ALLOWED_SYSCALLS
+        .iter()
+        .chain(ALLOWED_LEGACY_SYSCALLS)
+        .map(|&syscall| (syscall, Vec::new()))
+        .collect();
+    rules.insert(libc::SYS_socket, socket_rules()?);
+
+    SeccompFilter::new(
+        rules,
+        SeccompAction::KillProcess,
+        SeccompAction::Allow,
+        arch,
+    )
+    .and_then(BpfProgram::try_from)
+    .map_err(|e| Error::Sandbox(format!("Failed to compile seccomp filter: {}", e)))
+}
+
+/// Only stream and datagram sockets may be created, which rules out raw sockets
+fn socket_rules() -> Result<Vec<SeccompRule>, Error> {
+    // The socket type shares its argument with SOCK_NONBLOCK and SOCK_CLOEXEC
+    const SOCK_TYPE_MASK: u64 = 0xf;
+
+    [libc::SOCK_STREAM, libc::SOCK_DGRAM]
+        .into_iter()
+        .map(|kind| {
+            SeccompCondition::new(
+                1,
+                SeccompCmpArgLen::Dword,
+                SeccompCmpOp::MaskedEq(SOCK_TYPE_MASK),
+                kind as u64,
+            )
+            .and_then(|condition| SeccompRule::new(vec![condition]))
+        })
+        .collect::<Result<_, _>>()
+        .map_err(|e| Error::Sandbox(format!("Failed to build socket rules: {}", e)))
+}
+
+fn target_arch() -> Result<TargetArch, Error> {
+    TargetArch::try_from(std::env::consts::ARCH)
+        .map_err(|e| Error::Sandbox(format!("Seccomp is not supported here: {}", e)))
+}
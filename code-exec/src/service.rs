@@ -1,19 +1,105 @@
-use std::sync::Arc;
-use tokio::sync::Semaphore;
-use tracing::{debug, error, info};
+use serde::Serialize;
+use std::{
+    collections::{HashMap, HashSet},
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+use tokio::sync::{Semaphore, SemaphorePermit};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, info, warn};
 
 use crate::{
+    cache::DependencyCacheConfig,
     error::Error,
     executor::CodeExecutor,
-    sandbox::Sandbox,
-    types::{ExecutionRequest, ExecutionResult, ResourceLimits},
+    policy::{PolicyDecision, RegexPolicy, SubmissionPolicy},
+    sandbox::{leaked_sandboxes, KeptSandboxes, Sandbox},
+    scanner::DependencyScanner,
+    types::{
+        Dependency, ExecutionRequest, ExecutionResult, ExecutionStatus, Language, ResourceLimits,
+        SourceFile, WarmupResult,
+    },
 };
 
+/// Default cap on the size of a request's code and of its input (bytes)
+pub const DEFAULT_MAX_SOURCE_BYTES: usize = 1024 * 1024; // 1MB
+
+/// Languages whose imports [`CodeExecutionService::with_banned_imports`] can check
+pub const IMPORT_CHECKED_LANGUAGES: [Language; 4] = [
+    Language::Python,
+    Language::JavaScript,
+    Language::TypeScript,
+    Language::Go,
+];
+
+/// Line the programs run by [`CodeExecutionService::self_test`] print
+const SELF_TEST_OUTPUT: &str = "self-test ok";
+
+/// Program printing [`SELF_TEST_OUTPUT`] in `language`, `None` for custom languages
+fn self_test_program(language: Language) -> Option<&'static str> {
+    let program = match language {
+        Language::Python => r#"print("self-test ok")"#,
+        Language::JavaScript => r#"console.log("self-test ok");"#,
+        Language::TypeScript => r#"const message: string = "self-test ok"; console.log(message);"#,
+        Language::Rust => r#"fn main() { println!("self-test ok"); }"#,
+        Language::Go => concat!(
+            "package main\n",
+            "import \"fmt\"\n",
+            r#"func main() { fmt.Println("self-test ok") }"#,
+        ),
+        Language::Kotlin => r#"fun main() { println("self-test ok") }"#,
+        Language::Bash => r#"echo "self-test ok""#,
+        Language::Sql => "SELECT 'self-test ok' AS result;",
+        Language::Elixir => r#"IO.puts("self-test ok")"#,
+        Language::Custom => return None,
+    };
+    Some(program)
+}
+
+/// Snapshot of a service's state
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ServiceStats {
+    /// Executions that can start without waiting
+    pub available_slots: usize,
+    /// Executions holding a slot, from acquiring it until their sandbox is removed
+    pub active_executions: usize,
+    /// Sandbox directories that could not be removed after their execution
+    pub leaked_sandboxes: usize,
+}
+
+/// Clones share the concurrency limits and counters of the service they were cloned
+/// from, so a limit holds across every clone handed to a task
 #[derive(Clone)]
 pub struct CodeExecutionService {
     executor: Arc<CodeExecutor>,
     semaphore: Arc<Semaphore>,
-    resource_limits: ResourceLimits,
+    /// Number of executions holding a slot
+    active: Arc<AtomicUsize>,
+    /// Caps on concurrent executions of single languages, taken before the global slot
+    language_semaphores: HashMap<Language, Arc<Semaphore>>,
+    /// Fixed limits for every execution; `None` selects per-language defaults
+    resource_limits: Option<ResourceLimits>,
+    /// Whether [`Language::Bash`] requests are accepted
+    allow_shell: bool,
+    /// Programs [`Language::Custom`] requests may run, which are rejected if there are none
+    custom_programs: HashSet<String>,
+    /// Modules programs must not import, per language
+    banned_imports: HashMap<Language, Vec<String>>,
+    /// Toolchain directories searched before the system's, per language
+    toolchain_paths: HashMap<Language, Vec<PathBuf>>,
+    /// Largest `code` and `input` accepted, checked before anything is written to disk
+    max_source_bytes: usize,
+    /// Consulted before a submission gets a sandbox
+    policy: Arc<dyn SubmissionPolicy>,
+    /// Checks dependencies for known vulnerabilities before they are installed
+    scanner: Option<Arc<dyn DependencyScanner>>,
+    /// Where the sandboxes of failed executions go instead of being removed, if anywhere
+    kept_sandboxes: Option<Arc<KeptSandboxes>>,
 }
 
 impl CodeExecutionService {
@@ -26,17 +112,319 @@ impl CodeExecutionService {
         Ok(Self {
             executor: Arc::new(executor),
             semaphore: Arc::new(Semaphore::new(max_concurrent_executions)),
-            resource_limits: resource_limits,
+            active: Arc::new(AtomicUsize::new(0)),
+            language_semaphores: HashMap::new(),
+            resource_limits: Some(resource_limits),
+            allow_shell: false,
+            custom_programs: HashSet::new(),
+            banned_imports: HashMap::new(),
+            toolchain_paths: HashMap::new(),
+            max_source_bytes: DEFAULT_MAX_SOURCE_BYTES,
+            policy: Arc::new(RegexPolicy::default()),
+            scanner: None,
+            kept_sandboxes: None,
         })
     }
 
+    /// Create a service that picks limits per request via [`ResourceLimits::for_language`]
+    pub async fn with_language_defaults(max_concurrent_executions: usize) -> Result<Self, Error> {
+        let executor = CodeExecutor::new().await?;
+
+        Ok(Self {
+            executor: Arc::new(executor),
+            semaphore: Arc::new(Semaphore::new(max_concurrent_executions)),
+            active: Arc::new(AtomicUsize::new(0)),
+            language_semaphores: HashMap::new(),
+            resource_limits: None,
+            allow_shell: false,
+            custom_programs: HashSet::new(),
+            banned_imports: HashMap::new(),
+            toolchain_paths: HashMap::new(),
+            max_source_bytes: DEFAULT_MAX_SOURCE_BYTES,
+            policy: Arc::new(RegexPolicy::default()),
+            scanner: None,
+            kept_sandboxes: None,
+        })
+    }
+
+    /// Reuse installed dependencies across executions with identical dependency sets
+    pub async fn with_dependency_cache(
+        mut self,
+        config: DependencyCacheConfig,
+    ) -> Result<Self, Error> {
+        self.executor = Arc::new(CodeExecutor::with_dependency_cache(config).await?);
+        Ok(self)
+    }
+
+    /// Pin the toolchain version used for each language in `versions`
+    pub fn with_language_versions(mut self, versions: HashMap<Language, String>) -> Self {
+        let executor = (*self.executor).clone().with_language_versions(versions);
+        self.executor = Arc::new(executor);
+        self
+    }
+
+    /// Share one Cargo target directory between Rust builds, see
+    /// [`CodeExecutor::with_rust_target_dir`].
+    ///
+    /// Builds run as the service's user and their output is linked into later programs,
+    /// so programs must run as another user, set by [`ResourceLimits::run_as_uid`], and
+    /// the directory is made private to the service's user.
+    pub fn with_rust_target_dir(mut self, target_dir: PathBuf) -> Result<Self, Error> {
+        if self
+            .resource_limits
+            .as_ref()
+            .and_then(|limits| limits.run_as_uid)
+            .is_none()
+        {
+            return Err(Error::Validation(
+                "A shared Rust target directory requires run_as_uid, so programs cannot \
+                 write to it"
+                    .to_string(),
+            ));
+        }
+        std::fs::create_dir_all(&target_dir)
+            .and_then(|()| {
+                std::fs::set_permissions(&target_dir, std::fs::Permissions::from_mode(0o700))
+            })
+            .map_err(|e| {
+                Error::System(format!("Failed to create {}: {}", target_dir.display(), e))
+            })?;
+
+        let executor = (*self.executor).clone().with_rust_target_dir(target_dir);
+        self.executor = Arc::new(executor);
+        Ok(self)
+    }
+
+    /// Only run requests whose dependencies are already in the dependency cache, see
+    /// [`CodeExecutor::with_offline`]
+    pub fn with_offline(mut self, offline: bool) -> Self {
+        let executor = (*self.executor).clone().with_offline(offline);
+        self.executor = Arc::new(executor);
+        self
+    }
+
+    /// See [`CodeExecutor::with_fixtures_dir`]
+    pub fn with_fixtures_dir(mut self, dir: &Path) -> Self {
+        let executor = (*self.executor).clone().with_fixtures_dir(dir);
+        self.executor = Arc::new(executor);
+        self
+    }
+
+    /// Run at most `limits[language]` executions of each listed language at once, on top
+    /// of the service-wide limit
+    pub fn with_language_concurrency(mut self, limits: HashMap<Language, usize>) -> Self {
+        self.language_semaphores = limits
+            .into_iter()
+            .map(|(language, limit)| (language, Arc::new(Semaphore::new(limit))))
+            .collect();
+        self
+    }
+
+    /// Accept shell scripts, which are rejected by default.
+    ///
+    /// Scripts always run with the seccomp filter, whatever the configured limits say.
+    pub fn with_shell(mut self, allow_shell: bool) -> Self {
+        self.allow_shell = allow_shell;
+        self
+    }
+
+    /// Accept [`Language::Custom`] requests whose commands only run `programs`, looked up
+    /// on the `PATH`. Custom languages are rejected by default.
+    ///
+    /// Setup and compile commands run in the sandbox under the execution's limits, like
+    /// the program itself, as allowed programs can still run code the request chose.
+    pub fn with_custom_programs(mut self, programs: Vec<String>) -> Self {
+        self.custom_programs = programs.into_iter().collect();
+        self
+    }
+
+    /// Reject requests importing any of `banned_imports[language]`, or anything inside
+    /// them, e.g. `os` also covers `os.path` and `from os import system`, which is
+    /// reported as `os.system`.
+    ///
+    /// Imports are found by the ldm analyzers of [`IMPORT_CHECKED_LANGUAGES`], lists for
+    /// other languages are ignored. Programs can still load modules dynamically, e.g.
+    /// with `__import__`, so pair this with the submission policy.
+    pub fn with_banned_imports(mut self, banned_imports: HashMap<Language, Vec<String>>) -> Self {
+        self.banned_imports = banned_imports;
+        self
+    }
+
+    /// Run programs of a language with its `toolchain_paths` searched first, for toolchains
+    /// installed outside the system directories, e.g. a pinned Go under `/opt/go/bin`.
+    /// They go ahead of [`ResourceLimits::toolchain_paths`] set for every language.
+    pub fn with_toolchain_paths(
+        mut self,
+        toolchain_paths: HashMap<Language, Vec<PathBuf>>,
+    ) -> Self {
+        self.toolchain_paths = toolchain_paths;
+        self
+    }
+
+    /// Reject requests whose code, fixtures or input is over `max_source_bytes`
+    pub fn with_max_source_bytes(mut self, max_source_bytes: usize) -> Self {
+        self.max_source_bytes = max_source_bytes;
+        self
+    }
+
+    /// Replace the default policy, [`RegexPolicy`] with the built-in rules
+    pub fn with_submission_policy(mut self, policy: Arc<dyn SubmissionPolicy>) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Refuse requests with a dependency `scanner` reports a vulnerability for.
+    ///
+    /// Requests also fail if the scanner cannot be reached, rather than installing
+    /// unchecked packages.
+    pub fn with_dependency_scanner(mut self, scanner: Arc<dyn DependencyScanner>) -> Self {
+        self.scanner = Some(scanner);
+        self
+    }
+
+    /// Keep the sandbox directory of executions that fail with an error for `ttl`, and
+    /// report its path with [`Error::WithKeptSandbox`], so what the program left behind
+    /// can be inspected. A background task removes the directories once `ttl` passes.
+    ///
+    /// Must be called within a Tokio runtime.
+    pub fn with_keep_failed_sandboxes(mut self, ttl: Duration) -> Self {
+        self.kept_sandboxes = Some(KeptSandboxes::new(ttl));
+        self
+    }
+
+    pub fn max_source_bytes(&self) -> usize {
+        self.max_source_bytes
+    }
+
+    /// Languages requests may use, which leaves out Bash unless shell scripts are allowed
+    /// and custom languages unless some programs are
+    pub fn accepted_languages(&self) -> Vec<Language> {
+        Language::ALL
+            .into_iter()
+            .filter(|&language| match language {
+                Language::Bash => self.allow_shell,
+                Language::Custom => !self.custom_programs.is_empty(),
+                _ => true,
+            })
+            .collect()
+    }
+
+    /// Check that a request's custom commands, if it has any, only run allowed programs
+    fn check_custom(&self, request: &ExecutionRequest) -> Result<(), Error> {
+        match (&request.custom, request.language) {
+            (_, Language::Custom) if self.custom_programs.is_empty() => {
+                Err(Error::UnsupportedLanguage(
+                    "custom languages are disabled on this service".to_string(),
+                ))
+            }
+            (Some(custom), Language::Custom) => custom.validate(&self.custom_programs),
+            (None, Language::Custom) => Err(Error::Validation(
+                "Custom language requests need the commands to run them with".to_string(),
+            )),
+            (Some(_), language) => Err(Error::Validation(format!(
+                "Custom commands cannot be used with {} programs",
+                language
+            ))),
+            (None, _) => Ok(()),
+        }
+    }
+
+    /// Refuse requests importing a module banned for their language
+    fn check_imports(&self, request: &ExecutionRequest) -> Result<(), Error> {
+        let Some(banned) = self.banned_imports.get(&request.language) else {
+            return Ok(());
+        };
+        let registry = ldm::AnalyzerRegistry::shared();
+        let Some(analyzer) = registry.analyzer(request.language.as_str()) else {
+            return Ok(());
+        };
+
+        let sources =
+            std::iter::once(&request.code).chain(request.files.iter().map(|f| &f.contents));
+        for module in sources.flat_map(|source| analyzer.imports(source)) {
+            let is_banned = |banned: &String| {
+                module
+                    .strip_prefix(banned.as_str())
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with(['.', '/']))
+            };
+            if banned.iter().any(is_banned) {
+                return Err(Error::BannedImport { module });
+            }
+        }
+        Ok(())
+    }
+
+    /// Check a request's code, fixtures and input against the size limit, counting the
+    /// contents of all files as code
+    pub fn check_source_size(
+        &self,
+        code: &str,
+        files: &[SourceFile],
+        fixtures: &[SourceFile],
+        input: Option<&str>,
+    ) -> Result<(), Error> {
+        let code_size = code.len() + files.iter().map(|f| f.contents.len()).sum::<usize>();
+        let fixtures_size = fixtures.iter().map(|f| f.contents.len()).sum();
+        let fields = [
+            ("code", code_size),
+            ("fixtures", fixtures_size),
+            ("input", input.map_or(0, str::len)),
+        ];
+        for (field, size) in fields {
+            if size > self.max_source_bytes {
+                return Err(Error::SourceTooLarge {
+                    field,
+                    size,
+                    limit: self.max_source_bytes,
+                });
+            }
+        }
+        Ok(())
+    }
+
     pub async fn execute(&self, request: ExecutionRequest) -> Result<ExecutionResult, Error> {
-        // Acquire execution permit
-        let _permit = self
-            .semaphore
-            .acquire()
+        self.execute_cancellable(request, CancellationToken::new())
             .await
-            .map_err(|e| Error::System(format!("Failed to acquire execution permit: {}", e)))?;
+    }
+
+    /// Execute a request, stopping it with [`Error::Cancelled`] once `cancel` fires.
+    ///
+    /// Cancelling while the request waits for a slot gives up waiting, cancelling later
+    /// kills the running setup, install or compile step or the program's process group,
+    /// and frees the slot.
+    pub async fn execute_cancellable(
+        &self,
+        request: ExecutionRequest,
+        cancel: CancellationToken,
+    ) -> Result<ExecutionResult, Error> {
+        self.check_shell(request.language)?;
+        self.check_custom(&request)?;
+        request.validate_files()?;
+        request.validate_input()?;
+        request.validate_dependencies()?;
+        let limits = self.limits(request.language);
+        limits.env_policy.validate(&request.env_vars)?;
+        self.check_source_size(
+            &request.code,
+            &request.files,
+            &request.fixtures,
+            request.stdin().as_deref(),
+        )?;
+        let sources =
+            std::iter::once(&request.code).chain(request.files.iter().map(|f| &f.contents));
+        for source in sources {
+            if let PolicyDecision::Reject { rule } = self.policy.check(request.language, source) {
+                return Err(Error::PolicyRejected { rule });
+            }
+        }
+        self.check_imports(&request)?;
+        self.scan_dependencies(&request).await?;
+
+        let _permits = tokio::select! {
+            permits = self.acquire_permits(request.language) => permits?,
+            _ = cancel.cancelled() => return Err(Error::Cancelled),
+        };
+        let _active = ActiveExecution::new(&self.active);
 
         debug!(
             "Starting code execution for language: {:?}",
@@ -44,7 +432,7 @@ impl CodeExecutionService {
         );
 
         // Create new sandbox for this execution
-        let mut sandbox = Sandbox::new(self.resource_limits.clone()).await?;
+        let mut sandbox = Sandbox::new(limits).await?.with_cancellation(cancel);
 
         // Execute using shared executor but with isolated sandbox
         let result = self
@@ -52,6 +440,23 @@ impl CodeExecutionService {
             .execute_in_sandbox(request, &mut sandbox)
             .await;
 
+        let result = match (result, &self.kept_sandboxes) {
+            (Err(error), Some(kept)) => {
+                let path = kept.keep(sandbox);
+                warn!("Kept sandbox of failed execution at {}", path.display());
+                Err(Error::WithKeptSandbox {
+                    error: Box::new(error),
+                    path,
+                })
+            }
+            (result, _) => {
+                if let Err(e) = sandbox.cleanup().await {
+                    warn!("{}", e);
+                }
+                result
+            }
+        };
+
         match &result {
             Ok(_) => info!("Code execution completed successfully"),
             Err(e) => error!("Code execution failed: {}", e),
@@ -60,15 +465,203 @@ impl CodeExecutionService {
         result
     }
 
+    /// Install `language`'s toolchain and `dependencies` ahead of a burst of requests,
+    /// without running any code. The dependency set is left in the dependency cache, so
+    /// executions with the same set restore it instead of installing it again.
+    ///
+    /// Warming up takes an execution slot. Dependencies of languages the cache does not
+    /// keep, or of a service without a cache, fail with [`Error::Validation`].
+    pub async fn warmup(
+        &self,
+        language: Language,
+        dependencies: Vec<Dependency>,
+    ) -> Result<WarmupResult, Error> {
+        self.check_shell(language)?;
+        if language == Language::Custom {
+            return Err(Error::Validation(
+                "custom languages have no toolchain to warm up".to_string(),
+            ));
+        }
+        let request = ExecutionRequest {
+            language,
+            code: String::new(),
+            dependencies,
+            timeout: Duration::ZERO,
+            ..Default::default()
+        };
+        request.validate_dependencies()?;
+        self.scan_dependencies(&request).await?;
+
+        let _permits = self.acquire_permits(language).await?;
+        let _active = ActiveExecution::new(&self.active);
+        debug!("Warming up {} with {:?}", language, request.dependencies);
+
+        let sandbox = Sandbox::new(self.limits(language)).await?;
+        let result = self.executor.warmup(&request, &sandbox).await;
+        if let Err(e) = sandbox.cleanup().await {
+            warn!("{}", e);
+        }
+        result
+    }
+
+    /// Refuse [`Language::Bash`] unless the service accepts shell scripts
+    fn check_shell(&self, language: Language) -> Result<(), Error> {
+        if language == Language::Bash && !self.allow_shell {
+            return Err(Error::UnsupportedLanguage(
+                "shell execution is disabled on this service".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Limits of an execution in `language`
+    fn limits(&self, language: Language) -> ResourceLimits {
+        let mut limits = self
+            .resource_limits
+            .clone()
+            .unwrap_or_else(|| ResourceLimits::for_language(language));
+        if language == Language::Bash {
+            limits.seccomp = true;
+        }
+        if let Some(paths) = self.toolchain_paths.get(&language) {
+            limits.toolchain_paths.splice(0..0, paths.iter().cloned());
+        }
+        limits
+    }
+
+    /// Refuse a request with a dependency the scanner knows a vulnerability in
+    async fn scan_dependencies(&self, request: &ExecutionRequest) -> Result<(), Error> {
+        if let Some(scanner) = &self.scanner {
+            for dependency in &request.dependencies {
+                if let Some(advisory) = scanner.scan(request.language, dependency).await? {
+                    return Err(Error::VulnerableDependency {
+                        package: format!("{}@{}", dependency.name, dependency.version),
+                        advisory: advisory.id,
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Wait for a slot of `language`, if it has a cap, and then for a service-wide one.
+    ///
+    /// The language's slot comes first so requests queued behind a busy language do not
+    /// hold slots other languages could use.
+    async fn acquire_permits(
+        &self,
+        language: Language,
+    ) -> Result<(Option<SemaphorePermit<'_>>, SemaphorePermit<'_>), Error> {
+        let acquire_failed =
+            |e| Error::System(format!("Failed to acquire execution permit: {}", e));
+        let language_permit = match self.language_semaphores.get(&language) {
+            Some(semaphore) => Some(semaphore.acquire().await.map_err(acquire_failed)?),
+            None => None,
+        };
+        let permit = self.semaphore.acquire().await.map_err(acquire_failed)?;
+        Ok((language_permit, permit))
+    }
+
+    /// Run a known program in every accepted language at once, to find missing or broken
+    /// toolchains before requests arrive. Results are in the order of
+    /// [`accepted_languages`](Self::accepted_languages).
+    ///
+    /// Each language is cancelled once `timeout` passes, including while it compiles.
+    /// Custom languages have no known program and are left out.
+    pub async fn self_test(&self, timeout: Duration) -> Vec<(Language, Result<(), Error>)> {
+        let tests: Vec<_> = self
+            .accepted_languages()
+            .into_iter()
+            .filter_map(|language| Some((language, self_test_program(language)?)))
+            .map(|(language, program)| {
+                let service = self.clone();
+                let test = tokio::spawn(async move {
+                    service.self_test_language(language, program, timeout).await
+                });
+                (language, test)
+            })
+            .collect();
+
+        let mut results = Vec::with_capacity(tests.len());
+        for (language, test) in tests {
+            let result = test
+                .await
+                .unwrap_or_else(|e| Err(Error::System(format!("Self-test panicked: {}", e))));
+            results.push((language, result));
+        }
+        results
+    }
+
+    async fn self_test_language(
+        &self,
+        language: Language,
+        program: &str,
+        timeout: Duration,
+    ) -> Result<(), Error> {
+        let request = ExecutionRequest {
+            language,
+            code: program.to_string(),
+            timeout,
+            ..Default::default()
+        };
+
+        let cancel = CancellationToken::new();
+        let timer = tokio::spawn({
+            let cancel = cancel.clone();
+            async move {
+                tokio::time::sleep(timeout).await;
+                cancel.cancel();
+            }
+        });
+        let result = self.execute_cancellable(request, cancel).await;
+        timer.abort();
+
+        let result = match result {
+            Err(Error::Cancelled) => return Err(Error::WallTimeout(timeout.as_secs())),
+            result => result?,
+        };
+        if result.status != ExecutionStatus::Success || !result.stdout.contains(SELF_TEST_OUTPUT) {
+            return Err(Error::ExecutionError(format!(
+                "Self-test program ended with status {}: {}",
+                result.status,
+                result.stderr.trim()
+            )));
+        }
+        Ok(())
+    }
+
     pub fn get_available_slots(&self) -> usize {
         self.semaphore.available_permits()
     }
+
+    pub fn stats(&self) -> ServiceStats {
+        ServiceStats {
+            available_slots: self.get_available_slots(),
+            active_executions: self.active.load(Ordering::SeqCst),
+            leaked_sandboxes: leaked_sandboxes(),
+        }
+    }
+}
+
+/// Counts an execution in [`ServiceStats::active_executions`] until it is dropped
+struct ActiveExecution<'a>(&'a AtomicUsize);
+
+impl<'a> ActiveExecution<'a> {
+    fn new(active: &'a AtomicUsize) -> Self {
+        active.fetch_add(1, Ordering::SeqCst);
+        Self(active)
+    }
+}
+
+impl Drop for ActiveExecution<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::types::Language;
     use std::time::Duration;
 
     async fn execute_and_log(
@@ -96,26 +689,20 @@ mod tests {
             ExecutionRequest {
                 language: Language::Python,
                 code: r#"print("Hello from 1!")"#.to_string(),
-                input: None,
-                dependencies: vec![],
                 timeout: Duration::from_secs(5),
-                env_vars: Default::default(),
+                ..Default::default()
             },
             ExecutionRequest {
                 language: Language::Python,
                 code: r#"print("Hello from 2!")"#.to_string(),
-                input: None,
-                dependencies: vec![],
                 timeout: Duration::from_secs(5),
-                env_vars: Default::default(),
+                ..Default::default()
             },
             ExecutionRequest {
                 language: Language::Python,
                 code: r#"print("Hello from 3!")"#.to_string(),
-                input: None,
-                dependencies: vec![],
                 timeout: Duration::from_secs(5),
-                env_vars: Default::default(),
+                ..Default::default()
             },
         ];
 
@@ -150,4 +737,136 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_self_test() -> Result<(), Error> {
+        let service = CodeExecutionService::with_language_defaults(4).await?;
+
+        let results = service.self_test(Duration::from_secs(60)).await;
+        let languages: Vec<_> = results.iter().map(|(language, _)| *language).collect();
+        assert_eq!(languages, service.accepted_languages());
+
+        for (language, result) in &results {
+            if matches!(language, Language::Python | Language::JavaScript) {
+                assert!(result.is_ok(), "{} failed: {:?}", language, result);
+            }
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_language_concurrency_limit() -> Result<(), Error> {
+        let service = CodeExecutionService::new(3, ResourceLimits::default())
+            .await?
+            .with_language_concurrency(HashMap::from([(Language::Rust, 1)]));
+
+        let first_rust = service.acquire_permits(Language::Rust).await?;
+
+        // A second Rust execution waits for the first one to finish...
+        let second_rust = service.acquire_permits(Language::Rust);
+        tokio::pin!(second_rust);
+        let waited = tokio::time::timeout(Duration::from_millis(100), &mut second_rust).await;
+        assert!(waited.is_err());
+
+        // ...while Python, which has no cap of its own, still gets a slot
+        let python = tokio::time::timeout(
+            Duration::from_millis(100),
+            service.acquire_permits(Language::Python),
+        )
+        .await
+        .expect("Python execution should not wait for Rust")?;
+        assert_eq!(service.get_available_slots(), 1);
+
+        drop(first_rust);
+        let second_rust = tokio::time::timeout(Duration::from_millis(100), second_rust)
+            .await
+            .expect("second Rust execution should start once the first finishes")?;
+        assert!(second_rust.0.is_some());
+
+        drop(python);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_clones_share_concurrency_limit() -> Result<(), Error> {
+        let max_executions = 2;
+        let service = CodeExecutionService::new(max_executions, ResourceLimits::default()).await?;
+
+        let mut handles = vec![];
+        for i in 0..max_executions + 3 {
+            let request = ExecutionRequest {
+                language: Language::Python,
+                code: format!("import time\ntime.sleep(0.5)\nprint({})", i),
+                timeout: Duration::from_secs(10),
+                ..Default::default()
+            };
+            // Every execution runs on a clone of its own
+            let service = service.clone();
+            handles.push(tokio::spawn(async move { service.execute(request).await }));
+        }
+
+        // Watch the shared counter until every execution is done
+        let peak = Arc::new(AtomicUsize::new(0));
+        let probe = {
+            let (service, peak) = (service.clone(), peak.clone());
+            tokio::spawn(async move {
+                loop {
+                    let active = service.stats().active_executions;
+                    peak.fetch_max(active, Ordering::SeqCst);
+                    tokio::time::sleep(Duration::from_millis(5)).await;
+                }
+            })
+        };
+        for handle in handles {
+            let result = handle.await.unwrap()?;
+            assert_eq!(result.status, ExecutionStatus::Success, "{}", result.stderr);
+        }
+        probe.abort();
+
+        assert_eq!(peak.load(Ordering::SeqCst), max_executions);
+        assert_eq!(service.stats().active_executions, 0);
+        assert_eq!(service.get_available_slots(), max_executions);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_cancel_during_install_frees_slot() -> Result<(), Error> {
+        let service = CodeExecutionService::new(1, ResourceLimits::default())
+            .await?
+            .with_custom_programs(vec!["sleep".to_string(), "cat".to_string()]);
+        let request = ExecutionRequest {
+            language: Language::Custom,
+            code: "unused".to_string(),
+            timeout: Duration::from_secs(10),
+            custom: Some(crate::CustomLanguage {
+                file_extension: "txt".to_string(),
+                // Stands in for a slow dependency install
+                setup_commands: vec!["sleep 60".to_string()],
+                compile_command: None,
+                run_command: "cat {source}".to_string(),
+            }),
+            ..Default::default()
+        };
+
+        let cancel = CancellationToken::new();
+        let execution = tokio::spawn({
+            let (service, cancel) = (service.clone(), cancel.clone());
+            async move { service.execute_cancellable(request, cancel).await }
+        });
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        assert_eq!(service.get_available_slots(), 0);
+
+        cancel.cancel();
+        let result = tokio::time::timeout(Duration::from_secs(5), execution)
+            .await
+            .expect("cancelling should stop the setup step")
+            .unwrap();
+        assert!(
+            matches!(result, Err(Error::Cancelled)),
+            "{:?}",
+            result.map(|r| r.status)
+        );
+        assert_eq!(service.get_available_slots(), 1);
+        Ok(())
+    }
 }
@@ -0,0 +1,227 @@
+use super::utils::{
+    defaults::{default_test_limits, default_timeout},
+    dependencies::{lodash_dependency, numpy_dependency, serde_dependencies},
+};
+use crate::{
+    CodeExecutionService, Dependency, DependencyCache, DependencyCacheConfig, Error,
+    ExecutionRequest, ExecutionStatus, Language, Result,
+};
+use std::path::{Path, PathBuf};
+use tempfile::tempdir;
+
+fn test_cache(max_size: u64) -> (tempfile::TempDir, DependencyCache) {
+    let dir = tempdir().unwrap();
+    let cache = DependencyCache::new(DependencyCacheConfig {
+        dir: dir.path().to_path_buf(),
+        max_size,
+    })
+    .unwrap();
+    (dir, cache)
+}
+
+/// Files and directories under `dir` named after the crate programs are built as
+fn program_artifacts(dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut found = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if name.starts_with("code-execution") || name.starts_with("code_execution") {
+            found.push(entry.path());
+        } else if entry.file_type()?.is_dir() {
+            found.extend(program_artifacts(&entry.path())?);
+        }
+    }
+    Ok(found)
+}
+
+#[test]
+fn test_cache_key_ignores_dependency_order() {
+    let deps = serde_dependencies();
+    let reversed: Vec<_> = deps.iter().rev().cloned().collect();
+
+    assert_eq!(
+        DependencyCache::key(Language::Rust, &deps),
+        DependencyCache::key(Language::Rust, &reversed)
+    );
+    assert_ne!(
+        DependencyCache::key(Language::Rust, &deps),
+        DependencyCache::key(Language::Go, &deps)
+    );
+}
+
+#[tokio::test]
+async fn test_identical_dependencies_hit_cache() -> Result<()> {
+    let (_dir, cache) = test_cache(u64::MAX);
+    let key = DependencyCache::key(Language::JavaScript, &[lodash_dependency()]);
+
+    // First run populates node_modules and the cache
+    let first = tempdir()?;
+    assert!(!cache.restore(&key, first.path(), &["node_modules"]).await?);
+    std::fs::create_dir_all(first.path().join("node_modules/lodash"))?;
+    std::fs::write(
+        first.path().join("node_modules/lodash/index.js"),
+        "// lodash",
+    )?;
+    cache.store(&key, first.path(), &["node_modules"]).await?;
+
+    // Second run with the same dependency set is seeded from the cache
+    let second = tempdir()?;
+    assert!(
+        cache
+            .restore(&key, second.path(), &["node_modules"])
+            .await?
+    );
+    assert!(second.path().join("node_modules/lodash/index.js").exists());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_cache_evicts_beyond_max_size() -> Result<()> {
+    let (_dir, cache) = test_cache(1);
+    let key = DependencyCache::key(Language::JavaScript, &[lodash_dependency()]);
+
+    let sandbox = tempdir()?;
+    std::fs::create_dir_all(sandbox.path().join("node_modules"))?;
+    std::fs::write(sandbox.path().join("node_modules/big.js"), "x".repeat(1024))?;
+    cache.store(&key, sandbox.path(), &["node_modules"]).await?;
+
+    let next = tempdir()?;
+    assert!(!cache.restore(&key, next.path(), &["node_modules"]).await?);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_offline_uncached_dependency_fails() -> Result<()> {
+    let dir = tempdir()?;
+    let service = CodeExecutionService::new(1, default_test_limits())
+        .await?
+        .with_dependency_cache(DependencyCacheConfig {
+            dir: dir.path().to_path_buf(),
+            max_size: u64::MAX,
+        })
+        .await?
+        .with_offline(true);
+
+    let request = ExecutionRequest {
+        language: Language::JavaScript,
+        code: "console.log(require('lodash').VERSION)".to_string(),
+        dependencies: vec![lodash_dependency()],
+        timeout: default_timeout(),
+        ..Default::default()
+    };
+
+    // npm is never started: a failed download would be reported as a system error
+    let result = service.execute(request).await;
+    assert!(
+        matches!(&result, Err(Error::OfflineDependencyMissing { package }) if package == "lodash"),
+        "{:?}",
+        result
+    );
+    // Nothing was installed, so nothing was stored either
+    assert_eq!(std::fs::read_dir(dir.path())?.count(), 0);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_offline_dependencies_without_cache_dirs_are_refused() -> Result<()> {
+    let service = CodeExecutionService::new(1, default_test_limits())
+        .await?
+        .with_offline(true);
+
+    let request = ExecutionRequest {
+        language: Language::Go,
+        code: "package main\n\nfunc main() {}".to_string(),
+        dependencies: vec![Dependency {
+            name: "github.com/google/uuid".to_string(),
+            version: "1.6.0".to_string(),
+            source: None,
+        }],
+        timeout: default_timeout(),
+        ..Default::default()
+    };
+
+    // Go modules never reach the dependency cache, so no cache could ever satisfy this
+    let result = service.execute(request).await;
+    assert!(
+        matches!(&result, Err(Error::InvalidDependency(msg)) if msg.contains("offline")),
+        "{:?}",
+        result
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_warmup_populates_cache() -> Result<()> {
+    let dir = tempdir()?;
+    let service = CodeExecutionService::new(1, default_test_limits())
+        .await?
+        .with_dependency_cache(DependencyCacheConfig {
+            dir: dir.path().to_path_buf(),
+            max_size: u64::MAX,
+        })
+        .await?;
+
+    let warmup = service
+        .warmup(Language::Python, vec![numpy_dependency()])
+        .await?;
+    assert!(!warmup.cache_hit);
+    assert!(warmup.dependency_install_time.is_some());
+
+    // A request with the same dependency set is seeded from what the warmup installed
+    let request = ExecutionRequest {
+        language: Language::Python,
+        code: "import numpy\nprint(numpy.array([1, 2]).sum())".to_string(),
+        dependencies: vec![numpy_dependency()],
+        timeout: default_timeout(),
+        ..Default::default()
+    };
+    let result = service.execute(request).await?;
+    assert_eq!(result.status, ExecutionStatus::Success, "{}", result.stderr);
+    assert_eq!(result.stdout, "3\n");
+    assert_eq!(result.process_stats.dependency_cache_hit, Some(true));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_warmup_without_cache_is_rejected() -> Result<()> {
+    let service = CodeExecutionService::new(1, default_test_limits()).await?;
+
+    // Installed dependencies would be thrown away with the sandbox
+    let result = service
+        .warmup(Language::Python, vec![numpy_dependency()])
+        .await;
+    assert!(matches!(result, Err(Error::Validation(_))), "{:?}", result);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_rust_cache_holds_no_program_build() -> Result<()> {
+    let dir = tempdir()?;
+    let service = CodeExecutionService::new(1, default_test_limits())
+        .await?
+        .with_dependency_cache(DependencyCacheConfig {
+            dir: dir.path().to_path_buf(),
+            max_size: u64::MAX,
+        })
+        .await?;
+
+    let request = |message: &str| ExecutionRequest {
+        language: Language::Rust,
+        code: format!("fn main() {{ println!(\"{}\"); }}", message),
+        dependencies: serde_dependencies(),
+        timeout: default_timeout(),
+        ..Default::default()
+    };
+
+    let first = service.execute(request("first")).await?;
+    assert_eq!(first.stdout, "first\n", "{}", first.stderr);
+
+    // Only the dependencies were cached, so the second program is built from its own source
+    let second = service.execute(request("second")).await?;
+    assert_eq!(second.stdout, "second\n", "{}", second.stderr);
+    assert_eq!(second.process_stats.dependency_cache_hit, Some(true));
+
+    let cached = program_artifacts(dir.path())?;
+    assert!(cached.is_empty(), "program output cached: {:?}", cached);
+    Ok(())
+}
@@ -0,0 +1,101 @@
+use super::{
+    fixtures::test_scenarios::PYTHON_PRINT_CREDENTIALS, utils::defaults::default_test_limits,
+};
+use crate::{
+    credentials::REDACTED, sandbox::Sandbox, CodeExecutor, CredentialDelivery, Error,
+    ExecutionRequest, InjectedCredential, Language, Result,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use tokio::time::Duration;
+
+fn credential(name: &str, value: &str, delivery: CredentialDelivery) -> InjectedCredential {
+    InjectedCredential {
+        name: name.to_string(),
+        value: value.to_string(),
+        delivery,
+    }
+}
+
+fn request(injected_credentials: Vec<InjectedCredential>) -> ExecutionRequest {
+    ExecutionRequest {
+        language: Language::Python,
+        code: PYTHON_PRINT_CREDENTIALS.to_string(),
+        timeout: Duration::from_secs(5),
+        injected_credentials,
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn test_credentials_are_scrubbed_from_output() -> Result<()> {
+    let executor = CodeExecutor::new().await?;
+    let mut sandbox = Sandbox::new(default_test_limits()).await?;
+
+    let result = executor
+        .execute_in_sandbox(
+            request(vec![
+                credential("API_TOKEN", "tok-8f3a91c2", CredentialDelivery::Env),
+                credential("DB_PASSWORD", "pw-d41d8cd9", CredentialDelivery::File),
+            ]),
+            &mut sandbox,
+        )
+        .await?;
+
+    // The program saw both credentials, but their values never leave the sandbox
+    assert!(result.stdout.contains("API_TOKEN"));
+    assert!(result.stdout.contains("DB_PASSWORD_FILE"));
+    assert!(result.stdout.contains(REDACTED));
+    assert!(result.stderr.contains(REDACTED));
+    for output in [&result.stdout, &result.stderr] {
+        assert!(!output.contains("tok-8f3a91c2"));
+        assert!(!output.contains("pw-d41d8cd9"));
+    }
+
+    // File-delivered credentials are wiped once the program exits
+    assert!(!sandbox.root_dir.join(".credentials").exists());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_credentials_are_scrubbed_from_output_files() -> Result<()> {
+    let executor = CodeExecutor::new().await?;
+    let mut sandbox = Sandbox::new(default_test_limits()).await?;
+
+    let request = ExecutionRequest {
+        language: Language::Bash,
+        code: "printf '%s' \"$API_TOKEN\" > token.txt\ncat \"$DB_PASSWORD_FILE\" > password.txt"
+            .to_string(),
+        capture_outputs: vec!["*.txt".to_string()],
+        ..request(vec![
+            credential("API_TOKEN", "tok-8f3a91c2", CredentialDelivery::Env),
+            credential("DB_PASSWORD", "pw-d41d8cd9", CredentialDelivery::File),
+        ])
+    };
+    let result = executor.execute_in_sandbox(request, &mut sandbox).await?;
+
+    assert_eq!(result.output_files.len(), 2);
+    for file in &result.output_files {
+        let content = STANDARD.decode(&file.content_base64).unwrap();
+        assert_eq!(content, REDACTED.as_bytes(), "{}", file.path);
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_invalid_credential_name_is_rejected() -> Result<()> {
+    let executor = CodeExecutor::new().await?;
+    let mut sandbox = Sandbox::new(default_test_limits()).await?;
+
+    let result = executor
+        .execute_in_sandbox(
+            request(vec![credential(
+                "../escape",
+                "secret",
+                CredentialDelivery::File,
+            )]),
+            &mut sandbox,
+        )
+        .await;
+    assert!(matches!(result, Err(Error::Validation(_))));
+    Ok(())
+}
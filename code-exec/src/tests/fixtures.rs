@@ -15,6 +15,15 @@ func main() {
             println!("Hello from Rust!");
         }
     "#;
+    pub const KOTLIN_HELLO: &str = r#"fun main() {
+    println("Hello from Kotlin!")
+}"#;
+
+    pub const ELIXIR_HELLO: &str = r#"defmodule Hello do
+  def greet(name), do: IO.puts("Hello from #{name}!")
+end
+
+Hello.greet("Elixir")"#;
 }
 
 /// Test code samples with dependencies
@@ -50,6 +59,9 @@ func main() {
             println!("{}", data.to_string());
         }
     "#;
+
+    pub const ELIXIR_WITH_DEPS: &str =
+        r#"IO.puts(Jason.encode!(%{message: "Hello from Elixir with Jason!"}))"#;
 }
 
 /// Test code samples for specific scenarios
@@ -65,6 +77,16 @@ print(f"Factorial of 5 is {result}")"#;
     pub const PYTHON_WITH_INPUT: &str = r#"name = input()
 print(f"Hello, {name}!")"#;
 
+    pub const PYTHON_WRITE_OUTPUTS: &str = r#"import os
+os.makedirs("out")
+with open("out/result.csv", "w") as f:
+    f.write("a,b\n1,2\n")
+with open("out/notes.txt", "w") as f:
+    f.write("not captured")
+# Symlinks are never captured, so this cannot leak the host's file
+os.symlink("/etc/passwd", "out/passwd.csv")
+"#;
+
     pub const JS_WITH_TIMEOUT: &str = r#"
         setTimeout(() => {
             console.log('This should not print due to timeout');
@@ -105,6 +127,12 @@ func main() {
     fmt.Println(len(data))
 }"#;
 
+    pub const KOTLIN_WITH_TIMEOUT: &str = r#"fun main() {
+    Thread.sleep(10_000)
+}"#;
+
+    pub const ELIXIR_WITH_TIMEOUT: &str = r#"Process.sleep(10_000)"#;
+
     pub const RUST_RESOURCE_HEAVY: &str = r#"
         fn main() {
             // Allocate a large vector
@@ -112,4 +140,8 @@ func main() {
             println!("Length: {}", data.len());
         }
     "#;
+    pub const PYTHON_PRINT_CREDENTIALS: &str = r#"import os, sys
+print(dict(os.environ))
+print(open(os.environ["DB_PASSWORD_FILE"]).read())
+print(os.environ["API_TOKEN"], file=sys.stderr)"#;
 }
@@ -7,9 +7,8 @@ use super::{
 };
 use crate::{
     executor::CodeExecutor, sandbox::Sandbox, CodeExecutionService, Error, ExecutionRequest,
-    Language, Result,
+    ExecutionStatus, Language, Result,
 };
-use std::collections::HashMap;
 use tokio::time::Duration;
 
 /// Basic language execution tests
@@ -22,10 +21,8 @@ pub mod basic_execution {
         let request = ExecutionRequest {
             language,
             code: code.to_string(),
-            input: None,
-            dependencies: vec![],
             timeout: default_timeout(),
-            env_vars: Default::default(),
+            ..Default::default()
         };
 
         let result = service.execute(request).await?;
@@ -74,10 +71,8 @@ pub mod concurrent_execution {
             let request = ExecutionRequest {
                 language,
                 code: code.to_string(),
-                input: None,
-                dependencies: vec![],
                 timeout: default_timeout(),
-                env_vars: Default::default(),
+                ..Default::default()
             };
             let service_clone = service.clone();
             handles.push(tokio::spawn(
@@ -103,25 +98,22 @@ pub mod concurrent_execution {
                 language: Language::Python,
                 code: PYTHON_WITH_DEPS.to_string(),
                 dependencies: vec![numpy_dependency()],
-                input: None,
                 timeout: extended_timeout(),
-                env_vars: Default::default(),
+                ..Default::default()
             },
             ExecutionRequest {
                 language: Language::JavaScript,
                 code: JS_WITH_DEPS.to_string(),
                 dependencies: vec![lodash_dependency()],
-                input: None,
                 timeout: extended_timeout(),
-                env_vars: Default::default(),
+                ..Default::default()
             },
             ExecutionRequest {
                 language: Language::Rust,
                 code: RUST_WITH_DEPS.to_string(),
                 dependencies: serde_dependencies(),
-                input: None,
                 timeout: extended_timeout(),
-                env_vars: Default::default(),
+                ..Default::default()
             },
         ];
 
@@ -154,14 +146,12 @@ pub mod error_handling {
         let request = ExecutionRequest {
             language: Language::JavaScript,
             code: JS_WITH_TIMEOUT.to_string(),
-            input: None,
-            dependencies: vec![],
             timeout: Duration::from_secs(2),
-            env_vars: Default::default(),
+            ..Default::default()
         };
 
-        let result = service.execute(request).await;
-        assert!(matches!(result, Err(Error::Timeout(_))));
+        let result = service.execute(request).await?;
+        assert_eq!(result.status, ExecutionStatus::Timeout);
         Ok(())
     }
 
@@ -173,25 +163,157 @@ pub mod error_handling {
         let request = ExecutionRequest {
             language: Language::Python,
             code: PYTHON_RESOURCE_HEAVY.to_string(),
-            dependencies: vec![],
-            env_vars: HashMap::new(),
-            input: None,
             timeout: default_timeout(),
+            ..Default::default()
         };
 
-        let result = executor.execute_in_sandbox(request, &mut sandbox).await;
+        let result = executor.execute_in_sandbox(request, &mut sandbox).await?;
 
         #[cfg(target_os = "linux")]
         {
-            assert!(matches!(result, Err(Error::ResourceExceeded(_))));
+            assert_eq!(result.status, ExecutionStatus::MemoryExceeded);
         }
 
         #[cfg(target_os = "macos")]
         {
             // On macOS, we can't enforce memory limits, so the program should complete
-            assert!(result.is_ok());
+            assert_eq!(result.status, ExecutionStatus::Success);
         }
 
         Ok(())
     }
+    #[tokio::test]
+    async fn test_status_distinguishes_timeout_from_exit_code() -> Result<()> {
+        let service = CodeExecutionService::new(1, default_test_limits()).await?;
+
+        let request = |code: &str, timeout| ExecutionRequest {
+            language: Language::Python,
+            code: code.to_string(),
+            timeout,
+            ..Default::default()
+        };
+
+        let result = service
+            .execute(request(
+                "import time\ntime.sleep(30)",
+                Duration::from_secs(1),
+            ))
+            .await?;
+        assert_eq!(result.status, ExecutionStatus::Timeout);
+
+        let result = service
+            .execute(request("import sys\nsys.exit(3)", default_timeout()))
+            .await?;
+        assert_eq!(result.status, ExecutionStatus::RuntimeError(3));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_deterministic_output_is_stable() -> Result<()> {
+        let service = CodeExecutionService::new(1, default_test_limits()).await?;
+
+        // Set order follows string hashes, which are salted per process unless fixed
+        let code = "import random, time\n\
+                    words = {'apple', 'banana', 'cherry', 'damson', 'elder', 'fig', 'grape'}\n\
+                    print(list(words), {w: hash(w) for w in words})\n\
+                    print(random.random(), time.strftime('%Z'))";
+        let request = |deterministic| ExecutionRequest {
+            language: Language::Python,
+            code: code.to_string(),
+            timeout: default_timeout(),
+            deterministic,
+            ..Default::default()
+        };
+
+        let first = service.execute(request(true)).await?;
+        let second = service.execute(request(true)).await?;
+        assert_eq!(first.status, ExecutionStatus::Success, "{}", first.stderr);
+        assert_eq!(first.stdout, second.stdout);
+        assert!(first.stdout.trim().ends_with("UTC"), "{}", first.stdout);
+
+        let first = service.execute(request(false)).await?;
+        let second = service.execute(request(false)).await?;
+        assert_ne!(first.stdout, second.stdout);
+
+        // The program sees the same command line in both modes
+        let argv = |deterministic| ExecutionRequest {
+            code: "import sys\nprint(sys.argv, __name__)".to_string(),
+            args: vec!["--flag".to_string()],
+            ..request(deterministic)
+        };
+        let deterministic = service.execute(argv(true)).await?;
+        let regular = service.execute(argv(false)).await?;
+        assert_eq!(deterministic.stdout, regular.stdout);
+        assert_eq!(
+            deterministic.stdout.trim(),
+            "['source.py', '--flag'] __main__"
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_timeout_keeps_partial_output() -> Result<()> {
+        let service = CodeExecutionService::new(1, default_test_limits()).await?;
+
+        let request = ExecutionRequest {
+            language: Language::Python,
+            code: "import sys, time\n\
+                   print('before the loop', flush=True)\n\
+                   print('on stderr', file=sys.stderr, flush=True)\n\
+                   while True:\n    time.sleep(0.1)"
+                .to_string(),
+            timeout: Duration::from_secs(2),
+            ..Default::default()
+        };
+
+        let result = service.execute(request).await?;
+        assert_eq!(result.status, ExecutionStatus::Timeout);
+        assert_eq!(result.stdout.trim(), "before the loop");
+        assert_eq!(result.stderr.trim(), "on stderr");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_rejects_oversized_source() -> Result<()> {
+        let service = CodeExecutionService::new(1, default_test_limits())
+            .await?
+            .with_max_source_bytes(64);
+
+        let request = |code: String, input: Option<String>| ExecutionRequest {
+            language: Language::Python,
+            code,
+            input,
+            timeout: default_timeout(),
+            ..Default::default()
+        };
+
+        let result = service
+            .execute(request(format!("# {}\nprint(1)", "x".repeat(100)), None))
+            .await;
+        assert!(matches!(
+            result,
+            Err(Error::SourceTooLarge {
+                field: "code",
+                limit: 64,
+                ..
+            })
+        ));
+
+        let result = service
+            .execute(request("print(input())".to_string(), Some("x".repeat(100))))
+            .await;
+        assert!(matches!(
+            result,
+            Err(Error::SourceTooLarge { field: "input", .. })
+        ));
+
+        let result = service
+            .execute(request(
+                "print(input())".to_string(),
+                Some("ok".to_string()),
+            ))
+            .await?;
+        assert_eq!(result.stdout.trim(), "ok");
+        Ok(())
+    }
 }
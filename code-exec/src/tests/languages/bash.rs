@@ -0,0 +1,66 @@
+use crate::{languages::check_requirements, languages::BashExecutor, Error};
+
+use super::*;
+
+fn bash_request(code: &str) -> ExecutionRequest {
+    ExecutionRequest {
+        language: Language::Bash,
+        code: code.to_string(),
+        timeout: default_timeout(),
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn test_bash_basic() -> std::result::Result<(), Error> {
+    let service = CodeExecutionService::new(1, default_test_limits())
+        .await?
+        .with_shell(true);
+
+    let result = service.execute(bash_request("echo $((1+1))")).await?;
+    assert_eq!(result.status, ExecutionStatus::Success);
+    assert_eq!(result.stdout.trim(), "2");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_bash_strict_mode() -> std::result::Result<(), Error> {
+    let service = CodeExecutionService::new(1, default_test_limits())
+        .await?
+        .with_shell(true);
+
+    let result = service
+        .execute(bash_request("false | cat\necho unreachable"))
+        .await?;
+    assert_eq!(result.status, ExecutionStatus::RuntimeError(1));
+    assert!(!result.stdout.contains("unreachable"));
+
+    let result = service.execute(bash_request("echo \"$UNSET_VAR\"")).await?;
+    assert_eq!(result.status, ExecutionStatus::RuntimeError(1));
+    // The options are passed to bash rather than written into the script, so the line
+    // number is the user's
+    assert!(
+        result
+            .stderr
+            .contains("line 1: UNSET_VAR: unbound variable"),
+        "{}",
+        result.stderr
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_bash_rejected_unless_allowed() -> std::result::Result<(), Error> {
+    let service = CodeExecutionService::new(1, default_test_limits()).await?;
+
+    let result = service.execute(bash_request("echo $((1+1))")).await;
+    assert!(matches!(result, Err(Error::UnsupportedLanguage(_))));
+    Ok(())
+}
+
+// Bash executor specific tests
+#[tokio::test]
+async fn test_bash_requirements() -> std::result::Result<(), Error> {
+    check_requirements(&BashExecutor::new()).await?;
+    Ok(())
+}
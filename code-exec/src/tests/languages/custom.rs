@@ -0,0 +1,140 @@
+use crate::{CustomLanguage, Error, ResourceLimits};
+
+use super::*;
+
+fn custom_request(code: &str, custom: CustomLanguage) -> ExecutionRequest {
+    ExecutionRequest {
+        language: Language::Custom,
+        code: code.to_string(),
+        timeout: default_timeout(),
+        custom: Some(custom),
+        ..Default::default()
+    }
+}
+
+/// Python standing in for an interpreter the service has no executor for
+fn python_as_custom() -> CustomLanguage {
+    CustomLanguage {
+        file_extension: "py".to_string(),
+        setup_commands: vec!["mkdir build".to_string()],
+        compile_command: Some("cp {source} build/program.py".to_string()),
+        run_command: "python3 build/program.py".to_string(),
+    }
+}
+
+#[tokio::test]
+async fn test_custom_language_runs_interpreter() -> std::result::Result<(), Error> {
+    let service = CodeExecutionService::new(1, default_test_limits())
+        .await?
+        .with_custom_programs(vec![
+            "mkdir".to_string(),
+            "cp".to_string(),
+            "python3".to_string(),
+        ]);
+
+    let request = custom_request("print('Hello from custom')", python_as_custom());
+    let result = service.execute(request).await?;
+    assert_eq!(result.status, ExecutionStatus::Success, "{}", result.stderr);
+    assert_eq!(result.stdout.trim(), "Hello from custom");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_custom_language_rejects_unlisted_programs() -> std::result::Result<(), Error> {
+    let request = custom_request("print('Hello from custom')", python_as_custom());
+
+    let service = CodeExecutionService::new(1, default_test_limits()).await?;
+    let result = service.execute(request.clone()).await;
+    assert!(matches!(result, Err(Error::UnsupportedLanguage(_))));
+
+    // `cp` is missing from the allowlist
+    let service = service.with_custom_programs(vec!["mkdir".to_string(), "python3".to_string()]);
+    let result = service.execute(request).await;
+    assert!(
+        matches!(&result, Err(Error::Validation(message)) if message.contains("cp")),
+        "{:?}",
+        result.map(|r| r.status)
+    );
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+async fn test_custom_compile_command_stays_in_sandbox() -> std::result::Result<(), Error> {
+    use crate::tests::utils::host::has_cap_sys_admin;
+
+    if !has_cap_sys_admin() {
+        eprintln!("Skipping test_custom_compile_command_stays_in_sandbox: mount namespaces need CAP_SYS_ADMIN");
+        return Ok(());
+    }
+    let outside = tempfile::tempdir().map_err(|e| Error::System(e.to_string()))?;
+    // Listing a toolchain directory turns on the private, read-only view of the host
+    let toolchain = tempfile::tempdir().map_err(|e| Error::System(e.to_string()))?;
+    let limits = ResourceLimits {
+        readonly_mounts: vec![toolchain.path().to_path_buf()],
+        ..default_test_limits()
+    };
+    let service = CodeExecutionService::new(1, limits)
+        .await?
+        .with_custom_programs(vec!["python3".to_string(), "cat".to_string()]);
+
+    // The interpreter runs the submitted source as its "compile" step
+    let custom = CustomLanguage {
+        file_extension: "py".to_string(),
+        setup_commands: vec![],
+        compile_command: Some("python3 {source}".to_string()),
+        run_command: "cat {source}".to_string(),
+    };
+    let code = format!(
+        "open('{}/escaped.txt', 'w').write('escaped')",
+        outside.path().display()
+    );
+    let result = service.execute(custom_request(&code, custom)).await?;
+    assert_eq!(
+        result.status,
+        ExecutionStatus::CompilationError,
+        "{}",
+        result.stderr
+    );
+    assert!(
+        result.stderr.contains("Read-only file system"),
+        "{}",
+        result.stderr
+    );
+    assert!(!outside.path().join("escaped.txt").exists());
+    Ok(())
+}
+
+#[test]
+fn test_custom_language_validation() {
+    let allowed = ["python3".to_string()].into_iter().collect();
+    let custom = CustomLanguage {
+        file_extension: "py".to_string(),
+        setup_commands: vec![],
+        compile_command: None,
+        run_command: "python3 {source}".to_string(),
+    };
+    assert!(custom.validate(&allowed).is_ok());
+    assert_eq!(custom.argv(&custom.run_command), vec!["python3", "main.py"]);
+
+    for custom in [
+        CustomLanguage {
+            file_extension: "../py".to_string(),
+            ..custom.clone()
+        },
+        CustomLanguage {
+            run_command: "/usr/bin/python3 {source}".to_string(),
+            ..custom.clone()
+        },
+        CustomLanguage {
+            setup_commands: vec!["  ".to_string()],
+            ..custom.clone()
+        },
+    ] {
+        assert!(
+            matches!(custom.validate(&allowed), Err(Error::Validation(_))),
+            "{:?}",
+            custom
+        );
+    }
+}
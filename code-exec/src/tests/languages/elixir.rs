@@ -0,0 +1,69 @@
+use super::{
+    fixtures::{
+        code_samples::ELIXIR_HELLO, code_with_deps::ELIXIR_WITH_DEPS,
+        test_scenarios::ELIXIR_WITH_TIMEOUT,
+    },
+    utils::dependencies::jason_dependency,
+};
+use crate::{
+    executor::LanguageExecutor,
+    languages::{check_requirements, ElixirExecutor},
+    sandbox::Sandbox,
+    Dependency, Error,
+};
+
+use super::*;
+
+#[tokio::test]
+async fn test_elixir_basic() -> std::result::Result<(), Error> {
+    test_language_execution(Language::Elixir, ELIXIR_HELLO).await
+}
+
+#[tokio::test]
+async fn test_elixir_with_deps() -> std::result::Result<(), Error> {
+    test_language_with_deps(Language::Elixir, ELIXIR_WITH_DEPS, vec![jason_dependency()]).await
+}
+
+#[tokio::test]
+async fn test_elixir_timeout() -> std::result::Result<(), Error> {
+    test_language_timeout(Language::Elixir, ELIXIR_WITH_TIMEOUT).await
+}
+
+// Elixir executor specific tests
+#[tokio::test]
+async fn test_elixir_requirements() -> std::result::Result<(), Error> {
+    let executor = ElixirExecutor::new(None).with_mix(true);
+    check_requirements(&executor).await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_elixir_rejects_unsafe_dependencies() -> std::result::Result<(), Error> {
+    let executor = ElixirExecutor::new(None).with_mix(true);
+    let sandbox = Sandbox::new(default_test_limits()).await?;
+
+    let invalid = [
+        ("Jason", "1.4"),
+        ("jason", "1.4\"}]; System.cmd(\"id\", []); [{\""),
+        ("jason", "#{System.halt()}"),
+    ];
+    for (name, version) in invalid {
+        let dependency = Dependency {
+            name: name.to_string(),
+            version: version.to_string(),
+            source: None,
+        };
+        let result = executor
+            .install_dependencies(&sandbox.root_dir, &[dependency])
+            .await;
+        assert!(
+            matches!(result, Err(Error::InvalidDependency(_))),
+            "{} {}: {:?}",
+            name,
+            version,
+            result
+        );
+    }
+    assert!(!sandbox.root_dir.join("mix.exs").exists());
+    Ok(())
+}
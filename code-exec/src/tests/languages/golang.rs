@@ -4,8 +4,9 @@ use super::fixtures::{
     test_scenarios::{GO_RESOURCE_HEAVY, GO_WITH_TIMEOUT},
 };
 use crate::{
-    executor::LanguageExecutor,
-    languages::{check_requirements, GoExecutor, ToolCheck},
+    executor::{CodeExecutor, LanguageExecutor},
+    languages::{check_requirements, GoExecutor},
+    sandbox::Sandbox,
     Dependency, Error,
 };
 
@@ -43,3 +44,35 @@ async fn test_go_requirements() -> std::result::Result<(), Error> {
     check_requirements(&executor).await?;
     Ok(())
 }
+
+#[tokio::test]
+async fn test_go_run_fast_path() -> std::result::Result<(), Error> {
+    // Programs without dependencies take the `go run` path automatically
+    let executor = CodeExecutor::new().await?;
+    let mut sandbox = Sandbox::new(default_test_limits()).await?;
+    let request = ExecutionRequest {
+        language: Language::Go,
+        code: GO_HELLO.to_string(),
+        timeout: default_timeout(),
+        ..Default::default()
+    };
+
+    let result = executor.execute_in_sandbox(request, &mut sandbox).await?;
+    assert_eq!(result.stdout.trim(), "Hello from Go!");
+    assert!(!sandbox.root_dir.join("go.sum").exists());
+
+    // The same program through the module and build path
+    let build = GoExecutor::new(None);
+    let mut sandbox = Sandbox::new(default_test_limits()).await?;
+    let source_file = sandbox.root_dir.join("tmp/source.go");
+
+    build.ensure_directories(&sandbox.root_dir).await?;
+    tokio::fs::write(&source_file, GO_HELLO).await?;
+    build.setup_environment(&sandbox.root_dir).await?;
+    build.compile(&sandbox.root_dir, &source_file).await?;
+    let (stdout, _, _) = sandbox
+        .execute(build.run_command(), &[], &[], None, default_timeout())
+        .await?;
+    assert_eq!(stdout.trim(), "Hello from Go!");
+    Ok(())
+}
@@ -5,7 +5,7 @@ use super::{
 use crate::{
     executor::LanguageExecutor,
     languages::{check_requirements, JavaScriptExecutor, ToolCheck},
-    CodeExecutionService, Dependency, Error, ExecutionRequest, Language,
+    CodeExecutionService, Dependency, Error, ExecutionRequest, Language, SourceFile,
 };
 
 use super::*;
@@ -30,6 +30,31 @@ async fn test_javascript_timeout() -> std::result::Result<(), Error> {
     test_language_timeout(Language::JavaScript, JS_WITH_TIMEOUT).await
 }
 
+#[tokio::test]
+async fn test_javascript_files_cannot_write_node_modules() -> std::result::Result<(), Error> {
+    let service = CodeExecutionService::new(1, default_test_limits()).await?;
+
+    for path in ["node_modules/lodash/lodash.js", "package.json"] {
+        let request = ExecutionRequest {
+            language: Language::JavaScript,
+            code: JS_HELLO.to_string(),
+            files: vec![SourceFile {
+                path: path.to_string(),
+                contents: "module.exports = {};".to_string(),
+            }],
+            timeout: default_timeout(),
+            ..Default::default()
+        };
+        let result = service.execute(request).await;
+        assert!(
+            matches!(&result, Err(Error::Validation(msg)) if msg.contains("reserved")),
+            "{:?}",
+            result
+        );
+    }
+    Ok(())
+}
+
 // JavaScript executor specific tests
 #[tokio::test]
 async fn test_javascript_requirements() -> std::result::Result<(), Error> {
@@ -0,0 +1,65 @@
+use super::fixtures::{code_samples::KOTLIN_HELLO, test_scenarios::KOTLIN_WITH_TIMEOUT};
+use crate::{
+    executor::LanguageExecutor,
+    languages::{check_requirements, KotlinExecutor},
+    sandbox::Sandbox,
+    Error,
+};
+
+use super::*;
+
+#[tokio::test]
+async fn test_kotlin_basic() -> std::result::Result<(), Error> {
+    test_language_execution(Language::Kotlin, KOTLIN_HELLO).await
+}
+
+#[tokio::test]
+async fn test_kotlin_timeout() -> std::result::Result<(), Error> {
+    test_language_timeout(Language::Kotlin, KOTLIN_WITH_TIMEOUT).await
+}
+
+// Kotlin executor specific tests
+#[tokio::test]
+async fn test_kotlin_requirements() -> std::result::Result<(), Error> {
+    let executor = KotlinExecutor::new(None);
+    check_requirements(&executor).await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_kotlin_compiles_runnable_jar() -> std::result::Result<(), Error> {
+    let executor = KotlinExecutor::new(None);
+    let mut sandbox = Sandbox::new(default_test_limits()).await?;
+    let source_file = sandbox.root_dir.join("tmp/source.kt");
+
+    executor.ensure_directories(&sandbox.root_dir).await?;
+    tokio::fs::write(&source_file, KOTLIN_HELLO).await?;
+    executor.setup_environment(&sandbox.root_dir).await?;
+    executor.compile(&sandbox.root_dir, &source_file).await?;
+    assert!(sandbox.root_dir.join("app.jar").exists());
+
+    let args = executor.run_args();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let (stdout, _, _) = sandbox
+        .execute(executor.run_command(), &args, &[], None, default_timeout())
+        .await?;
+    assert_eq!(stdout.trim(), "Hello from Kotlin!");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_kotlin_compile_error() -> std::result::Result<(), Error> {
+    let executor = KotlinExecutor::new(None);
+    let sandbox = Sandbox::new(default_test_limits()).await?;
+    let source_file = sandbox.root_dir.join("tmp/source.kt");
+
+    executor.ensure_directories(&sandbox.root_dir).await?;
+    tokio::fs::write(&source_file, "fun main() { val x: Int = \"text\" }").await?;
+    match executor.compile(&sandbox.root_dir, &source_file).await {
+        Err(Error::CompilationError(message)) => {
+            assert!(message.contains("type mismatch"), "{}", message);
+        }
+        other => panic!("Expected compilation error, got {:?}", other),
+    }
+    Ok(())
+}
@@ -1,12 +1,20 @@
 use super::utils::defaults::*;
 pub use super::*;
-use crate::{CodeExecutionService, Dependency, Error, ExecutionRequest, Language, Result};
+use crate::{
+    CodeExecutionService, CodeExecutor, CustomLanguage, Dependency, ExecutionRequest,
+    ExecutionStatus, Language, Result,
+};
 use tokio::time::Duration;
 
+pub mod bash;
+pub mod custom;
+pub mod elixir;
 pub mod golang;
 pub mod javascript;
+pub mod kotlin;
 pub mod python;
 pub mod rust;
+pub mod sql;
 pub mod typescript;
 
 // Common test utilities for language tests
@@ -16,10 +24,8 @@ pub(crate) async fn test_language_execution(language: Language, code: &str) -> R
     let request = ExecutionRequest {
         language,
         code: code.to_string(),
-        input: None,
-        dependencies: vec![],
         timeout: default_timeout(),
-        env_vars: Default::default(),
+        ..Default::default()
     };
 
     let result = service.execute(request).await?;
@@ -38,10 +44,9 @@ pub(crate) async fn test_language_with_deps(
     let request = ExecutionRequest {
         language,
         code: code.to_string(),
-        input: None,
         dependencies,
         timeout: extended_timeout(),
-        env_vars: Default::default(),
+        ..Default::default()
     };
 
     let result = service.execute(request).await?;
@@ -56,13 +61,55 @@ pub(crate) async fn test_language_timeout(language: Language, code: &str) -> Res
     let request = ExecutionRequest {
         language,
         code: code.to_string(),
-        input: None,
-        dependencies: vec![],
         timeout: Duration::from_millis(100),
-        env_vars: Default::default(),
+        ..Default::default()
     };
 
-    let result = service.execute(request).await;
-    assert!(matches!(result, Err(Error::Timeout(_))));
+    let result = service.execute(request).await?;
+    assert_eq!(result.status, ExecutionStatus::Timeout);
     Ok(())
 }
+
+#[tokio::test]
+async fn test_every_language_has_an_executor() -> Result<()> {
+    let executor = CodeExecutor::new().await?;
+
+    for language in Language::ALL {
+        let request = ExecutionRequest {
+            language,
+            code: String::new(),
+            timeout: default_timeout(),
+            output_encoding: Default::default(),
+            // Custom languages are described by the request
+            custom: (language == Language::Custom).then(|| CustomLanguage {
+                file_extension: "txt".to_string(),
+                setup_commands: vec![],
+                compile_command: None,
+                run_command: "cat {source}".to_string(),
+            }),
+            ..Default::default()
+        };
+        assert!(
+            executor.create_executor(&request).is_ok(),
+            "no executor for {}",
+            language
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn test_language_names_round_trip() {
+    for language in Language::ALL {
+        assert_eq!(language.as_str().parse::<Language>(), Ok(language));
+        for alias in language.aliases() {
+            assert_eq!(alias.parse::<Language>(), Ok(language));
+        }
+        // Serde and the canonical name must agree
+        assert_eq!(
+            serde_json::to_value(language).unwrap(),
+            serde_json::json!(language.as_str())
+        );
+    }
+    assert!("cobol".parse::<Language>().is_err());
+}
@@ -4,9 +4,9 @@ use super::{
     utils::dependencies::numpy_dependency,
 };
 use crate::{
-    executor::LanguageExecutor,
     languages::{check_requirements, PythonExecutor},
-    CodeExecutionService, Dependency, Error, ExecutionRequest, Language,
+    CodeExecutionService, Dependency, Error, ExecutionRequest, InputStep, Language, OutputFile,
+    SourceFile,
 };
 use tempfile::tempdir;
 use tokio::process::Command;
@@ -36,9 +36,8 @@ async fn test_python_input() -> std::result::Result<(), Error> {
         language: Language::Python,
         code: PYTHON_WITH_INPUT.to_string(),
         input: Some("test user\n".to_string()),
-        dependencies: vec![],
         timeout: default_timeout(),
-        env_vars: Default::default(),
+        ..Default::default()
     };
 
     let result = service.execute(request).await?;
@@ -47,6 +46,134 @@ async fn test_python_input() -> std::result::Result<(), Error> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_python_input_script() -> std::result::Result<(), Error> {
+    let service = CodeExecutionService::new(1, default_test_limits()).await?;
+
+    // Nothing may arrive on stdin before the first prompt is printed
+    let code = r#"
+import select, sys, time
+time.sleep(0.5)
+early = bool(select.select([sys.stdin], [], [], 0)[0])
+name = input("Name: ")
+age = input("Age: ")
+print(f"\nearly={early} name={name} age={age}")
+"#;
+    let request = ExecutionRequest {
+        language: Language::Python,
+        code: code.to_string(),
+        input_script: vec![
+            InputStep {
+                wait_for: Some("Name: ".to_string()),
+                send: "Alice\n".to_string(),
+            },
+            InputStep {
+                wait_for: Some("Age: ".to_string()),
+                send: "30\n".to_string(),
+            },
+        ],
+        timeout: default_timeout(),
+        ..Default::default()
+    };
+
+    let result = service.execute(request.clone()).await?;
+    assert_eq!(result.status, ExecutionStatus::Success, "{}", result.stderr);
+    assert!(
+        result.stdout.contains("early=False name=Alice age=30"),
+        "{}",
+        result.stdout
+    );
+
+    let request = ExecutionRequest {
+        input: Some("Alice\n".to_string()),
+        ..request
+    };
+    let result = service.execute(request).await;
+    assert!(matches!(result, Err(Error::Validation(_))), "{:?}", result);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_python_args() -> std::result::Result<(), Error> {
+    let service = CodeExecutionService::new(1, default_test_limits()).await?;
+
+    let request = ExecutionRequest {
+        language: Language::Python,
+        code: "import sys\nprint(sys.argv[1])\n".to_string(),
+        input_script: vec![],
+        // Reaches the program verbatim, not through a shell
+        args: vec!["hello; echo $HOME".to_string()],
+        timeout: default_timeout(),
+        ..Default::default()
+    };
+
+    let result = service.execute(request).await?;
+    assert_eq!(result.stdout, "hello; echo $HOME\n");
+    assert!(result.stderr.is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_python_capture_outputs() -> std::result::Result<(), Error> {
+    let service = CodeExecutionService::new(1, default_test_limits()).await?;
+
+    let request = ExecutionRequest {
+        language: Language::Python,
+        code: PYTHON_WRITE_OUTPUTS.to_string(),
+        capture_outputs: vec!["out/*.csv".to_string(), "../escape".to_string()],
+        timeout: default_timeout(),
+        ..Default::default()
+    };
+    let result = service.execute(request.clone()).await;
+    assert!(matches!(result, Err(Error::Validation(_))), "{:?}", result);
+
+    let request = ExecutionRequest {
+        capture_outputs: vec!["out/*.csv".to_string()],
+        ..request
+    };
+    let result = service.execute(request).await?;
+    assert_eq!(result.status, ExecutionStatus::Success, "{}", result.stderr);
+    assert_eq!(
+        result.output_files,
+        vec![OutputFile {
+            path: "out/result.csv".to_string(),
+            // "a,b\n1,2\n"
+            content_base64: "YSxiCjEsMgo=".to_string(),
+        }]
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_python_fixtures() -> std::result::Result<(), Error> {
+    let service = CodeExecutionService::new(1, default_test_limits()).await?;
+
+    let request = ExecutionRequest {
+        language: Language::Python,
+        code: "print(open('data.txt').read(), end='')\n".to_string(),
+        fixtures: vec![SourceFile {
+            path: "../data.txt".to_string(),
+            contents: "a,b\n1,2\n".to_string(),
+        }],
+        timeout: default_timeout(),
+        ..Default::default()
+    };
+    let result = service.execute(request.clone()).await;
+    assert!(matches!(result, Err(Error::Validation(_))), "{:?}", result);
+
+    let request = ExecutionRequest {
+        fixtures: vec![SourceFile {
+            path: "data.txt".to_string(),
+            contents: "a,b\n1,2\n".to_string(),
+        }],
+        ..request
+    };
+    let result = service.execute(request).await?;
+    assert_eq!(result.status, ExecutionStatus::Success, "{}", result.stderr);
+    assert_eq!(result.stdout, "a,b\n1,2\n");
+    Ok(())
+}
+
 // Python executor specific tests
 #[tokio::test]
 async fn test_python_requirements() -> std::result::Result<(), Error> {
@@ -54,3 +181,125 @@ async fn test_python_requirements() -> std::result::Result<(), Error> {
     check_requirements(&executor).await?;
     Ok(())
 }
+
+#[tokio::test]
+async fn test_python_runs_in_sandbox_venv() -> std::result::Result<(), Error> {
+    let service = CodeExecutionService::new(1, default_test_limits()).await?;
+
+    let request = ExecutionRequest {
+        language: Language::Python,
+        code: "import sys\nprint(sys.prefix)\nprint(sys.base_prefix)".to_string(),
+        timeout: default_timeout(),
+        ..Default::default()
+    };
+
+    let result = service.execute(request).await?;
+    let mut lines = result.stdout.lines();
+    let prefix = lines.next().unwrap_or_default();
+    let base_prefix = lines.next().unwrap_or_default();
+    assert!(prefix.ends_with("/venv"), "not run in a venv: {}", prefix);
+    assert_ne!(prefix, base_prefix);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_python_conflicting_dependency_versions() -> std::result::Result<(), Error> {
+    let service = CodeExecutionService::new(2, default_test_limits()).await?;
+
+    let request = |version: &str| ExecutionRequest {
+        language: Language::Python,
+        code: "import six\nprint(six.__version__)".to_string(),
+        dependencies: vec![Dependency {
+            name: "six".to_string(),
+            version: version.to_string(),
+            source: None,
+        }],
+        timeout: extended_timeout(),
+        ..Default::default()
+    };
+
+    // Run concurrently, so both installs overlap
+    let (old, new) = tokio::join!(
+        service.execute(request("1.15.0")),
+        service.execute(request("1.16.0"))
+    );
+    assert_eq!(old?.stdout.trim(), "1.15.0");
+    assert_eq!(new?.stdout.trim(), "1.16.0");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_python_install_diagnostics() -> std::result::Result<(), Error> {
+    let service = CodeExecutionService::new(1, default_test_limits()).await?;
+
+    let request = |include_install_diagnostics: bool| ExecutionRequest {
+        language: Language::Python,
+        code: "import numpy\nprint(numpy.__name__)".to_string(),
+        dependencies: vec![numpy_dependency()],
+        timeout: extended_timeout(),
+        include_install_diagnostics,
+        ..Default::default()
+    };
+
+    let result = service.execute(request(true)).await?;
+    assert_eq!(result.stdout.trim(), "numpy");
+    let diagnostics = result
+        .install_diagnostics
+        .expect("pip output should be returned");
+    assert!(diagnostics.contains("numpy"), "{}", diagnostics);
+
+    let result = service.execute(request(false)).await?;
+    assert_eq!(result.install_diagnostics, None);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_python_multi_file() -> std::result::Result<(), Error> {
+    let service = CodeExecutionService::new(1, default_test_limits()).await?;
+
+    let file = |path: &str, contents: &str| SourceFile {
+        path: path.to_string(),
+        contents: contents.to_string(),
+    };
+    let request = |files: Vec<SourceFile>| ExecutionRequest {
+        language: Language::Python,
+        code: String::new(),
+        files,
+        entrypoint: Some("main.py".to_string()),
+        timeout: default_timeout(),
+        ..Default::default()
+    };
+
+    let result = service
+        .execute(request(vec![
+            file(
+                "main.py",
+                "from greetings.english import greet\nprint(greet('World'))",
+            ),
+            file("greetings/__init__.py", ""),
+            file(
+                "greetings/english.py",
+                "def greet(name):\n    return f'Hello, {name}!'",
+            ),
+        ]))
+        .await?;
+    assert_eq!(result.stdout.trim(), "Hello, World!");
+
+    for path in [
+        "../escape.py",
+        "/tmp/escape.py",
+        "greetings/../../escape.py",
+        "",
+    ] {
+        let result = service
+            .execute(request(vec![file("main.py", "print(1)"), file(path, "")]))
+            .await;
+        assert!(matches!(result, Err(Error::Validation(_))), "{}", path);
+    }
+
+    let result = service
+        .execute(request(vec![file("other.py", "print(1)")]))
+        .await;
+    assert!(matches!(result, Err(Error::Validation(_))));
+    Ok(())
+}
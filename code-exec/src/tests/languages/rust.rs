@@ -8,8 +8,9 @@ use super::{
 };
 use crate::{
     languages::{check_requirements, RustExecutor},
-    Error,
+    Error, ExecutionMode, ResourceLimits,
 };
+use std::{net::TcpListener, time::Instant};
 
 use super::*;
 
@@ -40,3 +41,208 @@ async fn test_rust_requirements() -> std::result::Result<(), Error> {
     check_requirements(&executor).await?;
     Ok(())
 }
+
+#[tokio::test]
+async fn test_rust_compile_failure_includes_sandbox_listing() -> std::result::Result<(), Error> {
+    let service = CodeExecutionService::new(1, default_test_limits()).await?;
+
+    let request = ExecutionRequest {
+        language: Language::Rust,
+        code: "fn main() { let x: i32 = \"not a number\"; }".to_string(),
+        timeout: extended_timeout(),
+        include_sandbox_listing: true,
+        ..Default::default()
+    };
+
+    let result = service.execute(request).await?;
+    assert_eq!(result.status, ExecutionStatus::CompilationError);
+    let paths: Vec<_> = result
+        .sandbox_listing
+        .iter()
+        .map(|entry| entry.path.as_str())
+        .collect();
+    assert!(paths.contains(&"Cargo.toml"));
+    assert!(paths.contains(&"src/"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_rust_compile_error_includes_diagnostics() -> std::result::Result<(), Error> {
+    let service = CodeExecutionService::new(1, default_test_limits()).await?;
+
+    let request = ExecutionRequest {
+        language: Language::Rust,
+        code: "fn main() { let x: i32 = \"not a number\"; }".to_string(),
+        timeout: extended_timeout(),
+        ..Default::default()
+    };
+
+    let result = service.execute(request).await?;
+    assert_eq!(result.status, ExecutionStatus::CompilationError);
+    assert!(
+        result.stderr.contains("mismatched types"),
+        "{}",
+        result.stderr
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_rust_compile_only_skips_run() -> std::result::Result<(), Error> {
+    let service = CodeExecutionService::new(1, default_test_limits()).await?;
+    let request = |code: &str| ExecutionRequest {
+        language: Language::Rust,
+        code: code.to_string(),
+        timeout: extended_timeout(),
+        mode: ExecutionMode::CompileOnly,
+        ..Default::default()
+    };
+
+    // Would print if it ran
+    let result = service.execute(request(RUST_HELLO)).await?;
+    assert_eq!(result.status, ExecutionStatus::Success);
+    assert!(result.stdout.is_empty());
+    assert!(result.process_stats.compile_time.is_some());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_rust_compile_only_reports_diagnostics() -> std::result::Result<(), Error> {
+    let service = CodeExecutionService::new(1, default_test_limits()).await?;
+    let request = |code: &str| ExecutionRequest {
+        language: Language::Rust,
+        code: code.to_string(),
+        timeout: extended_timeout(),
+        mode: ExecutionMode::CompileOnly,
+        ..Default::default()
+    };
+
+    let result = service
+        .execute(request("fn main() { let x: i32 = \"not a number\"; }"))
+        .await?;
+    assert_eq!(result.status, ExecutionStatus::CompilationError);
+    assert!(
+        result.stderr.contains("mismatched types"),
+        "{}",
+        result.stderr
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_rust_reports_build_timings() -> std::result::Result<(), Error> {
+    let service = CodeExecutionService::new(1, default_test_limits()).await?;
+
+    let request = ExecutionRequest {
+        language: Language::Rust,
+        code: RUST_WITH_DEPS.to_string(),
+        dependencies: serde_dependencies(),
+        timeout: extended_timeout(),
+        ..Default::default()
+    };
+
+    let result = service.execute(request).await?;
+    assert_eq!(result.status, ExecutionStatus::Success, "{}", result.stderr);
+    let stats = &result.process_stats;
+    assert!(stats.dependency_install_time.is_some_and(|t| !t.is_zero()));
+    assert!(stats.compile_time.is_some_and(|t| !t.is_zero()));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_rust_reports_peak_disk_usage() -> std::result::Result<(), Error> {
+    let service = CodeExecutionService::new(1, default_test_limits()).await?;
+
+    let request = ExecutionRequest {
+        language: Language::Rust,
+        code: RUST_HELLO.to_string(),
+        timeout: extended_timeout(),
+        ..Default::default()
+    };
+
+    let result = service.execute(request).await?;
+    assert_eq!(result.status, ExecutionStatus::Success, "{}", result.stderr);
+    // The build's target directory alone takes more than a megabyte
+    let peak = result.process_stats.peak_disk_bytes;
+    assert!(peak > 1024 * 1024, "peak disk usage was {} bytes", peak);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_rust_shared_target_dir_reuses_builds() -> std::result::Result<(), Error> {
+    let target_dir = tempfile::tempdir().map_err(|e| Error::System(e.to_string()))?;
+    let limits = ResourceLimits {
+        run_as_uid: Some(65534),
+        ..default_test_limits()
+    };
+    let service = CodeExecutionService::new(1, limits)
+        .await?
+        .with_rust_target_dir(target_dir.path().to_path_buf())?;
+
+    let request = ExecutionRequest {
+        language: Language::Rust,
+        code: RUST_WITH_DEPS.to_string(),
+        dependencies: serde_dependencies(),
+        timeout: extended_timeout(),
+        ..Default::default()
+    };
+
+    let first = service.execute(request.clone()).await?;
+    assert_eq!(first.status, ExecutionStatus::Success, "{}", first.stderr);
+    // Built in the subdirectory of its dependency set
+    let builds: Vec<_> = std::fs::read_dir(target_dir.path())?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<std::io::Result<_>>()?;
+    assert_eq!(builds.len(), 1);
+    assert!(builds[0].join("release/code-execution").exists());
+
+    // The dependencies are already compiled in the shared directory
+    let second = service.execute(request).await?;
+    assert_eq!(second.status, ExecutionStatus::Success, "{}", second.stderr);
+    assert_eq!(first.stdout, second.stdout);
+    assert!(second.process_stats.compile_time < first.process_stats.compile_time);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_rust_shared_target_dir_requires_run_as_uid() -> std::result::Result<(), Error> {
+    let target_dir = tempfile::tempdir().map_err(|e| Error::System(e.to_string()))?;
+    let result = CodeExecutionService::new(1, default_test_limits())
+        .await?
+        .with_rust_target_dir(target_dir.path().to_path_buf());
+    assert!(matches!(result, Err(Error::Validation(_))));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_rust_install_timeout() -> std::result::Result<(), Error> {
+    // Accepts connections but never answers, so fetching from it hangs
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let limits = ResourceLimits {
+        install_timeout: 3,
+        ..default_test_limits()
+    };
+    let service = CodeExecutionService::new(1, limits).await?;
+
+    let request = ExecutionRequest {
+        language: Language::Rust,
+        code: RUST_HELLO.to_string(),
+        dependencies: vec![Dependency {
+            name: "unreachable".to_string(),
+            version: "0.1".to_string(),
+            source: Some(format!("http://{}/unreachable.git", listener.local_addr()?)),
+        }],
+        timeout: extended_timeout(),
+        ..Default::default()
+    };
+
+    let started = Instant::now();
+    let result = service.execute(request).await;
+    assert!(
+        matches!(result, Err(Error::InstallTimeout(3))),
+        "{:?}",
+        result
+    );
+    assert!(started.elapsed() < Duration::from_secs(30));
+    Ok(())
+}
@@ -0,0 +1,77 @@
+use crate::{
+    languages::{check_requirements, SqlExecutor},
+    Error,
+};
+
+use super::*;
+
+fn sql_request(code: &str, input: Option<&str>) -> ExecutionRequest {
+    ExecutionRequest {
+        language: Language::Sql,
+        code: code.to_string(),
+        input: input.map(str::to_string),
+        timeout: default_timeout(),
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn test_sql_select_inserted_row() -> std::result::Result<(), Error> {
+    let service = CodeExecutionService::new(1, default_test_limits()).await?;
+
+    let code = "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT);\n\
+                INSERT INTO users (name) VALUES ('ada');\n\
+                SELECT id, name FROM users;";
+    let result = service.execute(sql_request(code, None)).await?;
+    assert_eq!(result.status, ExecutionStatus::Success);
+    assert!(result.stdout.contains("name"), "{}", result.stdout);
+    assert!(
+        result
+            .stdout
+            .lines()
+            .any(|line| line.split_whitespace().eq(["1", "ada"])),
+        "{}",
+        result.stdout
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_sql_seeded_from_input() -> std::result::Result<(), Error> {
+    let service = CodeExecutionService::new(1, default_test_limits()).await?;
+
+    let seed = "CREATE TABLE items (name TEXT); INSERT INTO items VALUES ('seeded');";
+    let result = service
+        .execute(sql_request("SELECT name FROM items;", Some(seed)))
+        .await?;
+    assert_eq!(result.status, ExecutionStatus::Success);
+    assert!(result.stdout.contains("seeded"), "{}", result.stdout);
+
+    // Each execution starts from an empty database
+    let result = service
+        .execute(sql_request("SELECT name FROM items;", None))
+        .await?;
+    assert_eq!(result.status, ExecutionStatus::RuntimeError(1));
+    assert!(result.stderr.contains("no such table"), "{}", result.stderr);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_sql_dot_commands_run_in_safe_mode() -> std::result::Result<(), Error> {
+    let service = CodeExecutionService::new(1, default_test_limits()).await?;
+
+    let result = service
+        .execute(sql_request("SELECT 1;\n.shell echo hi", None))
+        .await?;
+    assert_eq!(result.status, ExecutionStatus::RuntimeError(1));
+    assert!(!result.stdout.contains("hi"), "{}", result.stdout);
+    assert!(result.stderr.contains("safe mode"), "{}", result.stderr);
+    Ok(())
+}
+
+// SQL executor specific tests
+#[tokio::test]
+async fn test_sql_requirements() -> std::result::Result<(), Error> {
+    check_requirements(&SqlExecutor::new()).await?;
+    Ok(())
+}
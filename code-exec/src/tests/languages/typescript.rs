@@ -5,7 +5,7 @@ use super::{
 use crate::{
     executor::LanguageExecutor,
     languages::{check_requirements, ToolCheck, TypeScriptExecutor},
-    CodeExecutionService, Dependency, Error, ExecutionRequest, Language,
+    CodeExecutionService, CodeExecutor, Dependency, Error, ExecutionRequest, JsRuntime, Language,
 };
 
 use super::*;
@@ -37,3 +37,26 @@ async fn test_typescript_requirements() -> std::result::Result<(), Error> {
     check_requirements(&executor).await?;
     Ok(())
 }
+
+#[tokio::test]
+async fn test_typescript_deno_runtime() -> std::result::Result<(), Error> {
+    let executor = CodeExecutor::new().await?;
+    let mut sandbox = setup_test_sandbox().await?;
+
+    let request = ExecutionRequest {
+        language: Language::TypeScript,
+        // Type annotations run without a tsc step
+        code: "const runtime: string = \"Deno\";\nconsole.log(`Hello from ${runtime}!`);"
+            .to_string(),
+        timeout: default_timeout(),
+        js_runtime: JsRuntime::Deno,
+        ..Default::default()
+    };
+
+    let result = executor.execute_in_sandbox(request, &mut sandbox).await?;
+    assert_eq!(result.status, ExecutionStatus::Success, "{}", result.stderr);
+    assert_eq!(result.stdout.trim(), "Hello from Deno!");
+    // Nothing was installed with npm
+    assert!(!sandbox.root_dir.join("node_modules").exists());
+    Ok(())
+}
@@ -1,8 +1,14 @@
+pub mod cache;
+pub mod credentials;
 pub mod fixtures;
 pub mod integration;
 pub mod languages;
+pub mod policy;
 pub mod sandbox;
+pub mod scanner;
 pub mod utils;
+#[cfg(feature = "wasm")]
+pub mod wasm;
 
 use crate::Result;
 
@@ -0,0 +1,153 @@
+use super::utils::defaults::{default_test_limits, default_timeout};
+use crate::{
+    CodeExecutionService, Error, ExecutionRequest, ExecutionStatus, Language, PolicyDecision,
+    PolicyRule, RegexPolicy, Result, SubmissionPolicy,
+};
+use std::{collections::HashMap, sync::Arc};
+
+fn request(language: Language, code: &str) -> ExecutionRequest {
+    ExecutionRequest {
+        language,
+        code: code.to_string(),
+        timeout: default_timeout(),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_builtin_rules() {
+    let policy = RegexPolicy::default();
+    let rejected = |rule: &str| PolicyDecision::Reject {
+        rule: rule.to_string(),
+    };
+
+    assert_eq!(
+        policy.check(Language::Python, "import os\nos.system(':(){ :|:& };:')"),
+        rejected("fork_bomb")
+    );
+    assert_eq!(
+        policy.check(Language::Python, "import os\nwhile True:\n    os.fork()\n"),
+        rejected("fork_bomb")
+    );
+    assert_eq!(
+        policy.check(
+            Language::Python,
+            "import subprocess\nsubprocess.run('rm -rf /', shell=True)"
+        ),
+        rejected("rm_root")
+    );
+    assert_eq!(
+        policy.check(
+            Language::JavaScript,
+            "connect('stratum+tcp://pool.example.com:3333')"
+        ),
+        rejected("crypto_miner")
+    );
+
+    // Ordinary programs and paths below the root are fine
+    assert_eq!(
+        policy.check(Language::Python, "print('Hello, World!')"),
+        PolicyDecision::Allow
+    );
+    assert_eq!(
+        policy.check(Language::Python, "os.system('rm -rf /tmp/build')"),
+        PolicyDecision::Allow
+    );
+}
+
+#[test]
+fn test_rule_languages() -> Result<()> {
+    let rule = PolicyRule {
+        languages: vec![Language::Python],
+        ..PolicyRule::new("no_sockets", r"\bimport socket\b")
+    };
+    let policy = RegexPolicy::new(vec![rule])?;
+
+    assert!(matches!(
+        policy.check(Language::Python, "import socket"),
+        PolicyDecision::Reject { .. }
+    ));
+    assert_eq!(
+        policy.check(Language::JavaScript, "// import socket"),
+        PolicyDecision::Allow
+    );
+    Ok(())
+}
+
+#[test]
+fn test_rejects_invalid_pattern() {
+    let result = RegexPolicy::new(vec![PolicyRule::new("broken", "(")]);
+    assert!(matches!(result, Err(Error::Validation(_))));
+}
+
+#[tokio::test]
+async fn test_service_rejects_fork_bomb() -> Result<()> {
+    let service = CodeExecutionService::new(1, default_test_limits()).await?;
+
+    let result = service
+        .execute(request(
+            Language::Python,
+            "import os\nwhile True:\n    os.fork()\n",
+        ))
+        .await;
+    assert!(
+        matches!(&result, Err(Error::PolicyRejected { rule }) if rule == "fork_bomb"),
+        "{:?}",
+        result
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_service_custom_policy() -> Result<()> {
+    let policy = RegexPolicy::new(vec![PolicyRule::new("no_eval", r"\beval\(")])?;
+    let service = CodeExecutionService::new(1, default_test_limits())
+        .await?
+        .with_submission_policy(Arc::new(policy));
+
+    let result = service
+        .execute(request(Language::Python, "print(eval('1 + 1'))"))
+        .await;
+    assert!(matches!(result, Err(Error::PolicyRejected { .. })));
+
+    // Replacing the policy drops the built-in rules
+    let response = service
+        .execute(request(Language::Python, "print('no fork() here')"))
+        .await?;
+    assert_eq!(response.status, ExecutionStatus::Success);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_service_banned_imports() -> Result<()> {
+    let service = CodeExecutionService::new(1, default_test_limits())
+        .await?
+        .with_banned_imports(HashMap::from([(
+            Language::Python,
+            vec!["subprocess".to_string(), "os.system".to_string()],
+        )]));
+
+    for (code, banned) in [
+        ("import subprocess\nsubprocess.run(['ls'])", "subprocess"),
+        ("import math, subprocess as sp", "subprocess"),
+        ("from os import system\nsystem('ls')", "os.system"),
+    ] {
+        let result = service.execute(request(Language::Python, code)).await;
+        assert!(
+            matches!(&result, Err(Error::BannedImport { module }) if module == banned),
+            "{}: {:?}",
+            code,
+            result
+        );
+    }
+
+    let response = service
+        .execute(request(
+            Language::Python,
+            "import math\nprint(math.sqrt(16))",
+        ))
+        .await?;
+    assert_eq!(response.status, ExecutionStatus::Success);
+    assert_eq!(response.stdout.trim(), "4.0");
+    Ok(())
+}
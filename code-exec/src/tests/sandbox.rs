@@ -1,6 +1,14 @@
-use crate::{sandbox::Sandbox, tests::utils::defaults::default_test_limits, Error, Result};
+use crate::{
+    sandbox::{KeptSandboxes, Sandbox},
+    tests::utils::defaults::default_test_limits,
+    CancellationToken, CodeExecutionService, Error, ExecutionRequest, ExecutionStatus, Language,
+    OutputEncoding, ResourceKind, ResourceLimits, Result, SandboxBackend,
+};
 use tokio::time::Duration;
 
+#[cfg(target_os = "linux")]
+use crate::tests::utils::host::has_cap_sys_admin;
+
 #[tokio::test]
 async fn test_sandbox_basic() -> Result<()> {
     let mut sandbox = Sandbox::new(default_test_limits()).await?;
@@ -23,13 +31,230 @@ async fn test_sandbox_input() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_sandbox_cleanup() -> Result<()> {
+    let mut sandbox = Sandbox::new(default_test_limits()).await?;
+    sandbox
+        .execute(
+            "sh",
+            &["-c", "mkdir -p nested/dir && echo data > nested/dir/file"],
+            &[],
+            None,
+            Duration::from_secs(5),
+        )
+        .await?;
+    let root_dir = sandbox.root_dir.clone();
+    assert!(root_dir.join("nested/dir/file").exists());
+
+    sandbox.cleanup().await?;
+    assert!(!root_dir.exists());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_sandbox_cancellation() -> Result<()> {
+    let cancel = CancellationToken::new();
+    let mut sandbox = Sandbox::new(default_test_limits())
+        .await?
+        .with_cancellation(cancel.clone());
+
+    let canceller = tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        cancel.cancel();
+    });
+    let started = std::time::Instant::now();
+    let result = sandbox
+        .execute("sleep", &["30"], &[], None, Duration::from_secs(60))
+        .await;
+    canceller.await.unwrap();
+
+    assert!(matches!(result, Err(Error::Cancelled)));
+    assert!(started.elapsed() < Duration::from_secs(5));
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_sandbox_timeout() -> Result<()> {
     let mut sandbox = Sandbox::new(default_test_limits()).await?;
     let result = sandbox
         .execute("sleep", &["10"], &[], None, Duration::from_secs(1))
         .await;
-    assert!(matches!(result, Err(Error::Timeout(_))));
+    assert!(matches!(result, Err(Error::WallTimeout(_))));
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+async fn test_sandbox_timeout_kills_process_tree() -> Result<()> {
+    let mut sandbox = Sandbox::new(default_test_limits()).await?;
+
+    // A sleep duration unique to this test run identifies the grandchild in /proc
+    let marker = format!("300.{}", std::process::id());
+    let code = format!(
+        "import subprocess, time\nsubprocess.Popen(['sleep', '{}'])\ntime.sleep(100)",
+        marker
+    );
+    let result = sandbox
+        .execute("python3", &["-c", &code], &[], None, Duration::from_secs(1))
+        .await;
+    assert!(matches!(result, Err(Error::WallTimeout(_))));
+
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert!(!process_running(&marker));
+    Ok(())
+}
+
+/// Whether a live (non-zombie) process has `arg` on its command line
+#[cfg(target_os = "linux")]
+fn process_running(arg: &str) -> bool {
+    let Ok(entries) = std::fs::read_dir("/proc") else {
+        return false;
+    };
+    entries.filter_map(|entry| entry.ok()).any(|entry| {
+        let path = entry.path();
+        let cmdline = std::fs::read(path.join("cmdline")).unwrap_or_default();
+        let has_arg = cmdline
+            .split(|b| *b == 0)
+            .any(|part| part == arg.as_bytes());
+        let zombie = std::fs::read_to_string(path.join("stat"))
+            .map(|stat| {
+                stat.rsplit(") ")
+                    .next()
+                    .is_some_and(|rest| rest.starts_with('Z'))
+            })
+            .unwrap_or(false);
+        has_arg && !zombie
+    })
+}
+
+#[tokio::test]
+async fn test_sandbox_max_files() -> Result<()> {
+    let mut sandbox = Sandbox::new(ResourceLimits {
+        max_files: 100,
+        ..default_test_limits()
+    })
+    .await?;
+
+    // Without the file limit this would only stop at the wall-clock timeout
+    let result = sandbox
+        .execute(
+            "python3",
+            &[
+                "-c",
+                "i = 0\nwhile True:\n    open(f'f{i}', 'w').close()\n    i += 1",
+            ],
+            &[],
+            None,
+            Duration::from_secs(10),
+        )
+        .await;
+    assert!(matches!(
+        result,
+        Err(Error::ResourceExceeded {
+            resource: ResourceKind::Files,
+            limit: 100,
+            ..
+        })
+    ));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_sandbox_disk_space() -> Result<()> {
+    let mut sandbox = Sandbox::new(ResourceLimits {
+        disk_space: 10 * 1024 * 1024, // 10MB
+        ..default_test_limits()
+    })
+    .await?;
+
+    // Every file stays under the 10MB file size limit, together they do not
+    let result = sandbox
+        .execute(
+            "python3",
+            &[
+                "-c",
+                "for i in range(50):\n    open(f'f{i}', 'wb').write(b'x' * 2**20)",
+            ],
+            &[],
+            None,
+            Duration::from_secs(10),
+        )
+        .await;
+    assert!(
+        matches!(
+            result,
+            Err(Error::ResourceExceeded {
+                resource: ResourceKind::Disk,
+                limit: 10485760,
+                ..
+            })
+        ),
+        "{:?}",
+        result
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_sandbox_wall_timeout() -> Result<()> {
+    let mut sandbox = Sandbox::new(ResourceLimits {
+        wall_time: 1,
+        ..default_test_limits()
+    })
+    .await?;
+
+    // Sleeping uses no CPU time, so only the wall-clock limit can stop it
+    let result = sandbox
+        .execute(
+            "python3",
+            &["-c", "import time; time.sleep(100)"],
+            &[],
+            None,
+            Duration::from_secs(30),
+        )
+        .await;
+    assert!(matches!(result, Err(Error::WallTimeout(1))));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_sandbox_cpu_timeout() -> Result<()> {
+    let mut sandbox = Sandbox::new(ResourceLimits {
+        cpu_time: 1,
+        ..default_test_limits()
+    })
+    .await?;
+
+    let result = sandbox
+        .execute(
+            "python3",
+            &["-c", "while True: pass"],
+            &[],
+            None,
+            Duration::from_secs(30),
+        )
+        .await;
+    let error = result.unwrap_err();
+    assert!(matches!(error, Error::CpuTimeout(1)));
+    assert_eq!(error.resource_kind(), Some(ResourceKind::CpuTime));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_sandbox_sigkill_is_not_cpu_exceeded() -> Result<()> {
+    let mut sandbox = Sandbox::new(default_test_limits()).await?;
+
+    // Killed long before it could use up its CPU time, as by the OOM killer
+    let output = sandbox
+        .run(
+            "sh",
+            &["-c", "kill -9 $$"],
+            &[],
+            None,
+            Duration::from_secs(30),
+        )
+        .await?;
+    assert_eq!(output.status, ExecutionStatus::RuntimeError(137));
     Ok(())
 }
 
@@ -50,7 +275,10 @@ async fn test_sandbox_resource_limits() -> Result<()> {
 
     #[cfg(target_os = "linux")]
     {
-        assert!(matches!(result, Err(Error::ResourceExceeded(_))));
+        assert_eq!(
+            result.unwrap_err().resource_kind(),
+            Some(ResourceKind::Memory)
+        );
     }
 
     #[cfg(target_os = "macos")]
@@ -61,3 +289,435 @@ async fn test_sandbox_resource_limits() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn test_sandbox_listing_is_bounded() -> Result<()> {
+    let sandbox = Sandbox::new(default_test_limits()).await?;
+    tokio::fs::write(sandbox.root_dir.join("tmp/output.txt"), "data").await?;
+
+    let listing = sandbox.listing(100);
+    assert!(listing.iter().any(|e| e.path == "tmp/"));
+    assert!(listing
+        .iter()
+        .any(|e| e.path == "tmp/output.txt" && e.size == 4));
+
+    assert_eq!(sandbox.listing(2).len(), 2);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_sandbox_strips_denied_env() -> Result<()> {
+    let mut sandbox = Sandbox::new(default_test_limits()).await?;
+    let env = vec![
+        ("LD_PRELOAD".to_string(), "/tmp/evil.so".to_string()),
+        ("GREETING".to_string(), "hello".to_string()),
+    ];
+    let (stdout, _, _) = sandbox
+        .execute("env", &[], &env, None, Duration::from_secs(5))
+        .await?;
+    assert!(!stdout.contains("LD_PRELOAD"));
+    assert!(stdout.contains("GREETING=hello"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_sandbox_strict_env_rejects_denied() -> Result<()> {
+    let mut limits = default_test_limits();
+    limits.env_policy.strict = true;
+    let mut sandbox = Sandbox::new(limits).await?;
+    let env = vec![("NODE_OPTIONS".to_string(), "--require evil".to_string())];
+    let result = sandbox
+        .execute("env", &[], &env, None, Duration::from_secs(5))
+        .await;
+    assert!(matches!(result, Err(Error::Validation(_))));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_oversized_env_is_rejected() -> Result<()> {
+    let service = CodeExecutionService::new(1, default_test_limits()).await?;
+    let request = |env_vars| ExecutionRequest {
+        language: Language::Python,
+        code: "print('unreachable')".to_string(),
+        timeout: Duration::from_secs(5),
+        env_vars,
+        ..Default::default()
+    };
+
+    let too_many = (0..=crate::DEFAULT_MAX_ENV_VARS)
+        .map(|i| (format!("VAR_{}", i), "x".to_string()))
+        .collect();
+    let too_large = [("BIG".to_string(), "x".repeat(crate::DEFAULT_MAX_ENV_BYTES))].into();
+    let bad_name = [("A=B".to_string(), "x".to_string())].into();
+    for env_vars in [too_many, too_large, bad_name] {
+        let result = service.execute(request(env_vars)).await;
+        assert!(matches!(result, Err(Error::Validation(_))), "{:?}", result);
+    }
+    Ok(())
+}
+
+// Python program writing bytes that are not UTF-8 to stdout
+fn invalid_utf8_request(output_encoding: OutputEncoding) -> ExecutionRequest {
+    ExecutionRequest {
+        language: Language::Python,
+        code: "import sys\nsys.stdout.buffer.write(b'ok\\xff\\xfe\\n')".to_string(),
+        timeout: Duration::from_secs(10),
+        output_encoding,
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn test_lossy_output_replaces_invalid_utf8() -> Result<()> {
+    let service = CodeExecutionService::new(1, default_test_limits()).await?;
+    let result = service
+        .execute(invalid_utf8_request(OutputEncoding::Lossy))
+        .await?;
+    assert_eq!(result.status, ExecutionStatus::Success);
+    assert_eq!(result.stdout, "ok\u{FFFD}\u{FFFD}\n");
+    assert_eq!(result.stdout_base64, None);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_strict_output_rejects_invalid_utf8() -> Result<()> {
+    let service = CodeExecutionService::new(1, default_test_limits()).await?;
+    let result = service
+        .execute(invalid_utf8_request(OutputEncoding::Strict))
+        .await;
+    let Err(Error::InvalidOutput { stream, offset }) = result else {
+        panic!("expected invalid output, got {:?}", result);
+    };
+    assert_eq!(stream, "stdout");
+    assert_eq!(offset, 2);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_base64_output_keeps_raw_bytes() -> Result<()> {
+    let service = CodeExecutionService::new(1, default_test_limits()).await?;
+    let result = service
+        .execute(invalid_utf8_request(OutputEncoding::Base64))
+        .await?;
+    assert_eq!(result.status, ExecutionStatus::Success);
+    assert!(result.stdout.is_empty());
+    // b"ok\xff\xfe\n"
+    assert_eq!(result.stdout_base64.as_deref(), Some("b2v//go="));
+    assert_eq!(result.stderr_base64.as_deref(), Some(""));
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+async fn test_sandbox_seccomp_allows_ordinary_programs() -> Result<()> {
+    let mut sandbox = Sandbox::new(ResourceLimits {
+        seccomp: true,
+        ..default_test_limits()
+    })
+    .await?;
+
+    let (stdout, _, _) = sandbox
+        .execute(
+            "python3",
+            &[
+                "-c",
+                "import json, subprocess, threading\n\
+                 open('out.txt', 'w').write('ok')\n\
+                 t = threading.Thread(target=lambda: None); t.start(); t.join()\n\
+                 print(subprocess.run(['cat', 'out.txt'], capture_output=True).stdout.decode())",
+            ],
+            &[],
+            None,
+            Duration::from_secs(10),
+        )
+        .await?;
+    assert_eq!(stdout.trim(), "ok");
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+async fn test_sandbox_seccomp_kills_denied_syscall() -> Result<()> {
+    let mut sandbox = Sandbox::new(ResourceLimits {
+        seccomp: true,
+        ..default_test_limits()
+    })
+    .await?;
+
+    // chroot is not on the allowlist, so the process dies instead of getting EPERM
+    let result = sandbox
+        .execute(
+            "python3",
+            &[
+                "-c",
+                "import os\ntry:\n    os.chroot('.')\nexcept OSError:\n    pass\nprint('survived')",
+            ],
+            &[],
+            None,
+            Duration::from_secs(10),
+        )
+        .await;
+    match result {
+        Err(Error::Sandbox(msg)) => assert!(msg.contains("seccomp"), "unexpected error: {}", msg),
+        other => panic!("expected the process to be killed, got {:?}", other),
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+async fn test_sandbox_readonly_mounts() -> Result<()> {
+    if !has_cap_sys_admin() {
+        eprintln!("Skipping test_sandbox_readonly_mounts: mount namespaces need CAP_SYS_ADMIN");
+        return Ok(());
+    }
+    let toolchain = tempfile::tempdir().map_err(|e| Error::System(e.to_string()))?;
+    let elsewhere = tempfile::tempdir().map_err(|e| Error::System(e.to_string()))?;
+    let mut sandbox = Sandbox::new(ResourceLimits {
+        readonly_mounts: vec![toolchain.path().to_path_buf()],
+        ..default_test_limits()
+    })
+    .await?;
+
+    let script = format!(
+        "import tempfile\n\
+         open('own.txt', 'w').write('ok')\n\
+         tempfile.TemporaryFile().close()\n\
+         for path in ['{}/tool', '{}/other']:\n\
+         \x20   try:\n        open(path, 'w')\n    except OSError as e:\n        print(e.errno)",
+        toolchain.path().display(),
+        elsewhere.path().display()
+    );
+    let (stdout, _, _) = sandbox
+        .execute(
+            "python3",
+            &["-c", script.as_str()],
+            &[],
+            None,
+            Duration::from_secs(10),
+        )
+        .await?;
+
+    // EROFS inside the sandbox for the listed path and the rest of the host alike, while
+    // the host still sees writable directories
+    assert_eq!(stdout.split_whitespace().collect::<Vec<_>>(), ["30", "30"]);
+    assert!(!toolchain.path().join("tool").exists());
+    assert!(!elsewhere.path().join("other").exists());
+    assert!(sandbox.root_dir.join("own.txt").exists());
+    std::fs::write(toolchain.path().join("tool"), "host")
+        .map_err(|e| Error::System(e.to_string()))?;
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+async fn test_sandbox_runs_as_configured_user() -> Result<()> {
+    // Switching users needs root; otherwise the sandbox only warns
+    if !nix::unistd::Uid::effective().is_root() {
+        return Ok(());
+    }
+
+    let mut sandbox = Sandbox::new(ResourceLimits {
+        run_as_uid: Some(65534),
+        ..default_test_limits()
+    })
+    .await?;
+    let (stdout, _, _) = sandbox
+        .execute(
+            "sh",
+            &["-c", "id -u; id -g; umask; touch created"],
+            &[],
+            None,
+            Duration::from_secs(5),
+        )
+        .await?;
+
+    let lines: Vec<_> = stdout.lines().collect();
+    assert_eq!(lines, ["65534", "65534", "0077"]);
+    // The sandbox was handed over, so the program could still write to it
+    assert!(sandbox.root_dir.join("created").exists());
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+async fn test_sandbox_process_limit() -> Result<()> {
+    // Root is exempt from RLIMIT_NPROC, so it runs the program as nobody
+    let run_as_uid = nix::unistd::Uid::effective().is_root().then_some(65534);
+    let mut sandbox = Sandbox::new(ResourceLimits {
+        processes: 10,
+        run_as_uid,
+        ..default_test_limits()
+    })
+    .await?;
+
+    // A bounded fork bomb: children wait, so every fork adds a process
+    let script = [
+        "import os, time",
+        "forked = failed = 0",
+        "for _ in range(50):",
+        "    try:",
+        "        if os.fork() == 0:",
+        "            time.sleep(1)",
+        "            os._exit(0)",
+        "        forked += 1",
+        "    except OSError:",
+        "        failed += 1",
+        "print(forked, failed)",
+    ]
+    .join("\n");
+    let (stdout, _, _) = sandbox
+        .execute(
+            "python3",
+            &["-c", script.as_str()],
+            &[],
+            None,
+            Duration::from_secs(10),
+        )
+        .await?;
+
+    let counts: Vec<u32> = stdout
+        .split_whitespace()
+        .map(|n| n.parse().unwrap())
+        .collect();
+    // Threads of other processes of the user come and go, so only check that the
+    // forks past the allowance failed instead of counting them exactly
+    assert_eq!(counts.len(), 2, "{}", stdout);
+    assert!(counts[1] > 0, "no fork failed: {}", stdout);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_sandbox_timeout_with_unread_input() -> Result<()> {
+    let mut sandbox = Sandbox::new(default_test_limits()).await?;
+
+    // Far more than a pipe buffer holds, for a program that never reads it
+    let input = "x".repeat(4 * 1024 * 1024);
+    let output = tokio::time::timeout(
+        Duration::from_secs(15),
+        sandbox.run("sleep", &["30"], &[], Some(&input), Duration::from_secs(1)),
+    )
+    .await
+    .expect("blocked writing input past the timeout")?;
+
+    assert_eq!(output.status, ExecutionStatus::Timeout);
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+async fn test_sandbox_gvisor_backend() -> Result<()> {
+    let limits = ResourceLimits {
+        backend: SandboxBackend::Gvisor,
+        ..default_test_limits()
+    };
+
+    // runsc needs root and a supported kernel, so the test is opt-in
+    if std::env::var_os("CODE_EXEC_TEST_GVISOR").is_none() {
+        if which::which("runsc").is_err() {
+            assert!(matches!(Sandbox::new(limits).await, Err(Error::Sandbox(_))));
+        }
+        return Ok(());
+    }
+
+    let mut sandbox = Sandbox::new(limits).await?;
+    let (stdout, _, _) = sandbox
+        .execute(
+            "sh",
+            &[
+                "-c",
+                "echo hello > out.txt; cat out.txt; ls /tmp | wc -l; dmesg",
+            ],
+            &[],
+            None,
+            Duration::from_secs(30),
+        )
+        .await?;
+
+    let lines: Vec<_> = stdout.lines().collect();
+    assert_eq!(lines[0], "hello");
+    // Only this sandbox is visible in the container's /tmp
+    assert_eq!(lines[1], "1");
+    // The kernel log is gVisor's own
+    assert!(stdout.contains("gVisor"));
+    assert!(sandbox.root_dir.join("out.txt").exists());
+
+    let output = sandbox
+        .run("sh", &["-c", "sleep 30"], &[], None, Duration::from_secs(1))
+        .await?;
+    assert_eq!(output.status, ExecutionStatus::Timeout);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_failed_execution_keeps_sandbox() -> Result<()> {
+    let service = CodeExecutionService::new(1, default_test_limits())
+        .await?
+        .with_keep_failed_sandboxes(Duration::from_secs(60));
+
+    // SQL programs take no arguments, which fails once the sandbox exists
+    let request = ExecutionRequest {
+        language: Language::Sql,
+        code: "SELECT 1;".to_string(),
+        args: vec!["--verbose".to_string()],
+        timeout: Duration::from_secs(5),
+        ..Default::default()
+    };
+
+    let result = service.execute(request).await;
+    let Err(Error::WithKeptSandbox { error, path }) = result else {
+        panic!("expected a kept sandbox, got {:?}", result);
+    };
+    assert!(matches!(*error, Error::Validation(_)), "{:?}", error);
+    assert!(path.join("tmp").is_dir());
+
+    std::fs::remove_dir_all(&path)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_kept_sandboxes_expire() -> Result<()> {
+    let kept = KeptSandboxes::new(Duration::from_millis(100));
+    let path = kept.keep(Sandbox::new(default_test_limits()).await?);
+    assert!(path.is_dir());
+    assert_eq!(kept.paths(), [path.clone()]);
+
+    // The janitor checks at least once a second
+    tokio::time::sleep(Duration::from_millis(2500)).await;
+    assert!(!path.exists());
+    assert!(kept.paths().is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_sandbox_toolchain_paths() -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let toolchain = tempfile::tempdir()?;
+    let python = toolchain.path().join("python3");
+    std::fs::write(&python, "#!/bin/sh\necho \"fake python $PATH\"\n")?;
+    std::fs::set_permissions(&python, std::fs::Permissions::from_mode(0o755))?;
+
+    let mut sandbox = Sandbox::new(ResourceLimits {
+        toolchain_paths: vec![toolchain.path().to_path_buf()],
+        ..default_test_limits()
+    })
+    .await?;
+    let (stdout, _, _) = sandbox
+        .execute(
+            "python3",
+            &["-c", "print(1)"],
+            &[],
+            None,
+            Duration::from_secs(5),
+        )
+        .await?;
+    assert_eq!(
+        stdout.trim(),
+        format!(
+            "fake python {}:/usr/bin:/bin:/usr/sbin:/sbin",
+            toolchain.path().display()
+        )
+    );
+    Ok(())
+}
@@ -0,0 +1,201 @@
+use super::utils::defaults::{default_test_limits, default_timeout};
+use crate::{
+    Advisory, CodeExecutionService, Dependency, DependencyScanner, Error, ExecutionRequest,
+    Language, OsvScanner, Result,
+};
+use async_trait::async_trait;
+use axum::{extract::State, routing::post, Json, Router};
+use serde_json::{json, Value};
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+fn dependency(name: &str, version: &str) -> Dependency {
+    Dependency {
+        name: name.to_string(),
+        version: version.to_string(),
+        source: None,
+    }
+}
+
+/// Flags one version of one package and records every dependency it is asked about
+#[derive(Default)]
+struct MockScanner {
+    scanned: Mutex<Vec<String>>,
+}
+
+#[async_trait]
+impl DependencyScanner for MockScanner {
+    async fn scan(&self, _language: Language, dependency: &Dependency) -> Result<Option<Advisory>> {
+        self.scanned.lock().unwrap().push(dependency.name.clone());
+        Ok(
+            (dependency.name == "requests" && dependency.version == "2.19.0").then(|| Advisory {
+                id: "PYSEC-2018-28".to_string(),
+                summary: "Credentials leaked on redirect".to_string(),
+            }),
+        )
+    }
+}
+
+#[tokio::test]
+async fn test_vulnerable_dependency_blocks_install() -> Result<()> {
+    let scanner = Arc::new(MockScanner::default());
+    let service = CodeExecutionService::new(1, default_test_limits())
+        .await?
+        .with_dependency_scanner(scanner.clone());
+
+    let request = ExecutionRequest {
+        language: Language::Python,
+        code: "import requests".to_string(),
+        dependencies: vec![
+            dependency("six", "1.16.0"),
+            dependency("requests", "2.19.0"),
+        ],
+        timeout: default_timeout(),
+        ..Default::default()
+    };
+
+    let result = service.execute(request).await;
+    assert!(
+        matches!(
+            &result,
+            Err(Error::VulnerableDependency { package, advisory })
+                if package == "requests@2.19.0" && advisory == "PYSEC-2018-28"
+        ),
+        "{:?}",
+        result
+    );
+    assert_eq!(*scanner.scanned.lock().unwrap(), ["six", "requests"]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_conflicting_dependencies_are_rejected() -> Result<()> {
+    let scanner = Arc::new(MockScanner::default());
+    let service = CodeExecutionService::new(1, default_test_limits())
+        .await?
+        .with_dependency_scanner(scanner.clone());
+
+    let request = ExecutionRequest {
+        language: Language::JavaScript,
+        code: "const _ = require('lodash');".to_string(),
+        dependencies: vec![dependency("lodash", "^4"), dependency("lodash", "^3")],
+        timeout: default_timeout(),
+        ..Default::default()
+    };
+
+    let result = service.execute(request).await;
+    assert!(
+        matches!(
+            &result,
+            Err(Error::DependencyConflict { package, versions })
+                if package == "lodash" && versions == &["^4", "^3"]
+        ),
+        "{:?}",
+        result
+    );
+    // Refused before anything is scanned or installed
+    assert!(scanner.scanned.lock().unwrap().is_empty());
+    Ok(())
+}
+
+/// Serve an OSV-style query endpoint that flags `requests` 2.19.0, counting queries
+async fn mock_osv() -> (String, Arc<AtomicUsize>) {
+    async fn query(
+        State(queries): State<Arc<AtomicUsize>>,
+        Json(body): Json<Value>,
+    ) -> Json<Value> {
+        queries.fetch_add(1, Ordering::SeqCst);
+        if body["package"]["name"] == "requests" && body["version"] == "2.19.0" {
+            Json(json!({ "vulns": [{ "id": "PYSEC-2018-28", "summary": "Credentials leaked" }] }))
+        } else {
+            Json(json!({}))
+        }
+    }
+
+    let queries = Arc::new(AtomicUsize::new(0));
+    let app = Router::new()
+        .route("/v1/query", post(query))
+        .with_state(queries.clone());
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, app).await });
+    (url, queries)
+}
+
+#[tokio::test]
+async fn test_osv_scanner() -> Result<()> {
+    let (url, queries) = mock_osv().await;
+    let scanner = OsvScanner::new(&url);
+
+    let advisory = scanner
+        .scan(Language::Python, &dependency("requests", "==2.19.0"))
+        .await?;
+    assert_eq!(advisory.map(|a| a.id).as_deref(), Some("PYSEC-2018-28"));
+    assert!(scanner
+        .scan(Language::Python, &dependency("requests", "2.31.0"))
+        .await?
+        .is_none());
+    assert_eq!(queries.load(Ordering::SeqCst), 2);
+
+    // Answers are cached, and ranges or ecosystems OSV does not know are skipped
+    scanner
+        .scan(Language::Python, &dependency("requests", "2.19.0"))
+        .await?;
+    scanner
+        .scan(Language::Python, &dependency("requests", ">=2.0"))
+        .await?;
+    scanner
+        .scan(Language::Sql, &dependency("requests", "2.19.0"))
+        .await?;
+    assert_eq!(queries.load(Ordering::SeqCst), 2);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_osv_scanner_requiring_exact_versions() -> Result<()> {
+    let (url, queries) = mock_osv().await;
+    let scanner = OsvScanner::new(&url).with_require_exact(true);
+
+    let git = Dependency {
+        source: Some("https://github.com/psf/requests".to_string()),
+        ..dependency("requests", "2.19.0")
+    };
+    for unchecked in [
+        dependency("requests", ">=2.0"),
+        dependency("requests", ""),
+        git,
+    ] {
+        let result = scanner.scan(Language::Python, &unchecked).await;
+        assert!(
+            matches!(result, Err(Error::InvalidDependency(_))),
+            "{:?}",
+            result
+        );
+    }
+    assert_eq!(queries.load(Ordering::SeqCst), 0);
+
+    assert!(scanner
+        .scan(Language::Python, &dependency("requests", "2.31.0"))
+        .await?
+        .is_none());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_osv_scanner_cache_expires() -> Result<()> {
+    let (url, queries) = mock_osv().await;
+    let scanner = OsvScanner::new(&url).with_cache_ttl(Duration::ZERO);
+
+    for _ in 0..2 {
+        scanner
+            .scan(Language::Python, &dependency("requests", "2.31.0"))
+            .await?;
+    }
+    assert_eq!(queries.load(Ordering::SeqCst), 2);
+    Ok(())
+}
@@ -1,5 +1,5 @@
 pub mod defaults {
-    use crate::{sandbox::Sandbox, Error, ResourceLimits, Result};
+    use crate::{sandbox::Sandbox, EnvPolicy, Error, ResourceLimits, Result, SandboxBackend};
     use tokio::time::Duration;
 
     pub fn default_test_limits() -> ResourceLimits {
@@ -8,9 +8,21 @@ pub mod defaults {
             ResourceLimits {
                 memory: 100 * 1024 * 1024, // 100MB
                 cpu_time: 5,               // 5 seconds
+                wall_time: 60,             // 60 seconds
+                install_timeout: 300,
+                compile_timeout: 300,
                 processes: 10,
-                file_size: 10 * 1024 * 1024,   // 10MB
+                file_size: 10 * 1024 * 1024, // 10MB
+                max_files: 10_000,
                 disk_space: 100 * 1024 * 1024, // 100MB
+                env_policy: EnvPolicy::default(),
+                backend: SandboxBackend::default(),
+                seccomp: false,
+                seccomp_profile: None,
+                readonly_mounts: Vec::new(),
+                run_as_uid: None,
+                run_as_gid: None,
+                toolchain_paths: Vec::new(),
             }
         }
 
@@ -19,9 +31,21 @@ pub mod defaults {
             ResourceLimits {
                 memory: u64::MAX, // Memory limits not reliable on macOS
                 cpu_time: 5,      // 5 seconds
+                wall_time: 60,    // 60 seconds
+                install_timeout: 300,
+                compile_timeout: 300,
                 processes: 10,
-                file_size: 10 * 1024 * 1024,   // 10MB
+                file_size: 10 * 1024 * 1024, // 10MB
+                max_files: 10_000,
                 disk_space: 100 * 1024 * 1024, // 100MB
+                env_policy: EnvPolicy::default(),
+                backend: SandboxBackend::default(),
+                seccomp: false,
+                seccomp_profile: None,
+                readonly_mounts: Vec::new(),
+                run_as_uid: None,
+                run_as_gid: None,
+                toolchain_paths: Vec::new(),
             }
         }
     }
@@ -39,6 +63,23 @@ pub mod defaults {
     }
 }
 
+pub mod host {
+    /// Whether this process may create mount namespaces
+    #[cfg(target_os = "linux")]
+    pub fn has_cap_sys_admin() -> bool {
+        const CAP_SYS_ADMIN: u32 = 21;
+        std::fs::read_to_string("/proc/self/status")
+            .ok()
+            .and_then(|status| {
+                let caps = status
+                    .lines()
+                    .find_map(|line| line.strip_prefix("CapEff:"))?;
+                u64::from_str_radix(caps.trim(), 16).ok()
+            })
+            .is_some_and(|caps| caps & (1 << CAP_SYS_ADMIN) != 0)
+    }
+}
+
 pub mod dependencies {
     use crate::Dependency;
 
@@ -58,6 +99,14 @@ pub mod dependencies {
         }
     }
 
+    pub fn jason_dependency() -> Dependency {
+        Dependency {
+            name: "jason".to_string(),
+            version: "1.4".to_string(),
+            source: None,
+        }
+    }
+
     pub fn serde_dependencies() -> Vec<Dependency> {
         vec![
             Dependency {
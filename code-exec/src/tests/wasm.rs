@@ -0,0 +1,74 @@
+use super::{fixtures::code_samples::RUST_HELLO, utils::defaults::default_test_limits};
+use crate::{
+    sandbox::Sandbox, wasm::WasmSandbox, CodeExecutor, Error, ExecutionRequest, Language,
+    ResourceLimits, Result, SandboxBackend,
+};
+use tokio::time::Duration;
+
+#[tokio::test]
+async fn test_rust_hello_world_wasm_backend() -> Result<()> {
+    let executor = CodeExecutor::new().await?;
+    let mut sandbox = Sandbox::new(ResourceLimits {
+        backend: SandboxBackend::Wasm,
+        ..default_test_limits()
+    })
+    .await?;
+
+    let request = ExecutionRequest {
+        language: Language::Rust,
+        code: RUST_HELLO.to_string(),
+        timeout: Duration::from_secs(30),
+        ..Default::default()
+    };
+
+    let result = executor.execute_in_sandbox(request, &mut sandbox).await?;
+    assert_eq!(result.stdout.trim(), "Hello from Rust!");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_wasm_fuel_limits_cpu_time() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let module = dir.path().join("spin.wat");
+    std::fs::write(
+        &module,
+        r#"(module
+            (memory (export "memory") 1)
+            (func (export "_start") (loop (br 0))))"#,
+    )?;
+
+    let sandbox = WasmSandbox::new(ResourceLimits {
+        cpu_time: 1,
+        ..default_test_limits()
+    });
+    let result = sandbox
+        .execute(&module, dir.path(), &[], &[], None, Duration::from_secs(30))
+        .await;
+    assert!(matches!(result, Err(Error::CpuTimeout(1))));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_wasm_captures_wasi_stdout() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let module = dir.path().join("hello.wat");
+    std::fs::write(
+        &module,
+        r#"(module
+            (import "wasi_snapshot_preview1" "fd_write"
+                (func $fd_write (param i32 i32 i32 i32) (result i32)))
+            (memory (export "memory") 1)
+            (data (i32.const 16) "Hello from wasm!\n")
+            (func (export "_start")
+                (i32.store (i32.const 0) (i32.const 16))
+                (i32.store (i32.const 4) (i32.const 17))
+                (drop (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))))"#,
+    )?;
+
+    let sandbox = WasmSandbox::new(default_test_limits());
+    let (stdout, _, _) = sandbox
+        .execute(&module, dir.path(), &[], &[], None, Duration::from_secs(5))
+        .await?;
+    assert_eq!(stdout, "Hello from wasm!\n");
+    Ok(())
+}
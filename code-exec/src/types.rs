@@ -1,45 +1,164 @@
 use nix::sys::resource::Usage;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::{Component, Path, PathBuf};
 use std::str::FromStr;
 use std::time::Duration;
+use tracing::warn;
+
+use crate::error::Error;
 
 /// Supported programming languages
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum Language {
+    #[default]
     Python,
     JavaScript,
     TypeScript,
     Rust,
     Go,
+    Kotlin,
+    /// Bash scripts, only run by services that allow shell execution
+    Bash,
+    /// SQL run against a fresh SQLite database
+    Sql,
+    Elixir,
+    /// Built and run with commands from the request's [`CustomLanguage`], only by
+    /// services that allow the programs those commands run
+    Custom,
 }
 
 impl FromStr for Language {
     type Err = String;
 
+    /// Parse a canonical name or one of its [aliases](Language::aliases), ignoring case
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s {
-            "python" => Ok(Language::Python),
-            "javascript" => Ok(Language::JavaScript),
-            "typescript" => Ok(Language::TypeScript),
-            "rust" => Ok(Language::Rust),
-            "go" => Ok(Language::Go),
-            _ => Err(format!("Unsupported language: {}", s)),
+        Language::ALL
+            .into_iter()
+            .find(|language| {
+                std::iter::once(language.as_str())
+                    .chain(language.aliases().iter().copied())
+                    .any(|name| name.eq_ignore_ascii_case(s))
+            })
+            .ok_or_else(|| format!("Unsupported language: {}", s))
+    }
+}
+
+impl fmt::Display for Language {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Language {
+    /// Every supported language
+    pub const ALL: [Language; 10] = [
+        Language::Python,
+        Language::JavaScript,
+        Language::TypeScript,
+        Language::Rust,
+        Language::Go,
+        Language::Kotlin,
+        Language::Bash,
+        Language::Sql,
+        Language::Elixir,
+        Language::Custom,
+    ];
+
+    /// Canonical name, as used in JSON
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Language::Python => "python",
+            Language::JavaScript => "javascript",
+            Language::TypeScript => "typescript",
+            Language::Rust => "rust",
+            Language::Go => "go",
+            Language::Kotlin => "kotlin",
+            Language::Bash => "bash",
+            Language::Sql => "sql",
+            Language::Elixir => "elixir",
+            Language::Custom => "custom",
         }
     }
+
+    /// Other names the language is parsed from
+    pub fn aliases(&self) -> &'static [&'static str] {
+        match self {
+            Language::Python => &["py", "python3"],
+            Language::JavaScript => &["js", "node", "nodejs"],
+            Language::TypeScript => &["ts"],
+            Language::Rust => &["rs"],
+            Language::Go => &["golang"],
+            Language::Kotlin => &["kt", "kts"],
+            Language::Bash => &["shell", "sh"],
+            Language::Sql => &["sqlite", "sqlite3"],
+            Language::Elixir => &["ex", "exs"],
+            Language::Custom => &[],
+        }
+    }
+
+    /// Map a file extension, with or without its leading dot, to a language
+    pub fn from_extension(extension: &str) -> Option<Language> {
+        match extension.trim_start_matches('.') {
+            "py" => Some(Language::Python),
+            "js" | "mjs" | "cjs" => Some(Language::JavaScript),
+            "ts" | "mts" | "cts" => Some(Language::TypeScript),
+            "rs" => Some(Language::Rust),
+            "go" => Some(Language::Go),
+            "kt" => Some(Language::Kotlin),
+            "sh" | "bash" => Some(Language::Bash),
+            "sql" => Some(Language::Sql),
+            "ex" | "exs" => Some(Language::Elixir),
+            _ => None,
+        }
+    }
+
+    /// Languages the source code could be written in, according to the ldm analyzers
+    pub fn detect(source_code: &str) -> Vec<Language> {
+        ldm::AnalyzerRegistry::shared()
+            .detect_languages(source_code)
+            .into_iter()
+            .filter_map(|language| language.parse().ok())
+            .collect()
+    }
 }
 
 /// Code execution request
-#[derive(Debug, Clone, Serialize, Deserialize)]
+///
+/// Requests built in code usually set the language, code and timeout and take the rest
+/// from `..Default::default()`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ExecutionRequest {
     /// Programming language
     pub language: Language,
-    /// Source code to execute
+    /// Source code to execute, empty when `entrypoint` selects one of `files` instead
+    #[serde(default)]
     pub code: String,
+    /// Further source files, written next to the program with their directory layout
+    #[serde(default)]
+    pub files: Vec<SourceFile>,
+    /// Data files, such as a CSV to parse, written to the program's working directory
+    /// before it runs; never compiled or run themselves
+    #[serde(default)]
+    pub fixtures: Vec<SourceFile>,
+    /// Path of the file in `files` to run instead of `code`
+    #[serde(default)]
+    pub entrypoint: Option<String>,
     /// Input data for the program
     #[serde(default)]
     pub input: Option<String>,
+    /// Input written step by step as the program prompts for it, instead of `input`
+    #[serde(default)]
+    pub input_script: Vec<InputStep>,
+    /// Command-line arguments of the program, passed after those its runtime needs
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Paths or globs, relative to the sandbox, of files the program writes that are
+    /// returned with a successful result
+    #[serde(default)]
+    pub capture_outputs: Vec<String>,
     /// Dependencies required by the code
     #[serde(default)]
     pub dependencies: Vec<Dependency>,
@@ -49,6 +168,180 @@ pub struct ExecutionRequest {
     /// Environment variables
     #[serde(default)]
     pub env_vars: HashMap<String, String>,
+    /// Include a listing of the sandbox tree in the error when execution fails
+    #[serde(default)]
+    pub include_sandbox_listing: bool,
+    /// Return what the package manager printed while installing `dependencies`, such as
+    /// resolver warnings and npm's audit summary, in [`ExecutionResult::install_diagnostics`]
+    #[serde(default)]
+    pub include_install_diagnostics: bool,
+    /// Short-lived credentials available to the program only while it runs
+    #[serde(default)]
+    pub injected_credentials: Vec<InjectedCredential>,
+    /// Fix the timezone, hash seeds and, where the runtime allows, random seeds so
+    /// repeated runs print the same output
+    #[serde(default)]
+    pub deterministic: bool,
+    /// Runtime JavaScript and TypeScript programs run on
+    #[serde(default)]
+    pub js_runtime: JsRuntime,
+    /// Whether the program is run or only compiled
+    #[serde(default)]
+    pub mode: ExecutionMode,
+    /// How the program's stdout and stderr are returned
+    #[serde(default)]
+    pub output_encoding: OutputEncoding,
+    /// Commands a [`Language::Custom`] program is built and run with
+    #[serde(default)]
+    pub custom: Option<CustomLanguage>,
+}
+
+impl ExecutionRequest {
+    /// Source of the program to run, the entrypoint's contents if one is set
+    pub fn program(&self) -> Result<&str, Error> {
+        let Some(entrypoint) = &self.entrypoint else {
+            return Ok(&self.code);
+        };
+        self.files
+            .iter()
+            .find(|file| &file.path == entrypoint)
+            .map(|file| file.contents.as_str())
+            .ok_or_else(|| Error::Validation(format!("Entrypoint {} is not in files", entrypoint)))
+    }
+
+    /// Check that every file and output pattern stays inside the program's directory
+    /// and that the entrypoint, if any, is one of the files
+    pub fn validate_files(&self) -> Result<(), Error> {
+        for pattern in &self.capture_outputs {
+            crate::outputs::validate_pattern(pattern)?;
+        }
+
+        for files in [&self.files, &self.fixtures] {
+            let mut paths = HashSet::new();
+            for file in files {
+                let path = Path::new(&file.path);
+                let relative = !file.path.is_empty()
+                    && path
+                        .components()
+                        .all(|component| matches!(component, Component::Normal(_)));
+                if !relative {
+                    return Err(Error::Validation(format!(
+                        "File path must be relative and must not contain '..': {}",
+                        file.path
+                    )));
+                }
+                if !paths.insert(path) {
+                    return Err(Error::Validation(format!("Duplicate file: {}", file.path)));
+                }
+            }
+        }
+
+        if self.entrypoint.is_some() && !self.code.is_empty() {
+            return Err(Error::Validation(
+                "Set either code or an entrypoint, not both".to_string(),
+            ));
+        }
+        self.program().map(|_| ())
+    }
+
+    /// Everything the program is sent on stdin, whether as `input` or by `input_script`
+    pub fn stdin(&self) -> Option<String> {
+        if self.input_script.is_empty() {
+            return self.input.clone();
+        }
+        let script = self.input_script.iter().map(|step| step.send.as_str());
+        Some(script.collect())
+    }
+
+    /// Check that at most one of `input` and `input_script` is set
+    pub fn validate_input(&self) -> Result<(), Error> {
+        if self.input.is_some() && !self.input_script.is_empty() {
+            return Err(Error::Validation(
+                "Set either input or an input script, not both".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Check that no dependency is requested at two different versions, which would
+    /// otherwise only fail once the package manager runs
+    pub fn validate_dependencies(&self) -> Result<(), Error> {
+        let packages: Vec<_> = self
+            .dependencies
+            .iter()
+            .map(|dependency| ldm::Package {
+                name: dependency.name.clone(),
+                version: Some(dependency.version.clone()),
+                source: ldm::PackageSource::System,
+            })
+            .collect();
+        match ldm::InstallationManager::check_conflicts(&packages) {
+            Err(ldm::Error::DependencyConflict { package, versions }) => {
+                Err(Error::DependencyConflict { package, versions })
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Step of an [`ExecutionRequest::input_script`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InputStep {
+    /// Text the program must print to stdout, after the previous step's, before `send` is
+    /// written; waited for at most [`INPUT_STEP_TIMEOUT`], then `send` is written anyway
+    #[serde(default)]
+    pub wait_for: Option<String>,
+    /// Written to the program's stdin as is, include the newline a line-based read needs
+    pub send: String,
+}
+
+/// Longest an [`InputStep`] waits for its prompt
+pub const INPUT_STEP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Source file of a multi-file submission
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SourceFile {
+    /// Path relative to the program's directory, e.g. `utils/strings.py`
+    pub path: String,
+    pub contents: String,
+}
+
+/// Secret handed to the program for the run phase only
+///
+/// Credentials are never visible while dependencies are installed or code is compiled,
+/// are wiped from the sandbox once the program exits, and are replaced with
+/// [`REDACTED`](crate::credentials::REDACTED) wherever they appear in the output.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct InjectedCredential {
+    /// Environment variable name the credential is exposed under
+    pub name: String,
+    /// Secret value
+    pub value: String,
+    /// How the credential reaches the program
+    #[serde(default)]
+    pub delivery: CredentialDelivery,
+}
+
+impl fmt::Debug for InjectedCredential {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("InjectedCredential")
+            .field("name", &self.name)
+            .field("value", &crate::credentials::REDACTED)
+            .field("delivery", &self.delivery)
+            .finish()
+    }
+}
+
+/// How an injected credential is handed to the program
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CredentialDelivery {
+    /// Set the value in the environment variable `name`
+    #[default]
+    Env,
+    /// Write the value to a file readable only by the sandbox user and set `<name>_FILE`
+    /// to its path
+    File,
 }
 
 /// Dependency specification
@@ -63,6 +356,15 @@ pub struct Dependency {
     pub source: Option<String>,
 }
 
+/// Entry in a sandbox directory listing
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SandboxEntry {
+    /// Path relative to the sandbox root, with a trailing `/` for directories
+    pub path: String,
+    /// File size in bytes (0 for directories)
+    pub size: u64,
+}
+
 #[derive(Default, Debug, Clone, Serialize, Deserialize)]
 pub struct ProcessStats {
     /// Peak memory usage in bytes
@@ -87,6 +389,34 @@ pub struct ProcessStats {
     pub system_time: Duration,
     #[serde(with = "duration_serde")]
     pub execution_time: Duration,
+    /// Time spent compiling, or preparing the source for interpreted languages, before
+    /// the program ran
+    #[serde(default, with = "duration_serde::option")]
+    pub compile_time: Option<Duration>,
+    /// Time spent installing dependencies, `None` if there were none
+    #[serde(default, with = "duration_serde::option")]
+    pub dependency_install_time: Option<Duration>,
+    /// Whether the dependencies were restored from the dependency cache, `None` if the
+    /// cache was not consulted
+    #[serde(default)]
+    pub dependency_cache_hit: Option<bool>,
+    /// Largest disk space in bytes the sandbox took while installing, compiling and
+    /// running, sampled as `disk_space` is enforced
+    #[serde(default)]
+    pub peak_disk_bytes: u64,
+}
+
+/// Outcome of [`crate::CodeExecutionService::warmup`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WarmupResult {
+    /// Whether the dependency set was already in the dependency cache
+    pub cache_hit: bool,
+    /// Time spent installing dependencies, `None` if there were none
+    #[serde(default, with = "duration_serde::option")]
+    pub dependency_install_time: Option<Duration>,
+    /// Time the whole warmup took, including installing a missing toolchain
+    #[serde(with = "duration_serde")]
+    pub duration: Duration,
 }
 
 /// Execution result
@@ -98,56 +428,543 @@ pub struct ExecutionResult {
     pub stdout: String,
     /// Program errors (stderr)
     pub stderr: String,
+    /// Raw stdout, base64 encoded, with [`OutputEncoding::Base64`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stdout_base64: Option<String>,
+    /// Raw stderr, base64 encoded, with [`OutputEncoding::Base64`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stderr_base64: Option<String>,
     /// Process statistics
     pub process_stats: ProcessStats,
+    /// What the package manager printed while installing dependencies, when requested
+    /// with `include_install_diagnostics` and it printed anything
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub install_diagnostics: Option<String>,
+    /// Sandbox tree, when requested with `include_sandbox_listing` and the status is not
+    /// [`ExecutionStatus::Success`]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sandbox_listing: Vec<SandboxEntry>,
+    /// Files matching the request's `capture_outputs`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub output_files: Vec<OutputFile>,
 }
 
-/// Execution status
+/// File written by the program, returned because it matched `capture_outputs`
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-#[serde(rename_all = "lowercase")]
+pub struct OutputFile {
+    /// Path relative to the sandbox
+    pub path: String,
+    pub content_base64: String,
+}
+
+/// How an execution ended
+///
+/// Serialized in snake case, e.g. `"timeout"` or `{"runtime_error": 1}`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum ExecutionStatus {
+    /// The program exited with status 0
     Success,
-    Error,
+    /// The program ran past its wall-clock budget and was killed
     Timeout,
+    /// The program used more memory than allowed
+    MemoryExceeded,
+    /// The program used up its CPU time limit
+    CpuExceeded,
+    /// The program exited with status 0, but its output, or the files captured with
+    /// `capture_outputs`, were cut off at the capture limit
+    OutputTruncated,
+    /// The code did not compile; diagnostics are in `stderr`
     CompilationError,
-    SystemError,
+    /// The program exited with a non-zero status, or `128 + signal` if it was killed
+    RuntimeError(i32),
+}
+
+impl ExecutionStatus {
+    /// Status an error from compiling or running a program stands for, if any
+    pub fn from_error(error: &Error) -> Option<Self> {
+        match error {
+            Error::WallTimeout(_) => Some(ExecutionStatus::Timeout),
+            Error::CpuTimeout(_) => Some(ExecutionStatus::CpuExceeded),
+            Error::ResourceExceeded {
+                resource: ResourceKind::Memory,
+                ..
+            } => Some(ExecutionStatus::MemoryExceeded),
+            Error::CompilationError(_) => Some(ExecutionStatus::CompilationError),
+            _ => None,
+        }
+    }
 }
 
-impl ToString for ExecutionStatus {
-    fn to_string(&self) -> String {
+impl fmt::Display for ExecutionStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            ExecutionStatus::Success => "success".to_string(),
-            ExecutionStatus::Error => "error".to_string(),
-            ExecutionStatus::Timeout => "timeout".to_string(),
-            ExecutionStatus::CompilationError => "compilation_error".to_string(),
-            ExecutionStatus::SystemError => "system_error".to_string(),
+            ExecutionStatus::Success => f.write_str("success"),
+            ExecutionStatus::Timeout => f.write_str("timeout"),
+            ExecutionStatus::MemoryExceeded => f.write_str("memory_exceeded"),
+            ExecutionStatus::CpuExceeded => f.write_str("cpu_exceeded"),
+            ExecutionStatus::OutputTruncated => f.write_str("output_truncated"),
+            ExecutionStatus::CompilationError => f.write_str("compilation_error"),
+            ExecutionStatus::RuntimeError(code) => write!(f, "runtime_error({})", code),
         }
     }
 }
 
 /// Resource limits for code execution
+///
+/// Time is limited in two independent ways:
+///
+/// - `cpu_time` is enforced per process with `RLIMIT_CPU` and only counts time spent
+///   running on a CPU. Busy loops hit it; sleeping or blocking on I/O does not.
+///   Exceeding it ends the execution with [`ExecutionStatus::CpuExceeded`].
+/// - `wall_time` caps the elapsed real time of a run, including time spent sleeping or
+///   waiting. The effective budget is the smaller of `wall_time` and the request's
+///   `timeout`. Exceeding it kills the process and ends the execution with
+///   [`ExecutionStatus::Timeout`].
+///
+/// Installing dependencies and compiling happen before the run and have their own
+/// wall-clock budgets, `install_timeout` and `compile_timeout`. Exceeding them kills the
+/// package manager or compiler and fails the execution with [`Error::InstallTimeout`]
+/// or [`Error::CompilationTimeout`].
+///
+/// [`Error::InstallTimeout`]: crate::Error::InstallTimeout
+/// [`Error::CompilationTimeout`]: crate::Error::CompilationTimeout
 #[derive(Debug, Clone)]
 pub struct ResourceLimits {
-    /// Maximum CPU time (seconds)
+    /// Maximum CPU time per process (seconds)
     pub cpu_time: u32,
+    /// Maximum elapsed wall-clock time (seconds)
+    pub wall_time: u64,
+    /// Maximum time spent installing dependencies (seconds)
+    pub install_timeout: u64,
+    /// Maximum time spent compiling (seconds)
+    pub compile_timeout: u64,
     /// Maximum memory (bytes)
     pub memory: u64,
     /// Maximum disk space (bytes)
     pub disk_space: u64,
-    /// Maximum number of processes
+    /// Maximum number of processes and threads the program may add, enforced with
+    /// `RLIMIT_NPROC` on Linux. The kernel counts all of the user's threads and exempts
+    /// root, so set `run_as_uid` to a dedicated user for the limit to hold reliably.
     pub processes: u32,
     /// Maximum file size (bytes)
     pub file_size: u64,
+    /// Maximum number of files and directories a program may create
+    pub max_files: u64,
+    /// Environment variables the sandboxed program may receive
+    pub env_policy: EnvPolicy,
+    /// Isolation mechanism programs run under
+    pub backend: SandboxBackend,
+    /// Restrict native programs to an allowlist of system calls (Linux only)
+    pub seccomp: bool,
+    /// Seccomp profile in seccompiler's JSON format, replacing the built-in allowlist
+    pub seccomp_profile: Option<PathBuf>,
+    /// Host paths, such as toolchain directories, the program needs to read. When set, the
+    /// program runs in a private mount namespace in which the whole host filesystem is
+    /// read-only except the sandbox directory (Linux only, requires `CAP_SYS_ADMIN`)
+    pub readonly_mounts: Vec<PathBuf>,
+    /// User the program runs as instead of the server's, which requires the server to
+    /// run as root
+    pub run_as_uid: Option<u32>,
+    /// Group the program runs as, defaulting to `run_as_uid`
+    pub run_as_gid: Option<u32>,
+    /// Directories, such as the `bin` of a toolchain installed under `/opt`, searched for
+    /// the program's command before the server's `PATH` and put ahead of the system
+    /// directories on the program's `PATH`
+    pub toolchain_paths: Vec<PathBuf>,
+}
+
+/// System directories on the `PATH` of every program
+const SANDBOX_PATH: &str = "/usr/bin:/bin:/usr/sbin:/sbin";
+
+/// Default [`ResourceLimits::install_timeout`], enough for a cold Cargo or npm install
+pub const DEFAULT_INSTALL_TIMEOUT: u64 = 300;
+
+/// Default [`ResourceLimits::compile_timeout`]
+pub const DEFAULT_COMPILE_TIMEOUT: u64 = 300;
+
+/// Kind of resource a limit applies to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResourceKind {
+    /// CPU time (seconds)
+    CpuTime,
+    /// Elapsed wall-clock time (seconds)
+    WallTime,
+    /// Resident memory (bytes)
+    Memory,
+    /// Disk space (bytes)
+    Disk,
+    /// Files and directories created (count)
+    Files,
+    /// Processes (count)
+    Processes,
+}
+
+impl fmt::Display for ResourceKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ResourceKind::CpuTime => "CPU time",
+            ResourceKind::WallTime => "wall-clock time",
+            ResourceKind::Memory => "memory",
+            ResourceKind::Disk => "disk",
+            ResourceKind::Files => "file count",
+            ResourceKind::Processes => "process count",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Commands a [`Language::Custom`] program is set up, compiled and run with.
+///
+/// Each command is split on whitespace and run in the sandbox under the execution's
+/// limits without a shell, with `{source}` replaced by the program's file name,
+/// `main.<file_extension>`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CustomLanguage {
+    /// Extension of the program's file, without the leading dot
+    pub file_extension: String,
+    /// Commands run before compiling, e.g. to create a project
+    #[serde(default)]
+    pub setup_commands: Vec<String>,
+    #[serde(default)]
+    pub compile_command: Option<String>,
+    pub run_command: String,
+}
+
+impl CustomLanguage {
+    /// Placeholder for the program's file name in commands
+    pub const SOURCE_PLACEHOLDER: &'static str = "{source}";
+
+    /// Name of the file the program is written to
+    pub fn source_file(&self) -> String {
+        format!("main.{}", self.file_extension)
+    }
+
+    /// Split a command into its program and arguments, filling in the placeholder
+    pub fn argv(&self, command: &str) -> Vec<String> {
+        let source = self.source_file();
+        command
+            .split_whitespace()
+            .map(|arg| arg.replace(Self::SOURCE_PLACEHOLDER, &source))
+            .collect()
+    }
+
+    /// Every command, in the order they run
+    pub fn commands(&self) -> impl Iterator<Item = &str> {
+        self.setup_commands
+            .iter()
+            .chain(&self.compile_command)
+            .chain(std::iter::once(&self.run_command))
+            .map(String::as_str)
+    }
+
+    /// Check that the extension is a plain file name suffix and that every command runs
+    /// one of the `allowed` programs, named as they are found on the `PATH`
+    pub fn validate(&self, allowed: &HashSet<String>) -> Result<(), Error> {
+        let extension = &self.file_extension;
+        if extension.is_empty() || !extension.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return Err(Error::Validation(format!(
+                "Custom file extension must be alphanumeric: {:?}",
+                extension
+            )));
+        }
+        for command in self.commands() {
+            let Some(program) = command.split_whitespace().next() else {
+                return Err(Error::Validation(
+                    "Custom commands must not be empty".to_string(),
+                ));
+            };
+            if !allowed.contains(program) {
+                return Err(Error::Validation(format!(
+                    "Program {} is not allowed in custom commands",
+                    program
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// What an execution does with the program
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExecutionMode {
+    /// Install dependencies, compile the program and run it
+    #[default]
+    Run,
+    /// Install dependencies and compile the program, or check its syntax for
+    /// interpreted languages, without running it. Diagnostics of code that does not
+    /// compile are in `stderr`, with [`ExecutionStatus::CompilationError`].
+    CompileOnly,
+}
+
+/// How a program's stdout and stderr are returned
+///
+/// The WebAssembly backend only supports [`OutputEncoding::Lossy`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputEncoding {
+    /// Decoded as UTF-8, with invalid bytes replaced by U+FFFD
+    #[default]
+    Lossy,
+    /// Decoded as UTF-8, failing the execution with [`Error::InvalidOutput`] if it is not
+    Strict,
+    /// Returned as written, base64 encoded in `stdout_base64` and `stderr_base64`, with
+    /// `stdout` and `stderr` left empty
+    Base64,
+}
+
+/// Runtime for JavaScript and TypeScript programs
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JsRuntime {
+    /// Node, with dependencies installed by npm and TypeScript compiled by `tsc`
+    #[default]
+    Node,
+    /// Deno, which runs TypeScript directly and fetches imports as they are loaded.
+    ///
+    /// Much faster to start for self-contained scripts, since nothing is installed.
+    Deno,
+}
+
+/// Isolation mechanism used to run programs
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SandboxBackend {
+    /// Native process restricted with rlimits, fully enforced on Linux only
+    #[default]
+    Native,
+    /// WebAssembly module run under wasmtime with a fuel budget and memory cap.
+    ///
+    /// Behaves the same on every host, but only languages that can target WASI are
+    /// supported. Requires the `wasm` feature.
+    Wasm,
+    /// Native process run in a gVisor container with `runsc`, which intercepts its
+    /// system calls in a user-space kernel.
+    ///
+    /// Linux only, and needs `runsc` installed and the service running as root.
+    Gvisor,
 }
 
 impl Default for ResourceLimits {
     fn default() -> Self {
         Self {
             cpu_time: 30,
+            wall_time: 60,
+            install_timeout: DEFAULT_INSTALL_TIMEOUT,
+            compile_timeout: DEFAULT_COMPILE_TIMEOUT,
             memory: 512 * 1024 * 1024,     // 512MB
             disk_space: 100 * 1024 * 1024, // 100MB
             processes: 10,
             file_size: 10 * 1024 * 1024, // 10MB
+            max_files: 10_000,
+            env_policy: EnvPolicy::default(),
+            backend: SandboxBackend::default(),
+            seccomp: false,
+            seccomp_profile: None,
+            readonly_mounts: Vec::new(),
+            run_as_uid: None,
+            run_as_gid: None,
+            toolchain_paths: Vec::new(),
+        }
+    }
+}
+
+impl ResourceLimits {
+    /// Tuned defaults for a language, used when no explicit limits are configured.
+    ///
+    /// Compiled languages get more headroom since the limits also cover the build:
+    ///
+    /// | Language   | CPU time | Wall time | Memory | Disk   | Processes |
+    /// |------------|----------|-----------|--------|--------|-----------|
+    /// | Python     | 10s      | 30s       | 256MB  | 100MB  | 10        |
+    /// | JavaScript | 10s      | 30s       | 256MB  | 200MB  | 10        |
+    /// | TypeScript | 20s      | 60s       | 512MB  | 300MB  | 20        |
+    /// | Go         | 30s      | 90s       | 1GB    | 500MB  | 32        |
+    /// | Rust       | 60s      | 180s      | 2GB    | 1GB    | 64        |
+    /// | Kotlin     | 30s      | 90s       | 1GB    | 500MB  | 32        |
+    /// | Bash       | 10s      | 30s       | 256MB  | 100MB  | 10        |
+    /// | SQL        | 10s      | 30s       | 256MB  | 100MB  | 10        |
+    /// | Elixir     | 20s      | 60s       | 512MB  | 300MB  | 32        |
+    /// | Custom     | 30s      | 90s       | 1GB    | 500MB  | 32        |
+    pub fn for_language(language: Language) -> Self {
+        let (cpu_time, memory, disk_space, processes) = match language {
+            Language::Python => (10, 256 * 1024 * 1024, 100 * 1024 * 1024, 10),
+            Language::JavaScript => (10, 256 * 1024 * 1024, 200 * 1024 * 1024, 10),
+            Language::TypeScript => (20, 512 * 1024 * 1024, 300 * 1024 * 1024, 20),
+            Language::Go => (30, 1024 * 1024 * 1024, 500 * 1024 * 1024, 32),
+            Language::Rust => (60, 2048 * 1024 * 1024, 1024 * 1024 * 1024, 64),
+            Language::Kotlin => (30, 1024 * 1024 * 1024, 500 * 1024 * 1024, 32),
+            Language::Bash | Language::Sql => (10, 256 * 1024 * 1024, 100 * 1024 * 1024, 10),
+            Language::Elixir => (20, 512 * 1024 * 1024, 300 * 1024 * 1024, 32),
+            Language::Custom => (30, 1024 * 1024 * 1024, 500 * 1024 * 1024, 32),
+        };
+
+        Self {
+            cpu_time,
+            // Leave room for waiting on I/O, e.g. while installing dependencies
+            wall_time: cpu_time as u64 * 3,
+            install_timeout: DEFAULT_INSTALL_TIMEOUT,
+            compile_timeout: DEFAULT_COMPILE_TIMEOUT,
+            memory,
+            disk_space,
+            processes,
+            file_size: 10 * 1024 * 1024, // 10MB
+            max_files: 10_000,
+            env_policy: EnvPolicy::default(),
+            backend: SandboxBackend::default(),
+            seccomp: false,
+            seccomp_profile: None,
+            readonly_mounts: Vec::new(),
+            run_as_uid: None,
+            run_as_gid: None,
+            toolchain_paths: Vec::new(),
+        }
+    }
+
+    /// `PATH` of programs run under these limits, the toolchain directories first
+    pub fn program_path(&self) -> String {
+        let toolchains = self
+            .toolchain_paths
+            .iter()
+            .map(|dir| dir.display().to_string());
+        let dirs: Vec<_> = toolchains.chain([SANDBOX_PATH.to_string()]).collect();
+        dirs.join(":")
+    }
+
+    /// Where the program's command is found in the toolchain directories, if it is
+    pub fn find_toolchain_command(&self, command: &str) -> Option<PathBuf> {
+        self.toolchain_paths
+            .iter()
+            .map(|dir| dir.join(command))
+            .find(|path| path.is_file())
+    }
+}
+
+/// Variables that let a caller alter how the loader or a runtime starts the program
+pub const DEFAULT_ENV_DENYLIST: &[&str] = &[
+    "LD_PRELOAD",
+    "LD_LIBRARY_PATH",
+    "LD_AUDIT",
+    "DYLD_*",
+    "GCONV_PATH",
+    "NODE_OPTIONS",
+    "NODE_PATH",
+    "PYTHONSTARTUP",
+    "PYTHONHOME",
+    "PYTHONPATH",
+    "PYTHONINSPECT",
+    "PERL5OPT",
+    "RUBYOPT",
+    "BASH_ENV",
+    "ENV",
+    "JAVA_TOOL_OPTIONS",
+    "RUSTC_WRAPPER",
+    "GOFLAGS",
+];
+
+/// Most environment variables a request may set
+pub const DEFAULT_MAX_ENV_VARS: usize = 128;
+
+/// Most bytes of names and values a request's environment variables may add up to
+pub const DEFAULT_MAX_ENV_BYTES: usize = 64 * 1024;
+
+/// Policy applied to request environment variables before execution.
+///
+/// Names ending in `*` match any variable with that prefix.
+#[derive(Debug, Clone)]
+pub struct EnvPolicy {
+    /// Variables stripped before execution
+    pub denylist: Vec<String>,
+    /// If set, only these variables are passed through
+    pub allowlist: Option<Vec<String>>,
+    /// Reject requests that set a denied variable instead of stripping it
+    pub strict: bool,
+    /// Most variables a request may set
+    pub max_vars: usize,
+    /// Most bytes the names and values of a request's variables may add up to
+    pub max_bytes: usize,
+}
+
+impl Default for EnvPolicy {
+    fn default() -> Self {
+        Self {
+            denylist: DEFAULT_ENV_DENYLIST.iter().map(|s| s.to_string()).collect(),
+            allowlist: None,
+            strict: false,
+            max_vars: DEFAULT_MAX_ENV_VARS,
+            max_bytes: DEFAULT_MAX_ENV_BYTES,
+        }
+    }
+}
+
+impl EnvPolicy {
+    /// Check that a request's variables are within the limits and can be passed to a
+    /// process at all
+    pub fn validate(&self, env: &HashMap<String, String>) -> Result<(), Error> {
+        if env.len() > self.max_vars {
+            return Err(Error::Validation(format!(
+                "Too many environment variables: {} (max {})",
+                env.len(),
+                self.max_vars
+            )));
+        }
+        let bytes: usize = env.iter().map(|(key, value)| key.len() + value.len()).sum();
+        if bytes > self.max_bytes {
+            return Err(Error::Validation(format!(
+                "Environment variables too large: {} bytes (max {})",
+                bytes, self.max_bytes
+            )));
+        }
+        for (key, value) in env {
+            if key.is_empty() || key.contains(['=', '\0']) {
+                return Err(Error::Validation(format!(
+                    "Invalid environment variable name: {:?}",
+                    key
+                )));
+            }
+            if value.contains('\0') {
+                return Err(Error::Validation(format!(
+                    "Environment variable contains a NUL byte: {}",
+                    key
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Remove variables the policy does not permit
+    pub fn apply(&self, env: &[(String, String)]) -> Result<Vec<(String, String)>, Error> {
+        let mut permitted = Vec::with_capacity(env.len());
+
+        for (key, value) in env {
+            let denied = self.denylist.iter().any(|p| Self::matches(p, key));
+            let allowed = self
+                .allowlist
+                .as_ref()
+                .is_none_or(|list| list.iter().any(|p| Self::matches(p, key)));
+
+            if denied || !allowed {
+                if self.strict {
+                    return Err(Error::Validation(format!(
+                        "Environment variable not permitted: {}",
+                        key
+                    )));
+                }
+                warn!("Stripping environment variable: {}", key);
+                continue;
+            }
+            permitted.push((key.clone(), value.clone()));
+        }
+
+        Ok(permitted)
+    }
+
+    fn matches(pattern: &str, key: &str) -> bool {
+        match pattern.strip_suffix('*') {
+            Some(prefix) => key.starts_with(prefix),
+            None => pattern == key,
         }
     }
 }
@@ -170,4 +987,91 @@ mod duration_serde {
         let secs = u64::deserialize(deserializer)?;
         Ok(Duration::from_secs(secs))
     }
+
+    pub mod option {
+        use serde::{Deserialize, Deserializer, Serializer};
+        use std::time::Duration;
+
+        pub fn serialize<S>(duration: &Option<Duration>, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            match duration {
+                Some(duration) => serializer.serialize_some(&duration.as_secs()),
+                None => serializer.serialize_none(),
+            }
+        }
+
+        pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<Duration>, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            let secs = Option::<u64>::deserialize(deserializer)?;
+            Ok(secs.map(Duration::from_secs))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_language_from_extension() {
+        assert_eq!(Language::from_extension("py"), Some(Language::Python));
+        assert_eq!(Language::from_extension(".mjs"), Some(Language::JavaScript));
+        assert_eq!(Language::from_extension("ts"), Some(Language::TypeScript));
+        assert_eq!(Language::from_extension("rs"), Some(Language::Rust));
+        assert_eq!(Language::from_extension(".go"), Some(Language::Go));
+        assert_eq!(Language::from_extension("rb"), None);
+    }
+
+    #[test]
+    fn test_language_aliases() {
+        for (name, language) in [
+            ("py", Language::Python),
+            ("Python3", Language::Python),
+            ("PYTHON", Language::Python),
+            ("js", Language::JavaScript),
+            ("node", Language::JavaScript),
+            ("NodeJS", Language::JavaScript),
+            ("ts", Language::TypeScript),
+            ("TypeScript", Language::TypeScript),
+            ("rs", Language::Rust),
+            ("golang", Language::Go),
+            ("Go", Language::Go),
+            ("kt", Language::Kotlin),
+            ("kts", Language::Kotlin),
+            ("sh", Language::Bash),
+            ("Shell", Language::Bash),
+            ("sqlite3", Language::Sql),
+            ("SQL", Language::Sql),
+            ("ex", Language::Elixir),
+            ("exs", Language::Elixir),
+        ] {
+            assert_eq!(name.parse::<Language>(), Ok(language), "{}", name);
+        }
+
+        for name in ["c++", "cobol", "pyth", "", " py"] {
+            assert!(name.parse::<Language>().is_err(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_language_limits_rust_exceeds_python() {
+        let rust = ResourceLimits::for_language(Language::Rust);
+        let python = ResourceLimits::for_language(Language::Python);
+
+        assert!(rust.memory > python.memory);
+        assert!(rust.cpu_time > python.cpu_time);
+    }
+
+    #[test]
+    fn test_language_limits_compiled_exceed_scripting() {
+        let go = ResourceLimits::for_language(Language::Go);
+        let javascript = ResourceLimits::for_language(Language::JavaScript);
+
+        assert!(go.memory > javascript.memory);
+        assert!(go.disk_space > javascript.disk_space);
+    }
 }
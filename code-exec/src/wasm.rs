@@ -0,0 +1,162 @@
+//! WebAssembly sandbox backed by wasmtime
+//!
+//! Programs compiled to WASI preview 1 modules run with a fuel budget derived from
+//! `cpu_time`, a linear memory cap and a wall-clock deadline. None of these depend on
+//! the host OS, so limits are enforced the same way on Linux and macOS.
+
+use std::{
+    path::Path,
+    time::{Duration, Instant},
+};
+use tracing::debug;
+use wasmtime::{Config, Engine, Linker, Module, Store, StoreLimits, StoreLimitsBuilder, Trap};
+use wasmtime_wasi::{
+    pipe::{MemoryInputPipe, MemoryOutputPipe},
+    preview1::{self, WasiP1Ctx},
+    DirPerms, FilePerms, I32Exit, WasiCtxBuilder,
+};
+
+use crate::{error::Error, types::ResourceLimits, ProcessStats};
+
+/// Fuel granted per second of `cpu_time`; one unit is roughly one wasm instruction
+pub const FUEL_PER_CPU_SECOND: u64 = 1_000_000_000;
+
+/// Maximum bytes captured from each of stdout and stderr
+const MAX_OUTPUT_SIZE: usize = 10 * 1024 * 1024;
+
+/// Per-run state held by the wasmtime store
+struct WasmState {
+    wasi: WasiP1Ctx,
+    limits: StoreLimits,
+}
+
+/// Sandbox running WASI modules under wasmtime
+pub struct WasmSandbox {
+    limits: ResourceLimits,
+}
+
+impl WasmSandbox {
+    /// Create a new WebAssembly sandbox
+    pub fn new(limits: ResourceLimits) -> Self {
+        Self { limits }
+    }
+
+    /// Run a WASI module with `dir` preopened as its working directory and `args`
+    /// following the module's name in its argv.
+    ///
+    /// The module is interrupted once `timeout` or the configured `wall_time` elapses,
+    /// whichever is shorter.
+    pub async fn execute(
+        &self,
+        module: &Path,
+        dir: &Path,
+        args: &[String],
+        env: &[(String, String)],
+        input: Option<&str>,
+        timeout: Duration,
+    ) -> Result<(String, String, ProcessStats), Error> {
+        let timeout = timeout.min(Duration::from_secs(self.limits.wall_time));
+        let env = self.limits.env_policy.apply(env)?;
+
+        let mut config = Config::new();
+        config.consume_fuel(true).epoch_interruption(true);
+        let engine = Engine::new(&config)
+            .map_err(|e| Error::Sandbox(format!("Failed to create wasm engine: {}", e)))?;
+        let program = module
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let module = Module::from_file(&engine, module)
+            .map_err(|e| Error::Sandbox(format!("Failed to load wasm module: {}", e)))?;
+
+        let stdout = MemoryOutputPipe::new(MAX_OUTPUT_SIZE);
+        let stderr = MemoryOutputPipe::new(MAX_OUTPUT_SIZE);
+        let mut builder = WasiCtxBuilder::new();
+        builder
+            .arg(program)
+            .args(args)
+            .stdin(MemoryInputPipe::new(input.unwrap_or_default().to_string()))
+            .stdout(stdout.clone())
+            .stderr(stderr.clone())
+            .preopened_dir(dir, ".", DirPerms::all(), FilePerms::all())
+            .map_err(|e| Error::Sandbox(format!("Failed to preopen sandbox directory: {}", e)))?;
+        for (key, value) in &env {
+            builder.env(key, value);
+        }
+
+        let mut store = Store::new(
+            &engine,
+            WasmState {
+                wasi: builder.build_p1(),
+                limits: StoreLimitsBuilder::new()
+                    .memory_size(self.limits.memory as usize)
+                    .build(),
+            },
+        );
+        store.limiter(|state| &mut state.limits);
+        store
+            .set_fuel(self.limits.cpu_time as u64 * FUEL_PER_CPU_SECOND)
+            .map_err(|e| Error::Sandbox(format!("Failed to set fuel: {}", e)))?;
+        store.set_epoch_deadline(1);
+
+        let mut linker = Linker::new(&engine);
+        preview1::add_to_linker_sync(&mut linker, |state: &mut WasmState| &mut state.wasi)
+            .map_err(|e| Error::Sandbox(format!("Failed to link WASI: {}", e)))?;
+
+        // Interrupt the module once the wall-clock budget runs out
+        let deadline = {
+            let engine = engine.clone();
+            tokio::spawn(async move {
+                tokio::time::sleep(timeout).await;
+                engine.increment_epoch();
+            })
+        };
+
+        let start_time = Instant::now();
+        let result = tokio::task::spawn_blocking(move || {
+            let instance = linker.instantiate(&mut store, &module)?;
+            let start = instance.get_typed_func::<(), ()>(&mut store, "_start")?;
+            start.call(&mut store, ())
+        })
+        .await
+        .map_err(|e| Error::Sandbox(format!("Wasm execution task failed: {}", e)))?;
+        deadline.abort();
+        let execution_time = start_time.elapsed();
+
+        let stdout = String::from_utf8_lossy(&stdout.contents()).to_string();
+        let stderr = String::from_utf8_lossy(&stderr.contents()).to_string();
+
+        if let Err(e) = result {
+            let exit_code = match e.downcast_ref::<I32Exit>() {
+                Some(I32Exit(code)) => *code,
+                None => match e.downcast_ref::<Trap>() {
+                    Some(Trap::OutOfFuel) => return Err(Error::CpuTimeout(self.limits.cpu_time)),
+                    Some(Trap::Interrupt) => return Err(Error::WallTimeout(timeout.as_secs())),
+                    _ => {
+                        return Err(Error::Sandbox(format!(
+                            "Wasm module trapped: {:#} (stderr: {})",
+                            e, stderr
+                        )))
+                    }
+                },
+            };
+
+            if exit_code != 0 {
+                return Err(Error::Sandbox(format!(
+                    "Process exited with status: {} (stderr: {})",
+                    exit_code, stderr
+                )));
+            }
+        }
+
+        debug!("Wasm module finished in {:?}", execution_time);
+        Ok((
+            stdout,
+            stderr,
+            ProcessStats {
+                execution_time,
+                ..Default::default()
+            },
+        ))
+    }
+}
@@ -11,10 +11,16 @@ use blueprint_sdk::tangle::consumer::TangleConsumer;
 use blueprint_sdk::tangle::filters::MatchesServiceId;
 use blueprint_sdk::tangle::layers::TangleLayer;
 use blueprint_sdk::tangle::producer::TangleProducer;
-use secure_code_execution_lib::{EXECUTE_CODE_JOB_ID, ServiceContext, execute_code};
+use secure_code_execution_lib::{
+    CodeExecConfig, EXECUTE_CODE_JOB_ID, ServiceContext, execute_code, wait_for_server,
+};
+use std::time::Duration;
 use tower::filter::FilterLayer;
-use tracing::error;
 use tracing::level_filters::LevelFilter;
+use tracing::{error, info};
+
+// How long the code-exec server may take to start before startup fails
+const SERVER_STARTUP_TIMEOUT: Duration = Duration::from_secs(60);
 
 #[tokio::main]
 async fn main() -> Result<(), blueprint_sdk::Error> {
@@ -33,12 +39,23 @@ async fn main() -> Result<(), blueprint_sdk::Error> {
     let tangle_config = TangleConfig::default();
 
     let service_id = env.protocol_settings.tangle()?.service_id.unwrap();
+
+    // Jobs sent before the code-exec server listens would fail, so wait for it first
+    let context = ServiceContext::from_config(&CodeExecConfig::from_env()?);
+    wait_for_server(
+        &context.http_client,
+        &context.code_exec_url,
+        SERVER_STARTUP_TIMEOUT,
+    )
+    .await?;
+    info!("code-exec server at {} is healthy", context.code_exec_url);
+
     let result = BlueprintRunner::builder(tangle_config, env)
         .router(
             Router::new()
                 .route(EXECUTE_CODE_JOB_ID, execute_code.layer(TangleLayer))
                 .layer(FilterLayer::new(MatchesServiceId(service_id)))
-                .with_context(ServiceContext::new()),
+                .with_context(context),
         )
         .producer(tangle_producer)
         .consumer(tangle_consumer)
@@ -0,0 +1,271 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+// Attempts per request while the code-exec server cannot be reached
+const MAX_ATTEMPTS: u32 = 5;
+// Delay before the first retry, doubled after each attempt
+const INITIAL_BACKOFF: Duration = Duration::from_millis(200);
+// Requests in a row that found the server unreachable before the circuit opens
+const FAILURE_THRESHOLD: u32 = 3;
+// How long requests fail fast once the circuit is open
+const OPEN_DURATION: Duration = Duration::from_secs(30);
+// Delay between health checks while waiting for the server to start
+const HEALTH_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Fails requests fast once the code-exec server has been unreachable repeatedly.
+///
+/// After the circuit has been open for [`OPEN_DURATION`], the next request goes through
+/// again; it closes the circuit if the server answers and reopens it otherwise.
+#[derive(Debug, Default)]
+pub struct CircuitBreaker {
+    state: Mutex<BreakerState>,
+}
+
+#[derive(Debug, Default)]
+struct BreakerState {
+    consecutive_failures: u32,
+    opened_at: Option<Instant>,
+}
+
+impl CircuitBreaker {
+    fn check(&self) -> Result<(), blueprint_sdk::Error> {
+        let state = self.state.lock().unwrap();
+        match state.opened_at {
+            Some(opened_at) if opened_at.elapsed() < OPEN_DURATION => {
+                Err(blueprint_sdk::Error::Other(format!(
+                    "code-exec server unreachable in the last {} requests, not retrying for {}s",
+                    state.consecutive_failures,
+                    (OPEN_DURATION - opened_at.elapsed()).as_secs()
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn record_success(&self) {
+        *self.state.lock().unwrap() = BreakerState::default();
+    }
+
+    fn record_failure(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.consecutive_failures += 1;
+        if state.consecutive_failures >= FAILURE_THRESHOLD {
+            state.opened_at = Some(Instant::now());
+        }
+    }
+}
+
+/// POST `body` to `url` and decode the JSON response.
+///
+/// Connection errors, such as the server still starting, are retried with exponential
+/// backoff. Error statuses are returned straight away, as sending the same request again
+/// would not change the answer.
+pub async fn post_json<B, R>(
+    client: &reqwest::Client,
+    breaker: &CircuitBreaker,
+    url: &str,
+    body: &B,
+) -> Result<R, blueprint_sdk::Error>
+where
+    B: Serialize + ?Sized,
+    R: DeserializeOwned,
+{
+    breaker.check()?;
+
+    let mut backoff = INITIAL_BACKOFF;
+    let mut attempt = 1;
+    let response = loop {
+        match client.post(url).json(body).send().await {
+            Ok(response) => break response,
+            Err(e) if e.is_connect() && attempt < MAX_ATTEMPTS => {
+                tokio::time::sleep(backoff).await;
+                backoff *= 2;
+                attempt += 1;
+            }
+            Err(e) => {
+                if e.is_connect() {
+                    breaker.record_failure();
+                }
+                return Err(blueprint_sdk::Error::Other(e.to_string()));
+            }
+        }
+    };
+    breaker.record_success();
+
+    let status = response.status();
+    if !status.is_success() {
+        let message = response.text().await.unwrap_or_default();
+        return Err(blueprint_sdk::Error::Other(format!(
+            "code-exec server returned {}: {}",
+            status, message
+        )));
+    }
+
+    response
+        .json::<R>()
+        .await
+        .map_err(|e| blueprint_sdk::Error::Other(e.to_string()))
+}
+
+/// Poll `{url}/health` until the code-exec server answers successfully.
+///
+/// Fails if it has not within `timeout`, reporting the last error seen.
+pub async fn wait_for_server(
+    client: &reqwest::Client,
+    url: &str,
+    timeout: Duration,
+) -> Result<(), blueprint_sdk::Error> {
+    let health_url = format!("{}/health", url);
+    let deadline = Instant::now() + timeout;
+    loop {
+        let last_error = match client.get(&health_url).send().await {
+            Ok(response) if response.status().is_success() => return Ok(()),
+            Ok(response) => format!("health check returned {}", response.status()),
+            Err(e) => e.to_string(),
+        };
+
+        if Instant::now() >= deadline {
+            return Err(blueprint_sdk::Error::Other(format!(
+                "code-exec server at {} not healthy after {}s: {}",
+                url,
+                timeout.as_secs(),
+                last_error
+            )));
+        }
+        tokio::time::sleep(HEALTH_POLL_INTERVAL).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{Value, json};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    // Answer one request on `listener` with `status` and a JSON body
+    async fn respond(listener: TcpListener, status: &str, body: &str) {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut buffer = [0; 4096];
+        let _ = stream.read(&mut buffer).await.unwrap();
+        let response = format!(
+            "HTTP/1.1 {}\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+            status,
+            body.len(),
+            body
+        );
+        stream.write_all(response.as_bytes()).await.unwrap();
+    }
+
+    // Address nothing listens on, so connections are refused
+    async fn unused_address() -> std::net::SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        listener.local_addr().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_retries_until_server_accepts() {
+        let addr = unused_address().await;
+        let server = tokio::spawn(async move {
+            // The first attempt is refused, the server comes up before the retry
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            let listener = TcpListener::bind(addr).await.unwrap();
+            respond(listener, "200 OK", r#"{"stdout":"ok"}"#).await;
+        });
+
+        let breaker = CircuitBreaker::default();
+        let response: Value = post_json(
+            &reqwest::Client::new(),
+            &breaker,
+            &format!("http://{}/execute", addr),
+            &json!({}),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(response["stdout"], "ok");
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_client_errors_are_not_retried() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(respond(listener, "400 Bad Request", r#"{"error":"bad"}"#));
+
+        let breaker = CircuitBreaker::default();
+        let error = post_json::<_, Value>(
+            &reqwest::Client::new(),
+            &breaker,
+            &format!("http://{}/execute", addr),
+            &json!({}),
+        )
+        .await
+        .unwrap_err();
+
+        assert!(error.to_string().contains("400"));
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_circuit_opens_after_repeated_failures() {
+        let addr = unused_address().await;
+        let url = format!("http://{}/execute", addr);
+        let client = reqwest::Client::new();
+        let breaker = CircuitBreaker::default();
+
+        for _ in 0..FAILURE_THRESHOLD {
+            assert!(
+                post_json::<_, Value>(&client, &breaker, &url, &json!({}))
+                    .await
+                    .is_err()
+            );
+        }
+
+        // The server is up now, but the open circuit fails without contacting it
+        let listener = TcpListener::bind(addr).await.unwrap();
+        let started = Instant::now();
+        let error = post_json::<_, Value>(&client, &breaker, &url, &json!({}))
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("unreachable"));
+        assert!(started.elapsed() < INITIAL_BACKOFF);
+        drop(listener);
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_server_becomes_healthy() {
+        let addr = unused_address().await;
+        let server = tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(500)).await;
+            let listener = TcpListener::bind(addr).await.unwrap();
+            respond(listener, "200 OK", r#""OK""#).await;
+        });
+
+        wait_for_server(
+            &reqwest::Client::new(),
+            &format!("http://{}", addr),
+            Duration::from_secs(10),
+        )
+        .await
+        .unwrap();
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_server_times_out() {
+        let addr = unused_address().await;
+
+        let error = wait_for_server(
+            &reqwest::Client::new(),
+            &format!("http://{}", addr),
+            Duration::from_millis(500),
+        )
+        .await
+        .unwrap_err();
+        assert!(error.to_string().contains("not healthy"));
+    }
+}
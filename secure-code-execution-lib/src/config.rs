@@ -0,0 +1,108 @@
+use std::str::FromStr;
+
+// Port of the code-exec server unless `CODE_EXEC_PORT` is set
+const DEFAULT_PORT: u16 = 8080;
+// Jobs forwarded at once unless `CODE_EXEC_MAX_CONCURRENT` is set
+const DEFAULT_MAX_CONCURRENT: usize = 10;
+
+/// Where the blueprint finds the code-exec server and how many jobs it sends at once
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CodeExecConfig {
+    pub url: String,
+    pub max_concurrent: usize,
+}
+
+impl Default for CodeExecConfig {
+    fn default() -> Self {
+        Self {
+            url: format!("http://localhost:{}", DEFAULT_PORT),
+            max_concurrent: DEFAULT_MAX_CONCURRENT,
+        }
+    }
+}
+
+impl CodeExecConfig {
+    /// Read the configuration from the environment.
+    ///
+    /// `CODE_EXEC_URL` names the server, otherwise it is expected on localhost at
+    /// `CODE_EXEC_PORT` (default: 8080). `CODE_EXEC_MAX_CONCURRENT` (default: 10) caps the
+    /// jobs forwarded at once.
+    pub fn from_env() -> Result<Self, blueprint_sdk::Error> {
+        Self::from_vars(|name| std::env::var(name).ok())
+    }
+
+    /// Like [`from_env`](Self::from_env), with variables looked up by `var`
+    pub fn from_vars(var: impl Fn(&str) -> Option<String>) -> Result<Self, blueprint_sdk::Error> {
+        let port: u16 = parse(&var, "CODE_EXEC_PORT")?.unwrap_or(DEFAULT_PORT);
+        let url = var("CODE_EXEC_URL").unwrap_or_else(|| format!("http://localhost:{}", port));
+        let max_concurrent =
+            parse(&var, "CODE_EXEC_MAX_CONCURRENT")?.unwrap_or(DEFAULT_MAX_CONCURRENT);
+        if max_concurrent == 0 {
+            return Err(blueprint_sdk::Error::Other(
+                "CODE_EXEC_MAX_CONCURRENT must be at least 1".to_string(),
+            ));
+        }
+
+        Ok(Self {
+            url: url.trim_end_matches('/').to_string(),
+            max_concurrent,
+        })
+    }
+}
+
+fn parse<T: FromStr>(
+    var: &impl Fn(&str) -> Option<String>,
+    name: &str,
+) -> Result<Option<T>, blueprint_sdk::Error> {
+    var(name)
+        .map(|value| {
+            value
+                .parse()
+                .map_err(|_| blueprint_sdk::Error::Other(format!("Invalid {}: {:?}", name, value)))
+        })
+        .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn config(vars: &[(&str, &str)]) -> Result<CodeExecConfig, blueprint_sdk::Error> {
+        let vars: HashMap<_, _> = vars.iter().copied().collect();
+        CodeExecConfig::from_vars(|name| vars.get(name).map(|value| value.to_string()))
+    }
+
+    #[test]
+    fn test_defaults() {
+        assert_eq!(config(&[]).unwrap(), CodeExecConfig::default());
+    }
+
+    #[test]
+    fn test_port_override() {
+        let config = config(&[
+            ("CODE_EXEC_PORT", "9090"),
+            ("CODE_EXEC_MAX_CONCURRENT", "4"),
+        ])
+        .unwrap();
+        assert_eq!(config.url, "http://localhost:9090");
+        assert_eq!(config.max_concurrent, 4);
+    }
+
+    #[test]
+    fn test_url_override() {
+        let config = config(&[
+            ("CODE_EXEC_URL", "http://code-exec:3000/"),
+            ("CODE_EXEC_PORT", "9090"),
+        ])
+        .unwrap();
+        assert_eq!(config.url, "http://code-exec:3000");
+    }
+
+    #[test]
+    fn test_invalid_values() {
+        assert!(config(&[("CODE_EXEC_PORT", "eighty")]).is_err());
+        assert!(config(&[("CODE_EXEC_PORT", "70000")]).is_err());
+        assert!(config(&[("CODE_EXEC_MAX_CONCURRENT", "0")]).is_err());
+    }
+}
@@ -1,8 +1,16 @@
 use blueprint_sdk::extract::Context;
 use blueprint_sdk::macros::context::{ServicesContext, TangleClientContext};
 use blueprint_sdk::runner::config::BlueprintEnvironment;
-use blueprint_sdk::tangle::extract::{TangleArgs3, TangleResult};
+use blueprint_sdk::tangle::extract::{TangleArgs4, TangleResult};
 use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+
+mod client;
+mod config;
+
+pub use client::{CircuitBreaker, wait_for_server};
+pub use config::CodeExecConfig;
 
 // The job ID for execute_code
 pub const EXECUTE_CODE_JOB_ID: u32 = 0;
@@ -15,12 +23,44 @@ pub struct ServiceContext {
     pub call_id: Option<u64>,
     pub code_exec_url: String,
     pub http_client: reqwest::Client,
+    /// Shared by every job so an unreachable server fails them fast
+    pub circuit_breaker: Arc<CircuitBreaker>,
+    /// Caps the jobs forwarded to the server at once, unlimited if `None`
+    pub execution_permits: Option<Arc<Semaphore>>,
 }
 
 impl ServiceContext {
     pub fn new() -> Self {
         Self::default()
     }
+
+    /// Context sending jobs to the server described by `config`
+    pub fn from_config(config: &CodeExecConfig) -> Self {
+        Self {
+            code_exec_url: config.url.clone(),
+            execution_permits: Some(Arc::new(Semaphore::new(config.max_concurrent))),
+            ..Self::default()
+        }
+    }
+}
+
+/// Package the program needs, installed before it runs
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Dependency {
+    pub name: String,
+    pub version: String,
+}
+
+/// Result of the `execute_code` job
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExecutionOutput {
+    pub stdout: String,
+    /// What the program wrote to stderr, such as warnings, even though it succeeded
+    pub stderr: String,
+    /// Run time of the program in seconds
+    pub execution_time: u64,
+    /// Peak memory usage of the program in bytes
+    pub memory_usage: u64,
 }
 
 #[derive(Default, Debug, Serialize, Deserialize)]
@@ -28,41 +68,95 @@ struct CodeExecutionRequest {
     language: String,
     code: String,
     input: Option<String>,
+    dependencies: Option<Vec<Dependency>>,
 }
 
 #[derive(Default, Debug, Serialize, Deserialize)]
 struct CodeExecutionResponse {
     stdout: String,
     stderr: String,
+    status: ExecutionStatus,
+    process_stats: ProcessStats,
+}
+
+/// How the server reports an execution ended
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum ExecutionStatus {
+    #[default]
+    Success,
+    Timeout,
+    MemoryExceeded,
+    CpuExceeded,
+    /// Exited with status 0, with its output cut off at the server's capture limit
+    OutputTruncated,
+    CompilationError,
+    RuntimeError(i32),
+}
+
+impl ExecutionStatus {
+    /// Whether the program exited with status 0
+    fn is_success(self) -> bool {
+        matches!(
+            self,
+            ExecutionStatus::Success | ExecutionStatus::OutputTruncated
+        )
+    }
+}
+
+#[derive(Default, Debug, Serialize, Deserialize)]
+struct ProcessStats {
     execution_time: u64,
-    memory_usage: u64,
+    max_rss: u64,
 }
 
-// Execute code in the specified language
+// Execute code in the specified language, after installing its dependencies
 pub async fn execute_code(
     Context(ctx): Context<ServiceContext>,
-    TangleArgs3(language, code, input): TangleArgs3<String, String, Option<String>>,
-) -> Result<TangleResult<()>, blueprint_sdk::Error> {
+    TangleArgs4(language, code, input, dependencies): TangleArgs4<
+        String,
+        String,
+        Option<String>,
+        Option<Vec<Dependency>>,
+    >,
+) -> Result<TangleResult<ExecutionOutput>, blueprint_sdk::Error> {
     let request = CodeExecutionRequest {
         language,
         code,
         input,
+        dependencies,
+    };
+
+    let _permit = match &ctx.execution_permits {
+        Some(permits) => Some(
+            permits
+                .acquire()
+                .await
+                .map_err(|e| blueprint_sdk::Error::Other(e.to_string()))?,
+        ),
+        None => None,
     };
 
-    let response = ctx
-        .http_client
-        .post(&format!("{}/execute", ctx.code_exec_url))
-        .json(&request)
-        .send()
-        .await
-        .map_err(|e| blueprint_sdk::Error::Other(e.to_string()))?
-        .json::<CodeExecutionResponse>()
-        .await
-        .map_err(|e| blueprint_sdk::Error::Other(e.to_string()))?;
-
-    if !response.stderr.is_empty() {
-        return Err(blueprint_sdk::Error::Other(response.stderr));
+    let response: CodeExecutionResponse = client::post_json(
+        &ctx.http_client,
+        &ctx.circuit_breaker,
+        &format!("{}/execute", ctx.code_exec_url),
+        &request,
+    )
+    .await?;
+
+    // Programs may write warnings to stderr and still succeed, only the status tells
+    if !response.status.is_success() {
+        return Err(blueprint_sdk::Error::Other(format!(
+            "program ended with {:?}: {}",
+            response.status, response.stderr
+        )));
     }
 
-    Ok(TangleResult(()))
+    Ok(TangleResult(ExecutionOutput {
+        stdout: response.stdout,
+        stderr: response.stderr,
+        execution_time: response.process_stats.execution_time,
+        memory_usage: response.process_stats.max_rss,
+    }))
 }
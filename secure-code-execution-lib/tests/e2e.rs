@@ -4,19 +4,26 @@ use blueprint_sdk::testing::tempfile;
 use blueprint_sdk::testing::utils::harness::TestHarness;
 use blueprint_sdk::testing::utils::setup_log;
 use blueprint_sdk::testing::utils::tangle::TangleTestHarness;
-use blueprint_sdk::testing::utils::tangle::blueprint_serde::to_field;
-use secure_code_execution_blueprint_blueprint_lib::{ServiceContext, execute_code};
+use blueprint_sdk::testing::utils::tangle::blueprint_serde::{from_field, to_field};
+use secure_code_execution_lib::{
+    CodeExecConfig, Dependency, ExecutionOutput, ServiceContext, execute_code,
+};
 
 // The number of nodes to spawn in the test
 const N: usize = 1;
 
+// The job forwards programs to a running code-exec server
+fn service_context() -> ServiceContext {
+    ServiceContext::from_config(&CodeExecConfig::from_env().unwrap())
+}
+
 #[tokio::test]
 async fn test_blueprint() -> color_eyre::Result<()> {
     setup_log();
 
     // Initialize test harness (node, keys, deployment)
     let temp_dir = tempfile::TempDir::new()?;
-    let context = ServiceContext::new();
+    let context = service_context();
     let harness = TangleTestHarness::setup(temp_dir, context).await?;
 
     // Setup service with `N` nodes
@@ -34,15 +41,83 @@ async fn test_blueprint() -> color_eyre::Result<()> {
         to_field("python".to_string()).unwrap(),
         to_field("print('Hello World')".to_string()).unwrap(),
         to_field(None::<String>).unwrap(),
+        to_field(None::<Vec<Dependency>>).unwrap(),
+    ];
+    let job = harness.submit_job(service_id, 0, job_inputs).await?;
+
+    let results = harness.wait_for_job_execution(service_id, job).await?;
+
+    assert_eq!(results.service_id, service_id);
+
+    // Verify the program's output is returned with the job result
+    let output: ExecutionOutput = from_field(results.result[0].clone())?;
+    assert_eq!(output.stdout, "Hello World\n");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_blueprint_with_dependencies() -> color_eyre::Result<()> {
+    setup_log();
+
+    let temp_dir = tempfile::TempDir::new()?;
+    let context = service_context();
+    let harness = TangleTestHarness::setup(temp_dir, context).await?;
+
+    let (mut test_env, service_id, _) = harness.setup_services::<N>(false).await?;
+    test_env.initialize().await?;
+    test_env.add_job(execute_code.layer(TangleLayer)).await;
+    test_env.start().await?;
+
+    let job_inputs = vec![
+        to_field("python".to_string()).unwrap(),
+        to_field("import six\nprint(six.__version__)".to_string()).unwrap(),
+        to_field(None::<String>).unwrap(),
+        to_field(Some(vec![Dependency {
+            name: "six".to_string(),
+            version: "1.16.0".to_string(),
+        }]))
+        .unwrap(),
     ];
     let job = harness.submit_job(service_id, 0, job_inputs).await?;
 
     let results = harness.wait_for_job_execution(service_id, job).await?;
+    assert_eq!(results.service_id, service_id);
+
+    // Run time and memory usage vary, only the output is known
+    let output: ExecutionOutput = from_field(results.result[0].clone())?;
+    assert_eq!(output.stdout, "1.16.0\n");
+    assert!(output.memory_usage > 0);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_blueprint_stderr_is_not_failure() -> color_eyre::Result<()> {
+    setup_log();
+
+    let temp_dir = tempfile::TempDir::new()?;
+    let context = service_context();
+    let harness = TangleTestHarness::setup(temp_dir, context).await?;
 
-    // Verify results match expected output
-    let expected_outputs = vec![to_field("Hello, Alice!").unwrap()];
-    harness.verify_job(&results, expected_outputs);
+    let (mut test_env, service_id, _) = harness.setup_services::<N>(false).await?;
+    test_env.initialize().await?;
+    test_env.add_job(execute_code.layer(TangleLayer)).await;
+    test_env.start().await?;
+
+    // A warning on stderr from a program that exits 0 still succeeds
+    let job_inputs = vec![
+        to_field("python".to_string()).unwrap(),
+        to_field("import sys\nsys.stderr.write('deprecated\\n')\nprint('done')".to_string())
+            .unwrap(),
+        to_field(None::<String>).unwrap(),
+        to_field(None::<Vec<Dependency>>).unwrap(),
+    ];
+    let job = harness.submit_job(service_id, 0, job_inputs).await?;
 
+    let results = harness.wait_for_job_execution(service_id, job).await?;
     assert_eq!(results.service_id, service_id);
+
+    let output: ExecutionOutput = from_field(results.result[0].clone())?;
+    assert_eq!(output.stdout, "done\n");
+    assert_eq!(output.stderr, "deprecated\n");
     Ok(())
 }